@@ -0,0 +1,604 @@
+//! v4.5：动图（GIF / APNG / 动态 WebP）逐帧处理
+//!
+//! - 嗅探：只读块结构判断是否多帧（GIF ≥2 个图像描述符、APNG acTL 帧数 >1、WebP VP8X 动画位），
+//!   单帧文件照旧走静态管线；
+//! - 解码：image 的 AnimationDecoder 逐帧合成整幅画布（含延时与循环次数），边解码边按长边缩放，
+//!   超出帧数 / 总时长上限即截断（报告 `truncated`）；
+//! - 帧规划：完全相同的相邻帧合并延时；全不透明的动图只保留与上一帧不同的包围盒，未变像素置透明
+//!   （GIF 叠加到上一帧上显示，LZW 也压得更狠；WebP 解码端混合有舍入误差，只裁不置透明、直接覆盖）；
+//!   含透明的动图按可见像素包围盒裁剪，显示后恢复背景；
+//! - GIF 输出：逐帧局部调色板（≤255 色精确，否则 NeuQuant），外加 1 个透明索引；
+//! - WebP 输出：VP8X + ANIM + ANMF 容器，每帧为无损 VP8L（帧偏移须为偶数，包围盒向左上对齐）。
+
+use anyhow::{anyhow, bail, Result};
+use fast_image_resize as fr;
+use image::{AnimationDecoder, RgbaImage};
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::io::Cursor;
+
+/// 可能含动画的扩展名（是否真为多帧由内容嗅探决定）
+pub const ANIMATION_EXTENSIONS: [&str; 4] = ["gif", "png", "apng", "webp"];
+pub const DEFAULT_MAX_FRAMES: u32 = 300;
+pub const DEFAULT_MAX_DURATION_MS: u32 = 60_000;
+
+pub fn is_animation_extension(ext: &str) -> bool {
+    ANIMATION_EXTENSIONS
+        .iter()
+        .any(|e| e.eq_ignore_ascii_case(ext))
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AnimationFormat {
+    /// WebP 输出或 WebP 源 → 动态 WebP，其余 → GIF
+    Auto,
+    Gif,
+    WebP,
+}
+
+impl AnimationFormat {
+    pub fn parse(s: &str) -> Option<Self> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "auto" | "" => Some(Self::Auto),
+            "gif" => Some(Self::Gif),
+            "webp" => Some(Self::WebP),
+            _ => None,
+        }
+    }
+
+    /// 输出扩展名：`prefer_webp` = 输出格式为 WebP 或源为 WebP
+    pub fn extension(self, prefer_webp: bool) -> &'static str {
+        match self {
+            Self::Gif => "gif",
+            Self::WebP => "webp",
+            Self::Auto if prefer_webp => "webp",
+            Self::Auto => "gif",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AnimationSpec {
+    pub format: AnimationFormat,
+    /// 最多保留的帧数（0 = 不限）
+    pub max_frames: u32,
+    /// 最长总时长（毫秒，0 = 不限）
+    pub max_duration_ms: u32,
+}
+
+impl Default for AnimationSpec {
+    fn default() -> Self {
+        Self {
+            format: AnimationFormat::Auto,
+            max_frames: DEFAULT_MAX_FRAMES,
+            max_duration_ms: DEFAULT_MAX_DURATION_MS,
+        }
+    }
+}
+
+/// 已合成的整幅帧
+pub struct Frame {
+    pub image: RgbaImage,
+    pub delay_ms: u32,
+}
+
+pub struct Animation {
+    pub frames: Vec<Frame>,
+    /// 循环次数（0 = 无限）
+    pub loop_count: u16,
+    /// 源帧数超出上限被截断
+    pub truncated: bool,
+}
+
+/// 动图输出报告
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AnimationInfo {
+    /// 输出格式（gif / webp）
+    pub format: String,
+    pub width: u32,
+    pub height: u32,
+    /// 解码保留的帧数（截断后）
+    pub source_frames: u32,
+    /// 合并相同帧后实际写出的帧数
+    pub frames: u32,
+    pub duration_ms: u32,
+    pub truncated: bool,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Kind {
+    Gif,
+    Png,
+    WebP,
+}
+
+fn kind(data: &[u8]) -> Option<Kind> {
+    if data.starts_with(b"GIF8") {
+        Some(Kind::Gif)
+    } else if data.starts_with(b"\x89PNG\r\n\x1a\n") {
+        Some(Kind::Png)
+    } else if data.len() >= 12 && &data[..4] == b"RIFF" && &data[8..12] == b"WEBP" {
+        Some(Kind::WebP)
+    } else {
+        None
+    }
+}
+
+/// 是否为多帧动图（只读块结构，不解码像素）
+pub fn is_animated(data: &[u8]) -> bool {
+    match kind(data) {
+        Some(Kind::Gif) => gif_is_animated(data).unwrap_or(false),
+        Some(Kind::Png) => apng_is_animated(data),
+        Some(Kind::WebP) => data.len() > 20 && &data[12..16] == b"VP8X" && data[20] & 0x02 != 0,
+        None => false,
+    }
+}
+
+fn gif_is_animated(data: &[u8]) -> Option<bool> {
+    fn skip_sub_blocks(data: &[u8], mut pos: usize) -> Option<usize> {
+        loop {
+            let n = *data.get(pos)? as usize;
+            pos += 1 + n;
+            if n == 0 {
+                return Some(pos);
+            }
+        }
+    }
+    let color_table = |packed: u8| {
+        if packed & 0x80 != 0 {
+            3usize << ((packed & 7) + 1)
+        } else {
+            0
+        }
+    };
+    let mut pos = 13 + color_table(*data.get(10)?);
+    let mut images = 0;
+    loop {
+        match *data.get(pos)? {
+            0x21 => pos = skip_sub_blocks(data, pos + 2)?,
+            0x2C => {
+                images += 1;
+                if images >= 2 {
+                    return Some(true);
+                }
+                // 描述符 10 字节 + 局部色表 + LZW 最小码长 1 字节 + 数据子块
+                pos += 10 + color_table(*data.get(pos + 9)?) + 1;
+                pos = skip_sub_blocks(data, pos)?;
+            }
+            _ => return Some(false),
+        }
+    }
+}
+
+fn apng_is_animated(data: &[u8]) -> bool {
+    let be = |b: &[u8]| u32::from_be_bytes([b[0], b[1], b[2], b[3]]);
+    let mut pos = 8;
+    while pos + 8 <= data.len() {
+        let len = be(&data[pos..]) as usize;
+        match &data[pos + 4..pos + 8] {
+            b"acTL" => return data.len() >= pos + 12 && be(&data[pos + 8..]) > 1,
+            b"IDAT" => return false,
+            _ => pos += 12 + len,
+        }
+    }
+    false
+}
+
+/// 按 `max_dim`（长边，0 = 不缩放）计算输出尺寸，与静态图长边缩放一致
+pub fn target_size(width: u32, height: u32, max_dim: u32) -> (u32, u32) {
+    if max_dim == 0 || (width <= max_dim && height <= max_dim) {
+        return (width, height);
+    }
+    let scale = (max_dim as f32 / width as f32).min(max_dim as f32 / height as f32);
+    (
+        ((width as f32 * scale) as u32).max(1),
+        ((height as f32 * scale) as u32).max(1),
+    )
+}
+
+/// 解码全部帧（按上限截断），每帧合成后立即缩放到 `max_dim`，避免整段原尺寸帧驻留内存
+pub fn decode(data: &[u8], spec: &AnimationSpec, max_dim: u32) -> Result<Animation> {
+    use image::codecs::{gif::GifDecoder, png::PngDecoder, webp::WebPDecoder};
+    use image::metadata::LoopCount;
+
+    let (loop_count, frames) = match kind(data) {
+        Some(Kind::Gif) => {
+            let d = GifDecoder::new(Cursor::new(data))?;
+            (d.loop_count(), d.into_frames())
+        }
+        Some(Kind::Png) => {
+            let d = PngDecoder::new(Cursor::new(data))?.apng()?;
+            (d.loop_count(), d.into_frames())
+        }
+        Some(Kind::WebP) => {
+            let mut d = WebPDecoder::new(Cursor::new(data))?;
+            // 浏览器忽略 ANIM 背景色，按透明画布合成
+            d.set_background_color(image::Rgba([0, 0, 0, 0]))?;
+            (d.loop_count(), d.into_frames())
+        }
+        None => bail!("不是可识别的动图格式（GIF / APNG / WebP）"),
+    };
+    let loop_count = match loop_count {
+        LoopCount::Infinite => 0,
+        LoopCount::Finite(n) => n.get().min(u16::MAX as u32) as u16,
+    };
+
+    let mut resizer = fr::Resizer::new();
+    let mut out = Vec::new();
+    let mut duration = 0u64;
+    let mut truncated = false;
+    for frame in frames {
+        let frame = frame?;
+        let reached_frames = spec.max_frames > 0 && out.len() >= spec.max_frames as usize;
+        let (numer, denom) = frame.delay().numer_denom_ms();
+        let delay_ms = (numer / denom.max(1)).min(0xFF_FFFF);
+        let reached_duration = spec.max_duration_ms > 0
+            && !out.is_empty()
+            && duration + delay_ms as u64 > spec.max_duration_ms as u64;
+        if reached_frames || reached_duration {
+            truncated = true;
+            break;
+        }
+        duration += delay_ms as u64;
+        let image = frame.into_buffer();
+        let (w, h) = image.dimensions();
+        let (tw, th) = target_size(w, h, max_dim);
+        let image = if (tw, th) == (w, h) {
+            image
+        } else {
+            let src = fr::images::Image::from_vec_u8(w, h, image.into_raw(), fr::PixelType::U8x4)?;
+            let mut dst = fr::images::Image::new(tw, th, fr::PixelType::U8x4);
+            resizer.resize(&src, &mut dst, None)?;
+            RgbaImage::from_raw(tw, th, dst.into_vec())
+                .ok_or_else(|| anyhow!("Failed to create image buffer"))?
+        };
+        out.push(Frame { image, delay_ms });
+    }
+    if out.is_empty() {
+        bail!("动图不含任何帧");
+    }
+    Ok(Animation {
+        frames: out,
+        loop_count,
+        truncated,
+    })
+}
+
+/// 规划后的帧：画布上 (x, y) 处的补丁，alpha=0 的像素不覆盖画布
+struct Patch {
+    x: u32,
+    y: u32,
+    image: RgbaImage,
+    delay_ms: u32,
+    /// 显示后把补丁区域恢复为透明背景
+    dispose_background: bool,
+    /// 与画布 alpha 混合（false = 直接覆盖）
+    blend: bool,
+}
+
+/// `webp`：补丁左上角按偶数对齐，且不与画布混合
+fn plan(frames: &[Frame], webp: bool) -> Vec<Patch> {
+    let align = if webp { 2 } else { 1 };
+    let opaque = frames.iter().all(|f| f.image.pixels().all(|p| p[3] == 255));
+    let (width, height) = frames[0].image.dimensions();
+    let mut patches: Vec<Patch> = Vec::new();
+    for (i, f) in frames.iter().enumerate() {
+        let prev = i.checked_sub(1).map(|j| &frames[j].image);
+        if let (Some(prev), Some(last)) = (prev, patches.last_mut()) {
+            if prev.as_raw() == f.image.as_raw() {
+                last.delay_ms = last.delay_ms.saturating_add(f.delay_ms);
+                continue;
+            }
+        }
+        let rect = match prev {
+            Some(prev) if opaque => bbox(&f.image, |x, y, p| prev.get_pixel(x, y) != p),
+            _ if opaque => Some((0, 0, width, height)),
+            _ => bbox(&f.image, |_, _, p| p[3] > 0),
+        };
+        let (x0, y0, w, h) = rect.unwrap_or((0, 0, 1, 1));
+        let (x, y) = (x0 - x0 % align, y0 - y0 % align);
+        let (w, h) = (w + x0 - x, h + y0 - y);
+        let mut image = image::imageops::crop_imm(&f.image, x, y, w, h).to_image();
+        let blend = opaque && !webp;
+        if let (true, Some(prev)) = (blend, prev) {
+            for (dx, dy, p) in image.enumerate_pixels_mut() {
+                if prev.get_pixel(x + dx, y + dy) == p {
+                    p[3] = 0;
+                }
+            }
+        }
+        patches.push(Patch {
+            x,
+            y,
+            image,
+            delay_ms: f.delay_ms,
+            dispose_background: !opaque,
+            blend,
+        });
+    }
+    patches
+}
+
+/// 满足条件的像素包围盒 (x, y, w, h)；无则 None
+fn bbox(
+    img: &RgbaImage,
+    hit: impl Fn(u32, u32, &image::Rgba<u8>) -> bool,
+) -> Option<(u32, u32, u32, u32)> {
+    let (mut x0, mut y0, mut x1, mut y1) = (u32::MAX, u32::MAX, 0, 0);
+    for (x, y, p) in img.enumerate_pixels() {
+        if hit(x, y, p) {
+            x0 = x0.min(x);
+            y0 = y0.min(y);
+            x1 = x1.max(x);
+            y1 = y1.max(y);
+        }
+    }
+    (x0 != u32::MAX).then(|| (x0, y0, x1 - x0 + 1, y1 - y0 + 1))
+}
+
+fn info(anim: &Animation, format: &str, frames: usize) -> AnimationInfo {
+    let (width, height) = anim.frames[0].image.dimensions();
+    AnimationInfo {
+        format: format.to_string(),
+        width,
+        height,
+        source_frames: anim.frames.len() as u32,
+        frames: frames as u32,
+        duration_ms: anim.frames.iter().map(|f| f.delay_ms).sum(),
+        truncated: anim.truncated,
+    }
+}
+
+/// 编码为优化 GIF；`quality`（1-100）决定 NeuQuant 采样率（越高越精细越慢）
+pub fn encode_gif(anim: &Animation, quality: u8) -> Result<(Vec<u8>, AnimationInfo)> {
+    let (width, height) = anim.frames[0].image.dimensions();
+    if width > u16::MAX as u32 || height > u16::MAX as u32 {
+        bail!("GIF 尺寸上限 65535px，当前 {}x{}", width, height);
+    }
+    let samplefac = (1 + (100 - quality.min(100) as i32) / 3).clamp(1, 30);
+    let patches = plan(&anim.frames, false);
+    let quantized: Vec<(Vec<u8>, Vec<u8>, u8)> = patches
+        .par_iter()
+        .map(|p| quantize(&p.image, samplefac))
+        .collect();
+
+    let mut buf = Vec::new();
+    {
+        let mut encoder = gif::Encoder::new(&mut buf, width as u16, height as u16, &[])?;
+        encoder.set_repeat(match anim.loop_count {
+            0 => gif::Repeat::Infinite,
+            n => gif::Repeat::Finite(n),
+        })?;
+        for (p, (palette, indices, transparent)) in patches.iter().zip(quantized) {
+            let frame = gif::Frame {
+                delay: ((p.delay_ms + 5) / 10).min(u16::MAX as u32) as u16,
+                dispose: if p.dispose_background {
+                    gif::DisposalMethod::Background
+                } else {
+                    gif::DisposalMethod::Keep
+                },
+                transparent: Some(transparent),
+                left: p.x as u16,
+                top: p.y as u16,
+                width: p.image.width() as u16,
+                height: p.image.height() as u16,
+                palette: Some(palette),
+                buffer: indices.into(),
+                ..Default::default()
+            };
+            encoder.write_frame(&frame)?;
+        }
+    }
+    let info = info(anim, "gif", patches.len());
+    Ok((buf, info))
+}
+
+/// 量化为 (RGB 调色板, 索引, 透明索引)；alpha < 128 视为透明
+fn quantize(img: &RgbaImage, samplefac: i32) -> (Vec<u8>, Vec<u8>, u8) {
+    let visible = |p: &image::Rgba<u8>| p[3] >= 128;
+    let mut exact: HashMap<[u8; 3], u8> = HashMap::new();
+    for p in img.pixels().filter(|p| visible(p)) {
+        let len = exact.len();
+        if len > 255 {
+            break;
+        }
+        exact.entry([p[0], p[1], p[2]]).or_insert(len as u8);
+    }
+    if exact.len() <= 255 {
+        let transparent = exact.len() as u8;
+        let mut palette = vec![0u8; (exact.len() + 1) * 3];
+        for (c, &i) in &exact {
+            palette[i as usize * 3..][..3].copy_from_slice(c);
+        }
+        let indices = img
+            .pixels()
+            .map(|p| match visible(p) {
+                true => exact[&[p[0], p[1], p[2]]],
+                false => transparent,
+            })
+            .collect();
+        return (palette, indices, transparent);
+    }
+
+    let samples: Vec<u8> = img
+        .pixels()
+        .filter(|p| visible(p))
+        .flat_map(|p| [p[0], p[1], p[2], 255])
+        .collect();
+    let nq = color_quant::NeuQuant::new(samplefac, 255, &samples);
+    let mut palette = nq.color_map_rgb();
+    palette.extend_from_slice(&[0, 0, 0]);
+    let indices = img
+        .pixels()
+        .map(|p| match visible(p) {
+            true => nq.index_of(&[p[0], p[1], p[2], 255]) as u8,
+            false => 255,
+        })
+        .collect();
+    (palette, indices, 255)
+}
+
+/// 编码为动态 WebP（逐帧无损）
+pub fn encode_webp(anim: &Animation) -> Result<(Vec<u8>, AnimationInfo)> {
+    let (width, height) = anim.frames[0].image.dimensions();
+    if width > 1 << 14 || height > 1 << 14 {
+        bail!("WebP 尺寸上限 16384px，当前 {}x{}", width, height);
+    }
+    let patches = plan(&anim.frames, true);
+    let bitstreams: Vec<Vec<u8>> = patches
+        .par_iter()
+        .map(|p| vp8l_chunk(&p.image))
+        .collect::<Result<_>>()?;
+
+    let u24 = |v: u32| [v as u8, (v >> 8) as u8, (v >> 16) as u8];
+    let mut body = Vec::new();
+    // VP8X：动画 + alpha，画布尺寸减 1
+    body.extend_from_slice(b"VP8X");
+    body.extend_from_slice(&10u32.to_le_bytes());
+    body.extend_from_slice(&[0x02 | 0x10, 0, 0, 0]);
+    body.extend_from_slice(&u24(width - 1));
+    body.extend_from_slice(&u24(height - 1));
+    // ANIM：透明背景 + 循环次数
+    body.extend_from_slice(b"ANIM");
+    body.extend_from_slice(&6u32.to_le_bytes());
+    body.extend_from_slice(&[0, 0, 0, 0]);
+    body.extend_from_slice(&anim.loop_count.to_le_bytes());
+    for (p, chunk) in patches.iter().zip(&bitstreams) {
+        let flags = (!p.blend as u8) << 1 | p.dispose_background as u8;
+        body.extend_from_slice(b"ANMF");
+        body.extend_from_slice(&(16 + chunk.len() as u32).to_le_bytes());
+        body.extend_from_slice(&u24(p.x / 2));
+        body.extend_from_slice(&u24(p.y / 2));
+        body.extend_from_slice(&u24(p.image.width() - 1));
+        body.extend_from_slice(&u24(p.image.height() - 1));
+        body.extend_from_slice(&u24(p.delay_ms.min(0xFF_FFFF)));
+        body.push(flags);
+        body.extend_from_slice(chunk);
+    }
+
+    let mut buf = Vec::with_capacity(body.len() + 12);
+    buf.extend_from_slice(b"RIFF");
+    buf.extend_from_slice(&(4 + body.len() as u32).to_le_bytes());
+    buf.extend_from_slice(b"WEBP");
+    buf.extend_from_slice(&body);
+    Ok((buf, info(anim, "webp", patches.len())))
+}
+
+/// 无损编码单帧，取出其中完整的 VP8L 块（含块头与补齐字节）
+fn vp8l_chunk(img: &RgbaImage) -> Result<Vec<u8>> {
+    let mut webp = Vec::new();
+    image::codecs::webp::WebPEncoder::new_lossless(&mut webp).encode(
+        img.as_raw(),
+        img.width(),
+        img.height(),
+        image::ExtendedColorType::Rgba8,
+    )?;
+    let mut pos = 12;
+    while pos + 8 <= webp.len() {
+        let len = u32::from_le_bytes([webp[pos + 4], webp[pos + 5], webp[pos + 6], webp[pos + 7]]);
+        let end = (pos + 8 + len as usize + (len as usize & 1)).min(webp.len());
+        if &webp[pos..pos + 4] == b"VP8L" {
+            return Ok(webp[pos..end].to_vec());
+        }
+        pos = end;
+    }
+    bail!("WebP 编码输出缺少 VP8L 块")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 3 帧 40x30：红底上移动的蓝块，第 3 帧与第 2 帧相同
+    fn sample() -> Animation {
+        let frame = |x: u32| {
+            let mut img = RgbaImage::from_pixel(40, 30, image::Rgba([200, 30, 30, 255]));
+            for y in 10..20 {
+                for dx in 0..6 {
+                    img.put_pixel(x + dx, y, image::Rgba([20, 40, 220, 255]));
+                }
+            }
+            img
+        };
+        Animation {
+            frames: vec![
+                Frame {
+                    image: frame(4),
+                    delay_ms: 100,
+                },
+                Frame {
+                    image: frame(20),
+                    delay_ms: 100,
+                },
+                Frame {
+                    image: frame(20),
+                    delay_ms: 50,
+                },
+            ],
+            loop_count: 0,
+            truncated: false,
+        }
+    }
+
+    fn decode_all(data: &[u8]) -> Vec<(RgbaImage, u32)> {
+        let spec = AnimationSpec::default();
+        decode(data, &spec, 0)
+            .unwrap()
+            .frames
+            .into_iter()
+            .map(|f| (f.image, f.delay_ms))
+            .collect()
+    }
+
+    #[test]
+    fn test_gif_webp_roundtrip() {
+        let anim = sample();
+        for (data, info) in [encode_gif(&anim, 85).unwrap(), encode_webp(&anim).unwrap()] {
+            assert!(is_animated(&data), "{}", info.format);
+            // 相同帧合并延时：3 源帧 → 2 输出帧，总时长不变
+            assert_eq!(
+                (info.source_frames, info.frames, info.duration_ms),
+                (3, 2, 250)
+            );
+            let frames = decode_all(&data);
+            assert_eq!(frames.len(), 2);
+            assert_eq!((frames[0].1, frames[1].1), (100, 150));
+            // 差异帧叠加后与源帧逐像素一致（颜色少于 256，GIF 调色板精确）
+            assert_eq!(frames[0].0, anim.frames[0].image);
+            assert_eq!(frames[1].0, anim.frames[1].image);
+        }
+    }
+
+    #[test]
+    fn test_limits_and_sniff() {
+        let (gif, _) = encode_gif(&sample(), 85).unwrap();
+        let spec = AnimationSpec {
+            max_frames: 1,
+            ..Default::default()
+        };
+        let anim = decode(&gif, &spec, 20).unwrap();
+        assert!(anim.truncated);
+        assert_eq!(anim.frames.len(), 1);
+        assert_eq!(anim.frames[0].image.dimensions(), (20, 15));
+        let spec = AnimationSpec {
+            max_duration_ms: 120,
+            ..Default::default()
+        };
+        assert_eq!(decode(&gif, &spec, 0).unwrap().frames.len(), 1);
+
+        // 单帧 GIF / 普通 PNG 不算动图
+        let mut single = Vec::new();
+        image::DynamicImage::new_rgba8(4, 4)
+            .write_to(&mut Cursor::new(&mut single), image::ImageFormat::Gif)
+            .unwrap();
+        assert!(!is_animated(&single));
+        let mut png = Vec::new();
+        image::DynamicImage::new_rgba8(4, 4)
+            .write_to(&mut Cursor::new(&mut png), image::ImageFormat::Png)
+            .unwrap();
+        assert!(!is_animated(&png));
+        assert_eq!(AnimationFormat::parse("WebP"), Some(AnimationFormat::WebP));
+        assert_eq!(AnimationFormat::Auto.extension(true), "webp");
+    }
+}
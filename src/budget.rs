@@ -0,0 +1,114 @@
+//! v4.5：批量总体积预算（邮件附件 / 上传表单「30 张合计 ≤20MB」）
+//!
+//! 平台预设只管单张上限，总量上限需要在文件之间分配：
+//! - 首轮按权重（输出像素 × 内容复杂度，见 `Processor::budget_weight`）按比例切分总预算，
+//!   每个文件的份额作为它的 target_kb 走原有的质量二分；
+//! - 简单图往往用不完份额，余量在第二轮分给「顶到份额」的文件（实际体积 ≥ 份额 90%，
+//!   说明质量被预算压住了）重压。新份额 = 已用体积 + 按权重分得的余量，
+//!   未重压文件的体积不变，因此合计仍 ≤ 总预算。
+//!
+//! 预算只作用于 JPEG 编码（PNG/WebP 无体积二分）；最低质量仍超份额时如实报告 `fits=false`。
+
+use serde::{Deserialize, Serialize};
+
+/// 余量低于总预算的该比例时不再重平衡（重压收益太小）
+const REBALANCE_MIN_FRACTION: f64 = 0.02;
+/// 实际体积达到份额的该比例即视为「顶到预算」
+const CONSTRAINED_FRACTION: f64 = 0.9;
+/// 重平衡最多轮数
+pub const MAX_REBALANCE_PASSES: u32 = 2;
+
+/// 批量预算执行报告（JSON data.batch_budget）
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct BatchBudgetReport {
+    pub budget_kb: u32,
+    /// 实际合计体积（全部成功输出，含跳过/透传）
+    pub total_bytes: u64,
+    pub fits: bool,
+    /// 重平衡轮数（0 = 首轮即收敛）
+    pub rebalance_passes: u32,
+    /// 被重压过的文件数
+    pub rebalanced_files: usize,
+}
+
+/// 按权重比例切分总预算（KB，向下取整、每个至少 1KB）
+pub fn allocate_budgets(total_kb: u32, weights: &[f64]) -> Vec<u32> {
+    let sum: f64 = weights.iter().map(|w| w.max(0.0)).sum();
+    if weights.is_empty() {
+        return Vec::new();
+    }
+    weights
+        .iter()
+        .map(|w| {
+            let share = if sum > 0.0 {
+                total_kb as f64 * w.max(0.0) / sum
+            } else {
+                total_kb as f64 / weights.len() as f64
+            };
+            (share.floor() as u32).max(1)
+        })
+        .collect()
+}
+
+/// 重平衡：把未用完的余量按权重分给顶到份额的文件。
+/// 返回新份额（未变化的文件保持原份额），无可重分时返回 None
+pub fn rebalance_budgets(
+    total_kb: u32,
+    budgets: &[u32],
+    actual_bytes: &[u64],
+    weights: &[f64],
+) -> Option<Vec<u32>> {
+    let total = total_kb as f64 * 1024.0;
+    let used: f64 = actual_bytes.iter().map(|&b| b as f64).sum();
+    let leftover = total - used;
+    if leftover < total * REBALANCE_MIN_FRACTION {
+        return None;
+    }
+    let constrained: Vec<usize> = (0..budgets.len())
+        .filter(|&i| actual_bytes[i] as f64 >= budgets[i] as f64 * 1024.0 * CONSTRAINED_FRACTION)
+        .collect();
+    let weight_sum: f64 = constrained.iter().map(|&i| weights[i].max(0.0)).sum();
+    if constrained.is_empty() || weight_sum <= 0.0 {
+        return None;
+    }
+    let mut next = budgets.to_vec();
+    for &i in &constrained {
+        let share = leftover * weights[i].max(0.0) / weight_sum;
+        next[i] = ((actual_bytes[i] as f64 + share) / 1024.0).floor() as u32;
+    }
+    (next != budgets).then_some(next)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_allocate_proportional_to_weight() {
+        let b = allocate_budgets(1000, &[1.0, 3.0]);
+        assert_eq!(b, vec![250, 750]);
+        assert!(allocate_budgets(1000, &[1.0, 1.0, 1.0]).iter().sum::<u32>() <= 1000);
+        // 全零权重退化为均分
+        assert_eq!(allocate_budgets(100, &[0.0, 0.0]), vec![50, 50]);
+    }
+
+    #[test]
+    fn test_rebalance_gives_leftover_to_constrained_and_keeps_total() {
+        // 1000KB：A 份额 500 只用了 100KB；B 份额 500 顶满
+        let budgets = vec![500, 500];
+        let actual = vec![100 * 1024, 495 * 1024];
+        let next = rebalance_budgets(1000, &budgets, &actual, &[1.0, 1.0]).unwrap();
+        assert_eq!(next[0], 500, "未顶到预算的文件不重分");
+        assert_eq!(next[1], 495 + 405);
+        // 新份额 + 未重压文件的实际体积 ≤ 总预算
+        assert!(next[1] as u64 * 1024 + actual[0] <= 1000 * 1024);
+
+        // 都没用完 / 余量太小：不重平衡
+        assert!(
+            rebalance_budgets(1000, &budgets, &[100 * 1024, 100 * 1024], &[1.0, 1.0]).is_none()
+        );
+        assert!(
+            rebalance_budgets(1000, &budgets, &[495 * 1024, 495 * 1024], &[1.0, 1.0]).is_none()
+        );
+    }
+}
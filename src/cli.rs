@@ -1,7 +1,22 @@
 use clap::Parser;
 use serde::{Deserialize, Serialize};
-use std::path::PathBuf;
-
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+use xtap_compress::animation::AnimationInfo;
+use xtap_compress::budget::BatchBudgetReport;
+use xtap_compress::contact_sheet::ContactSheetConfig;
+use xtap_compress::crop::CropRect;
+use xtap_compress::external::ExternalDecoder;
+use xtap_compress::grid::TileOutput;
+use xtap_compress::icon::IconSet;
+use xtap_compress::pad::PadPlacement;
+use xtap_compress::placeholder::Placeholder;
+use xtap_compress::platform_sim::PlatformSimResult;
+use xtap_compress::print::PrintFit;
+use xtap_compress::srcset::SrcsetManifest;
+use xtap_compress::tiff_pages::TiffPages;
 use xtap_compress::{AppConfig, ColorSpace, OutputFormat, ProcessMode};
 
 // ============================================================================
@@ -28,8 +43,10 @@ use xtap_compress::{AppConfig, ColorSpace, OutputFormat, ProcessMode};
   或 --json-in 直接传 JSON 字符串(零翻译损失)。\n\n\
 说明:\n\
   - 目录默认递归(含子目录);--no-recursive 仅处理当前层。\n\
-  - RAW(.cr3/.nef/.arw/.dng 等)仅在 macOS 支持(依赖系统 sips);\n\
-    Windows 上请先在 Mac 处理,或转成 JPG/PNG。\n\
+  - RAW(.cr3/.nef/.arw/.dng 等):macOS 用系统 sips;其他平台 DNG 全分辨率显影,其余取内嵌 JPEG 预览\n\
+    (尺寸以相机写入的预览为准,EXIF 随之迁移)。\n\
+  - HEIC/HEIF(iPhone 照片):需以 --features heic 编译(系统 libheif),或用 --external-decoder 接 heif-convert。\n\
+  - JPEG XL(.jxl)输入:需以 --features jxl 编译(纯 Rust),或用 --external-decoder 接 djxl;jxl 输出需 cjxl。\n\
   - 退出码: 0=正常,1=有失败,2=参数错误。")]
 pub struct Cli {
     #[arg(long, short = 'i', value_name = "FILE/DIR")]
@@ -164,9 +181,240 @@ pub struct Cli {
     pub usage_mode: Option<CliUsageMode>,
 
     /// 平台阈值预设（§2 实测表）：选后自动填长边/体积/Q 并强制 sRGB，规避平台二压
-    /// wechat=保守1080/900KB | wechat-new=iOS新宽幅2560/2000KB | xiaohongshu=1440/800KB | instagram=1080/1000KB
-    #[arg(long, value_enum)]
-    pub platform: Option<CliPlatform>,
+    /// wechat=保守1080/900KB | wechat-new=iOS新宽幅2560/2000KB | xiaohongshu=1660/4500KB | instagram=1080/1000KB | general。
+    /// 也接受 presets.toml 中自定义的任意预设名（--capabilities 列出合并后的全集）。
+    /// 逗号分隔多个预设（如 wechat,xiaohongshu）即多预设扇出：源图解码一次，每个预设各出一份；
+    /// 再加 --usage-mode archive 额外出一份高清存档
+    #[arg(long, value_name = "PRESET[,PRESET…]")]
+    pub platform: Option<String>,
+
+    /// 多预设扇出的输出区分方式：suffix=文件名后缀（photo_wechat.jpg）/ subdir=按预设分子目录
+    #[arg(long, value_enum, default_value_t = CliFanoutLayout::Suffix)]
+    pub fanout_layout: CliFanoutLayout,
+
+    /// 智能裁剪到平台画幅（显著性 + 肤色先验定位主体）：画幅取 --crop-aspect，缺省取平台预设 aspect
+    #[arg(long)]
+    pub smart_crop: bool,
+
+    /// 智能裁剪目标画幅，如 3:4 / 4:5 / 1:1（隐含 --smart-crop，覆盖平台预设 aspect）
+    #[arg(long, value_name = "W:H", value_parser = parse_aspect_arg)]
+    pub crop_aspect: Option<String>,
+
+    /// 补边到固定画布（整图保留，不裁内容）：画布取 --pad-canvas，缺省取平台预设 pad_canvas / aspect。
+    /// 与 --smart-crop 二选一，同时给出时补边优先
+    #[arg(long)]
+    pub pad: bool,
+
+    /// 补边画布：1080x1350 = 精确像素（输出即该尺寸）/ 1:1 = 画幅比（隐含 --pad）
+    #[arg(long, value_name = "WxH|W:H", value_parser = parse_pad_canvas_arg)]
+    pub pad_canvas: Option<String>,
+
+    /// 补边底色：blur（模糊放大原图，默认）/ #RRGGBB / white / black
+    #[arg(long, value_name = "FILL", value_parser = parse_pad_fill_arg)]
+    pub pad_fill: Option<String>,
+
+    /// 九宫格切图：3x3 / 2x2 / 1x3（列x行，最多 9 片）。在裁剪/补边之后均分，
+    /// 每片独立按当前预设压缩，输出编号 _1…_9（行优先，即朋友圈上传顺序）
+    #[arg(long, value_name = "COLSxROWS", value_parser = parse_grid_arg)]
+    pub grid: Option<String>,
+
+    /// 九宫格切片间缝宽（像素，缝内像素丢弃）
+    #[arg(long, value_name = "PX", default_value_t = 0)]
+    pub grid_gutter: u32,
+
+    /// 长图分页：宽缩到平台宽度（--max-dim / 平台预设）后按此页高（输出像素）纵向切页，
+    /// 切线落在低细节行（消息间空白），每页独立卡体积预算，输出编号 _01…_99
+    #[arg(long, value_name = "PX", conflicts_with = "grid", value_parser = clap::value_parser!(u32).range(1..))]
+    pub slice_height: Option<u32>,
+
+    /// 冲印尺寸：纸张 + DPI（A4@200 / 6x4in@300 / 15x10cm@300 / 4R，缺省 300dpi）。
+    /// 按纸张像素框等比缩放（方向跟随图片、不放大），输出写入 JFIF/EXIF/pHYs 密度
+    #[arg(long, value_name = "SIZE@DPI", value_parser = parse_print_size_arg, conflicts_with_all = ["grid", "slice_height"])]
+    pub print_size: Option<String>,
+
+    /// 响应式图片组：逗号分隔的宽度档（如 320,640,1280,2560）。源图解码一次，每档各自缩放/编码，
+    /// 输出 _{宽}w；宽于原图的档位不放大。每张图旁生成 .srcset.json 清单与 .srcset.html（<picture> 片段）
+    #[arg(long, value_name = "WIDTHS", value_parser = parse_srcset_arg, conflicts_with_all = ["grid", "slice_height", "print_size"])]
+    pub srcset: Option<String>,
+
+    /// 响应式图片组的格式：jpeg / webp，可逗号组合（缺省跟随 --output-format）
+    #[arg(long, value_name = "FORMATS", value_parser = parse_srcset_formats_arg, requires = "srcset")]
+    pub srcset_formats: Option<String>,
+
+    /// 响应式图片组 <img sizes> 属性
+    #[arg(
+        long,
+        value_name = "SIZES",
+        default_value = "100vw",
+        requires = "srcset"
+    )]
+    pub srcset_sizes: String,
+
+    /// 低清占位图：每张图附带 BlurHash、主色与长边 ≤32px 的 base64 缩略图（由已缩放缓冲生成，
+    /// 随结果 placeholder 与 manifest 输出，CMS 入库无需再解码）
+    #[arg(long)]
+    pub placeholder: bool,
+
+    /// 占位缩略图格式
+    #[arg(long, value_name = "FORMAT", default_value = "jpeg", value_parser = ["jpeg", "webp"], requires = "placeholder")]
+    pub placeholder_format: String,
+
+    /// 占位缩略图长边（px，上限 32）
+    #[arg(long, value_name = "PX", default_value_t = 32, value_parser = clap::value_parser!(u32).range(1..=32), requires = "placeholder")]
+    pub placeholder_size: u32,
+
+    /// 图标组模式：每张方图生成 {名}_icons/（favicon.ico 16–256 多分辨率、favicon-16/32、
+    /// apple-touch-icon 180、android-chrome 192/512 与 site.webmanifest），取代压缩输出
+    #[arg(long, conflicts_with_all = ["grid", "slice_height", "print_size", "srcset"])]
+    pub icon: bool,
+
+    /// 图标四周留白，占边长比例（0-0.4）
+    #[arg(long, value_name = "RATIO", default_value_t = 0.0, requires = "icon")]
+    pub icon_padding: f32,
+
+    /// 图标圆角半径，占边长比例（0 = 直角，0.5 = 圆形；apple-touch-icon 不切圆角）
+    #[arg(long, value_name = "RATIO", default_value_t = 0.0, requires = "icon")]
+    pub icon_radius: f32,
+
+    /// 图标底色 #RRGGBB（缺省透明；apple-touch-icon 缺省白底）
+    #[arg(long, value_name = "#RRGGBB", requires = "icon")]
+    pub icon_background: Option<String>,
+
+    /// 外部解码器（可重复）：`ext1,ext2=程序 参数…`，{input} / {output} / {stem} 为占位符，
+    /// 如 `cr3,nef=dcraw_emu -T -Z {output} {input}`；任意平台生效，优先于内置解码
+    #[arg(long = "external-decoder", value_name = "EXTS=CMD", value_parser = ExternalDecoder::parse)]
+    pub external_decoder: Vec<ExternalDecoder>,
+
+    /// 外部解码器（及 macOS 内置 sips/qlmanage）的超时（秒）
+    #[arg(long, value_name = "SECS", default_value_t = xtap_compress::external::DEFAULT_TIMEOUT_SECS, value_parser = clap::value_parser!(u64).range(1..=3600))]
+    pub decoder_timeout: u64,
+
+    /// 外部解码工具全进程并发上限（0 = 缺省 4）
+    #[arg(long, value_name = "N", default_value_t = 0)]
+    pub decoder_concurrency: usize,
+
+    /// JXL 输出时 JPEG 输入无损重压缩（不解码像素、跳过缩放等处理，可逐字节还原原 JPEG；需 libjxl 的 cjxl）
+    #[arg(long)]
+    pub jxl_lossless_jpeg: bool,
+
+    /// 动图（GIF / APNG / 动态 WebP）只取首帧按静态图处理（缺省逐帧处理）
+    #[arg(long)]
+    pub first_frame_only: bool,
+
+    /// 动图输出格式：auto（WebP 输出或 WebP 源 → 动态 WebP，其余 → 优化 GIF）/ gif / webp
+    #[arg(long, value_name = "FORMAT", default_value = "auto", value_parser = ["auto", "gif", "webp"])]
+    pub animation_format: String,
+
+    /// 动图最多保留帧数（超出截断，0 = 不限）
+    #[arg(long, value_name = "N", default_value_t = xtap_compress::animation::DEFAULT_MAX_FRAMES)]
+    pub animation_max_frames: u32,
+
+    /// 动图最长总时长（毫秒，超出截断，0 = 不限）
+    #[arg(long, value_name = "MS", default_value_t = xtap_compress::animation::DEFAULT_MAX_DURATION_MS)]
+    pub animation_max_duration_ms: u32,
+
+    /// SVG 栅格化后走常规管线（缺省关闭：SVG 为不支持格式，可用 --passthrough-unsupported 原样透传）
+    #[cfg(feature = "svg")]
+    #[arg(long)]
+    pub svg_rasterize: bool,
+
+    /// SVG 渲染尺寸：长边像素（如 2048，缺省）或 DPI（如 300dpi，按 SVG 物理尺寸换算）
+    #[cfg(feature = "svg")]
+    #[arg(long, value_name = "PX|DPI", value_parser = parse_svg_size_arg, requires = "svg_rasterize")]
+    pub svg_size: Option<String>,
+
+    /// SVG 底色：transparent（缺省）/ white / black / #RRGGBB
+    #[cfg(feature = "svg")]
+    #[arg(long, value_name = "COLOR", value_parser = parse_svg_background_arg, requires = "svg_rasterize")]
+    pub svg_background: Option<String>,
+
+    /// 16 位 / 浮点源量化到 8 位的抖动：blue-noise（缺省）/ ordered / none
+    #[arg(long, value_name = "MODE", default_value = "blue-noise", value_parser = ["blue-noise", "ordered", "none"])]
+    pub dither: String,
+
+    /// 浮点（HDR）源的色调映射：none（截断到 [0,1]，缺省）/ reinhard / aces
+    #[arg(long, value_name = "CURVE", default_value = "none", value_parser = ["none", "reinhard", "aces"])]
+    pub tone_map: String,
+
+    /// 多页 TIFF 取页：first（缺省，只取第 1 页并告警）/ all（每页输出 _p01…）/ largest（只取最大一页）
+    #[arg(long, value_name = "MODE", default_value = "first", value_parser = ["first", "all", "largest"])]
+    pub tiff_pages: String,
+
+    /// 批量总体积预算（KB）：按输出像素×内容复杂度把总量分给各文件作为单文件预算，
+    /// 未用完的余量再分给顶到预算的文件重压；结果报告实际合计（仅 JPEG 输出受预算约束）
+    #[arg(long, value_name = "KB", value_parser = clap::value_parser!(u32).range(1..))]
+    pub batch_budget_kb: Option<u32>,
+
+    /// 水印：PNG 标志路径（与 --watermark-text 二选一，标志优先）
+    #[arg(long, value_name = "PNG")]
+    pub watermark_image: Option<PathBuf>,
+
+    /// 水印：文字内容（需 --watermark-font 指定 TTF/OTF）
+    #[arg(long, value_name = "TEXT")]
+    pub watermark_text: Option<String>,
+
+    /// 水印：文字字体（TTF/OTF）
+    #[arg(long, value_name = "FILE")]
+    pub watermark_font: Option<PathBuf>,
+
+    /// 水印：文字颜色 #RRGGBB
+    #[arg(long, value_name = "#RRGGBB", default_value = "#FFFFFF")]
+    pub watermark_color: String,
+
+    /// 水印位置：top-left / top / top-right / left / center / right / bottom-left / bottom / bottom-right / tile（平铺）
+    #[arg(long, value_name = "POS", default_value = "bottom-right")]
+    pub watermark_position: String,
+
+    /// 水印边距（平铺时为间距），占输出短边比例
+    #[arg(long, value_name = "RATIO", default_value_t = 0.03)]
+    pub watermark_margin: f32,
+
+    /// 水印不透明度 0-1
+    #[arg(long, value_name = "0-1", default_value_t = 0.5)]
+    pub watermark_opacity: f32,
+
+    /// 水印尺寸：标志宽度 / 文字字号占输出短边比例（缺省标志 0.2、文字 0.05）
+    #[arg(long, value_name = "RATIO")]
+    pub watermark_scale: Option<f32>,
+
+    /// 联系表：批量缩略图总览（.jpg / .png），每格附文件名与原始→压缩体积，发片前人工过目
+    #[arg(long, value_name = "FILE", conflicts_with = "ab")]
+    pub contact_sheet: Option<PathBuf>,
+
+    /// 联系表每行格数（1-20）
+    #[arg(
+        long,
+        value_name = "N",
+        default_value_t = 6,
+        requires = "contact_sheet"
+    )]
+    pub contact_sheet_columns: u32,
+
+    /// 联系表缩略图格边长（px，64-1024）
+    #[arg(
+        long,
+        value_name = "PX",
+        default_value_t = 240,
+        requires = "contact_sheet"
+    )]
+    pub contact_sheet_cell: u32,
+
+    /// 联系表每页格数（0 = 不分页；分页输出 _01、_02…）
+    #[arg(
+        long,
+        value_name = "N",
+        default_value_t = 0,
+        requires = "contact_sheet"
+    )]
+    pub contact_sheet_per_page: u32,
+
+    /// 联系表说明文字字体（TTF/OTF；缺省探测系统字体，再退回内置点阵）
+    #[arg(long, value_name = "FILE", requires = "contact_sheet")]
+    pub contact_sheet_font: Option<PathBuf>,
+
+    /// 额外平台预设文件（TOML，表名=预设名），在内置 / 系统 / 用户 presets.toml 之后合并，优先级最高
+    #[arg(long, value_name = "FILE")]
+    pub presets: Option<PathBuf>,
 
     /// 色彩子采样（v4.3.0）：420=照片(默认,省~1/3码率) / 444=截图文字(防文字模糊) / 422=平衡
     #[arg(long)]
@@ -192,6 +440,15 @@ pub struct Cli {
     #[arg(long, value_name = "0.0-1.0")]
     pub cas_strength: Option<f32>,
 
+    /// 平台二压模拟：输出再按平台模型重编码一遍，把「过平台后」的 SSIM/PSNR 追加到每个结果
+    /// （wechat / wechat-new / xiaohongshu / instagram，或 --simulate-config 中定义的平台）
+    #[arg(long, value_name = "PLATFORM")]
+    pub simulate_platform: Option<String>,
+
+    /// 平台二压模型覆盖（TOML，表名=平台名，如 [wechat] quality = 70），未写字段沿用内置值
+    #[arg(long, value_name = "FILE")]
+    pub simulate_config: Option<PathBuf>,
+
     /// 覆盖平台默认体积安全线（KB）：触发质量二分搜索压到线内，防止微信/小红书/IG 二次重压
     #[arg(long)]
     pub target_budget_kb: Option<u32>,
@@ -208,10 +465,115 @@ pub struct Cli {
     #[arg(long)]
     pub benchmark: bool,
 
+    /// 基准结果落盘为 JSON（机器可比对，可作下次 --benchmark-baseline 的基线）；隐含 --benchmark。
+    /// 未给任何输入文件时自动使用内置生成语料（完全离线，适合 CI）
+    #[arg(long, value_name = "FILE")]
+    pub benchmark_json: Option<PathBuf>,
+
+    /// 与已保存的基线 JSON 对比：体积增长或 SSIM 下降超出 --max-regression 容差即退出码 1；隐含 --benchmark
+    #[arg(long, value_name = "FILE")]
+    pub benchmark_baseline: Option<PathBuf>,
+
+    /// 回归容差（百分比），如 "1%" 或 "0.5"（=0.5%），默认 1%
+    #[arg(long, value_name = "PCT", default_value = "1%", value_parser = parse_percent)]
+    pub max_regression: f64,
+
     #[arg(value_name = "FILE/DIR")]
     pub positional: Vec<PathBuf>,
 }
 
+/// --crop-aspect 校验（clap value_parser）：原样保留字符串，非法画幅在解析期报错
+fn parse_aspect_arg(s: &str) -> Result<String, String> {
+    xtap_compress::crop::parse_aspect(s)
+        .map(|_| s.trim().to_string())
+        .ok_or_else(|| format!("无法解析画幅 '{}'（示例：3:4 / 4:5 / 1:1）", s))
+}
+
+/// --grid 校验（clap value_parser）
+fn parse_grid_arg(s: &str) -> Result<String, String> {
+    xtap_compress::grid::GridSpec::parse(s, 0)
+        .map(|_| s.trim().to_string())
+        .ok_or_else(|| format!("无法解析九宫格 '{}'（示例：3x3 / 2x2 / 1x3，最多 9 片）", s))
+}
+
+/// --print-size 校验（clap value_parser）
+fn parse_print_size_arg(s: &str) -> Result<String, String> {
+    xtap_compress::print::PrintSize::parse(s)
+        .map(|_| s.trim().to_string())
+        .ok_or_else(|| {
+            format!(
+                "无法解析冲印尺寸 '{}'（示例：A4@200 / 6x4in@300 / 15x10cm@300，DPI 50–2400）",
+                s
+            )
+        })
+}
+
+/// --srcset 校验（clap value_parser）
+fn parse_srcset_arg(s: &str) -> Result<String, String> {
+    xtap_compress::srcset::parse_widths(s)
+        .map(|_| s.trim().to_string())
+        .ok_or_else(|| format!("无法解析宽度档 '{}'（示例：320,640,1280,2560）", s))
+}
+
+/// --srcset-formats 校验（clap value_parser）
+fn parse_srcset_formats_arg(s: &str) -> Result<String, String> {
+    xtap_compress::srcset::parse_formats(s)
+        .map(|_| s.trim().to_string())
+        .ok_or_else(|| format!("无法解析格式 '{}'（可选 jpeg / webp，逗号组合）", s))
+}
+
+/// --pad-canvas 校验（clap value_parser）
+fn parse_pad_canvas_arg(s: &str) -> Result<String, String> {
+    xtap_compress::pad::PadCanvas::parse(s)
+        .map(|_| s.trim().to_string())
+        .ok_or_else(|| {
+            format!(
+                "无法解析画布 '{}'（示例：1080x1350 / 1:1；像素画布不超过 1 亿像素）",
+                s
+            )
+        })
+}
+
+/// --svg-size 校验（clap value_parser）
+#[cfg(feature = "svg")]
+fn parse_svg_size_arg(s: &str) -> Result<String, String> {
+    xtap_compress::svg::SvgSize::parse(s)
+        .map(|_| s.trim().to_string())
+        .ok_or_else(|| format!("无法解析 SVG 尺寸 '{}'（示例：2048 / 300dpi）", s))
+}
+
+/// --svg-background 校验（clap value_parser）
+#[cfg(feature = "svg")]
+fn parse_svg_background_arg(s: &str) -> Result<String, String> {
+    xtap_compress::svg::parse_background(s)
+        .map(|_| s.trim().to_string())
+        .ok_or_else(|| {
+            format!(
+                "无法解析 SVG 底色 '{}'（transparent / white / black / #RRGGBB）",
+                s
+            )
+        })
+}
+
+/// --pad-fill 校验（clap value_parser）
+fn parse_pad_fill_arg(s: &str) -> Result<String, String> {
+    xtap_compress::pad::PadFill::parse(s)
+        .map(|_| s.trim().to_string())
+        .ok_or_else(|| format!("无法解析底色 '{}'（示例：blur / #FFFFFF / white）", s))
+}
+
+/// 解析百分比容差："1%" / "1" → 0.01；"0.5%" → 0.005。返回小数比例（clap value_parser）
+pub fn parse_percent(s: &str) -> Result<f64, String> {
+    let t = s.trim().trim_end_matches('%').trim();
+    let v: f64 = t
+        .parse()
+        .map_err(|_| format!("无法解析百分比 '{}'（示例：1% 或 0.5）", s))?;
+    if !v.is_finite() || v < 0.0 {
+        return Err(format!("百分比必须为非负数: '{}'", s));
+    }
+    Ok(v / 100.0)
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
 pub enum CliProcessMode {
     WeChat,
@@ -224,6 +586,7 @@ pub enum CliOutputFormat {
     Jpeg,
     KeepOriginal,
     WebP,
+    Jxl,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
@@ -264,27 +627,6 @@ impl From<CliQuantMode> for xtap_compress::perceptual::QuantMode {
     }
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
-pub enum CliPlatform {
-    Wechat,
-    WechatNew,
-    Xiaohongshu,
-    Instagram,
-    General,
-}
-
-impl CliPlatform {
-    pub fn as_str(self) -> &'static str {
-        match self {
-            CliPlatform::Wechat => "wechat",
-            CliPlatform::WechatNew => "wechat-new",
-            CliPlatform::Xiaohongshu => "xiaohongshu",
-            CliPlatform::Instagram => "instagram",
-            CliPlatform::General => "general",
-        }
-    }
-}
-
 #[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
 pub enum CliQualityMode {
     /// 小而美：感知压缩（CSF 量化表），同体积画质更好
@@ -325,42 +667,253 @@ impl CliUsageMode {
     }
 }
 
-/// 平台阈值预设（蓝图 §2 实测表，2025–2026）
-/// 返回：(长边 px 上限, 本地 JPEG Q, 体积安全线 KB, 强制 sRGB)
-/// 本地 Q 必须 ≥80（平台二压吞 5–10 点，本地留余地）；强制 sRGB 防社交 CDN 扁平化。
-pub fn platform_preset(platform: &str) -> Option<(u32, u8, u32, bool)> {
-    match platform.to_lowercase().as_str() {
-        // 保守：短边≤1080 用长边上限 1080 直接卡死，兼容所有微信版本
-        "wechat" => Some((1080, 92, 900, true)),
-        // iOS 8.0.64+ 宽幅：长边≤2560，体积线放宽到 2000KB
-        "wechat-new" => Some((2560, 90, 2000, true)),
-        // 竖版 3:4：高≤1660（宽1242），体积线 4500KB（官方单图≤5MB）
-        "xiaohongshu" => Some((1660, 92, 4500, true)),
-        // 4:5：宽≤1080（长边封顶 1080 即保证宽≤1080），体积线建议≤1000KB
-        "instagram" => Some((1080, 90, 1000, true)),
-        // 通用（中画幅/网盘/非社交渠道）：长边 2560、Q92、2MB 线；不强转 sRGB（保留原色域）
-        "general" => Some((2560, 92, 2000, false)),
-        _ => None,
+/// v4.5：多预设扇出的输出区分方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum CliFanoutLayout {
+    /// 文件名后缀：photo_wechat.jpg / photo_archive.jpg
+    Suffix,
+    /// 子目录：wechat/photo_wx.jpg / archive/photo_hd.jpg
+    Subdir,
+}
+
+impl CliFanoutLayout {
+    pub fn parse(s: &str) -> Option<Self> {
+        match s.trim().to_lowercase().as_str() {
+            "suffix" => Some(CliFanoutLayout::Suffix),
+            "subdir" => Some(CliFanoutLayout::Subdir),
+            _ => None,
+        }
+    }
+}
+
+/// v4.5：拆分逗号分隔的平台预设列表（小写、去空白、去重保序）
+pub fn split_platforms(s: &str) -> Vec<String> {
+    let mut out: Vec<String> = Vec::new();
+    for p in s.split(',').map(|p| p.trim().to_lowercase()) {
+        if !p.is_empty() && !out.contains(&p) {
+            out.push(p);
+        }
     }
+    out
 }
 
-/// v4.4.0 画质优先预设表（quality_mode="max"，防二压画质优先）
-/// 核心思想：卡住平台二压阈值，把画质预算顶到天花板——Q96 起步 + 4:4:4 色度全保留，
+/// v4.5：扇出目标名单——多个平台预设，或平台预设 + archive。单目标（旧语义）返回 None
+pub fn fanout_target_names(platforms: &[String], archive: bool) -> Option<Vec<String>> {
+    let targets = platforms.len() + usize::from(archive && !platforms.is_empty());
+    if targets < 2 {
+        return None;
+    }
+    let mut names = platforms.to_vec();
+    if archive {
+        names.push("archive".to_string());
+    }
+    Some(names)
+}
+
+// ============================================================================
+// 平台预设注册表（内置 + 系统/用户 presets.toml 合并）
+// ============================================================================
+
+/// 平台预设：内置表为默认值，系统/用户 presets.toml（表名=预设名）按字段覆盖或新增。
+/// 平台改限制（微博/抖音/知乎/X/Telegram/内部 CMS）时改配置即可，无需等发版。
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PlatformPreset {
+    /// 显示名（GUI 下拉框 / capabilities），缺省用预设名
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub label: Option<String>,
+    /// 长边 px 上限
+    pub max_dim: u32,
+    /// 本地 JPEG Q（普通/感知档）
+    pub quality: u8,
+    /// 体积安全线 KB
+    pub budget_kb: u32,
+    /// 强制转 sRGB（社交 CDN 会扁平化色域）
+    #[serde(default = "default_true")]
+    pub srgb: bool,
+    /// 画质优先档（quality_mode=max）Q 起步，缺省沿用 quality
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_quality: Option<u8>,
+    /// 画质优先档体积安全线 KB，缺省沿用 budget_kb
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_budget_kb: Option<u32>,
+    /// 色度子采样 420/422/444，缺省跟随画质档（max=444，其余 420）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub subsampling: Option<String>,
+    /// CAS 锐化强度 0-1，缺省跟随画质档（max=0.35，其余 0）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cas_strength: Option<f32>,
+    /// 输出格式 jpeg/webp/original（仅替换默认 jpeg，显式 --output-format 优先）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub format: Option<String>,
+    /// 画幅约束（如 "3:4" / "4:5"），供裁剪/补边使用
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub aspect: Option<String>,
+    /// 补边画布（"1080x1350" 精确像素 / "1:1" 画幅比），缺省用 aspect
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pad_canvas: Option<String>,
+    /// 补边底色（blur / #RRGGBB / white / black），缺省 blur
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pad_fill: Option<String>,
+}
+
+fn default_true() -> bool {
+    true
+}
+
+impl PlatformPreset {
+    fn builtin(label: &str, max_dim: u32, quality: u8, budget_kb: u32, srgb: bool) -> Self {
+        Self {
+            label: Some(label.to_string()),
+            max_dim,
+            quality,
+            budget_kb,
+            srgb,
+            max_quality: None,
+            max_budget_kb: None,
+            subsampling: None,
+            cas_strength: None,
+            format: None,
+            aspect: None,
+            pad_canvas: None,
+            pad_fill: None,
+        }
+    }
+
+    fn with_max(mut self, quality: u8, budget_kb: u32) -> Self {
+        self.max_quality = Some(quality);
+        self.max_budget_kb = Some(budget_kb);
+        self
+    }
+}
+
+/// 内置预设（蓝图 §2 实测表，2025–2026）。
+/// 本地 Q 必须 ≥80（平台二压吞 5–10 点，本地留余地）；强制 sRGB 防社交 CDN 扁平化。
+/// v4.4.0 画质优先档（max_*）：卡住平台二压阈值，Q96 起步 + 4:4:4 色度全保留，
 /// 体积只是安全线（超线才二分降 Q），**不为压小而压小**。
+pub fn builtin_presets() -> BTreeMap<String, PlatformPreset> {
+    let mut m = BTreeMap::new();
+    // 保守：短边≤1080 用长边上限 1080 直接卡死，兼容所有微信版本
+    m.insert(
+        "wechat".to_string(),
+        PlatformPreset::builtin("微信 (全版本)", 1080, 92, 900, true).with_max(96, 900),
+    );
+    // iOS 8.0.64+ 宽幅：长边≤2560，体积线放宽到 2000KB
+    m.insert(
+        "wechat-new".to_string(),
+        PlatformPreset::builtin("微信-new (iOS 新)", 2560, 90, 2000, true).with_max(95, 2000),
+    );
+    // 竖版 3:4：高≤1660（宽1242），体积线 4500KB（官方单图≤5MB）
+    let mut xhs = PlatformPreset::builtin("小红书", 1660, 92, 4500, true).with_max(96, 4500);
+    xhs.aspect = Some("3:4".to_string());
+    m.insert("xiaohongshu".to_string(), xhs);
+    // 4:5：宽≤1080（长边封顶 1080 即保证宽≤1080），体积线建议≤1000KB
+    let mut ig = PlatformPreset::builtin("Instagram", 1080, 90, 1000, true).with_max(95, 1000);
+    ig.aspect = Some("4:5".to_string());
+    m.insert("instagram".to_string(), ig);
+    // 通用（中画幅/网盘/非社交渠道）：长边 2560、Q92、2MB 线（画质优先 4.9MB）；不强转 sRGB（保留原色域）
+    m.insert(
+        "general".to_string(),
+        PlatformPreset::builtin("通用 (中画幅)", 2560, 92, 2000, false).with_max(96, 4900),
+    );
+    m
+}
+
+/// 预设文件搜索路径（低 → 高优先级）：系统级、用户级；--presets 显式文件最后合并
+pub fn preset_search_paths() -> Vec<PathBuf> {
+    let mut paths = Vec::new();
+    #[cfg(unix)]
+    paths.push(PathBuf::from("/etc/xtap_compress/presets.toml"));
+    #[cfg(windows)]
+    if let Some(pd) = std::env::var_os("ProgramData") {
+        paths.push(PathBuf::from(pd).join("xtap_compress").join("presets.toml"));
+    }
+    if let Some(dir) = dirs::config_dir() {
+        paths.push(dir.join("xtap_compress").join("presets.toml"));
+    }
+    paths
+}
+
+/// 把一份 presets.toml 按字段合并进注册表：已有预设只覆盖写出的字段，新名字需给全必填字段
+pub fn merge_presets_toml(
+    presets: &mut BTreeMap<String, PlatformPreset>,
+    text: &str,
+) -> Result<(), String> {
+    let table: toml::Table = toml::from_str(text).map_err(|e| e.to_string())?;
+    // 先在副本上合并，整份文件有效才提交（不留半成品）
+    let mut next = presets.clone();
+    for (name, value) in table {
+        let name = name.to_lowercase();
+        let toml::Value::Table(overrides) = value else {
+            return Err(format!("[{}] 必须是表", name));
+        };
+        let mut merged = match next.get(&name) {
+            Some(base) => toml::Table::try_from(base).map_err(|e| e.to_string())?,
+            None => toml::Table::new(),
+        };
+        merged.extend(overrides);
+        let preset: PlatformPreset = toml::Value::Table(merged)
+            .try_into()
+            .map_err(|e| format!("[{}] {}", name, e))?;
+        next.insert(name, preset);
+    }
+    *presets = next;
+    Ok(())
+}
+
+/// 加载合并后的预设集：内置 → 系统 → 用户 → extra。
+/// 自动发现的文件出错只告警跳过（不因坏配置打不开工具）；显式 extra 出错返回 Err。
+pub fn load_presets(extra: Option<&Path>) -> Result<BTreeMap<String, PlatformPreset>, String> {
+    let mut presets = builtin_presets();
+    for path in preset_search_paths() {
+        if let Ok(text) = std::fs::read_to_string(&path) {
+            if let Err(e) = merge_presets_toml(&mut presets, &text) {
+                eprintln!("[WARN] 平台预设文件无效，已忽略 {}: {}", path.display(), e);
+            }
+        }
+    }
+    if let Some(path) = extra {
+        let text = std::fs::read_to_string(path)
+            .map_err(|e| format!("平台预设文件读取失败 {}: {}", path.display(), e))?;
+        merge_presets_toml(&mut presets, &text)
+            .map_err(|e| format!("平台预设文件无效 {}: {}", path.display(), e))?;
+    }
+    Ok(presets)
+}
+
+static PRESETS: OnceLock<BTreeMap<String, PlatformPreset>> = OnceLock::new();
+
+/// 启动时（CLI 解析后）初始化注册表；必须在首次 presets() 之前调用，否则 extra 不生效
+pub fn init_presets(extra: Option<&Path>) -> Result<(), String> {
+    let loaded = load_presets(extra)?;
+    let _ = PRESETS.set(loaded);
+    Ok(())
+}
+
+/// 合并后的预设集（GUI 未显式初始化时按内置 + 系统/用户文件惰性加载）
+pub fn presets() -> &'static BTreeMap<String, PlatformPreset> {
+    PRESETS.get_or_init(|| load_presets(None).unwrap_or_else(|_| builtin_presets()))
+}
+
+/// 平台阈值预设（普通/感知档）
+/// 返回：(长边 px 上限, 本地 JPEG Q, 体积安全线 KB, 强制 sRGB)
+pub fn platform_preset(platform: &str) -> Option<(u32, u8, u32, bool)> {
+    presets()
+        .get(&platform.to_lowercase())
+        .map(|p| (p.max_dim, p.quality, p.budget_kb, p.srgb))
+}
+
+/// v4.4.0 画质优先预设（quality_mode="max"，防二压画质优先）
 /// 返回：(长边 px, Q 起步, 体积安全线 KB, 强制 sRGB)
 pub fn platform_preset_max(platform: &str) -> Option<(u32, u8, u32, bool)> {
-    match platform.to_lowercase().as_str() {
-        // 微信保守档（默认）：1080 全版本兼容，900KB 线内 Q96+444 色彩饱满
-        "wechat" => Some((1080, 96, 900, true)),
-        // iOS 新宽幅：2560 长边，2MB 线
-        "wechat-new" => Some((2560, 95, 2000, true)),
-        // 小红书：官方单图 ≤5MB，竖版 1242×1660 主流 → 长边 1660 + 4.5MB 安全线
-        "xiaohongshu" => Some((1660, 96, 4500, true)),
-        "instagram" => Some((1080, 95, 1000, true)),
-        // 通用：2560 长边 + 4.9MB 线，不强转 sRGB
-        "general" => Some((2560, 96, 4900, false)),
-        _ => None,
-    }
+    presets().get(&platform.to_lowercase()).map(|p| {
+        (
+            p.max_dim,
+            p.max_quality.unwrap_or(p.quality),
+            p.max_budget_kb.unwrap_or(p.budget_kb),
+            p.srgb,
+        )
+    })
 }
 
 /// v4.4.0 统一收口：按 quality_mode 选预设表并应用到 AppConfig。
@@ -386,6 +939,35 @@ pub fn apply_platform_preset(cfg: &mut xtap_compress::AppConfig, platform: &str)
         cfg.subsampling = "444".to_string();
         cfg.cas_strength = 0.35;
     }
+    // 预设显式写出的字段覆盖画质档默认值
+    if let Some(p) = presets().get(&platform.to_lowercase()) {
+        // 智能裁剪：未显式指定画幅时用预设画幅
+        if cfg.smart_crop && cfg.crop_aspect.is_none() {
+            cfg.crop_aspect = p.aspect.clone();
+        }
+        // 补边：未显式指定画布/底色时用预设值（画布缺省退回预设画幅）
+        if cfg.pad {
+            if cfg.pad_canvas.is_none() {
+                cfg.pad_canvas = p.pad_canvas.clone().or_else(|| p.aspect.clone());
+            }
+            if cfg.pad_fill.is_none() {
+                cfg.pad_fill = p.pad_fill.clone();
+            }
+        }
+        if let Some(ref sub) = p.subsampling {
+            cfg.subsampling = sub.to_lowercase();
+        }
+        if let Some(cas) = p.cas_strength {
+            cfg.cas_strength = cas.clamp(0.0, 1.0);
+        }
+        if cfg.output_format == OutputFormat::Jpeg {
+            match p.format.as_deref().map(str::to_lowercase).as_deref() {
+                Some("webp") => cfg.output_format = OutputFormat::WebP,
+                Some("original") | Some("keep") => cfg.output_format = OutputFormat::KeepOriginal,
+                _ => {}
+            }
+        }
+    }
 }
 
 // ============================================================================
@@ -444,6 +1026,10 @@ pub struct JsonInput {
     pub quality_ceil: Option<u8>,
     /// 平台阈值预设：wechat / wechat-new / xiaohongshu / instagram（自动填长边/体积/Q 并强制 sRGB）
     pub platform: Option<String>,
+    /// 多预设扇出：["wechat", "xiaohongshu"]（源图解码一次，每个预设各出一份；usage_mode=archive 再加存档）
+    pub platforms: Option<Vec<String>>,
+    /// 扇出输出区分：suffix（默认）/ subdir
+    pub fanout_layout: Option<String>,
     /// 用途预设：social(社交分享) / archive(高清存档) / custom(自定义)。GUI 用；CLI 可省略
     pub usage_mode: Option<String>,
     /// 画质模式：perceptual(小而美感知压缩) / normal(普通标准压缩) / max(v4.4.0 防二压画质优先)
@@ -464,6 +1050,85 @@ pub struct JsonInput {
     pub quality_first: Option<bool>,
     /// CAS 锐化补偿强度 0.0-1.0（内容自适应、无光晕；画质优先档默认 0.35，其他档默认 0）
     pub cas_strength: Option<f32>,
+
+    // ========== v4.5 画幅 ==========
+    /// 智能裁剪到画幅（crop_aspect 缺省取平台预设 aspect）
+    pub smart_crop: Option<bool>,
+    /// 智能裁剪目标画幅，如 "3:4"（隐含 smart_crop）
+    pub crop_aspect: Option<String>,
+    /// 补边到画布（与 smart_crop 二选一，补边优先）
+    pub pad: Option<bool>,
+    /// 补边画布："1080x1350" 精确像素 / "1:1" 画幅比（隐含 pad）
+    pub pad_canvas: Option<String>,
+    /// 补边底色：blur / #RRGGBB / white / black
+    pub pad_fill: Option<String>,
+    /// 九宫格切图："3x3" / "2x2" / "1x3"（列x行，最多 9 片）
+    pub grid: Option<String>,
+    /// 九宫格切片间缝宽（像素）
+    pub grid_gutter: Option<u32>,
+    /// 长图分页页高（输出像素，与 grid 互斥、grid 优先）
+    pub slice_height: Option<u32>,
+    /// 冲印尺寸："A4@200" / "6x4in@300" / "15x10cm"（仅单图输出，与 grid/slice_height 互斥）
+    pub print_size: Option<String>,
+    /// 响应式图片组宽度档（如 [320, 640, 1280, 2560]）
+    pub srcset: Option<Vec<u32>>,
+    /// 响应式图片组格式（["jpeg", "webp"]，缺省跟随 output_format）
+    pub srcset_formats: Option<Vec<String>>,
+    /// 响应式图片组 <img sizes> 属性（缺省 100vw）
+    pub srcset_sizes: Option<String>,
+    /// 低清占位图（BlurHash + 主色 + base64 缩略图）
+    pub placeholder: Option<bool>,
+    /// 占位缩略图格式：jpeg（默认）/ webp
+    pub placeholder_format: Option<String>,
+    /// 占位缩略图长边（px，默认 32，上限 32）
+    pub placeholder_size: Option<u32>,
+    /// 图标组模式（favicon.ico + 各尺寸 PNG + site.webmanifest，取代压缩输出）
+    pub icon: Option<bool>,
+    /// 图标留白，占边长比例（0-0.4）
+    pub icon_padding: Option<f32>,
+    /// 图标圆角半径，占边长比例（0-0.5）
+    pub icon_radius: Option<f32>,
+    /// 图标底色 #RRGGBB（缺省透明）
+    pub icon_background: Option<String>,
+    /// 外部解码器：[{name?, extensions, command: [程序, 参数…], output_ext?, timeout_secs?}]
+    pub external_decoders: Option<Vec<ExternalDecoder>>,
+    /// 外部解码器缺省超时（秒，默认 60）
+    pub decoder_timeout_secs: Option<u64>,
+    /// 外部解码工具并发上限（默认 4）
+    pub decoder_concurrency: Option<usize>,
+    /// JXL 输出时 JPEG 输入无损重压缩（需 libjxl 的 cjxl）
+    pub jxl_lossless_jpeg: Option<bool>,
+    /// 动图只取首帧（默认逐帧处理）
+    pub first_frame_only: Option<bool>,
+    /// 动图输出格式 auto / gif / webp
+    pub animation_format: Option<String>,
+    /// 动图最多保留帧数（默认 300，0 = 不限）
+    pub animation_max_frames: Option<u32>,
+    /// 动图最长总时长（毫秒，默认 60000，0 = 不限）
+    pub animation_max_duration_ms: Option<u32>,
+    /// SVG 栅格化（默认关闭，SVG 按不支持格式处理）
+    pub svg_rasterize: Option<bool>,
+    /// SVG 渲染尺寸：长边像素 "2048" 或 "300dpi"
+    pub svg_size: Option<String>,
+    /// SVG 底色 transparent / white / black / #RRGGBB
+    pub svg_background: Option<String>,
+    /// 高位深源抖动 blue-noise / ordered / none
+    pub dither: Option<String>,
+    /// 浮点源色调映射 none / reinhard / aces
+    pub tone_map: Option<String>,
+    /// 多页 TIFF 取页 first / all / largest
+    pub tiff_pages: Option<String>,
+    /// 批量总体积预算（KB）：按复杂度分配单文件预算并重平衡，data.batch_budget 报告实际合计
+    pub batch_budget_kb: Option<u32>,
+    /// 水印：{image|text+font, color, position, margin, opacity, scale}
+    pub watermark: Option<xtap_compress::watermark::WatermarkConfig>,
+    /// 联系表：{path, columns, cell, per_page, font}
+    pub contact_sheet: Option<ContactSheetConfig>,
+
+    /// 平台二压模拟：结果追加 platform_sim（过平台后相对原图的 SSIM/PSNR）
+    pub simulate_platform: Option<String>,
+    /// 平台二压模型覆盖 TOML 路径
+    pub simulate_config: Option<String>,
 }
 
 // ============================================================================
@@ -497,6 +1162,12 @@ pub struct JsonOutputData {
     /// v4.3.1：输入→输出映射清单（含未压缩项），便于 agent 回映射源目录
     #[serde(skip_serializing_if = "Vec::is_empty")]
     pub manifest: Vec<ManifestEntry>,
+    /// v4.5：批量总预算执行报告（仅给出 batch_budget_kb 时输出）
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub batch_budget: Option<BatchBudgetReport>,
+    /// v4.5：联系表各页路径（仅给出 contact_sheet 时输出）
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub contact_sheets: Vec<String>,
 }
 
 /// 输入→输出映射项（v4.3.1），便于调用方回映射源目录
@@ -508,6 +1179,17 @@ pub struct ManifestEntry {
     pub output: Option<String>,
     /// compressed | skipped | passthrough | failed
     pub status: String,
+    /// 九宫格切片序号（1 起，即上传顺序；非切图缺省）
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tile: Option<u32>,
+    /// v4.5：多页 TIFF 源页码（1 起）与源文件总页数（非多页 TIFF 缺省）
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub page: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub page_count: Option<u32>,
+    /// 低清占位图（启用 placeholder 时；切图只随第 1 片）
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub placeholder: Option<Placeholder>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Default)]
@@ -533,6 +1215,14 @@ pub struct JsonOutput {
     pub results: Vec<FileResult>,
 }
 
+/// v4.5：多预设扇出中单个目标的结果（target = 预设名 / archive）
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct FanoutOutput {
+    pub target: String,
+    #[serde(flatten)]
+    pub result: FileResult,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
 #[allow(dead_code)]
 pub struct FileResult {
@@ -556,6 +1246,39 @@ pub struct FileResult {
     /// v4.2.0-exp 感知压缩指标（perceptual=None 时缺省，向下兼容）；字段缺省不输出
     #[serde(skip_serializing_if = "Option::is_none")]
     pub perceptual: Option<PerceptualMetricsOut>,
+    /// 智能裁剪窗口（原图坐标，仅发生裁剪时输出）
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub crop: Option<CropRect>,
+    /// 补边画布与原图放置区域（仅发生补边时输出）
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pad: Option<PadPlacement>,
+    /// 九宫格切片 / 长图分页（按上传顺序；output 为第 1 片，compressed_size 为全部切片合计）
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tiles: Option<Vec<TileOutput>>,
+    /// 冲印尺寸换算（像素 / 写入 DPI / 实际印刷英寸 / 源图密度，仅冲印模式输出）
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub print: Option<PrintFit>,
+    /// 响应式图片组（全部变体、<picture> 片段与清单路径，仅 srcset 模式输出；compressed_size 为全部变体合计）
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub srcset: Option<SrcsetManifest>,
+    /// v4.5：低清占位图（BlurHash / 主色 / ≤32px base64 缩略图；多输出时取第一个输出）
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub placeholder: Option<Placeholder>,
+    /// v4.5：图标组（全部文件、site.webmanifest 内容与 <head> 片段；compressed_size 为全部文件合计）
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub icons: Option<IconSet>,
+    /// v4.5：动图输出（格式、尺寸、源帧数 / 写出帧数、总时长、是否截断）
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub animation: Option<AnimationInfo>,
+    /// v4.5：多页 TIFF（总页数、未输出的整页数与各输出页；多页输出时 compressed_size 为全部页合计）
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tiff_pages: Option<TiffPages>,
+    /// 多预设扇出：每个目标一份结果（output 为第一份，compressed_size 为全部成功输出合计）
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub outputs: Option<Vec<FanoutOutput>>,
+    /// 平台二压模拟结果（仅 --simulate-platform 时输出）
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub platform_sim: Option<PlatformSimResult>,
 }
 
 /// 感知压缩单文件指标（§5.3，输出到 FileResult.perceptual；旧字段缺失时整块缺省）
@@ -598,6 +1321,103 @@ pub struct StepTimings {
     pub encode_ms: u64,
 }
 
+// ============================================================================
+// 基准报告（--benchmark-json 落盘 / --benchmark-baseline 回归门禁）
+// ============================================================================
+
+/// 基准报告：一次 --benchmark 运行的机器可比对结果
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BenchmarkReport {
+    pub schema_version: String,
+    /// 固定 "benchmark"
+    pub command: String,
+    pub version: String,
+    pub entries: Vec<BenchmarkEntry>,
+}
+
+/// 单文件基准项（old = v4.1.0 旧路径，new = 感知路径）；SSIM/PSNR 无法计算时为 null
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct BenchmarkEntry {
+    /// 文件名（不含目录，跨机器 / 临时目录可比对）
+    pub file: String,
+    pub old_bytes: u64,
+    pub new_bytes: u64,
+    pub old_ssim: Option<f64>,
+    pub new_ssim: Option<f64>,
+    pub old_psnr: Option<f64>,
+    pub new_psnr: Option<f64>,
+    pub new_quality: u8,
+    /// 耗时仅供参考，不参与回归判定（机器负载波动大）
+    pub elapsed_ms: u64,
+}
+
+impl BenchmarkReport {
+    pub fn new(entries: Vec<BenchmarkEntry>) -> Self {
+        Self {
+            schema_version: "1.0".to_string(),
+            command: "benchmark".to_string(),
+            version: env!("CARGO_PKG_VERSION").to_string(),
+            entries,
+        }
+    }
+}
+
+/// 对比基线与本次结果，返回全部超出容差的回归描述（空 = 通过）。
+/// 判定：体积增长 > tolerance 或 SSIM 相对下降 > tolerance；基线有而本次缺失的文件/指标同样记为回归。
+/// tolerance 为小数比例（0.01 = 1%）。
+pub fn benchmark_regressions(
+    baseline: &BenchmarkReport,
+    current: &BenchmarkReport,
+    tolerance: f64,
+) -> Vec<String> {
+    let mut out = Vec::new();
+    for base in &baseline.entries {
+        let cur = match current.entries.iter().find(|e| e.file == base.file) {
+            Some(c) => c,
+            None => {
+                out.push(format!("{}: 基线中存在，本次未产出结果", base.file));
+                continue;
+            }
+        };
+        for (label, b, c) in [
+            ("old", base.old_bytes, cur.old_bytes),
+            ("new", base.new_bytes, cur.new_bytes),
+        ] {
+            if b > 0 && c as f64 > b as f64 * (1.0 + tolerance) {
+                out.push(format!(
+                    "{}: {} 体积 {} → {} bytes（+{:.2}%）",
+                    base.file,
+                    label,
+                    b,
+                    c,
+                    (c as f64 / b as f64 - 1.0) * 100.0
+                ));
+            }
+        }
+        for (label, b, c) in [
+            ("old", base.old_ssim, cur.old_ssim),
+            ("new", base.new_ssim, cur.new_ssim),
+        ] {
+            match (b, c) {
+                (Some(b), Some(c)) if c < b * (1.0 - tolerance) => out.push(format!(
+                    "{}: {} SSIM {:.4} → {:.4}（-{:.2}%）",
+                    base.file,
+                    label,
+                    b,
+                    c,
+                    (1.0 - c / b) * 100.0
+                )),
+                (Some(b), None) => out.push(format!(
+                    "{}: {} SSIM {:.4} → 无法计算（输出缺失或解码失败）",
+                    base.file, label, b
+                )),
+                _ => {}
+            }
+        }
+    }
+    out
+}
+
 // ============================================================================
 // 能力探测（--capabilities 输出）
 // ============================================================================
@@ -612,6 +1432,8 @@ pub struct Capabilities {
     pub json_input_schema: serde_json::Value,
     pub cli_parameters: Vec<CliParamDoc>,
     pub json_output_envelope: serde_json::Value,
+    /// 合并后的平台预设全集（内置 + 系统/用户 presets.toml + --presets）
+    pub platform_presets: BTreeMap<String, PlatformPreset>,
     pub notes: Vec<String>,
 }
 
@@ -629,6 +1451,7 @@ pub struct CliParamDoc {
 
 /// 生成 --capabilities 的完整参数 schema
 pub fn build_capabilities() -> Capabilities {
+    let preset_names: Vec<String> = presets().keys().cloned().collect();
     let _json_example = serde_json::json!({
         "files": ["照片1.jpg", "照片2.png"],
         "mode": "custom",
@@ -663,6 +1486,7 @@ pub fn build_capabilities() -> Capabilities {
         runtime: serde_json::json!({
             "cpu_count": cpu_count,
             "recommended_max_workers": cpu_count,
+            "optional_features": {"fonts": cfg!(feature = "fonts"), "heic": cfg!(feature = "heic"), "jxl": cfg!(feature = "jxl"), "svg": cfg!(feature = "svg")},
             "description": "默认并行数=cpu_count；服务器共享负载建议 max_workers 设为 cpu_count/2"
         }),
         json_input_schema: serde_json::json!({
@@ -676,7 +1500,7 @@ pub fn build_capabilities() -> Capabilities {
                 "target_kb": {"type": "integer", "default": 0, "description": "目标体积 KB，0=不限"},
                 "overwrite": {"type": "boolean", "default": false, "description": "覆盖原文件"},
                 "keep_original_name": {"type": "boolean", "default": false, "description": "保留原文件名（不加后缀）"},
-                "output_format": {"type": "string", "enum": ["jpeg", "original", "webp"], "default": "jpeg", "description": "输出格式（webp 更省体积、支持透明）"},
+                "output_format": {"type": "string", "enum": ["jpeg", "original", "webp", "jxl"], "default": "jpeg", "description": "输出格式（webp 更省体积、支持透明；jxl 需 libjxl 的 cjxl 在 PATH 中）"},
                 "output_dir": {"type": "string", "default": null, "description": "输出目录，未指定时默认 ./compressed/"},
                 "enable_sharpening": {"type": "boolean", "default": false, "description": "启用智能自适应锐化"},
                 "sharpening_radius": {"type": "number", "default": 1.0, "description": "锐化半径"},
@@ -697,10 +1521,75 @@ pub fn build_capabilities() -> Capabilities {
                 "focus_mode": {"type": "string", "enum": ["auto", "center"], "default": "auto", "description": "锐化焦点：auto=显著性检测 / center=中心权重"},
                 "target_budget_kb": {"type": "integer", "default": null, "description": "覆盖平台默认体积安全线（KB），触发质量二分搜索"},
                 "quality_ceil": {"type": "integer", "min": 1, "max": 100, "default": 95, "description": "感知模式质量上限，防止过度堆质量爆体积"},
-                "platform": {"type": "string", "enum": ["wechat", "wechat-new", "xiaohongshu", "instagram"], "default": null, "description": "平台阈值预设，自动填长边/体积/Q 并强制 sRGB"},
+                "platforms": {"type": "array", "items": {"type": "string", "enum": preset_names}, "default": null, "description": "多预设扇出：源图解码一次，每个预设各出一份（usage_mode=archive 时再加一份高清存档）；结果 outputs 按目标列出，output 为第一份。与 overwrite / batch_budget_kb 不兼容"},
+                "fanout_layout": {"type": "string", "enum": ["suffix", "subdir"], "default": "suffix", "description": "扇出输出区分：suffix=文件名后缀 _<预设名>（photo_wechat.jpg）/ subdir=输出目录下按预设分子目录"},
+                "platform": {"type": "string", "enum": preset_names, "default": null, "description": "平台阈值预设（内置 + presets.toml 自定义，详见 platform_presets），自动填长边/体积/Q 并强制 sRGB"},
                 "preserve_structure": {"type": "boolean", "default": false, "description": "输出时保留源目录相对路径（默认拍平到 output_dir）"},
                 "output_suffix": {"type": "string", "default": null, "description": "自定义输出后缀（覆盖默认 _wx/_hd/_da；空串=无后缀）"},
-                "passthrough_unsupported": {"type": "boolean", "default": false, "description": "不支持的格式（如 SVG）原样透传复制，不报失败"}
+                "passthrough_unsupported": {"type": "boolean", "default": false, "description": "不支持的格式（如 SVG）原样透传复制，不报失败"},
+                "smart_crop": {"type": "boolean", "default": false, "description": "智能裁剪到画幅（显著性+肤色定位主体），crop_aspect 缺省取平台预设 aspect；结果 crop 字段报告裁剪窗口"},
+                "crop_aspect": {"type": "string", "default": null, "description": "智能裁剪目标画幅，如 3:4 / 4:5 / 1:1（隐含 smart_crop）"},
+                "grid": {"type": "string", "default": null, "description": "九宫格切图：3x3 / 2x2 / 1x3（列x行，最多 9 片），裁剪/补边之后均分，每片独立压缩；结果 tiles 列出切片，manifest 每片一条（tile=上传序号）"},
+                "grid_gutter": {"type": "integer", "default": 0, "description": "九宫格切片间缝宽（像素）"},
+                "watermark": {
+                    "type": "object",
+                    "default": null,
+                    "description": "水印（缩放后、锐化与编码前叠加；尺寸/边距相对输出短边）。image 与 text 二选一（image 优先），text 需 font",
+                    "properties": {
+                        "image": {"type": "string", "description": "PNG 标志路径"},
+                        "text": {"type": "string", "description": "水印文字"},
+                        "font": {"type": "string", "description": "TTF/OTF 字体路径"},
+                        "color": {"type": "string", "default": "#FFFFFF"},
+                        "position": {"type": "string", "default": "bottom-right", "enum": ["top-left", "top", "top-right", "left", "center", "right", "bottom-left", "bottom", "bottom-right", "tile"]},
+                        "margin": {"type": "number", "default": 0.03, "description": "边距（平铺为间距），占短边比例"},
+                        "opacity": {"type": "number", "default": 0.5},
+                        "scale": {"type": "number", "description": "标志宽度/文字字号占短边比例（缺省标志 0.2、文字 0.05）"}
+                    }
+                },
+                "contact_sheet": {
+                    "type": "object",
+                    "default": null,
+                    "description": "联系表：整批压缩输出的缩略图按网格排版，每格附文件名与原始→压缩体积（失败项红底标 FAILED）；data.contact_sheets 列出各页路径",
+                    "properties": {
+                        "path": {"type": "string", "description": "输出路径（.jpg / .jpeg / .png），分页时追加 _01、_02…"},
+                        "columns": {"type": "integer", "default": 6, "description": "每行格数（1-20）"},
+                        "cell": {"type": "integer", "default": 240, "description": "缩略图格边长 px（64-1024）"},
+                        "per_page": {"type": "integer", "default": 0, "description": "每页格数（0 = 不分页）"},
+                        "font": {"type": "string", "description": "说明文字 TTF/OTF 字体（缺省探测系统字体，再退回内置 ASCII 点阵）"}
+                    }
+                },
+                "batch_budget_kb": {"type": "integer", "default": null, "description": "批量总体积预算（KB）：按输出像素×复杂度分配单文件预算，余量分给顶到预算的文件重压；data.batch_budget 报告 total_bytes/fits/rebalance_passes（仅 JPEG 输出受约束）"},
+                "slice_height": {"type": "integer", "default": null, "description": "长图分页页高（输出像素）：宽缩到平台宽度后纵向切页，切线落在低细节行，每页独立卡预算；输出 _01…_99，结果 tiles 列出各页（与 grid 互斥、grid 优先）"},
+                "pad": {"type": "boolean", "default": false, "description": "补边到画布（整图保留，与 smart_crop 二选一、补边优先），画布缺省取平台预设；结果 pad 字段报告画布与放置区域"},
+                "pad_canvas": {"type": "string", "default": null, "description": "补边画布：1080x1350 精确像素 / 1:1 画幅比（隐含 pad）"},
+                "print_size": {"type": "string", "default": null, "description": "冲印尺寸：纸张 + DPI（A4@200 / 6x4in@300 / 15x10cm@300 / 4R，缺省 300dpi）。按纸张像素框等比缩放（方向跟随图片、不放大；像素不够时降低写入 DPI），输出写入 JFIF/EXIF/pHYs 密度；结果 print 报告像素、DPI、印刷尺寸与源图密度。仅单图输出（与 grid/slice_height 互斥）"},
+                "srcset": {"type": "array", "items": {"type": "integer"}, "default": null, "description": "响应式图片组宽度档（如 [320, 640, 1280, 2560]）：源图解码一次，每档 × 每种格式各自缩放/编码，输出 _{宽}w；宽于原图的档位不放大。每张图旁写 .srcset.json 清单与 .srcset.html（<picture> 片段），结果 srcset 列出变体与片段（与 grid/slice_height/print_size 互斥）"},
+                "srcset_formats": {"type": "array", "items": {"type": "string", "enum": ["jpeg", "webp"]}, "default": null, "description": "响应式图片组格式，缺省跟随 output_format；非 JPEG 格式作 <source>，JPEG 作 <img> 兜底"},
+                "srcset_sizes": {"type": "string", "default": "100vw", "description": "<img sizes> 属性"},
+                "placeholder": {"type": "boolean", "default": false, "description": "低清占位图：由编码前的已缩放缓冲生成 BlurHash（4x3）、主色 #rrggbb 与长边 ≤32px 的 base64 缩略图（data: URI），写入结果 placeholder 与 manifest；多输出时取第一个输出"},
+                "placeholder_format": {"type": "string", "enum": ["jpeg", "webp"], "default": "jpeg", "description": "占位缩略图格式（JPEG 透明区按白底合成，WebP 保留透明）"},
+                "placeholder_size": {"type": "integer", "default": 32, "description": "占位缩略图长边（px，上限 32）"},
+                "icon": {"type": "boolean", "default": false, "description": "图标组模式：每张方图（非方图居中补透明边）生成 {名}_icons/：favicon.ico（16/24/32/48/64/128/256）、favicon-16x16/32x32.png、apple-touch-icon.png（180，不切圆角、不透明）、android-chrome-192x192/512x512.png 与 site.webmanifest；结果 icons 列出文件、清单与 <head> 片段。与 grid/slice_height/print_size/srcset 互斥"},
+                "icon_padding": {"type": "number", "default": 0.0, "description": "图标四周留白，占边长比例（0-0.4）"},
+                "icon_radius": {"type": "number", "default": 0.0, "description": "图标圆角半径，占边长比例（0-0.5，0.5 为圆形）"},
+                "icon_background": {"type": "string", "default": null, "description": "图标底色 #RRGGBB（缺省透明；apple-touch-icon 缺省白底），同时作为 webmanifest 的 theme/background_color"},
+                "external_decoders": {"type": "array", "default": [], "items": {"type": "object", "properties": {"name": {"type": "string"}, "extensions": {"type": "array", "items": {"type": "string"}}, "command": {"type": "array", "items": {"type": "string"}, "description": "程序 + 参数（不经 shell），须含 {input} 与 {output} 占位符，可用 {stem}"}, "output_ext": {"type": "string", "default": "tiff", "description": "工具写出的文件格式扩展名"}, "timeout_secs": {"type": "integer", "description": "单独超时（1-3600）"}}, "required": ["extensions", "command"]}, "description": "外部解码器：按扩展名交给 dcraw_emu / darktable-cli / ImageMagick 等解码后走常规管线；任意平台生效，优先于内置解码；登记的扩展名会被目录扫描收录。配置无效按参数错误退出"},
+                "decoder_timeout_secs": {"type": "integer", "default": 60, "description": "外部解码器（未单独配置者）及 macOS 内置 sips/qlmanage 的超时（秒）"},
+                "decoder_concurrency": {"type": "integer", "default": 4, "description": "外部解码工具全进程并发上限（信号量，含 sips）"},
+                "jxl_lossless_jpeg": {"type": "boolean", "default": false, "description": "output_format=jxl 时 JPEG 输入无损重压缩（不解码像素、跳过缩放等全部处理，可经 djxl 逐字节还原原 JPEG）"},
+                "first_frame_only": {"type": "boolean", "default": false, "description": "多帧 GIF / APNG / 动态 WebP 只取首帧按静态图处理（缺省逐帧缩放后输出动图，结果 animation 报告帧数与时长）"},
+                "animation_format": {"type": "string", "enum": ["auto", "gif", "webp"], "default": "auto", "description": "动图输出格式：auto = output_format 为 webp 或源为 WebP 时输出动态 WebP（逐帧无损），否则输出优化 GIF（逐帧调色板 + 差异裁剪）"},
+                "animation_max_frames": {"type": "integer", "default": 300, "description": "动图最多保留帧数，超出截断（0 = 不限）"},
+                "animation_max_duration_ms": {"type": "integer", "default": 60000, "description": "动图最长总时长（毫秒），超出截断（0 = 不限）"},
+                "svg_rasterize": {"type": "boolean", "default": false, "description": "SVG 栅格化（resvg，需 feature svg，未启用时为 true 按参数错误退出）后走常规管线；关闭时 SVG 为不支持格式（可配合 passthrough_unsupported 原样透传）。开启后目录扫描收录 .svg"},
+                "svg_size": {"type": "string", "default": "2048", "description": "SVG 渲染尺寸：长边像素（如 2048）或 DPI（如 300dpi，按 SVG 物理尺寸以 96 DPI 为基准换算）；之后仍按 max_dim 缩放"},
+                "svg_background": {"type": "string", "default": "transparent", "description": "SVG 底色：transparent / white / black / #RRGGBB（JPEG 输出时透明区按白底合成）"},
+                "dither": {"type": "string", "enum": ["blue-noise", "ordered", "none"], "default": "blue-noise", "description": "16 位 / 浮点源（16 位 TIFF/PNG、DNG 显影、HDR/EXR）按原精度缩放后量化到 8 位的抖动方式，消除渐变条带；8 位源不受影响"},
+                "tone_map": {"type": "string", "enum": ["none", "reinhard", "aces"], "default": "none", "description": "浮点（HDR / EXR / 浮点 TIFF）源的色调映射：none 截断到 [0,1]；reinhard / aces 按线性光压缩高光并做 sRGB 编码"},
+                "tiff_pages": {"type": "string", "enum": ["first", "all", "largest"], "default": "first", "description": "多页 TIFF 取页：first 只取第 1 页（有未输出的整页时 warnings 告警）；all 每页单独输出 {名}_p01…（跳过缩小分辨率子图，结果 tiff_pages 列出各页，manifest 每页一条）；largest 只取像素最多的一页。图标组 / 九宫格 / 长图分页 / srcset 模式下 all 按 first 处理"},
+                "pad_fill": {"type": "string", "default": "blur", "description": "补边底色：blur / #RRGGBB / white / black"},
+                "simulate_platform": {"type": "string", "default": null, "description": "平台二压模拟：结果追加 platform_sim（过平台后相对原图的 SSIM/PSNR）"},
+                "simulate_config": {"type": "string", "default": null, "description": "平台二压模型覆盖 TOML（表名=平台名，未写字段沿用内置值）"}
             }
         }),
         cli_parameters: vec![
@@ -773,8 +1662,14 @@ pub fn build_capabilities() -> Capabilities {
                 short: None,
                 kind: "STRING".into(),
                 default: "jpeg".into(),
-                description: "输出格式（webp 更省体积、支持透明）".into(),
-                available_values: Some(vec!["jpeg".into(), "keep-original".into(), "webp".into()]),
+                description: "输出格式（webp 更省体积、支持透明；jxl 需 libjxl 的 cjxl）".into(),
+                available_values: Some(
+                    <CliOutputFormat as clap::ValueEnum>::value_variants()
+                        .iter()
+                        .filter_map(clap::ValueEnum::to_possible_value)
+                        .map(|v| v.get_name().to_string())
+                        .collect(),
+                ),
             },
             CliParamDoc {
                 name: "--json".into(),
@@ -934,8 +1829,442 @@ pub fn build_capabilities() -> Capabilities {
                 short: None,
                 kind: "STRING".into(),
                 default: "(无)".into(),
-                description: "平台阈值预设：wechat/wechat-new/xiaohongshu/instagram/general，自动填长边/体积/Q（general 不强转 sRGB，其余强制）".into(),
-                available_values: Some(vec!["wechat".into(), "wechat-new".into(), "xiaohongshu".into(), "instagram".into(), "general".into()]),
+                description: "平台阈值预设：内置 wechat/wechat-new/xiaohongshu/instagram/general + presets.toml 自定义，自动填长边/体积/Q（general 不强转 sRGB，其余强制）；逗号分隔多个即多预设扇出（一次解码、每个预设各出一份）".into(),
+                available_values: Some(preset_names.clone()),
+            },
+            CliParamDoc {
+                name: "--fanout-layout".into(),
+                short: None,
+                kind: "ENUM".into(),
+                default: "suffix".into(),
+                description: "多预设扇出（--platform a,b 或 --platform a --usage-mode archive）的输出区分：suffix=_<预设名> 后缀 / subdir=按预设分子目录".into(),
+                available_values: Some(vec!["suffix".into(), "subdir".into()]),
+            },
+            CliParamDoc {
+                name: "--presets".into(),
+                short: None,
+                kind: "FILE".into(),
+                default: "(无)".into(),
+                description: "额外平台预设 TOML（表名=预设名），在内置/系统/用户 presets.toml 之后按字段合并".into(),
+                available_values: None,
+            },
+            CliParamDoc {
+                name: "--smart-crop".into(),
+                short: None,
+                kind: "FLAG".into(),
+                default: "false".into(),
+                description: "智能裁剪到平台画幅（显著性+肤色先验定位主体），画幅缺省取平台预设 aspect（小红书 3:4 / Instagram 4:5）".into(),
+                available_values: None,
+            },
+            CliParamDoc {
+                name: "--crop-aspect".into(),
+                short: None,
+                kind: "W:H".into(),
+                default: "(预设 aspect)".into(),
+                description: "智能裁剪目标画幅，如 3:4 / 4:5 / 1:1（隐含 --smart-crop）；结果 crop 字段报告原图坐标裁剪窗口".into(),
+                available_values: None,
+            },
+            CliParamDoc {
+                name: "--grid".into(),
+                short: None,
+                kind: "COLSxROWS".into(),
+                default: "(不切)".into(),
+                description: "朋友圈九宫格切图：3x3 / 2x2 / 1x3（最多 9 片）；裁剪/补边之后均分，每片按当前预设压缩，输出 _1…_9（行优先=上传顺序）".into(),
+                available_values: None,
+            },
+            CliParamDoc {
+                name: "--grid-gutter".into(),
+                short: None,
+                kind: "PX".into(),
+                default: "0".into(),
+                description: "九宫格切片间缝宽（像素，缝内像素丢弃，拼接处内容对得上）".into(),
+                available_values: None,
+            },
+            CliParamDoc {
+                name: "--watermark-image".into(),
+                short: None,
+                kind: "PNG".into(),
+                default: "(无)".into(),
+                description: "水印标志（PNG，带透明最佳）；缩放后、锐化与编码前叠加".into(),
+                available_values: None,
+            },
+            CliParamDoc {
+                name: "--watermark-text".into(),
+                short: None,
+                kind: "TEXT".into(),
+                default: "(无)".into(),
+                description: "文字水印（需 --watermark-font 与 feature fonts；与 --watermark-image 二选一，标志优先）".into(),
+                available_values: None,
+            },
+            CliParamDoc {
+                name: "--watermark-font".into(),
+                short: None,
+                kind: "FILE".into(),
+                default: "(无)".into(),
+                description: "文字水印字体（TTF/OTF，不内置字体）".into(),
+                available_values: None,
+            },
+            CliParamDoc {
+                name: "--watermark-color".into(),
+                short: None,
+                kind: "#RRGGBB".into(),
+                default: "#FFFFFF".into(),
+                description: "文字水印颜色".into(),
+                available_values: None,
+            },
+            CliParamDoc {
+                name: "--watermark-position".into(),
+                short: None,
+                kind: "POS".into(),
+                default: "bottom-right".into(),
+                description: "水印位置：九宫位或 tile（铺满平铺，奇数行错开半格）".into(),
+                available_values: Some(vec![
+                    "top-left".into(),
+                    "top".into(),
+                    "top-right".into(),
+                    "left".into(),
+                    "center".into(),
+                    "right".into(),
+                    "bottom-left".into(),
+                    "bottom".into(),
+                    "bottom-right".into(),
+                    "tile".into(),
+                ]),
+            },
+            CliParamDoc {
+                name: "--watermark-margin".into(),
+                short: None,
+                kind: "RATIO".into(),
+                default: "0.03".into(),
+                description: "水印边距（平铺时为间距），占输出短边比例".into(),
+                available_values: None,
+            },
+            CliParamDoc {
+                name: "--watermark-opacity".into(),
+                short: None,
+                kind: "0-1".into(),
+                default: "0.5".into(),
+                description: "水印不透明度".into(),
+                available_values: None,
+            },
+            CliParamDoc {
+                name: "--watermark-scale".into(),
+                short: None,
+                kind: "RATIO".into(),
+                default: "标志 0.2 / 文字 0.05".into(),
+                description: "标志宽度 / 文字字号占输出短边比例".into(),
+                available_values: None,
+            },
+            CliParamDoc {
+                name: "--contact-sheet".into(),
+                short: None,
+                kind: "FILE".into(),
+                default: "(不生成)".into(),
+                description: "联系表：整批缩略图总览（.jpg / .png），每格附文件名与原始→压缩体积；与 --ab 互斥".into(),
+                available_values: None,
+            },
+            CliParamDoc {
+                name: "--contact-sheet-columns".into(),
+                short: None,
+                kind: "N".into(),
+                default: "6".into(),
+                description: "联系表每行格数（1-20）".into(),
+                available_values: None,
+            },
+            CliParamDoc {
+                name: "--contact-sheet-cell".into(),
+                short: None,
+                kind: "PX".into(),
+                default: "240".into(),
+                description: "联系表缩略图格边长（64-1024）".into(),
+                available_values: None,
+            },
+            CliParamDoc {
+                name: "--contact-sheet-per-page".into(),
+                short: None,
+                kind: "N".into(),
+                default: "0".into(),
+                description: "联系表每页格数（0 = 不分页；分页输出 _01、_02…）".into(),
+                available_values: None,
+            },
+            CliParamDoc {
+                name: "--contact-sheet-font".into(),
+                short: None,
+                kind: "FILE".into(),
+                default: "系统字体 / 内置点阵".into(),
+                description: "联系表说明文字字体（TTF/OTF，需 feature fonts）".into(),
+                available_values: None,
+            },
+            CliParamDoc {
+                name: "--batch-budget-kb".into(),
+                short: None,
+                kind: "KB".into(),
+                default: "(不限)".into(),
+                description: "批量总体积预算：按输出像素×内容复杂度分配单文件预算（覆盖 --target-kb），余量分给顶到预算的文件重压（最多 2 轮）；报告实际合计，超出时 JSON warnings 告警".into(),
+                available_values: None,
+            },
+            CliParamDoc {
+                name: "--slice-height".into(),
+                short: None,
+                kind: "PX".into(),
+                default: "(不分页)".into(),
+                description: "长图分页：宽缩到平台宽度后按此页高纵向切页，切线落在消息间空白等低细节行；每页独立卡体积预算，输出 _01…_99；与 --grid 互斥".into(),
+                available_values: None,
+            },
+            CliParamDoc {
+                name: "--print-size".into(),
+                short: None,
+                kind: "SIZE@DPI".into(),
+                default: "(不启用)".into(),
+                description: "冲印尺寸：A4@200 / 6x4in@300 / 15x10cm@300 / 4R（缺省 300dpi）；按纸张像素框等比缩放、不放大，输出写入 JFIF/EXIF/pHYs 密度；与 --grid / --slice-height 互斥".into(),
+                available_values: None,
+            },
+            CliParamDoc {
+                name: "--srcset".into(),
+                short: None,
+                kind: "WIDTHS".into(),
+                default: "(不启用)".into(),
+                description: "响应式图片组：320,640,1280,2560 等宽度档，一次解码多档编码，输出 _{宽}w 与 .srcset.json / .srcset.html；与 --grid / --slice-height / --print-size 互斥".into(),
+                available_values: None,
+            },
+            CliParamDoc {
+                name: "--srcset-formats".into(),
+                short: None,
+                kind: "FORMATS".into(),
+                default: "跟随 --output-format".into(),
+                description: "响应式图片组格式，逗号组合；非 JPEG 作 <source>，JPEG 作 <img> 兜底".into(),
+                available_values: Some(vec!["jpeg".into(), "webp".into()]),
+            },
+            CliParamDoc {
+                name: "--srcset-sizes".into(),
+                short: None,
+                kind: "SIZES".into(),
+                default: "100vw".into(),
+                description: "响应式图片组 <img sizes> 属性".into(),
+                available_values: None,
+            },
+            CliParamDoc {
+                name: "--placeholder".into(),
+                short: None,
+                kind: "FLAG".into(),
+                default: "false".into(),
+                description: "低清占位图：BlurHash + 主色 + 长边 ≤32px 的 base64 缩略图，随结果 placeholder 与 manifest 输出".into(),
+                available_values: None,
+            },
+            CliParamDoc {
+                name: "--placeholder-format".into(),
+                short: None,
+                kind: "FORMAT".into(),
+                default: "jpeg".into(),
+                description: "占位缩略图格式".into(),
+                available_values: Some(vec!["jpeg".into(), "webp".into()]),
+            },
+            CliParamDoc {
+                name: "--placeholder-size".into(),
+                short: None,
+                kind: "PX".into(),
+                default: "32".into(),
+                description: "占位缩略图长边（1..=32）".into(),
+                available_values: None,
+            },
+            CliParamDoc {
+                name: "--icon".into(),
+                short: None,
+                kind: "FLAG".into(),
+                default: "false".into(),
+                description: "图标组模式：favicon.ico（16–256）+ favicon-16/32 + apple-touch-icon 180 + android-chrome 192/512 + site.webmanifest，输出到 {名}_icons/；与 --grid / --slice-height / --print-size / --srcset 互斥".into(),
+                available_values: None,
+            },
+            CliParamDoc {
+                name: "--icon-padding".into(),
+                short: None,
+                kind: "RATIO".into(),
+                default: "0".into(),
+                description: "图标四周留白，占边长比例（0-0.4）".into(),
+                available_values: None,
+            },
+            CliParamDoc {
+                name: "--icon-radius".into(),
+                short: None,
+                kind: "RATIO".into(),
+                default: "0".into(),
+                description: "图标圆角半径，占边长比例（0-0.5；apple-touch-icon 不切圆角）".into(),
+                available_values: None,
+            },
+            CliParamDoc {
+                name: "--icon-background".into(),
+                short: None,
+                kind: "#RRGGBB".into(),
+                default: "透明".into(),
+                description: "图标底色（apple-touch-icon 缺省白底）".into(),
+                available_values: None,
+            },
+            CliParamDoc {
+                name: "--external-decoder".into(),
+                short: None,
+                kind: "EXTS=CMD".into(),
+                default: "无".into(),
+                description: "外部解码器（可重复）：ext1,ext2=程序 参数…，{input}/{output}/{stem} 为占位符，如 cr3,nef=dcraw_emu -T -Z {output} {input}；任意平台生效，优先于内置解码".into(),
+                available_values: None,
+            },
+            CliParamDoc {
+                name: "--decoder-timeout".into(),
+                short: None,
+                kind: "SECS".into(),
+                default: "60".into(),
+                description: "外部解码器（及 macOS sips/qlmanage）超时，超时即终止进程并报错".into(),
+                available_values: None,
+            },
+            CliParamDoc {
+                name: "--decoder-concurrency".into(),
+                short: None,
+                kind: "N".into(),
+                default: "0".into(),
+                description: "外部解码工具全进程并发上限（0 = 缺省 4）".into(),
+                available_values: None,
+            },
+            CliParamDoc {
+                name: "--jxl-lossless-jpeg".into(),
+                short: None,
+                kind: "FLAG".into(),
+                default: "false".into(),
+                description: "--output-format jxl 时 JPEG 输入无损重压缩（不解码像素、跳过缩放等处理；需 libjxl 的 cjxl）"
+                    .into(),
+                available_values: None,
+            },
+            CliParamDoc {
+                name: "--first-frame-only".into(),
+                short: None,
+                kind: "FLAG".into(),
+                default: "false".into(),
+                description: "动图（GIF / APNG / 动态 WebP）只取首帧按静态图处理".into(),
+                available_values: None,
+            },
+            CliParamDoc {
+                name: "--animation-format".into(),
+                short: None,
+                kind: "FORMAT".into(),
+                default: "auto".into(),
+                description: "动图输出格式：auto（WebP 输出或 WebP 源 → 动态 WebP，其余 → 优化 GIF）"
+                    .into(),
+                available_values: Some(vec!["auto".into(), "gif".into(), "webp".into()]),
+            },
+            CliParamDoc {
+                name: "--animation-max-frames".into(),
+                short: None,
+                kind: "N".into(),
+                default: "300".into(),
+                description: "动图最多保留帧数，超出截断（0 = 不限）".into(),
+                available_values: None,
+            },
+            CliParamDoc {
+                name: "--animation-max-duration-ms".into(),
+                short: None,
+                kind: "MS".into(),
+                default: "60000".into(),
+                description: "动图最长总时长（毫秒），超出截断（0 = 不限）".into(),
+                available_values: None,
+            },
+            #[cfg(feature = "svg")]
+            CliParamDoc {
+                name: "--svg-rasterize".into(),
+                short: None,
+                kind: "FLAG".into(),
+                default: "false".into(),
+                description: "SVG 栅格化后走常规管线（缺省关闭，SVG 为不支持格式）".into(),
+                available_values: None,
+            },
+            #[cfg(feature = "svg")]
+            CliParamDoc {
+                name: "--svg-size".into(),
+                short: None,
+                kind: "PX|DPI".into(),
+                default: "2048".into(),
+                description: "SVG 渲染尺寸：长边像素或 DPI（如 300dpi）；需 --svg-rasterize".into(),
+                available_values: None,
+            },
+            #[cfg(feature = "svg")]
+            CliParamDoc {
+                name: "--svg-background".into(),
+                short: None,
+                kind: "COLOR".into(),
+                default: "transparent".into(),
+                description: "SVG 底色：transparent / white / black / #RRGGBB；需 --svg-rasterize"
+                    .into(),
+                available_values: None,
+            },
+            CliParamDoc {
+                name: "--dither".into(),
+                short: None,
+                kind: "MODE".into(),
+                default: "blue-noise".into(),
+                description: "16 位 / 浮点源量化到 8 位的抖动方式（消除渐变条带）".into(),
+                available_values: Some(vec![
+                    "blue-noise".into(),
+                    "ordered".into(),
+                    "none".into(),
+                ]),
+            },
+            CliParamDoc {
+                name: "--tone-map".into(),
+                short: None,
+                kind: "CURVE".into(),
+                default: "none".into(),
+                description: "浮点（HDR）源的色调映射（none = 截断到 [0,1]）".into(),
+                available_values: Some(vec!["none".into(), "reinhard".into(), "aces".into()]),
+            },
+            CliParamDoc {
+                name: "--tiff-pages".into(),
+                short: None,
+                kind: "MODE".into(),
+                default: "first".into(),
+                description: "多页 TIFF 取页：first 只取第 1 页 / all 每页输出 _p01… / largest 只取最大一页".into(),
+                available_values: Some(vec!["first".into(), "all".into(), "largest".into()]),
+            },
+            CliParamDoc {
+                name: "--pad".into(),
+                short: None,
+                kind: "FLAG".into(),
+                default: "false".into(),
+                description: "补边到固定画布（整图保留）：画布缺省取平台预设 pad_canvas / aspect；与 --smart-crop 二选一、补边优先".into(),
+                available_values: None,
+            },
+            CliParamDoc {
+                name: "--pad-canvas".into(),
+                short: None,
+                kind: "WxH|W:H".into(),
+                default: "(预设)".into(),
+                description: "补边画布：1080x1350 精确像素（输出即该尺寸）/ 1:1 画幅比（之后照常长边缩放）；隐含 --pad".into(),
+                available_values: None,
+            },
+            CliParamDoc {
+                name: "--pad-fill".into(),
+                short: None,
+                kind: "FILL".into(),
+                default: "blur".into(),
+                description: "补边底色：blur（模糊放大原图铺底）/ #RRGGBB / white / black".into(),
+                available_values: None,
+            },
+            CliParamDoc {
+                name: "--simulate-platform".into(),
+                short: None,
+                kind: "STRING".into(),
+                default: "(无)".into(),
+                description: "平台二压模拟：输出再过一遍平台重编码模型，结果追加 platform_sim（SSIM/PSNR vs 原图）".into(),
+                available_values: Some(
+                    xtap_compress::platform_sim::BUILTIN_PLATFORMS
+                        .iter()
+                        .map(|s| s.to_string())
+                        .collect(),
+                ),
+            },
+            CliParamDoc {
+                name: "--simulate-config".into(),
+                short: None,
+                kind: "FILE".into(),
+                default: "(无)".into(),
+                description: "平台二压模型覆盖 TOML：[平台名] max_long_edge/quality/subsampling/passthrough_kb/size_cap_kb".into(),
+                available_values: None,
             },
             CliParamDoc {
                 name: "--target-budget-kb".into(),
@@ -969,6 +2298,30 @@ pub fn build_capabilities() -> Capabilities {
                 description: "基准对比：输出 体积/SSIM/PSNR/各步耗时 对比表（旧 vs 新）".into(),
                 available_values: None,
             },
+            CliParamDoc {
+                name: "--benchmark-json".into(),
+                short: None,
+                kind: "FILE".into(),
+                default: "(无)".into(),
+                description: "基准结果落盘为 JSON（可作下次基线）；隐含 --benchmark，无输入时用内置生成语料".into(),
+                available_values: None,
+            },
+            CliParamDoc {
+                name: "--benchmark-baseline".into(),
+                short: None,
+                kind: "FILE".into(),
+                default: "(无)".into(),
+                description: "与基线 JSON 对比：体积增长或 SSIM 下降超出容差时退出码 1；隐含 --benchmark".into(),
+                available_values: None,
+            },
+            CliParamDoc {
+                name: "--max-regression".into(),
+                short: None,
+                kind: "PCT".into(),
+                default: "1%".into(),
+                description: "回归容差百分比（如 1% / 0.5），配合 --benchmark-baseline".into(),
+                available_values: None,
+            },
             CliParamDoc {
                 name: "--self-check".into(),
                 short: None,
@@ -1010,9 +2363,11 @@ pub fn build_capabilities() -> Capabilities {
             "errors": [],
             "metrics": {"original_bytes": 5000000, "compressed_bytes": 1200000, "bytes_saved": 3800000, "avg_ratio": 4.17, "total_time_ms": 1234}
         }),
+        platform_presets: presets().clone(),
         notes: vec![
             "路径含空格/中文必须用引号包住！AI 最稳：--json-in".to_string(),
-            "RAW 格式仅在 macOS 支持；Windows 会明确报错".to_string(),
+            "RAW 格式：macOS 用系统 sips；Linux/Windows 上 DNG 走纯 Rust 全分辨率显影（未压缩 / 无损 JPEG，双线性去马赛克 + AsShotNeutral 白平衡 + ColorMatrix → sRGB + 默认色调曲线，输出 16 位），其余 RAW（及不支持的 DNG 编码）取内嵌 JPEG 预览（尺寸以相机写入的最大预览为准），EXIF（IFD0 + Exif + GPS，不含 MakerNote）迁移到 JPEG 输出；找不到可解码预览时报错".to_string(),
+            "v4.5：HEIC/HEIF 输入（iPhone 照片）：以 --features heic 编译时经 libheif 解码主图（含 grid 分块拼接与 irot/imir 摆正），EXIF（Orientation 复位为 1）/ XMP / ICC 写回 JPEG 输出；未启用时按不支持格式处理（--passthrough-unsupported 原样透传），可用 --external-decoder \"heic,heif=heif-convert {input} {output}\" 代替（元数据同样迁移）".to_string(),
             "目录默认递归（最深 20 层），--no-recursive 仅当前层".to_string(),
             "未指定 --output-dir 时默认输出到 ./compressed/ 目录".to_string(),
             "退出码：0=正常（含隐藏文件跳过/透传）, 1=有真正失败（不支持且未透传/解码损坏/权限）, 2=参数错误".to_string(),
@@ -1031,6 +2386,46 @@ pub fn build_capabilities() -> Capabilities {
                 .to_string(),
             "v4.4.0：--quality-first 简写（= quality_mode max）；--cas-strength 0-1 控制锐化强度（画质优先档默认 0.35，缩放比 >1.3 生效，平坦区自动跳过）"
                 .to_string(),
+            format!(
+                "平台预设可扩展：内置表为默认，依次合并 {} 与 --presets（表名=预设名，已有预设按字段覆盖，新预设需给 max_dim/quality/budget_kb）",
+                preset_search_paths()
+                    .iter()
+                    .map(|p| p.display().to_string())
+                    .collect::<Vec<_>>()
+                    .join("、")
+            ),
+            "平台二压模拟：--simulate-platform 把输出按平台模型（缩放阈值/重编码 Q/色度子采样/免二压体积线/体积上限）再编码一次，platform_sim 报告过平台后的画质"
+                .to_string(),
+            "画幅与切图顺序：补边（--pad）与智能裁剪（--smart-crop）二选一、补边优先 → 九宫格（--grid）均分或长图分页（--slice-height，宽对齐平台宽度）→ 每片照常缩放/编码；输出 _1…_9 / _01…_99 即上传顺序"
+                .to_string(),
+            "冲印尺寸：--print-size 在补边/裁剪之后按纸张像素框缩放（取代 --max-dim）；纸张方向跟随图片，要铺满纸面先用 --smart-crop / --pad 调到纸张画幅；WebP 无密度字段"
+                .to_string(),
+            "联系表：--contact-sheet 在整批处理完后，用压缩输出（客户实际拿到的画面）排缩略图，按输入顺序排列；说明文字缺省探测系统中文字体，找不到（或未启用 feature fonts）时退回内置 ASCII 点阵（中文显示为 ?），可用 --contact-sheet-font 指定"
+                .to_string(),
+            "多预设扇出：--platform wechat,xiaohongshu [--usage-mode archive] 源图只解码一次，按各预设分别处理；每个输入一条结果，outputs 列出各目标；不兼容 --overwrite / --batch-budget-kb / --ab / 基准模式"
+                .to_string(),
+            "图标组：--icon 每个尺寸都从源图直接缩放，留白 → 底色 → 圆角遮罩；不做画幅适配与压缩编码，site.webmanifest / <head> 片段按站点根目录引用，整个 {名}_icons/ 目录直接放到站点根目录即可"
+                .to_string(),
+            "v4.5：JPEG XL：.jxl 输入以 --features jxl 编译时经 jxl-oxide 进程内解码（纯 Rust，方向摆正、透明与 16 位保留；为 jxl 配置的外部解码器优先）；未启用时按不支持格式处理，可登记外部解码器：JSON external_decoders 写 {\"extensions\": [\"jxl\"], \"command\": [\"djxl\", \"{input}\", \"{output}\"], \"output_ext\": \"png\"}（config.toml 的 [[external_decoders]] 同理；djxl 不写 TIFF，须指定 output_ext）。输出不需要编译特性：--output-format jxl 经 libjxl 的 cjxl 编码（cjxl 不在 PATH 中时处理前按参数错误退出），--target-kb 按质量二分（每步一次 cjxl），透明通道保留、源图元数据不迁移；--jxl-lossless-jpeg 让 JPEG 输入无损重压缩（元数据随 JPEG 位流保留）。cjxl 与外部解码器共用并发上限与 --decoder-timeout"
+                .to_string(),
+            "动图：多帧 GIF / APNG / 动态 WebP 逐帧按 --max-dim 缩放（可叠水印）后输出 .gif / .webp；GIF 逐帧调色板（--quality 越高量化越精细），全不透明动图只编码与上一帧不同的区域，相同帧合并延时；动态 WebP 逐帧无损。补边 / 裁剪 / 锐化 / 体积目标只作用于静态图；图标组 / 九宫格 / 分页 / 响应式 / 冲印模式与 --first-frame-only 只取首帧"
+                .to_string(),
+            "SVG（feature svg）：--svg-rasterize 用 resvg 渲染（文字取系统字体，相对路径外链图片按 SVG 所在目录解析）后照常缩放编码；--output-format keep 时输出 PNG。未开启时 SVG 仍为不支持格式，--passthrough-unsupported 原样透传"
+                .to_string(),
+            "高位深：16 位 TIFF/PNG、DNG 显影结果与 HDR/EXR/浮点 TIFF 按原精度（U16 / F32）缩放，量化到 8 位时按 --dither 抖动（缺省蓝噪声），天空、背景纸等渐变不再出台阶；--tone-map 只作用于浮点源，锐化 / 水印等仍在 8 位上进行"
+                .to_string(),
+            "CMYK / YCCK JPEG（印刷稿）：按 Adobe APP14 标志判断反相与 YCCK，内嵌 CMYK ICC 时按 ICC 转 sRGB，否则按朴素公式换算；输出为 RGB JPEG，原 CMYK ICC 换成 sRGB ICC"
+                .to_string(),
+            "多页 TIFF：缺省只取第 1 页，其余整页未输出时给出告警；--tiff-pages all 每页单独输出 {名}_p01.jpg…（跳过 NewSubfileType 标记的缩略子图），largest 只取像素最多的一页；manifest 带 page_count 与 page（源页码）"
+                .to_string(),
+            "外部解码器：命令不经 shell 直接执行，工具须把结果写到 {output}（格式由 output_ext 决定，缺省 tiff）；错误分为无法启动 / 超时 / 非零退出（附 stderr 末尾）/ 无输出 / 输出无法解码。config.toml 可写 [[external_decoders]] 表供 GUI 使用"
+                .to_string(),
+            "低清占位图：--placeholder 取编码前已缩放（含水印）的输出缓冲计算，不额外解码；九宫格/分页/响应式图片组取第一个输出，响应式清单 .srcset.json 同步附带"
+                .to_string(),
+            "响应式图片组：--srcset 在补边/裁剪之后按各档宽度缩放（取代 --max-dim），体积目标逐个变体生效；<picture> 片段只写文件名，与图片放在同一目录引用"
+                .to_string(),
+            "基准回归门禁：--benchmark-json 落盘结果，--benchmark-baseline 对比基线（--max-regression 容差），无输入文件时用内置生成语料离线运行"
+                .to_string(),
         ],
     }
 }
@@ -1055,6 +2450,7 @@ impl From<CliOutputFormat> for OutputFormat {
             CliOutputFormat::Jpeg => OutputFormat::Jpeg,
             CliOutputFormat::KeepOriginal => OutputFormat::KeepOriginal,
             CliOutputFormat::WebP => OutputFormat::WebP,
+            CliOutputFormat::Jxl => OutputFormat::Jxl,
         }
     }
 }
@@ -1069,7 +2465,98 @@ impl From<CliColorSpace> for ColorSpace {
 }
 
 impl Cli {
+    /// 是否进入基准对比模式（--benchmark 或任一基准落盘/基线参数）
+    pub fn benchmark_mode(&self) -> bool {
+        self.benchmark || self.benchmark_json.is_some() || self.benchmark_baseline.is_some()
+    }
+
+    /// v4.5：由 --watermark-* 组装水印配置（未给标志/文字时为 None）
+    pub fn watermark_config(&self) -> Option<xtap_compress::watermark::WatermarkConfig> {
+        if self.watermark_image.is_none() && self.watermark_text.is_none() {
+            return None;
+        }
+        Some(xtap_compress::watermark::WatermarkConfig {
+            image: self
+                .watermark_image
+                .as_ref()
+                .map(|p| p.display().to_string()),
+            text: self.watermark_text.clone(),
+            font: self
+                .watermark_font
+                .as_ref()
+                .map(|p| p.display().to_string()),
+            color: self.watermark_color.clone(),
+            position: self.watermark_position.clone(),
+            margin: self.watermark_margin,
+            opacity: self.watermark_opacity,
+            scale: self.watermark_scale,
+        })
+    }
+
+    /// v4.5：联系表配置（未给 --contact-sheet 返回 None）
+    pub fn contact_sheet_config(&self) -> Option<ContactSheetConfig> {
+        Some(ContactSheetConfig {
+            path: self.contact_sheet.as_ref()?.display().to_string(),
+            columns: self.contact_sheet_columns,
+            cell: self.contact_sheet_cell,
+            per_page: self.contact_sheet_per_page,
+            font: self
+                .contact_sheet_font
+                .as_ref()
+                .map(|p| p.display().to_string()),
+        })
+    }
+
     pub fn to_app_config(&self) -> AppConfig {
+        let platform = self
+            .platform
+            .as_deref()
+            .and_then(|p| split_platforms(p).into_iter().next());
+        self.app_config_for(platform.as_deref(), self.usage_mode)
+    }
+
+    /// v4.5：与多预设扇出互斥的已给选项（非扇出或无冲突时为空）：
+    /// 覆盖原文件 / 批量预算 / A/B / 基准都假定一个输入只有一份输出
+    pub fn fanout_conflicts(&self) -> Vec<&'static str> {
+        if self.fanout_targets().is_none() {
+            return Vec::new();
+        }
+        [
+            (self.overwrite, "--overwrite"),
+            (self.batch_budget_kb.is_some(), "--batch-budget-kb"),
+            (self.ab, "--ab"),
+            (self.benchmark_mode(), "--benchmark"),
+        ]
+        .into_iter()
+        .filter_map(|(given, flag)| given.then_some(flag))
+        .collect()
+    }
+
+    /// v4.5：多预设扇出目标（名称 + 各自的 AppConfig）；非扇出返回 None
+    pub fn fanout_targets(&self) -> Option<Vec<(String, AppConfig)>> {
+        let platforms = split_platforms(self.platform.as_deref().unwrap_or(""));
+        let archive = self.usage_mode == Some(CliUsageMode::Archive);
+        let names = fanout_target_names(&platforms, archive)?;
+        Some(
+            names
+                .into_iter()
+                .map(|name| {
+                    let cfg = if name == "archive" {
+                        self.app_config_for(None, Some(CliUsageMode::Archive))
+                    } else {
+                        self.app_config_for(Some(&name), Some(CliUsageMode::Social))
+                    };
+                    (name, cfg)
+                })
+                .collect(),
+        )
+    }
+
+    fn app_config_for(
+        &self,
+        platform: Option<&str>,
+        usage_mode: Option<CliUsageMode>,
+    ) -> AppConfig {
         let mut cfg = AppConfig {
             config_version: 2,
             mode: self.mode.into(),
@@ -1089,11 +2576,11 @@ impl Cli {
             // - 显式 --usage-mode → 直接用
             // - 未给 usage-mode 但给了 --platform → social（平台预设驱动）
             // - 都没给 → custom（走 --mode/--max-dim/--quality/--target-kb 旧语义，v4.1.0 完全不变）
-            usage_mode: match self.usage_mode {
+            usage_mode: match usage_mode {
                 Some(u) => u.as_str().to_string(),
                 None => {
                     // v4.4.0：--quality-first 也是「平台驱动」信号 → social（默认 wechat 预设）
-                    if self.platform.is_some() || self.quality_first {
+                    if platform.is_some() || self.quality_first {
                         "social".to_string()
                     } else {
                         "custom".to_string()
@@ -1115,9 +2602,8 @@ impl Cli {
                         }
                     })
             },
-            platform: self
-                .platform
-                .map(|p| p.as_str().to_string())
+            platform: platform
+                .map(|p| p.to_lowercase())
                 .unwrap_or_else(|| "wechat".to_string()),
             // v4.3.0：色彩子采样（默认 420 照片；截图文字可切 444 防模糊）
             subsampling: self
@@ -1131,11 +2617,54 @@ impl Cli {
             cas_strength: 0.0,
             // v4.4.1：CLI 不持久化自定义导出目录；GUI 专用
             custom_output_dir: None,
+            // v4.5：--crop-aspect 隐含 --smart-crop
+            smart_crop: self.smart_crop || self.crop_aspect.is_some(),
+            crop_aspect: self.crop_aspect.clone(),
+            // v4.5：--pad-canvas 隐含 --pad
+            pad: self.pad || self.pad_canvas.is_some(),
+            pad_canvas: self.pad_canvas.clone(),
+            pad_fill: self.pad_fill.clone(),
+            grid: self.grid.clone(),
+            grid_gutter: self.grid_gutter,
+            slice_height: self.slice_height.unwrap_or(0),
+            print_size: self.print_size.clone(),
+            srcset: self.srcset.clone(),
+            srcset_formats: self.srcset_formats.clone(),
+            srcset_sizes: self.srcset.as_ref().map(|_| self.srcset_sizes.clone()),
+            placeholder: self.placeholder,
+            placeholder_format: Some(self.placeholder_format.clone()),
+            placeholder_size: self.placeholder_size,
+            icon: self.icon,
+            icon_padding: self.icon_padding,
+            icon_radius: self.icon_radius,
+            icon_background: self.icon_background.clone(),
+            external_decoders: self.external_decoder.clone(),
+            decoder_timeout_secs: self.decoder_timeout,
+            decoder_concurrency: self.decoder_concurrency,
+            jxl_lossless_jpeg: self.jxl_lossless_jpeg,
+            first_frame_only: self.first_frame_only,
+            animation_format: self.animation_format.clone(),
+            animation_max_frames: self.animation_max_frames,
+            animation_max_duration_ms: self.animation_max_duration_ms,
+            // v4.5：SVG 参数仅 feature svg 提供，见下方
+            svg_rasterize: false,
+            svg_size: None,
+            svg_background: None,
+            dither: self.dither.clone(),
+            tone_map: self.tone_map.clone(),
+            tiff_pages: self.tiff_pages.clone(),
+            watermark: self.watermark_config(),
         };
+        #[cfg(feature = "svg")]
+        {
+            cfg.svg_rasterize = self.svg_rasterize;
+            cfg.svg_size = self.svg_size.clone();
+            cfg.svg_background = self.svg_background.clone();
+        }
         // 平台预设自动填长边/体积/Q 并强制 sRGB（§2）。显式 --target-budget-kb 覆盖预设体积线。
         // --usage-mode social 但没给 --platform 时按默认 wechat 预设（与 GUI 默认一致）。
-        let effective_platform = match self.platform {
-            Some(p) => Some(p.as_str().to_string()),
+        let effective_platform = match platform {
+            Some(p) => Some(p.to_lowercase()),
             None if cfg.usage_mode == "social" => Some("wechat".to_string()),
             None => None,
         };
@@ -1194,8 +2723,10 @@ pub fn build_envelope(results: &[FileResult], start: std::time::Instant) -> Json
             skipped,
             results: results.to_vec(),
             manifest: build_manifest(results),
+            batch_budget: None,
+            contact_sheets: Vec::new(),
         },
-        warnings: vec![],
+        warnings: tiff_page_warnings(results),
         errors: vec![],
         metrics: JsonMetrics {
             original_bytes,
@@ -1207,11 +2738,58 @@ pub fn build_envelope(results: &[FileResult], start: std::time::Instant) -> Json
     }
 }
 
+/// v4.5：多页 TIFF 只输出了部分整页时的告警（first 模式）
+pub(crate) fn tiff_page_warnings(results: &[FileResult]) -> Vec<String> {
+    results
+        .iter()
+        .filter_map(|r| {
+            let pages = r.tiff_pages.as_ref().filter(|p| p.skipped > 0)?;
+            Some(format!(
+                "多页 TIFF 只输出了第 1 页（共 {} 页，另有 {} 个整页未处理；--tiff-pages all 逐页输出）: {}",
+                pages.count, pages.skipped, r.input
+            ))
+        })
+        .collect()
+}
+
 /// 由 results 构建输入→输出映射清单（v4.3.1）
 fn build_manifest(results: &[FileResult]) -> Vec<ManifestEntry> {
     results
         .iter()
-        .map(|r| {
+        .flat_map(|r| {
+            // v4.5：九宫格每片一条，按上传顺序
+            if let Some(tiles) = &r.tiles {
+                return tiles
+                    .iter()
+                    .enumerate()
+                    .map(|(i, t)| ManifestEntry {
+                        input: r.input.clone(),
+                        output: Some(t.output.display().to_string().replace('\\', "/")),
+                        status: "compressed".to_string(),
+                        tile: Some(t.tile.index),
+                        page: None,
+                        page_count: None,
+                        placeholder: r.placeholder.clone().filter(|_| i == 0),
+                    })
+                    .collect();
+            }
+            // v4.5：多页 TIFF 逐页输出时每页一条
+            if let Some(pages) = r.tiff_pages.as_ref().filter(|p| p.outputs.len() > 1) {
+                return pages
+                    .outputs
+                    .iter()
+                    .enumerate()
+                    .map(|(i, p)| ManifestEntry {
+                        input: r.input.clone(),
+                        output: Some(p.output.display().to_string().replace('\\', "/")),
+                        status: "compressed".to_string(),
+                        tile: None,
+                        page: Some(p.page),
+                        page_count: Some(pages.count),
+                        placeholder: r.placeholder.clone().filter(|_| i == 0),
+                    })
+                    .collect();
+            }
             let status = if !r.success {
                 "failed".to_string()
             } else if r.passthrough.unwrap_or(false) {
@@ -1221,11 +2799,236 @@ fn build_manifest(results: &[FileResult]) -> Vec<ManifestEntry> {
             } else {
                 "compressed".to_string()
             };
-            ManifestEntry {
+            vec![ManifestEntry {
                 input: r.input.clone(),
                 output: r.output.clone(),
                 status,
-            }
+                tile: None,
+                page: r
+                    .tiff_pages
+                    .as_ref()
+                    .and_then(|p| p.outputs.first())
+                    .map(|o| o.page),
+                page_count: r.tiff_pages.as_ref().map(|p| p.count),
+                placeholder: r.placeholder.clone(),
+            }]
         })
         .collect()
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fanout_target_names() {
+        let plats = split_platforms(" WeChat,xiaohongshu,,wechat ");
+        assert_eq!(plats, vec!["wechat", "xiaohongshu"]);
+        assert_eq!(
+            fanout_target_names(&plats, true),
+            Some(vec![
+                "wechat".to_string(),
+                "xiaohongshu".to_string(),
+                "archive".to_string()
+            ])
+        );
+        assert_eq!(fanout_target_names(&plats[..1], false), None);
+        assert_eq!(
+            fanout_target_names(&plats[..1], true).map(|n| n.len()),
+            Some(2)
+        );
+        assert_eq!(fanout_target_names(&[], true), None);
+    }
+
+    #[test]
+    fn test_fanout_conflicts() {
+        let parse = |args: &[&str]| Cli::try_parse_from(args).unwrap();
+        let cli = parse(&[
+            "x",
+            "--platform",
+            "wechat,xiaohongshu",
+            "--overwrite",
+            "a.jpg",
+        ]);
+        assert_eq!(cli.fanout_conflicts(), vec!["--overwrite"]);
+        let cli = parse(&[
+            "x",
+            "--platform",
+            "wechat",
+            "--usage-mode",
+            "archive",
+            "--batch-budget-kb",
+            "500",
+            "--benchmark",
+            "a.jpg",
+        ]);
+        assert_eq!(
+            cli.fanout_conflicts(),
+            vec!["--batch-budget-kb", "--benchmark"]
+        );
+        // 单预设不扇出，同样的选项不冲突
+        let cli = parse(&["x", "--platform", "wechat", "--overwrite", "a.jpg"]);
+        assert!(cli.fanout_conflicts().is_empty());
+    }
+
+    fn entry(file: &str, new_bytes: u64, new_ssim: Option<f64>) -> BenchmarkEntry {
+        BenchmarkEntry {
+            file: file.to_string(),
+            old_bytes: 1000,
+            new_bytes,
+            old_ssim: Some(0.95),
+            new_ssim,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn presets_toml_overrides_fields_and_adds_new_platforms() {
+        let mut presets = builtin_presets();
+        let text = r#"
+            [wechat]
+            budget_kb = 800
+
+            [weibo]
+            label = "微博"
+            max_dim = 2048
+            quality = 90
+            budget_kb = 2000
+            subsampling = "444"
+        "#;
+        merge_presets_toml(&mut presets, text).unwrap();
+
+        // 已有预设只覆盖写出的字段
+        let wechat = &presets["wechat"];
+        assert_eq!(wechat.budget_kb, 800);
+        assert_eq!(wechat.max_dim, 1080);
+        assert_eq!(wechat.max_quality, Some(96));
+
+        // 新预设：srgb 缺省为 true，max_* 缺省沿用普通档
+        let weibo = &presets["weibo"];
+        assert_eq!(
+            (weibo.max_dim, weibo.quality, weibo.budget_kb),
+            (2048, 90, 2000)
+        );
+        assert!(weibo.srgb);
+        assert_eq!(weibo.subsampling.as_deref(), Some("444"));
+    }
+
+    #[test]
+    fn presets_toml_rejects_incomplete_or_misspelled_entries() {
+        let mut presets = builtin_presets();
+        assert!(merge_presets_toml(&mut presets, "[douyin]\nquality = 90\n").is_err());
+        assert!(merge_presets_toml(&mut presets, "[wechat]\nbudget = 800\n").is_err());
+        let mixed = "[wechat]\nbudget_kb = 800\n[douyin]\nquality = 90\n";
+        assert!(merge_presets_toml(&mut presets, mixed).is_err());
+        assert_eq!(presets, builtin_presets(), "失败的合并不应留下半成品");
+    }
+
+    #[test]
+    fn parse_percent_accepts_suffix_and_bare() {
+        assert!((parse_percent("1%").unwrap() - 0.01).abs() < 1e-12);
+        assert!((parse_percent("0.5").unwrap() - 0.005).abs() < 1e-12);
+        assert!(parse_percent("-1%").is_err());
+        assert!(parse_percent("abc").is_err());
+    }
+
+    #[test]
+    fn benchmark_regression_gate_respects_tolerance() {
+        let base = BenchmarkReport::new(vec![entry("a.png", 1000, Some(0.95))]);
+
+        // 体积 +0.5%、SSIM 持平：1% 容差内通过
+        let ok = BenchmarkReport::new(vec![entry("a.png", 1005, Some(0.95))]);
+        assert!(benchmark_regressions(&base, &ok, 0.01).is_empty());
+
+        // 体积 +5%：回归
+        let bigger = BenchmarkReport::new(vec![entry("a.png", 1050, Some(0.95))]);
+        assert_eq!(benchmark_regressions(&base, &bigger, 0.01).len(), 1);
+
+        // SSIM 下降 5% / 无法计算 / 文件缺失：均为回归
+        let worse = BenchmarkReport::new(vec![entry("a.png", 1000, Some(0.90))]);
+        assert_eq!(benchmark_regressions(&base, &worse, 0.01).len(), 1);
+        let missing_ssim = BenchmarkReport::new(vec![entry("a.png", 1000, None)]);
+        assert_eq!(benchmark_regressions(&base, &missing_ssim, 0.01).len(), 1);
+        let missing_file = BenchmarkReport::new(vec![]);
+        assert_eq!(benchmark_regressions(&base, &missing_file, 0.01).len(), 1);
+    }
+
+    #[test]
+    fn manifest_lists_grid_tiles_in_upload_order() {
+        use xtap_compress::grid::{grid_layout, GridSpec};
+        let tiles = grid_layout(900, 900, GridSpec::parse("3x3", 0).unwrap())
+            .into_iter()
+            .map(|t| TileOutput {
+                tile: t,
+                output: PathBuf::from(format!("out/a_wx_{}.jpg", t.index)),
+                bytes: 100,
+            })
+            .collect();
+        let results = vec![
+            FileResult {
+                input: "a.jpg".into(),
+                output: Some("out/a_wx_1.jpg".into()),
+                success: true,
+                tiles: Some(tiles),
+                ..Default::default()
+            },
+            FileResult {
+                input: "b.jpg".into(),
+                output: Some("out/b_wx.jpg".into()),
+                success: true,
+                ..Default::default()
+            },
+        ];
+        let m = build_manifest(&results);
+        assert_eq!(m.len(), 10);
+        assert_eq!(m[0].tile, Some(1));
+        assert_eq!(m[8].output.as_deref(), Some("out/a_wx_9.jpg"));
+        assert_eq!((m[9].input.as_str(), m[9].tile), ("b.jpg", None));
+    }
+
+    #[test]
+    fn manifest_lists_tiff_pages_with_page_count() {
+        use xtap_compress::tiff_pages::PageOutput;
+        let page = |page: u32| PageOutput {
+            page,
+            width: 100,
+            height: 100,
+            output: PathBuf::from(format!("out/scan_wx_p{:02}.jpg", page)),
+            bytes: 100,
+        };
+        let results = vec![
+            FileResult {
+                input: "scan.tif".into(),
+                output: Some("out/scan_wx_p01.jpg".into()),
+                success: true,
+                tiff_pages: Some(TiffPages {
+                    count: 3,
+                    skipped: 0,
+                    outputs: vec![page(1), page(3)],
+                }),
+                ..Default::default()
+            },
+            FileResult {
+                input: "fax.tif".into(),
+                output: Some("out/fax_wx.jpg".into()),
+                success: true,
+                tiff_pages: Some(TiffPages {
+                    count: 4,
+                    skipped: 3,
+                    outputs: vec![page(1)],
+                }),
+                ..Default::default()
+            },
+        ];
+        let m = build_manifest(&results);
+        assert_eq!(m.len(), 3);
+        assert_eq!((m[1].page, m[1].page_count), (Some(3), Some(3)));
+        assert_eq!(m[1].output.as_deref(), Some("out/scan_wx_p03.jpg"));
+        assert_eq!((m[2].page, m[2].page_count), (Some(1), Some(4)));
+        assert_eq!(m[2].output.as_deref(), Some("out/fax_wx.jpg"));
+
+        let warnings = tiff_page_warnings(&results);
+        assert_eq!(warnings.len(), 1);
+        assert!(warnings[0].contains("fax.tif"));
+    }
+}
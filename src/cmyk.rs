@@ -0,0 +1,419 @@
+//! v4.5：CMYK / YCCK JPEG 输入（印刷部门的稿件常见）
+//!
+//! 通用解码把四通道 JPEG 一律按「Adobe 反相 CMYK」的朴素公式转 RGB，不看内嵌 ICC，
+//! 颜色发灰、过艳，无 APP14 的文件甚至整体反色。这里单独处理：
+//! - SOF 分量数为 4 即为四通道；Adobe APP14 的 transform 区分 CMYK(0) / YCCK(2)，
+//!   带 APP14 的按 Adobe 惯例反相存储（Photoshop 写出的都是），无 APP14 按墨量直存；
+//! - 内嵌 CMYK ICC 时经 moxcms 按 ICC 转 sRGB，无 ICC（或 ICC 不可用）时按 R = (1-C)(1-K) 换算；
+//! - 输出为 RGB，保留元数据时剔除原 CMYK ICC 与 APP14，改写 sRGB ICC（[`rgb_segments`]）。
+
+use anyhow::{anyhow, Result};
+use bytes::Bytes;
+use img_parts::jpeg::{markers, JpegSegment};
+use moxcms::{ColorProfile, DataColorSpace, Layout, Transform8BitExecutor, TransformOptions};
+use rayon::prelude::*;
+use std::sync::{Arc, OnceLock};
+use zune_core::bytestream::ZCursor;
+use zune_core::colorspace::ColorSpace;
+use zune_core::options::DecoderOptions;
+
+const ICC_TAG: &[u8] = b"ICC_PROFILE\0";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Transform {
+    Cmyk,
+    Ycck,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CmykInfo {
+    pub transform: Transform,
+    /// 带 Adobe APP14：通道值按 255 - 墨量存储
+    pub inverted: bool,
+}
+
+/// 扫描 JPEG 头部（至 SOS 为止），四通道时返回色彩变换；其余（含未知 transform）返回 None 交给通用解码
+pub fn probe(data: &[u8]) -> Option<CmykInfo> {
+    if !data.starts_with(&[0xFF, 0xD8]) {
+        return None;
+    }
+    let mut pos = 2;
+    let mut four_components = false;
+    let mut adobe = None;
+    while pos + 4 <= data.len() {
+        if data[pos] != 0xFF {
+            return None;
+        }
+        let marker = data[pos + 1];
+        if marker == 0xFF {
+            pos += 1;
+            continue;
+        }
+        let len = u16::from_be_bytes([data[pos + 2], data[pos + 3]]) as usize;
+        let body = data.get(pos + 4..pos + 2 + len.max(2))?;
+        match marker {
+            markers::APP14 if body.starts_with(b"Adobe") && body.len() >= 12 => {
+                adobe = Some(body[11]);
+            }
+            // SOF0-15（C4 DHT / C8 JPG / CC DAC 除外）：第 6 字节为分量数
+            0xC0..=0xCF if !matches!(marker, 0xC4 | 0xC8 | 0xCC) => {
+                four_components = body.get(5) == Some(&4);
+            }
+            markers::SOS => break,
+            _ => {}
+        }
+        pos += 2 + len;
+    }
+    if !four_components {
+        return None;
+    }
+    match adobe {
+        None => Some(CmykInfo {
+            transform: Transform::Cmyk,
+            inverted: false,
+        }),
+        Some(0) => Some(CmykInfo {
+            transform: Transform::Cmyk,
+            inverted: true,
+        }),
+        Some(2) => Some(CmykInfo {
+            transform: Transform::Ycck,
+            inverted: true,
+        }),
+        Some(_) => None,
+    }
+}
+
+/// 解码四通道 JPEG 为 RGB8（内嵌 CMYK ICC 时按 ICC 转 sRGB）
+pub fn decode(data: &[u8], info: &CmykInfo) -> Result<image::DynamicImage> {
+    let options = DecoderOptions::default()
+        .set_strict_mode(false)
+        .set_max_width(usize::MAX)
+        .set_max_height(usize::MAX);
+    let mut decoder = zune_jpeg::JpegDecoder::new_with_options(ZCursor::new(data), options);
+    decoder
+        .decode_headers()
+        .map_err(|e| anyhow!("CMYK JPEG 头解析失败: {:?}", e))?;
+    let space = match info.transform {
+        Transform::Cmyk => ColorSpace::CMYK,
+        Transform::Ycck => ColorSpace::YCCK,
+    };
+    if decoder.input_colorspace() != Some(space) {
+        return Err(anyhow!(
+            "CMYK JPEG 色彩空间不符: {:?}",
+            decoder.input_colorspace()
+        ));
+    }
+    // 输出与输入同色彩空间：解码器原样给出四通道，不做内置的朴素换算
+    decoder.set_options(decoder.options().jpeg_set_out_colorspace(space));
+    let mut ink = decoder
+        .decode()
+        .map_err(|e| anyhow!("CMYK JPEG 解码失败: {:?}", e))?;
+    let (width, height) = decoder
+        .dimensions()
+        .ok_or_else(|| anyhow!("CMYK JPEG 尺寸缺失"))?;
+
+    ink.par_chunks_exact_mut(4).for_each(|p| {
+        if info.transform == Transform::Ycck {
+            // YCC 还原出的 RGB 即 255 - 存储值（libjpeg 惯例）
+            let [r, g, b] = ycc_to_rgb(p[0], p[1], p[2]);
+            p[0] = 255 - r;
+            p[1] = 255 - g;
+            p[2] = 255 - b;
+        }
+        if info.inverted {
+            p.iter_mut().for_each(|v| *v = 255 - *v);
+        }
+    });
+    let rgb = to_rgb(&ink, width, decoder.icc_profile().as_deref());
+    let img = image::RgbImage::from_raw(width as u32, height as u32, rgb)
+        .ok_or_else(|| anyhow!("Failed to create image buffer"))?;
+    Ok(image::DynamicImage::ImageRgb8(img))
+}
+
+fn ycc_to_rgb(y: u8, cb: u8, cr: u8) -> [u8; 3] {
+    let (y, cb, cr) = (y as f32, cb as f32 - 128.0, cr as f32 - 128.0);
+    [
+        y + 1.402 * cr,
+        y - 0.344_136 * cb - 0.714_136 * cr,
+        y + 1.772 * cb,
+    ]
+    .map(|v| v.round().clamp(0.0, 255.0) as u8)
+}
+
+/// 墨量 CMYK（0 = 无墨）→ RGB8
+fn to_rgb(ink: &[u8], width: usize, icc: Option<&[u8]>) -> Vec<u8> {
+    let mut rgb = vec![0u8; ink.len() / 4 * 3];
+    let (src_rows, dst_rows) = (ink.par_chunks(width * 4), rgb.par_chunks_mut(width * 3));
+    if let Some(transform) = icc.and_then(icc_transform) {
+        let ok = src_rows
+            .zip(dst_rows)
+            .all(|(src, dst)| transform.transform(src, dst).is_ok());
+        if ok {
+            return rgb;
+        }
+    }
+    rgb.par_chunks_exact_mut(3)
+        .zip(ink.par_chunks_exact(4))
+        .for_each(|(dst, p)| {
+            let k = 255 - p[3] as u32;
+            for i in 0..3 {
+                dst[i] = (((255 - p[i] as u32) * k + 127) / 255) as u8;
+            }
+        });
+    rgb
+}
+
+/// 内嵌 ICC 为 CMYK 配置时建立到 sRGB 的变换
+fn icc_transform(icc: &[u8]) -> Option<Arc<Transform8BitExecutor>> {
+    let profile = ColorProfile::new_from_slice(icc).ok()?;
+    if profile.color_space != DataColorSpace::Cmyk {
+        return None;
+    }
+    profile
+        .create_transform_8bit(
+            Layout::Rgba,
+            &ColorProfile::new_srgb(),
+            Layout::Rgb,
+            TransformOptions::default(),
+        )
+        .ok()
+}
+
+/// sRGB ICC 配置（全进程只编码一次；编码失败时为空）
+pub fn srgb_icc() -> &'static [u8] {
+    static ICC: OnceLock<Vec<u8>> = OnceLock::new();
+    ICC.get_or_init(|| ColorProfile::new_srgb().encode().unwrap_or_default())
+}
+
+/// 原图 APP 段用于 RGB 输出：去掉 CMYK ICC 与 Adobe APP14，在 APP0/APP1 之后写入 sRGB ICC
+pub fn rgb_segments(segments: Vec<JpegSegment>) -> Vec<JpegSegment> {
+    let mut out: Vec<JpegSegment> = segments
+        .into_iter()
+        .filter(|s| {
+            let c = s.contents();
+            !(s.marker() == markers::APP2 && c.starts_with(ICC_TAG)
+                || s.marker() == markers::APP14 && c.starts_with(b"Adobe"))
+        })
+        .collect();
+    let icc = srgb_icc();
+    if !icc.is_empty() {
+        let pos = out
+            .iter()
+            .take_while(|s| matches!(s.marker(), markers::APP0 | markers::APP1))
+            .count();
+        let contents = [ICC_TAG, &[1, 1], icc].concat();
+        out.insert(
+            pos,
+            JpegSegment::new_with_contents(markers::APP2, Bytes::from(contents)),
+        );
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 测试用最小基线 JPEG：4 分量、不降采样、量化表全 1，每个 8×8 块取单一颜色（只编 DC）
+    fn encode_fixture(blocks: &[[u8; 4]], adobe: Option<u8>, icc: Option<&[u8]>) -> Vec<u8> {
+        fn segment(out: &mut Vec<u8>, marker: u8, body: &[u8]) {
+            out.extend_from_slice(&[0xFF, marker]);
+            out.extend_from_slice(&(body.len() as u16 + 2).to_be_bytes());
+            out.extend_from_slice(body);
+        }
+        // 标准亮度 DC 表；AC 表只有 EOB（码长 1，码字 0）
+        const DC_BITS: [u8; 16] = [0, 1, 5, 1, 1, 1, 1, 1, 1, 0, 0, 0, 0, 0, 0, 0];
+        let mut dc_codes = [(0u16, 0u8); 12];
+        let (mut code, mut sym) = (0u16, 0);
+        for (len, &n) in (1..=16u8).zip(DC_BITS.iter()) {
+            for _ in 0..n {
+                dc_codes[sym] = (code, len);
+                code += 1;
+                sym += 1;
+            }
+            code <<= 1;
+        }
+
+        let mut out = vec![0xFF, 0xD8];
+        if let Some(t) = adobe {
+            segment(
+                &mut out,
+                0xEE,
+                &[b'A', b'd', b'o', b'b', b'e', 0, 100, 0, 0, 0, 0, t],
+            );
+        }
+        if let Some(icc) = icc {
+            segment(&mut out, 0xE2, &[ICC_TAG, &[1, 1], icc].concat());
+        }
+        segment(&mut out, 0xDB, &[[0u8].as_slice(), &[1; 64]].concat());
+        let w = (blocks.len() * 8) as u16;
+        let mut sof = vec![8, 0, 8, (w >> 8) as u8, w as u8, 4];
+        for id in 1..=4 {
+            sof.extend_from_slice(&[id, 0x11, 0]);
+        }
+        segment(&mut out, 0xC0, &sof);
+        let dc: Vec<u8> = [[0x00].as_slice(), &DC_BITS, &(0..12).collect::<Vec<u8>>()].concat();
+        segment(&mut out, 0xC4, &dc);
+        segment(
+            &mut out,
+            0xC4,
+            &[[0x10, 1].as_slice(), &[0; 15], &[0]].concat(),
+        );
+        segment(&mut out, 0xDA, &[4, 1, 0, 2, 0, 3, 0, 4, 0, 0, 63, 0]);
+
+        let (mut acc, mut nbits, mut prev) = (0u32, 0u32, [0i32; 4]);
+        let mut put = |out: &mut Vec<u8>, bits: u32, len: u32| {
+            acc = (acc << len) | bits;
+            nbits += len;
+            while nbits >= 8 {
+                let byte = (acc >> (nbits - 8)) as u8;
+                out.push(byte);
+                if byte == 0xFF {
+                    out.push(0);
+                }
+                nbits -= 8;
+            }
+        };
+        for block in blocks {
+            for c in 0..4 {
+                let dc = 8 * (block[c] as i32 - 128);
+                let diff = dc - prev[c];
+                prev[c] = dc;
+                let cat = 32 - diff.unsigned_abs().leading_zeros();
+                let (code, len) = dc_codes[cat as usize];
+                put(&mut out, code as u32, len as u32);
+                if cat > 0 {
+                    let v = if diff < 0 { diff - 1 } else { diff };
+                    put(&mut out, v as u32 & ((1 << cat) - 1), cat);
+                }
+                put(&mut out, 0, 1);
+            }
+        }
+        // 末字节以 1 填充
+        put(&mut out, 0x7F, 7);
+        out.extend_from_slice(&[0xFF, 0xD9]);
+        out
+    }
+
+    fn rgb_at(data: &[u8], block: u32) -> [u8; 3] {
+        let info = probe(data).unwrap();
+        decode(data, &info)
+            .unwrap()
+            .to_rgb8()
+            .get_pixel(block * 8 + 4, 4)
+            .0
+    }
+
+    fn close(a: [u8; 3], b: [u8; 3]) -> bool {
+        a.iter().zip(b).all(|(&x, y)| x.abs_diff(y) <= 3)
+    }
+
+    #[test]
+    fn test_cmyk_ycck_decode() {
+        // 墨量：青 / 纯黑 / 50% 品红
+        let inks = [[255, 0, 0, 0], [0, 0, 0, 255], [0, 128, 0, 0]];
+        let expect = [[0, 255, 255], [0, 0, 0], [255, 127, 255]];
+
+        let adobe: Vec<[u8; 4]> = inks.iter().map(|p| p.map(|v| 255 - v)).collect();
+        let plain = encode_fixture(&inks, None, None);
+        let inverted = encode_fixture(&adobe, Some(0), None);
+        let ycck: Vec<[u8; 4]> = inks
+            .iter()
+            .map(|&[c, m, y, k]| {
+                let (r, g, b) = (c as f32, m as f32, y as f32);
+                let luma = 0.299 * r + 0.587 * g + 0.114 * b;
+                let cb = 128.0 + (b - luma) / 1.772;
+                let cr = 128.0 + (r - luma) / 1.402;
+                let [l, cb, cr] = [luma, cb, cr].map(|v| v.round() as u8);
+                [l, cb, cr, 255 - k]
+            })
+            .collect();
+        let ycck = encode_fixture(&ycck, Some(2), None);
+
+        assert!(!probe(&plain).unwrap().inverted);
+        assert_eq!(probe(&ycck).unwrap().transform, Transform::Ycck);
+        for data in [&plain, &inverted, &ycck] {
+            for (i, e) in expect.iter().enumerate() {
+                let got = rgb_at(data, i as u32);
+                assert!(close(got, *e), "block {i}: {got:?} vs {e:?}");
+            }
+        }
+
+        let mut rgb = Vec::new();
+        image::RgbImage::new(16, 16)
+            .write_to(
+                &mut std::io::Cursor::new(&mut rgb),
+                image::ImageFormat::Jpeg,
+            )
+            .unwrap();
+        assert!(probe(&rgb).is_none());
+    }
+
+    /// 只看 K 的 CMYK 配置（lut8，A2B0 → Lab）：L = 100 × (1 - K)
+    fn k_only_icc() -> Vec<u8> {
+        let mut lut = b"mft1\0\0\0\0".to_vec();
+        lut.extend_from_slice(&[4, 3, 2, 0]);
+        for i in 0..9 {
+            let v: i32 = if i % 4 == 0 { 0x10000 } else { 0 };
+            lut.extend_from_slice(&v.to_be_bytes());
+        }
+        for _ in 0..4 {
+            lut.extend(0..=255u8);
+        }
+        for idx in 0..16 {
+            let k = idx & 1;
+            lut.extend_from_slice(&[if k == 1 { 0 } else { 255 }, 128, 128]);
+        }
+        for _ in 0..3 {
+            lut.extend(0..=255u8);
+        }
+
+        let size = 128 + 4 + 12 + lut.len();
+        let mut icc = (size as u32).to_be_bytes().to_vec();
+        icc.extend_from_slice(b"none");
+        icc.extend_from_slice(&[2, 0x10, 0, 0]);
+        icc.extend_from_slice(b"prtrCMYKLab ");
+        icc.extend_from_slice(&[0; 12]);
+        icc.extend_from_slice(b"acsp");
+        icc.resize(68, 0);
+        for v in [0x0000_F6D6u32, 0x0001_0000, 0x0000_D32D] {
+            icc.extend_from_slice(&v.to_be_bytes());
+        }
+        icc.resize(128, 0);
+        icc.extend_from_slice(&1u32.to_be_bytes());
+        icc.extend_from_slice(b"A2B0");
+        icc.extend_from_slice(&(144u32).to_be_bytes());
+        icc.extend_from_slice(&(lut.len() as u32).to_be_bytes());
+        icc.extend_from_slice(&lut);
+        icc
+    }
+
+    #[test]
+    fn test_icc_and_srgb_segments() {
+        let icc = k_only_icc();
+        // Adobe 反相存储的青 / 纯黑；配置只看 K，青色按 ICC 为白（朴素公式下才是青色）
+        let stored = [[0u8, 255, 255, 255], [255, 255, 255, 0]];
+        let data = encode_fixture(&stored, Some(0), Some(&icc));
+        assert!(
+            close(rgb_at(&data, 0), [255, 255, 255]),
+            "{:?}",
+            rgb_at(&data, 0)
+        );
+        assert!(close(rgb_at(&data, 1), [0, 0, 0]), "{:?}", rgb_at(&data, 1));
+
+        let segs = vec![
+            JpegSegment::new_with_contents(markers::APP1, Bytes::from_static(b"Exif\0\0")),
+            JpegSegment::new_with_contents(
+                markers::APP2,
+                Bytes::from([ICC_TAG, &[1, 1], &icc].concat()),
+            ),
+            JpegSegment::new_with_contents(markers::APP14, Bytes::from_static(b"Adobe\0d")),
+        ];
+        let out = rgb_segments(segs);
+        assert_eq!(out.len(), 2);
+        assert_eq!(out[0].marker(), markers::APP1);
+        let srgb = ColorProfile::new_from_slice(&out[1].contents()[14..]).unwrap();
+        assert_eq!(srgb.color_space, DataColorSpace::Rgb);
+    }
+}
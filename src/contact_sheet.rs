@@ -0,0 +1,507 @@
+//! v4.5：联系表（批量总览图：N 张缩略图按网格排版，供发片前人工过目）
+//!
+//! - 每格一张缩略图（取压缩后的输出，即客户实际拿到的画面），下方两行说明：文件名、原始→压缩体积；
+//!   失败项画浅红底格并标 FAILED，输出解码不了（透传的非图片等）画灰格；
+//! - 每页格数 `per_page` > 0 时分页，输出 `{名}_01.jpg`…；只有一页时不加页码；
+//! - 说明文字字体：`font` 指定 TTF/OTF → 系统常见字体（含中文）→ 内置 5x7 点阵（仅 ASCII，
+//!   其他字符显示为 ?），保证无字体环境也能出图；矢量字体需 cargo feature `fonts`，未启用时只用点阵；
+//! - 联系表按扩展名保存为 JPEG（.jpg/.jpeg）或 PNG（.png）。
+
+use anyhow::{anyhow, Result};
+use image::{imageops, Rgb, RgbImage, Rgba, RgbaImage};
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+/// 常见系统字体（按顺序探测，取第一个可解析的；中文字体优先）
+const SYSTEM_FONTS: [&str; 10] = [
+    "/System/Library/Fonts/PingFang.ttc",
+    "/System/Library/Fonts/STHeiti Medium.ttc",
+    "/System/Library/Fonts/Supplemental/Arial Unicode.ttf",
+    "C:\\Windows\\Fonts\\msyh.ttc",
+    "C:\\Windows\\Fonts\\simhei.ttf",
+    "C:\\Windows\\Fonts\\arial.ttf",
+    "/usr/share/fonts/opentype/noto/NotoSansCJK-Regular.ttc",
+    "/usr/share/fonts/noto-cjk/NotoSansCJK-Regular.ttc",
+    "/usr/share/fonts/truetype/wqy/wqy-microhei.ttc",
+    "/usr/share/fonts/truetype/dejavu/DejaVuSans.ttf",
+];
+
+/// 页边距 / 格间距（px）
+const MARGIN: u32 = 16;
+const GAP: u32 = 12;
+const BACKGROUND: Rgb<u8> = Rgb([255, 255, 255]);
+const CELL_BACKGROUND: Rgb<u8> = Rgb([238, 238, 238]);
+const FAILED_BACKGROUND: Rgb<u8> = Rgb([246, 213, 213]);
+const CAPTION_COLOR: [u8; 3] = [34, 34, 34];
+const DETAIL_COLOR: [u8; 3] = [102, 102, 102];
+const FAILED_COLOR: [u8; 3] = [200, 40, 40];
+
+/// 联系表配置（CLI / JSON 共用）
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields, default)]
+pub struct ContactSheetConfig {
+    /// 输出路径（.jpg / .jpeg / .png）；分页时追加 _01、_02…
+    pub path: String,
+    /// 每行格数
+    pub columns: u32,
+    /// 缩略图格边长（px）
+    pub cell: u32,
+    /// 每页格数（0 = 不分页）
+    pub per_page: u32,
+    /// 说明文字 TTF / OTF 字体（缺省探测系统字体，再退回内置点阵）
+    pub font: Option<String>,
+}
+
+impl Default for ContactSheetConfig {
+    fn default() -> Self {
+        Self {
+            path: String::new(),
+            columns: 6,
+            cell: 240,
+            per_page: 0,
+            font: None,
+        }
+    }
+}
+
+/// 联系表中的一格
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SheetEntry {
+    /// 缩略图来源（压缩输出；None = 无输出）
+    pub image: Option<PathBuf>,
+    /// 第一行说明（文件名）
+    pub caption: String,
+    pub original_bytes: Option<u64>,
+    pub compressed_bytes: Option<u64>,
+    pub failed: bool,
+}
+
+impl ContactSheetConfig {
+    /// 校验参数；指定了字体时顺带解析字体
+    pub fn validate(&self) -> Result<()> {
+        self.is_png()?;
+        if !(1..=20).contains(&self.columns) {
+            return Err(anyhow!("联系表每行格数须在 1-20: {}", self.columns));
+        }
+        if !(64..=1024).contains(&self.cell) {
+            return Err(anyhow!("联系表格边长须在 64-1024px: {}", self.cell));
+        }
+        if let Some(font) = &self.font {
+            load_font(font)?;
+        }
+        Ok(())
+    }
+
+    /// 按扩展名判定输出格式：PNG 返回 true、JPEG 返回 false
+    fn is_png(&self) -> Result<bool> {
+        let ext = Path::new(&self.path)
+            .extension()
+            .map(|e| e.to_string_lossy().to_ascii_lowercase())
+            .unwrap_or_default();
+        match ext.as_str() {
+            "jpg" | "jpeg" => Ok(false),
+            "png" => Ok(true),
+            _ => Err(anyhow!(
+                "联系表输出须为 .jpg / .jpeg / .png: '{}'",
+                self.path
+            )),
+        }
+    }
+
+    /// 第 `page`（1 起）页的输出路径；总页数为 1 时即 path 本身
+    pub fn page_path(&self, page: usize, pages: usize) -> PathBuf {
+        let path = PathBuf::from(&self.path);
+        if pages <= 1 {
+            return path;
+        }
+        let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+        let ext = path.extension().unwrap_or_default().to_string_lossy();
+        path.with_file_name(format!("{}_{:02}.{}", stem, page, ext))
+    }
+}
+
+/// 渲染并保存联系表，返回各页路径（空批次不出图）
+pub fn render(config: &ContactSheetConfig, entries: &[SheetEntry]) -> Result<Vec<PathBuf>> {
+    config.validate()?;
+    let png = config.is_png()?;
+    if entries.is_empty() {
+        return Ok(Vec::new());
+    }
+    let font = match &config.font {
+        Some(f) => load_font(f)?,
+        None => SYSTEM_FONTS
+            .iter()
+            .filter(|p| Path::new(p).is_file())
+            .find_map(|p| load_font(p).ok())
+            .unwrap_or(CaptionFont::Bitmap),
+    };
+    let per_page = if config.per_page == 0 {
+        entries.len()
+    } else {
+        config.per_page as usize
+    };
+    let pages: Vec<&[SheetEntry]> = entries.chunks(per_page).collect();
+    let columns = (config.columns as usize).min(per_page).max(1);
+    let mut written = Vec::with_capacity(pages.len());
+    for (i, page) in pages.iter().enumerate() {
+        let sheet = render_page(page, columns as u32, config.cell, &font);
+        let out = config.page_path(i + 1, pages.len());
+        if let Some(dir) = out.parent().filter(|d| !d.as_os_str().is_empty()) {
+            std::fs::create_dir_all(dir)?;
+        }
+        if png {
+            sheet
+                .save(&out)
+                .map_err(|e| anyhow!("联系表写入失败 {}: {}", out.display(), e))?;
+        } else {
+            let (w, h) = sheet.dimensions();
+            let data = mozjpeg_rs::Encoder::default()
+                .quality(85)
+                .progressive(true)
+                .encode_rgb(sheet.as_raw(), w, h)
+                .map_err(|e| anyhow!("联系表编码失败: {}", e))?;
+            std::fs::write(&out, data)
+                .map_err(|e| anyhow!("联系表写入失败 {}: {}", out.display(), e))?;
+        }
+        written.push(out);
+    }
+    Ok(written)
+}
+
+/// 排一页：缩略图并行解码，按行列贴格、写两行说明
+fn render_page(entries: &[SheetEntry], columns: u32, cell: u32, font: &CaptionFont) -> RgbImage {
+    let px = (cell as f32 / 12.0).clamp(12.0, 22.0);
+    let line_h = (px * 1.3).ceil() as u32;
+    let caption_h = line_h * 2 + 6;
+    let rows = (entries.len() as u32).div_ceil(columns);
+    let width = MARGIN * 2 + columns * cell + (columns - 1) * GAP;
+    let height = MARGIN * 2 + rows * (cell + caption_h) + (rows - 1) * GAP;
+    let mut sheet = RgbImage::from_pixel(width, height, BACKGROUND);
+
+    let thumbs: Vec<Option<RgbImage>> = entries
+        .par_iter()
+        .map(|e| {
+            // 等比缩进格内（不放大）
+            let img = image::open(e.image.as_ref()?).ok()?.to_rgb8();
+            let (w, h) = img.dimensions();
+            let s = (cell as f32 / w.max(h) as f32).min(1.0);
+            let (tw, th) = (
+                ((w as f32 * s).round() as u32).clamp(1, cell),
+                ((h as f32 * s).round() as u32).clamp(1, cell),
+            );
+            Some(imageops::thumbnail(&img, tw, th))
+        })
+        .collect();
+
+    for (i, (entry, thumb)) in entries.iter().zip(thumbs).enumerate() {
+        let x = MARGIN + (i as u32 % columns) * (cell + GAP);
+        let y = MARGIN + (i as u32 / columns) * (cell + caption_h + GAP);
+        let bg = if entry.failed {
+            FAILED_BACKGROUND
+        } else {
+            CELL_BACKGROUND
+        };
+        imageops::replace(
+            &mut sheet,
+            &RgbImage::from_pixel(cell, cell, bg),
+            x as i64,
+            y as i64,
+        );
+        if let Some(t) = thumb {
+            // 缩略图居中
+            let (tw, th) = t.dimensions();
+            imageops::replace(
+                &mut sheet,
+                &t,
+                (x + (cell - tw) / 2) as i64,
+                (y + (cell - th) / 2) as i64,
+            );
+        }
+        let detail = if entry.failed {
+            "FAILED".to_string()
+        } else {
+            match (entry.original_bytes, entry.compressed_bytes) {
+                (Some(o), Some(c)) => format!("{} → {}", human_bytes(o), human_bytes(c)),
+                (_, Some(c)) => human_bytes(c),
+                _ => String::new(),
+            }
+        };
+        let detail_color = if entry.failed {
+            FAILED_COLOR
+        } else {
+            DETAIL_COLOR
+        };
+        let text_y = y + cell + 4;
+        draw_text(
+            &mut sheet,
+            font,
+            &entry.caption,
+            px,
+            CAPTION_COLOR,
+            (x, text_y),
+            cell,
+        );
+        draw_text(
+            &mut sheet,
+            font,
+            &detail,
+            px,
+            detail_color,
+            (x, text_y + line_h),
+            cell,
+        );
+    }
+    sheet
+}
+
+/// 体积可读化：B / KB / MB（1024 进制）
+pub fn human_bytes(bytes: u64) -> String {
+    if bytes < 1024 {
+        format!("{} B", bytes)
+    } else if bytes < 1024 * 1024 {
+        format!("{:.0} KB", bytes as f64 / 1024.0)
+    } else {
+        format!("{:.1} MB", bytes as f64 / (1024.0 * 1024.0))
+    }
+}
+
+#[cfg(feature = "fonts")]
+fn load_font(path: &str) -> Result<CaptionFont> {
+    let bytes = std::fs::read(path).map_err(|e| anyhow!("联系表字体读取失败 {}: {}", path, e))?;
+    ab_glyph::FontVec::try_from_vec(bytes)
+        .map(CaptionFont::Vector)
+        .map_err(|_| anyhow!("联系表字体解析失败（需 TTF/OTF）: {}", path))
+}
+
+#[cfg(not(feature = "fonts"))]
+fn load_font(path: &str) -> Result<CaptionFont> {
+    Err(anyhow!(
+        "联系表字体需以 --features fonts 编译（本构建只有内置点阵）: {}",
+        path
+    ))
+}
+
+/// 说明文字字体：矢量字体或内置点阵
+enum CaptionFont {
+    #[cfg(feature = "fonts")]
+    Vector(ab_glyph::FontVec),
+    Bitmap,
+}
+
+impl CaptionFont {
+    fn render(&self, text: &str, px: f32, color: [u8; 3]) -> Option<RgbaImage> {
+        match self {
+            #[cfg(feature = "fonts")]
+            CaptionFont::Vector(font) => crate::watermark::render_text(font, text, px, color),
+            CaptionFont::Bitmap => render_bitmap(text, px, color),
+        }
+    }
+}
+
+/// 单行文字画到 (x, y)，超出 max_width 时截断并补省略号
+fn draw_text(
+    sheet: &mut RgbImage,
+    font: &CaptionFont,
+    text: &str,
+    px: f32,
+    color: [u8; 3],
+    (x, y): (u32, u32),
+    max_width: u32,
+) {
+    let chars: Vec<char> = text.chars().collect();
+    let fits = |img: &RgbaImage| img.width() <= max_width;
+    let mut label = match font.render(text, px, color) {
+        Some(img) => img,
+        None => return,
+    };
+    if !fits(&label) {
+        // 二分最长可容纳的前缀
+        let (mut lo, mut hi) = (0usize, chars.len());
+        let mut best = None;
+        while lo < hi {
+            let mid = (lo + hi).div_ceil(2);
+            let s: String = chars[..mid].iter().chain(['…'].iter()).collect();
+            match font.render(&s, px, color).filter(fits) {
+                Some(img) => {
+                    best = Some(img);
+                    lo = mid;
+                }
+                None => hi = mid - 1,
+            }
+        }
+        label = match best.or_else(|| font.render("…", px, color).filter(fits)) {
+            Some(img) => img,
+            None => return,
+        };
+    }
+    for (lx, ly, p) in label.enumerate_pixels() {
+        let (sx, sy) = (x + lx, y + ly);
+        if sx >= sheet.width() || sy >= sheet.height() || p[3] == 0 {
+            continue;
+        }
+        let a = p[3] as u32;
+        let dst = sheet.get_pixel_mut(sx, sy);
+        for c in 0..3 {
+            dst[c] = ((p[c] as u32 * a + dst[c] as u32 * (255 - a) + 127) / 255) as u8;
+        }
+    }
+}
+
+/// 内置 5x7 点阵（行自上而下，每行低 5 位、高位在左）；小写按大写显示
+const BITMAP_GLYPHS: [(char, [u8; 7]); 48] = [
+    (' ', [0, 0, 0, 0, 0, 0, 0]),
+    ('0', [0x0E, 0x11, 0x13, 0x15, 0x19, 0x11, 0x0E]),
+    ('1', [0x04, 0x0C, 0x04, 0x04, 0x04, 0x04, 0x0E]),
+    ('2', [0x0E, 0x11, 0x01, 0x02, 0x04, 0x08, 0x1F]),
+    ('3', [0x1F, 0x02, 0x04, 0x02, 0x01, 0x11, 0x0E]),
+    ('4', [0x02, 0x06, 0x0A, 0x12, 0x1F, 0x02, 0x02]),
+    ('5', [0x1F, 0x10, 0x1E, 0x01, 0x01, 0x11, 0x0E]),
+    ('6', [0x06, 0x08, 0x10, 0x1E, 0x11, 0x11, 0x0E]),
+    ('7', [0x1F, 0x01, 0x02, 0x04, 0x08, 0x08, 0x08]),
+    ('8', [0x0E, 0x11, 0x11, 0x0E, 0x11, 0x11, 0x0E]),
+    ('9', [0x0E, 0x11, 0x11, 0x0F, 0x01, 0x02, 0x0C]),
+    ('A', [0x0E, 0x11, 0x11, 0x1F, 0x11, 0x11, 0x11]),
+    ('B', [0x1E, 0x11, 0x11, 0x1E, 0x11, 0x11, 0x1E]),
+    ('C', [0x0E, 0x11, 0x10, 0x10, 0x10, 0x11, 0x0E]),
+    ('D', [0x1C, 0x12, 0x11, 0x11, 0x11, 0x12, 0x1C]),
+    ('E', [0x1F, 0x10, 0x10, 0x1E, 0x10, 0x10, 0x1F]),
+    ('F', [0x1F, 0x10, 0x10, 0x1E, 0x10, 0x10, 0x10]),
+    ('G', [0x0E, 0x11, 0x10, 0x17, 0x11, 0x11, 0x0F]),
+    ('H', [0x11, 0x11, 0x11, 0x1F, 0x11, 0x11, 0x11]),
+    ('I', [0x0E, 0x04, 0x04, 0x04, 0x04, 0x04, 0x0E]),
+    ('J', [0x07, 0x02, 0x02, 0x02, 0x02, 0x12, 0x0C]),
+    ('K', [0x11, 0x12, 0x14, 0x18, 0x14, 0x12, 0x11]),
+    ('L', [0x10, 0x10, 0x10, 0x10, 0x10, 0x10, 0x1F]),
+    ('M', [0x11, 0x1B, 0x15, 0x15, 0x11, 0x11, 0x11]),
+    ('N', [0x11, 0x11, 0x19, 0x15, 0x13, 0x11, 0x11]),
+    ('O', [0x0E, 0x11, 0x11, 0x11, 0x11, 0x11, 0x0E]),
+    ('P', [0x1E, 0x11, 0x11, 0x1E, 0x10, 0x10, 0x10]),
+    ('Q', [0x0E, 0x11, 0x11, 0x11, 0x15, 0x12, 0x0D]),
+    ('R', [0x1E, 0x11, 0x11, 0x1E, 0x14, 0x12, 0x11]),
+    ('S', [0x0F, 0x10, 0x10, 0x0E, 0x01, 0x01, 0x1E]),
+    ('T', [0x1F, 0x04, 0x04, 0x04, 0x04, 0x04, 0x04]),
+    ('U', [0x11, 0x11, 0x11, 0x11, 0x11, 0x11, 0x0E]),
+    ('V', [0x11, 0x11, 0x11, 0x11, 0x11, 0x0A, 0x04]),
+    ('W', [0x11, 0x11, 0x11, 0x15, 0x15, 0x15, 0x0A]),
+    ('X', [0x11, 0x11, 0x0A, 0x04, 0x0A, 0x11, 0x11]),
+    ('Y', [0x11, 0x11, 0x11, 0x0A, 0x04, 0x04, 0x04]),
+    ('Z', [0x1F, 0x01, 0x02, 0x04, 0x08, 0x10, 0x1F]),
+    ('.', [0, 0, 0, 0, 0, 0x0C, 0x0C]),
+    ('-', [0, 0, 0, 0x1F, 0, 0, 0]),
+    ('_', [0, 0, 0, 0, 0, 0, 0x1F]),
+    ('(', [0x02, 0x04, 0x08, 0x08, 0x08, 0x04, 0x02]),
+    (')', [0x08, 0x04, 0x02, 0x02, 0x02, 0x04, 0x08]),
+    ('>', [0x08, 0x04, 0x02, 0x01, 0x02, 0x04, 0x08]),
+    ('?', [0x0E, 0x11, 0x01, 0x02, 0x04, 0, 0x04]),
+    (':', [0, 0x0C, 0x0C, 0, 0x0C, 0x0C, 0]),
+    ('/', [0, 0x01, 0x02, 0x04, 0x08, 0x10, 0]),
+    ('%', [0x18, 0x19, 0x02, 0x04, 0x08, 0x13, 0x03]),
+    ('+', [0, 0x04, 0x04, 0x1F, 0x04, 0x04, 0]),
+];
+
+/// 点阵渲染：每点放大为 scale×scale（scale 取字号 / 9 的整数倍），字距 1 点
+fn render_bitmap(text: &str, px: f32, color: [u8; 3]) -> Option<RgbaImage> {
+    let text = text.replace('→', "->").replace('…', "..");
+    let scale = ((px / 9.0).round() as u32).max(1);
+    let chars: Vec<[u8; 7]> = text
+        .chars()
+        .map(|c| {
+            let c = c.to_ascii_uppercase();
+            let glyph = |c: char| BITMAP_GLYPHS.iter().find(|(g, _)| *g == c);
+            glyph(c)
+                .or_else(|| glyph('?'))
+                .map(|(_, rows)| *rows)
+                .unwrap_or_default()
+        })
+        .collect();
+    if chars.is_empty() {
+        return None;
+    }
+    let w = (chars.len() as u32 * 6 - 1) * scale;
+    let h = 7 * scale;
+    let mut out = RgbaImage::from_pixel(w, h, Rgba([color[0], color[1], color[2], 0]));
+    for (i, rows) in chars.iter().enumerate() {
+        for (ry, bits) in rows.iter().enumerate() {
+            for rx in 0..5u32 {
+                if bits & (0x10 >> rx) == 0 {
+                    continue;
+                }
+                let x0 = (i as u32 * 6 + rx) * scale;
+                let y0 = ry as u32 * scale;
+                for dy in 0..scale {
+                    for dx in 0..scale {
+                        out.get_pixel_mut(x0 + dx, y0 + dy)[3] = 255;
+                    }
+                }
+            }
+        }
+    }
+    Some(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_pages_and_layout() {
+        let dir = std::env::temp_dir().join(format!("xtap_contact_sheet_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let photo = dir.join("a.png");
+        RgbImage::from_pixel(300, 200, Rgb([20, 120, 200]))
+            .save(&photo)
+            .unwrap();
+        let entry = |name: &str, failed: bool| SheetEntry {
+            image: (!failed).then(|| photo.clone()),
+            caption: name.to_string(),
+            original_bytes: Some(3 * 1024 * 1024),
+            compressed_bytes: Some(300 * 1024),
+            failed,
+        };
+        let entries = vec![
+            entry("a.png", false),
+            entry("a_very_long_file_name_that_does_not_fit.png", false),
+            entry("b.png", true),
+            entry("c.png", false),
+            entry("d.png", false),
+        ];
+        let config = ContactSheetConfig {
+            path: dir.join("sheet.jpg").display().to_string(),
+            columns: 2,
+            cell: 100,
+            per_page: 4,
+            font: None,
+        };
+        let pages = render(&config, &entries).unwrap();
+        assert_eq!(
+            pages,
+            vec![dir.join("sheet_01.jpg"), dir.join("sheet_02.jpg")]
+        );
+        // 2 列 x 2 行：宽 16*2 + 2*100 + 12，高 16*2 + 2*(100+说明区) + 12
+        let first = image::open(&pages[0]).unwrap();
+        assert_eq!(first.width(), 244);
+        let caption_h = (12.0f32 * 1.3).ceil() as u32 * 2 + 6;
+        assert_eq!(first.height(), 32 + 2 * (100 + caption_h) + 12);
+        // 缩略图 100x66 居中贴在第 1 格
+        let px = first.to_rgb8().get_pixel(16 + 50, 16 + 50).0;
+        assert!(px[2] > 150 && px[0] < 80, "{:?}", px);
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert!(ContactSheetConfig {
+            path: "x.webp".into(),
+            ..Default::default()
+        }
+        .validate()
+        .is_err());
+    }
+
+    #[test]
+    fn test_bitmap_caption_and_sizes() {
+        let img = render_bitmap("ab 1->2", 18.0, [0, 0, 0]).unwrap();
+        assert_eq!((img.width(), img.height()), ((7 * 6 - 1) * 2, 14));
+        assert_eq!(human_bytes(512), "512 B");
+        assert_eq!(human_bytes(300 * 1024), "300 KB");
+        assert_eq!(human_bytes(3 * 1024 * 1024 + 200 * 1024), "3.2 MB");
+    }
+}
@@ -0,0 +1,231 @@
+//! v4.5：显著性驱动的智能裁剪（平台画幅：小红书 3:4 / Instagram 4:5）
+//!
+//! 平台对非目标画幅的图一律居中裁，主体偏一侧就会被切掉。这里先在本地按目标画幅裁好：
+//! - 打分图 = `perceptual::saliency_mask`（频谱残差显著性，按局部梯度加权去伪影）
+//!   + `is_skin_color` 肤色先验（人像主体优先入框）；
+//! - 只裁多出来的那一轴，把二维问题投影成一维剖面，前缀和滑窗 O(n) 求窗口得分；
+//! - 得分接近最优（≥98%）的窗口里，选中心离主体重心最近的一个——主体完整入框且尽量居中，
+//!   而不是被推到窗口边缘；平坦图无主体时自然退化为居中裁。
+//!
+//! 全程在长边 256px 的缩略图上计算（毫秒级），坐标再映射回原图。
+
+use crate::perceptual::saliency_mask;
+use image::{imageops, RgbaImage};
+use serde::{Deserialize, Serialize};
+
+/// 打分缩略图长边：足够定位主体，且与原图尺寸无关地保持毫秒级
+const SCORE_DIM: u32 = 256;
+/// 肤色先验权重（显著性 0-1 之外的额外加分）
+const SKIN_WEIGHT: f32 = 0.6;
+/// 梯度置信度满分阈值（亮度差，0-255）
+const GRAD_REF: f32 = 12.0;
+/// 候选窗口得分下限（相对最优）：在「主体完整入框」的窗口里再挑居中的
+const NEAR_BEST: f64 = 0.98;
+
+/// 裁剪窗口（原图像素坐标）
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CropRect {
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
+/// 解析画幅比："3:4" / "4x5" / "16/9" → (宽, 高)；非法或含 0 返回 None
+pub fn parse_aspect(s: &str) -> Option<(u32, u32)> {
+    let (a, b) = s.trim().split_once([':', 'x', 'X', '/'])?;
+    let a: u32 = a.trim().parse().ok()?;
+    let b: u32 = b.trim().parse().ok()?;
+    (a > 0 && b > 0).then_some((a, b))
+}
+
+/// 计算目标画幅下的最佳裁剪窗口；原图已符合画幅（误差 <1%）时返回 None
+pub fn smart_crop_rect(img: &RgbaImage, aspect: (u32, u32)) -> Option<CropRect> {
+    let (w, h) = img.dimensions();
+    if w == 0 || h == 0 || aspect.0 == 0 || aspect.1 == 0 {
+        return None;
+    }
+    let target = aspect.0 as f64 / aspect.1 as f64;
+    let current = w as f64 / h as f64;
+    if (current / target - 1.0).abs() < 0.01 {
+        return None;
+    }
+    // 只裁多出来的那一轴
+    let horizontal = current > target;
+    let (cw, ch) = if horizontal {
+        (((h as f64 * target).round() as u32).clamp(1, w), h)
+    } else {
+        (w, ((w as f64 / target).round() as u32).clamp(1, h))
+    };
+
+    let scale = (SCORE_DIM as f64 / w.max(h) as f64).min(1.0);
+    let sw = ((w as f64 * scale).round() as u32).max(1);
+    let sh = ((h as f64 * scale).round() as u32).max(1);
+    let small = if scale < 1.0 {
+        imageops::resize(img, sw, sh, imageops::FilterType::Triangle)
+    } else {
+        img.clone()
+    };
+    let sal = saliency_mask(&small);
+    // 显著性按掩码最大值归一化，平坦区也会被放大出 FFT 周期边界伪影；
+    // 乘以局部梯度置信度（无纹理处≈0），肤色先验单独叠加（肤色块内部本身平坦）
+    let luma: Vec<f32> = small
+        .pixels()
+        .map(|p| 0.299 * p[0] as f32 + 0.587 * p[1] as f32 + 0.114 * p[2] as f32)
+        .collect();
+    let (swu, shu) = (sw as usize, sh as usize);
+    let weights: Vec<f64> = small
+        .pixels()
+        .enumerate()
+        .map(|(i, p)| {
+            let (x, y) = (i % swu, i / swu);
+            let gx = if x + 1 < swu {
+                (luma[i + 1] - luma[i]).abs()
+            } else {
+                0.0
+            };
+            let gy = if y + 1 < shu {
+                (luma[i + swu] - luma[i]).abs()
+            } else {
+                0.0
+            };
+            let confidence = (gx.max(gy) / GRAD_REF).min(1.0);
+            (sal[i] * confidence + SKIN_WEIGHT * crate::is_skin_color(p[0], p[1], p[2])) as f64
+        })
+        .collect();
+
+    // 沿可滑动轴投影成一维剖面
+    let (len, win) = if horizontal {
+        (
+            sw as usize,
+            ((cw as f64 * scale).round() as usize).clamp(1, sw as usize),
+        )
+    } else {
+        (
+            sh as usize,
+            ((ch as f64 * scale).round() as usize).clamp(1, sh as usize),
+        )
+    };
+    let mut profile = vec![0f64; len];
+    for (i, wgt) in weights.iter().enumerate() {
+        let (x, y) = (i % swu, i / swu);
+        profile[if horizontal { x } else { y }] += wgt;
+    }
+
+    let mut prefix = vec![0f64; len + 1];
+    for i in 0..len {
+        prefix[i + 1] = prefix[i] + profile[i];
+    }
+    let window_score = |start: usize| prefix[start + win] - prefix[start];
+    let best = (0..=len - win).map(window_score).fold(0f64, f64::max);
+
+    // 主体重心（平方加权突出峰值，压低背景底噪）
+    let (mut num, mut den) = (0f64, 0f64);
+    for (i, v) in profile.iter().enumerate() {
+        num += (i as f64 + 0.5) * v * v;
+        den += v * v;
+    }
+    let centroid = if den > 0.0 {
+        num / den
+    } else {
+        len as f64 / 2.0
+    };
+
+    let start = (0..=len - win)
+        .filter(|&s| window_score(s) >= best * NEAR_BEST)
+        .min_by(|&a, &b| {
+            let da = (a as f64 + win as f64 / 2.0 - centroid).abs();
+            let db = (b as f64 + win as f64 / 2.0 - centroid).abs();
+            da.total_cmp(&db)
+        })
+        .unwrap_or((len - win) / 2);
+
+    let rect = if horizontal {
+        let x = ((start as f64 / scale).round() as u32).min(w - cw);
+        CropRect {
+            x,
+            y: 0,
+            width: cw,
+            height: ch,
+        }
+    } else {
+        let y = ((start as f64 / scale).round() as u32).min(h - ch);
+        CropRect {
+            x: 0,
+            y,
+            width: cw,
+            height: ch,
+        }
+    };
+    Some(rect)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::Rgba;
+
+    /// 灰色平底 + 一个偏心的棋盘格圆盘（高对比纹理 = 显著主体）
+    fn off_center_subject(w: u32, h: u32, cx: u32, cy: u32, r: u32) -> RgbaImage {
+        let mut img = RgbaImage::from_pixel(w, h, Rgba([128, 128, 128, 255]));
+        for y in cy.saturating_sub(r)..(cy + r).min(h) {
+            for x in cx.saturating_sub(r)..(cx + r).min(w) {
+                let (dx, dy) = (x as i64 - cx as i64, y as i64 - cy as i64);
+                if dx * dx + dy * dy <= (r * r) as i64 {
+                    let v = if (x / 8 + y / 8) % 2 == 0 { 20 } else { 235 };
+                    img.put_pixel(x, y, Rgba([v, v, v, 255]));
+                }
+            }
+        }
+        img
+    }
+
+    #[test]
+    fn test_parse_aspect_forms() {
+        assert_eq!(parse_aspect("3:4"), Some((3, 4)));
+        assert_eq!(parse_aspect(" 4x5 "), Some((4, 5)));
+        assert_eq!(parse_aspect("16/9"), Some((16, 9)));
+        assert_eq!(parse_aspect("0:4"), None);
+        assert_eq!(parse_aspect("abc"), None);
+    }
+
+    #[test]
+    fn test_crop_window_follows_off_center_subject() {
+        // 横图 1200x600 → 3:4 竖裁（窗口宽 450），主体在右侧 x≈950
+        let img = off_center_subject(1200, 600, 950, 300, 120);
+        let r = smart_crop_rect(&img, (3, 4)).expect("画幅不符应裁剪");
+        assert_eq!((r.width, r.height), (450, 600));
+        assert!(
+            r.x <= 950 - 120 && r.x + r.width >= 950 + 120,
+            "主体应完整入框: {:?}",
+            r
+        );
+
+        // 镜像：主体在左侧 x≈250
+        let img = off_center_subject(1200, 600, 250, 300, 120);
+        let r = smart_crop_rect(&img, (3, 4)).unwrap();
+        assert!(r.x <= 250 - 120 && r.x + r.width >= 250 + 120, "{:?}", r);
+    }
+
+    #[test]
+    fn test_crop_prefers_skin_region_vertically() {
+        // 竖图 600x1200 → 1:1（窗口高 600），肤色块在上部 y≈250
+        let mut img = RgbaImage::from_pixel(600, 1200, Rgba([90, 110, 140, 255]));
+        for y in 150..350 {
+            for x in 200..400 {
+                img.put_pixel(x, y, Rgba([224, 172, 140, 255]));
+            }
+        }
+        let r = smart_crop_rect(&img, (1, 1)).unwrap();
+        assert_eq!((r.width, r.height), (600, 600));
+        assert!(r.y <= 150, "肤色区域应入框: {:?}", r);
+    }
+
+    #[test]
+    fn test_flat_image_crops_centered_and_matching_aspect_skips() {
+        let flat = RgbaImage::from_pixel(1000, 500, Rgba([128, 128, 128, 255]));
+        let r = smart_crop_rect(&flat, (1, 1)).unwrap();
+        assert_eq!(r.x, 250);
+        assert!(smart_crop_rect(&flat, (2, 1)).is_none());
+    }
+}
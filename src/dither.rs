@@ -0,0 +1,347 @@
+//! v4.5：16 位 / 浮点（HDR）源的高精度缩放与抖动量化
+//!
+//! 直接 `to_rgba8()` 再缩放会把 16 位渐变（天空、影棚背景纸）压成 8 位台阶，JPEG 编码后条带明显。
+//! 高位深源改为：
+//! - 16 位源按 U16x4、浮点源按 F32x4 缩放（与 8 位同一重采样器）；
+//! - 浮点源可选色调映射（Reinhard / ACES，按线性光处理并做 sRGB 编码）；缺省按 [0,1] 截断，与原先一致；
+//! - 最后量化到 8 位时叠加蓝噪声（void-and-cluster 64×64 阈值图，全进程生成一次）或 8×8 Bayer 有序抖动，
+//!   台阶被打散为人眼不敏感的高频噪声，局部均值仍等于原始高精度值。
+
+use anyhow::{anyhow, Result};
+use fast_image_resize as fr;
+use image::{DynamicImage, RgbaImage};
+use std::sync::OnceLock;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DitherMode {
+    /// 四舍五入（不抖动）
+    None,
+    /// 8×8 Bayer 有序抖动
+    Ordered,
+    /// 蓝噪声阈值抖动
+    BlueNoise,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ToneMap {
+    /// 截断到 [0,1]（不做色调映射）
+    Clip,
+    Reinhard,
+    Aces,
+}
+
+impl DitherMode {
+    pub fn parse(s: &str) -> Option<Self> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "none" | "off" => Some(Self::None),
+            "ordered" | "bayer" => Some(Self::Ordered),
+            "blue-noise" | "bluenoise" | "blue_noise" => Some(Self::BlueNoise),
+            _ => None,
+        }
+    }
+}
+
+impl ToneMap {
+    pub fn parse(s: &str) -> Option<Self> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "none" | "clip" => Some(Self::Clip),
+            "reinhard" => Some(Self::Reinhard),
+            "aces" => Some(Self::Aces),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DitherSpec {
+    pub mode: DitherMode,
+    /// 仅作用于浮点源
+    pub tone_map: ToneMap,
+}
+
+impl Default for DitherSpec {
+    fn default() -> Self {
+        Self {
+            mode: DitherMode::BlueNoise,
+            tone_map: ToneMap::Clip,
+        }
+    }
+}
+
+/// 是否为高位深源（16 位整数或 32 位浮点）
+pub fn is_high_depth(img: &DynamicImage) -> bool {
+    use image::ColorType::*;
+    matches!(img.color(), L16 | La16 | Rgb16 | Rgba16 | Rgb32F | Rgba32F)
+}
+
+/// 高精度缩放到 `width`×`height` 后抖动量化为 8 位 RGBA
+pub fn resize_to_rgba8(
+    img: &DynamicImage,
+    width: u32,
+    height: u32,
+    spec: &DitherSpec,
+) -> Result<RgbaImage> {
+    let (w, h) = (img.width(), img.height());
+    let float = matches!(
+        img,
+        DynamicImage::ImageRgb32F(_) | DynamicImage::ImageRgba32F(_)
+    );
+    let (pixel_type, raw): (fr::PixelType, Vec<u8>) = if float {
+        let raw = img.to_rgba32f().into_raw();
+        (
+            fr::PixelType::F32x4,
+            raw.iter().flat_map(|v| v.to_ne_bytes()).collect(),
+        )
+    } else {
+        let raw = img.to_rgba16().into_raw();
+        (
+            fr::PixelType::U16x4,
+            raw.iter().flat_map(|v| v.to_ne_bytes()).collect(),
+        )
+    };
+    let src = fr::images::Image::from_vec_u8(w, h, raw, pixel_type)?;
+    let mut dst = fr::images::Image::new(width, height, pixel_type);
+    fr::Resizer::new().resize(&src, &mut dst, None)?;
+
+    let values: Vec<f32> = if float {
+        dst.buffer()
+            .chunks_exact(4)
+            .map(|b| f32::from_ne_bytes([b[0], b[1], b[2], b[3]]))
+            .collect()
+    } else {
+        dst.buffer()
+            .chunks_exact(2)
+            .map(|b| u16::from_ne_bytes([b[0], b[1]]) as f32 / 65535.0)
+            .collect()
+    };
+    let tone_map = if float { spec.tone_map } else { ToneMap::Clip };
+    quantize(&values, width, height, spec.mode, tone_map)
+}
+
+/// RGBA 浮点（[0,1]，浮点源可超出）→ 8 位；alpha 只四舍五入不抖动
+fn quantize(
+    values: &[f32],
+    width: u32,
+    height: u32,
+    mode: DitherMode,
+    tone_map: ToneMap,
+) -> Result<RgbaImage> {
+    let noise = match mode {
+        DitherMode::BlueNoise => Some(blue_noise()),
+        _ => None,
+    };
+    let mut out = Vec::with_capacity(values.len());
+    for (i, px) in values.chunks_exact(4).enumerate() {
+        let (x, y) = (i % width as usize, i / width as usize);
+        for (c, &v) in px[..3].iter().enumerate() {
+            let v = match tone_map {
+                ToneMap::Clip => v,
+                ToneMap::Reinhard => srgb_encode(v.max(0.0) / (1.0 + v.max(0.0))),
+                ToneMap::Aces => srgb_encode(aces(v.max(0.0))),
+            };
+            // 各通道阈值图错位取样，避免三通道同步抖动成灰噪
+            let (cx, cy) = (x + c * 19, y + c * 37);
+            let t = match (mode, noise) {
+                (DitherMode::BlueNoise, Some(n)) => n[(cy % NOISE) * NOISE + cx % NOISE],
+                (DitherMode::Ordered, _) => (BAYER8[cy % 8][cx % 8] as f32 + 0.5) / 64.0,
+                _ => 0.5,
+            };
+            out.push((v.clamp(0.0, 1.0) * 255.0 + t).floor().min(255.0) as u8);
+        }
+        out.push((px[3].clamp(0.0, 1.0) * 255.0).round() as u8);
+    }
+    RgbaImage::from_raw(width, height, out).ok_or_else(|| anyhow!("Failed to create image buffer"))
+}
+
+fn srgb_encode(v: f32) -> f32 {
+    if v <= 0.003_130_8 {
+        v * 12.92
+    } else {
+        1.055 * v.powf(1.0 / 2.4) - 0.055
+    }
+}
+
+/// ACES 电影曲线（Narkowicz 拟合）
+fn aces(v: f32) -> f32 {
+    let v = v * 0.6;
+    ((v * (2.51 * v + 0.03)) / (v * (2.43 * v + 0.59) + 0.14)).clamp(0.0, 1.0)
+}
+
+const BAYER8: [[u8; 8]; 8] = [
+    [0, 32, 8, 40, 2, 34, 10, 42],
+    [48, 16, 56, 24, 50, 18, 58, 26],
+    [12, 44, 4, 36, 14, 46, 6, 38],
+    [60, 28, 52, 20, 62, 30, 54, 22],
+    [3, 35, 11, 43, 1, 33, 9, 41],
+    [51, 19, 59, 27, 49, 17, 57, 25],
+    [15, 47, 7, 39, 13, 45, 5, 37],
+    [63, 31, 55, 23, 61, 29, 53, 21],
+];
+
+const NOISE: usize = 64;
+
+/// 64×64 蓝噪声阈值图（值域 (0,1)），void-and-cluster 生成（高斯 σ=1.5，环面距离）
+fn blue_noise() -> &'static [f32] {
+    static NOISE_MAP: OnceLock<Vec<f32>> = OnceLock::new();
+    NOISE_MAP.get_or_init(|| {
+        let n = NOISE * NOISE;
+        let mut kernel = vec![0f32; n];
+        for dy in 0..NOISE {
+            for dx in 0..NOISE {
+                let x = dx.min(NOISE - dx) as f32;
+                let y = dy.min(NOISE - dy) as f32;
+                kernel[dy * NOISE + dx] = (-(x * x + y * y) / (2.0 * 1.5 * 1.5)).exp();
+            }
+        }
+        let splat = |energy: &mut [f32], p: usize, sign: f32| {
+            let (px, py) = (p % NOISE, p / NOISE);
+            for y in 0..NOISE {
+                let row = ((y + NOISE - py) % NOISE) * NOISE;
+                for x in 0..NOISE {
+                    energy[y * NOISE + x] += sign * kernel[row + (x + NOISE - px) % NOISE];
+                }
+            }
+        };
+        // 最密的点（能量最高的 1）/ 最大的空洞（能量最低的 0）
+        let extreme = |bits: &[bool], energy: &[f32], ones: bool| {
+            (0..n)
+                .filter(|&i| bits[i] == ones)
+                .max_by(|&a, &b| {
+                    let (ea, eb) = if ones {
+                        (energy[a], energy[b])
+                    } else {
+                        (energy[b], energy[a])
+                    };
+                    ea.total_cmp(&eb)
+                })
+                .unwrap_or(0)
+        };
+
+        // 初始点集：确定性伪随机撒 10%，再反复把最密点挪到最大空洞直到稳定
+        let mut bits = vec![false; n];
+        let mut energy = vec![0f32; n];
+        let ones = n / 10;
+        let mut seed = 0x2545_F491u32;
+        let mut placed = 0;
+        while placed < ones {
+            seed = seed.wrapping_mul(1_664_525).wrapping_add(1_013_904_223);
+            let p = (seed >> 8) as usize % n;
+            if !bits[p] {
+                bits[p] = true;
+                splat(&mut energy, p, 1.0);
+                placed += 1;
+            }
+        }
+        for _ in 0..n {
+            let cluster = extreme(&bits, &energy, true);
+            bits[cluster] = false;
+            splat(&mut energy, cluster, -1.0);
+            let void = extreme(&bits, &energy, false);
+            bits[void] = true;
+            splat(&mut energy, void, 1.0);
+            if void == cluster {
+                break;
+            }
+        }
+
+        let mut rank = vec![0usize; n];
+        // 阶段 1：从初始点集依次移除最密点，秩递减
+        let (initial, initial_energy) = (bits.clone(), energy.clone());
+        for r in (0..ones).rev() {
+            let cluster = extreme(&bits, &energy, true);
+            bits[cluster] = false;
+            splat(&mut energy, cluster, -1.0);
+            rank[cluster] = r;
+        }
+        // 阶段 2：从初始点集依次填最大空洞，秩递增
+        let (mut bits, mut energy) = (initial, initial_energy);
+        for r in ones..n {
+            let void = extreme(&bits, &energy, false);
+            bits[void] = true;
+            splat(&mut energy, void, 1.0);
+            rank[void] = r;
+        }
+        rank.iter().map(|&r| (r as f32 + 0.5) / n as f32).collect()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 16 位水平渐变（只跨约 10 个 8 位台阶）量化到 8 位后测条带：
+    /// 以 16 列 × 64 行窗口的局部均值对比真实渐变，最大偏差（8 位单位）即台阶造成的亮度误差
+    #[test]
+    fn test_gradient_banding() {
+        let (w, h) = (1024u32, 64u32);
+        let value = |x: u32| 0.30 + 0.04 * x as f32 / (w - 1) as f32;
+        let src = image::ImageBuffer::from_fn(w, h, |x, _| {
+            let v = (value(x) * 65535.0).round() as u16;
+            image::Rgb([v, v, v])
+        });
+        let img = DynamicImage::ImageRgb16(src);
+        assert!(is_high_depth(&img));
+
+        let banding = |mode: DitherMode| {
+            let spec = DitherSpec {
+                mode,
+                tone_map: ToneMap::Clip,
+            };
+            let out = resize_to_rgba8(&img, w, h, &spec).unwrap();
+            let column: Vec<f32> = (0..w)
+                .map(|x| (0..h).map(|y| out.get_pixel(x, y)[1] as f32).sum::<f32>() / h as f32)
+                .collect();
+            (0..w - 16)
+                .map(|x0| {
+                    let mean = column[x0 as usize..][..16].iter().sum::<f32>() / 16.0;
+                    let truth = (x0..x0 + 16).map(|x| value(x) * 255.0).sum::<f32>() / 16.0;
+                    (mean - truth).abs()
+                })
+                .fold(0f32, f32::max)
+        };
+
+        // 不抖动：约 100px 宽的台阶，局部亮度偏差接近半个 8 位单位
+        let plain = banding(DitherMode::None);
+        assert!(plain > 0.3, "plain {}", plain);
+        // 抖动：台阶被打散，局部均值贴近真实渐变
+        for mode in [DitherMode::BlueNoise, DitherMode::Ordered] {
+            let err = banding(mode);
+            assert!(err < 0.1, "{:?} {}", mode, err);
+        }
+    }
+
+    #[test]
+    fn test_float_tone_map_and_noise() {
+        let src = image::ImageBuffer::from_fn(8, 8, |x, _| {
+            let v = x as f32 * 2.0; // 0 … 14，远超 1.0
+            image::Rgb([v, v, v])
+        });
+        let img = DynamicImage::ImageRgb32F(src);
+        let clip = DitherSpec {
+            mode: DitherMode::None,
+            tone_map: ToneMap::Clip,
+        };
+        let out = resize_to_rgba8(&img, 8, 8, &clip).unwrap();
+        assert_eq!(out.get_pixel(3, 0)[0], 255);
+        for tone_map in [ToneMap::Reinhard, ToneMap::Aces] {
+            let spec = DitherSpec {
+                mode: DitherMode::None,
+                tone_map,
+            };
+            let out = resize_to_rgba8(&img, 8, 8, &spec).unwrap();
+            let row: Vec<u8> = (0..8).map(|x| out.get_pixel(x, 0)[0]).collect();
+            // 高光被压缩而不截断：单调递增，且最亮处仍低于 255（ACES 在 14 处可能已饱和）
+            assert!(row.windows(2).all(|p| p[0] <= p[1]), "{:?}", row);
+            assert!(row[3] < 255 || tone_map == ToneMap::Aces, "{:?}", row);
+            assert_eq!(row[0], 0);
+        }
+
+        // 蓝噪声阈值图是 0..4096 的一个排列
+        let noise = blue_noise();
+        let mut ranks: Vec<usize> = noise.iter().map(|v| (v * 4096.0) as usize).collect();
+        ranks.sort_unstable();
+        assert!(ranks.iter().enumerate().all(|(i, &r)| i == r));
+        assert_eq!(DitherMode::parse("blue-noise"), Some(DitherMode::BlueNoise));
+        assert_eq!(ToneMap::parse("none"), Some(ToneMap::Clip));
+    }
+}
@@ -0,0 +1,881 @@
+//! v4.5：DNG 纯 Rust 显影（全分辨率输出，替代内嵌预览）
+//!
+//! - 取 IFD0 / SubIFDs 中 NewSubfileType=0 的主图（CFA 32803 或 LinearRaw 34892）；
+//! - 数据：未压缩（8/16 位或按位打包）与无损 JPEG（SOF3，条带或分块，DNG 常见的双分量交错）；
+//! - 线性化表 → ActiveArea 裁边 → 黑/白电平（支持 BlackLevelRepeatDim）→ 归一化；
+//! - CFA 双线性去马赛克（按 CFAPattern 逐色取 3x3 / 5x5 邻域均值，不限拜耳排列）→ DefaultCrop；
+//! - AsShotNeutral 白平衡（最小增益归一为 1 并截顶，防高光偏粉）→ ColorMatrix（优先 D65 标定）
+//!   推出的相机 → sRGB 矩阵 → BaselineExposure → sRGB 伽马 → 默认色调曲线（温和 S 曲线）；
+//! - 输出 16 位 RGB；方向不旋转像素，EXIF（含 Orientation）由 [`crate::raw_preview::raw_exif`] 迁移。
+
+use crate::raw_preview::{Entry, Tiff};
+use anyhow::{anyhow, Result};
+use image::{DynamicImage, ImageBuffer};
+use rayon::prelude::*;
+use std::collections::HashSet;
+
+const PHOTOMETRIC_CFA: u32 = 32803;
+const PHOTOMETRIC_LINEAR_RAW: u32 = 34892;
+/// 校准光源 D65（EXIF LightSource 21）
+const ILLUMINANT_D65: u32 = 21;
+/// sRGB(D65) → XYZ
+const XYZ_FROM_SRGB: [[f32; 3]; 3] = [
+    [0.412_453, 0.357_580, 0.180_423],
+    [0.212_671, 0.715_160, 0.072_169],
+    [0.019_334, 0.119_193, 0.950_227],
+];
+/// 默认色调曲线强度（0 = 纯 sRGB 伽马，1 = smoothstep）
+const TONE_STRENGTH: f32 = 0.25;
+/// 主图 / 单个条带或分块的像素上限（2 亿，高于现有中画幅机身）；尺寸取自文件，超限按损坏处理而不是照单分配
+const MAX_PIXELS: usize = 200_000_000;
+
+/// 解码 DNG 主图为 16 位 RGB
+pub fn decode(data: &[u8]) -> Result<DynamicImage> {
+    let tiff = Tiff::new(data).ok_or_else(|| anyhow!("不是 TIFF/DNG 文件"))?;
+    let ifd0 = tiff.first_ifd().ok_or_else(|| anyhow!("DNG 缺少 IFD0"))?;
+    let (root, _) = tiff.ifd(ifd0).ok_or_else(|| anyhow!("DNG IFD0 损坏"))?;
+    let raw = raw_ifd(&tiff, ifd0).ok_or_else(|| anyhow!("DNG 中未找到 CFA / LinearRaw 主图"))?;
+    let tag = |tag: u16| raw.iter().find(|e| e.tag == tag);
+    let reals = |tag: u16| tag_reals(&tiff, &raw, &root, tag);
+
+    let (samples, width, height, spp) = read_samples(&tiff, &raw)?;
+    let bps = tiff.value(&raw, 0x0102).unwrap_or(16);
+    let photometric = tiff.value(&raw, 0x0106).unwrap_or(0);
+
+    // 线性化表 + ActiveArea（上, 左, 下, 右）
+    let lut: Option<Vec<u16>> = tag(0xC618).map(|e| {
+        tiff.values(e)
+            .into_iter()
+            .map(|v| v.min(u16::MAX as u32) as u16)
+            .collect()
+    });
+    let area = match reals(0xC68D)[..] {
+        [t, l, b, r] => [t as usize, l as usize, b as usize, r as usize],
+        _ => [0, 0, height, width],
+    };
+    if area[2] > height || area[3] > width || area[0] >= area[2] || area[1] >= area[3] {
+        return Err(anyhow!("DNG ActiveArea 越界"));
+    }
+    let (aw, ah) = (area[3] - area[1], area[2] - area[0]);
+
+    // 黑电平按 RepeatDim 平铺；白电平逐通道（缺省 2^bps-1）
+    let black_dim = match reals(0xC619)[..] {
+        [r, c] if (1.0..=16.0).contains(&r) && (1.0..=16.0).contains(&c) => {
+            (r as usize, c as usize)
+        }
+        _ => (1, 1),
+    };
+    let mut black = reals(0xC61A);
+    if black.len() < black_dim.0 * black_dim.1 * spp {
+        let b = black.first().copied().unwrap_or(0.0);
+        black = vec![b; black_dim.0 * black_dim.1 * spp];
+    }
+    let default_white = ((1u64 << bps.min(16)) - 1) as f64;
+    let white = reals(0xC61D);
+    let white = |ch: usize| {
+        white
+            .get(ch)
+            .or(white.first())
+            .copied()
+            .unwrap_or(default_white)
+    };
+
+    let norm: Vec<f32> = (0..ah)
+        .into_par_iter()
+        .flat_map_iter(|y| {
+            let row = &samples[((area[0] + y) * width + area[1]) * spp..][..aw * spp];
+            let (lut, black, white) = (&lut, &black, &white);
+            row.iter().enumerate().map(move |(i, &v)| {
+                let (x, ch) = (i / spp, i % spp);
+                let v = match lut {
+                    Some(t) => t[(v as usize).min(t.len() - 1)],
+                    None => v,
+                } as f64;
+                let b = black[((y % black_dim.0) * black_dim.1 + x % black_dim.1) * spp + ch];
+                (((v - b) / (white(ch) - b).max(1.0)) as f32).clamp(0.0, 1.0)
+            })
+        })
+        .collect();
+
+    let mut rgb = match photometric {
+        PHOTOMETRIC_CFA if spp == 1 => {
+            let dims = tag(0x828D).map(|e| tiff.values(e)).unwrap_or_default();
+            let pattern: Vec<u8> = tag(0x828E)
+                .map(|e| tiff.data[e.value_off..e.value_off + e.value_len].to_vec())
+                .unwrap_or_default();
+            let (pr, pc) = match dims[..] {
+                [r, c] => (r as usize, c as usize),
+                _ => (2, 2),
+            };
+            if pattern.len() != pr * pc || pattern.iter().any(|&c| c > 2) {
+                return Err(anyhow!("不支持的 CFA 排列"));
+            }
+            demosaic(&norm, aw, ah, &pattern, pc, pr)
+        }
+        PHOTOMETRIC_LINEAR_RAW if spp >= 3 => norm
+            .chunks_exact(spp)
+            .flat_map(|p| [p[0], p[1], p[2]])
+            .collect(),
+        _ => {
+            return Err(anyhow!(
+                "不支持的 DNG 主图（光度解释 {}，{} 通道）",
+                photometric,
+                spp
+            ))
+        }
+    };
+
+    // DefaultCrop：相对 ActiveArea
+    let (mut w, mut h) = (aw, ah);
+    if let ([ox, oy], [cw, ch]) = (&reals(0xC61F)[..], &reals(0xC620)[..]) {
+        let (ox, oy) = (ox.round() as usize, oy.round() as usize);
+        let (cw, ch) = (cw.round() as usize, ch.round() as usize);
+        if cw > 0 && ch > 0 && ox + cw <= w && oy + ch <= h && (cw, ch) != (w, h) {
+            rgb = (oy..oy + ch)
+                .flat_map(|y| rgb[(y * w + ox) * 3..(y * w + ox + cw) * 3].iter().copied())
+                .collect();
+            (w, h) = (cw, ch);
+        }
+    }
+
+    let wb = white_balance(&reals(0xC628));
+    let rgb_cam = rgb_from_camera(&tiff, &raw, &root);
+    let exposure = 2f32.powf(reals(0xC62A).first().copied().unwrap_or(0.0) as f32);
+    let out: Vec<u16> = rgb
+        .par_chunks_exact(3)
+        .flat_map_iter(|p| {
+            let cam = [0, 1, 2].map(|c| (p[c] * wb[c]).min(1.0));
+            (0..3).map(move |r| {
+                let lin =
+                    (rgb_cam[r][0] * cam[0] + rgb_cam[r][1] * cam[1] + rgb_cam[r][2] * cam[2])
+                        * exposure;
+                (tone(srgb_gamma(lin.clamp(0.0, 1.0))) * 65535.0 + 0.5) as u16
+            })
+        })
+        .collect();
+    let buf = ImageBuffer::from_raw(w as u32, h as u32, out)
+        .ok_or_else(|| anyhow!("DNG 输出缓冲尺寸不符"))?;
+    Ok(DynamicImage::ImageRgb16(buf))
+}
+
+/// 标签值：主图 IFD 优先，其次 IFD0（颜色矩阵 / AsShotNeutral 等在 IFD0）
+fn tag_reals(tiff: &Tiff, raw: &[Entry], root: &[Entry], tag: u16) -> Vec<f64> {
+    raw.iter()
+        .chain(root)
+        .find(|e| e.tag == tag)
+        .map(|e| tiff.reals(e))
+        .unwrap_or_default()
+}
+
+/// 遍历 IFD0 + SubIFDs，取最大的 NewSubfileType=0 的 CFA / LinearRaw 图
+fn raw_ifd(tiff: &Tiff, ifd0: usize) -> Option<Vec<Entry>> {
+    let mut queue = vec![ifd0];
+    let mut visited = HashSet::new();
+    let mut best: Option<(u32, Vec<Entry>)> = None;
+    while let Some(off) = queue.pop() {
+        if visited.len() >= 64 || !visited.insert(off) {
+            continue;
+        }
+        let Some((entries, _)) = tiff.ifd(off) else {
+            continue;
+        };
+        if let Some(e) = entries.iter().find(|e| e.tag == 0x014A) {
+            queue.extend(tiff.values(e).into_iter().map(|v| v as usize));
+        }
+        let main = tiff.value(&entries, 0x00FE).unwrap_or(0) == 0;
+        let photometric = tiff.value(&entries, 0x0106).unwrap_or(0);
+        let width = tiff.value(&entries, 0x0100).unwrap_or(0);
+        if main
+            && matches!(photometric, PHOTOMETRIC_CFA | PHOTOMETRIC_LINEAR_RAW)
+            && best.as_ref().map_or(true, |(w, _)| width > *w)
+        {
+            best = Some((width, entries));
+        }
+    }
+    best.map(|(_, e)| e)
+}
+
+/// 读取主图全部样本（行优先，通道交错），返回 (样本, 宽, 高, 每像素样本数)
+fn read_samples(tiff: &Tiff, raw: &[Entry]) -> Result<(Vec<u16>, usize, usize, usize)> {
+    let get = |tag: u16| tiff.value(raw, tag).map(|v| v as usize);
+    let list = |tag: u16| {
+        raw.iter()
+            .find(|e| e.tag == tag)
+            .map(|e| tiff.values(e))
+            .unwrap_or_default()
+    };
+    let (width, height) = (get(0x0100).unwrap_or(0), get(0x0101).unwrap_or(0));
+    let spp = get(0x0115).unwrap_or(1);
+    let bps = get(0x0102).unwrap_or(16);
+    let compression = get(0x0103).unwrap_or(1);
+    if width == 0 || height == 0 || !(1..=4).contains(&spp) || !(1..=16).contains(&bps) {
+        return Err(anyhow!("DNG 主图参数无效"));
+    }
+    let (tw, th, offsets, counts) = if raw.iter().any(|e| e.tag == 0x0144) {
+        (
+            get(0x0142).unwrap_or(0),
+            get(0x0143).unwrap_or(0),
+            list(0x0144),
+            list(0x0145),
+        )
+    } else {
+        (
+            width,
+            get(0x0116).unwrap_or(height).min(height),
+            list(0x0111),
+            list(0x0117),
+        )
+    };
+    if tw == 0 || th == 0 || offsets.len() != counts.len() {
+        return Err(anyhow!("DNG 条带/分块信息无效"));
+    }
+    let total = sample_count(width, height, spp)?;
+    sample_count(tw, th, spp)?;
+    let across = width.div_ceil(tw);
+    let needed = across * height.div_ceil(th);
+    if offsets.len() < needed {
+        return Err(anyhow!("DNG 条带/分块数量不足"));
+    }
+    // 多出的条目不解码（多个偏移指向同一段数据时不会重复分配）
+    let tile_samples = tw * th * spp;
+
+    let tiles: Vec<Vec<u16>> = offsets[..needed]
+        .par_iter()
+        .zip(counts[..needed].par_iter())
+        .map(|(&o, &c)| {
+            let bytes = tiff
+                .data
+                .get(o as usize..(o as usize).saturating_add(c as usize))
+                .ok_or_else(|| anyhow!("DNG 数据越界"))?;
+            match compression {
+                1 => Ok(unpack(bytes, tw * spp, th, bps, tiff.le)),
+                7 => Ok(lossless_jpeg(bytes, tile_samples)?.0),
+                other => Err(anyhow!("不支持的 DNG 压缩方式: {}", other)),
+            }
+        })
+        .collect::<Result<_>>()?;
+
+    let mut samples = vec![0u16; total];
+    let row_len = tw * spp;
+    for (i, tile) in tiles.iter().enumerate() {
+        let (tx, ty) = ((i % across) * tw, (i / across) * th);
+        if ty >= height {
+            break;
+        }
+        let cols = (width - tx).min(tw) * spp;
+        for (r, src) in tile.chunks(row_len).enumerate().take((height - ty).min(th)) {
+            let dst = ((ty + r) * width + tx) * spp;
+            let n = cols.min(src.len());
+            samples[dst..dst + n].copy_from_slice(&src[..n]);
+        }
+    }
+    Ok((samples, width, height, spp))
+}
+
+/// 宽 × 高 × 分量数（溢出或超过 [`MAX_PIXELS`] 时报错）
+fn sample_count(width: usize, height: usize, comps: usize) -> Result<usize> {
+    width
+        .checked_mul(height)
+        .filter(|&pixels| pixels <= MAX_PIXELS)
+        .and_then(|pixels| pixels.checked_mul(comps))
+        .ok_or_else(|| {
+            anyhow!(
+                "DNG 图像尺寸 {}x{} 超出上限（{} 像素）",
+                width,
+                height,
+                MAX_PIXELS
+            )
+        })
+}
+
+/// 未压缩样本：16 位按文件字节序；其他位深按 MSB 优先打包，每行字节对齐。
+/// 输出按实际字节数计容量（数据不足一块时不按整块分配）
+fn unpack(bytes: &[u8], row_samples: usize, rows: usize, bps: usize, le: bool) -> Vec<u16> {
+    let row_bytes = (row_samples * bps).div_ceil(8);
+    let available = match bps {
+        16 => bytes.len() / 2,
+        8 => bytes.len(),
+        _ => bytes.len().div_ceil(row_bytes).saturating_mul(row_samples),
+    };
+    let mut out = Vec::with_capacity(available.min(row_samples * rows));
+    match bps {
+        16 => out.extend(bytes.chunks_exact(2).take(row_samples * rows).map(|b| {
+            if le {
+                u16::from_le_bytes([b[0], b[1]])
+            } else {
+                u16::from_be_bytes([b[0], b[1]])
+            }
+        })),
+        8 => out.extend(bytes.iter().take(row_samples * rows).map(|&b| b as u16)),
+        _ => {
+            for row in bytes.chunks(row_bytes).take(rows) {
+                let mut bit = 0usize;
+                for _ in 0..row_samples {
+                    let mut v = 0u32;
+                    for _ in 0..bps {
+                        let byte = row.get(bit / 8).copied().unwrap_or(0);
+                        v = (v << 1) | ((byte >> (7 - bit % 8)) & 1) as u32;
+                        bit += 1;
+                    }
+                    out.push(v as u16);
+                }
+            }
+        }
+    }
+    out
+}
+
+/// 双线性去马赛克：本色直取，缺色取 3x3 邻域同色均值（无则 5x5）
+fn demosaic(raw: &[f32], w: usize, h: usize, pattern: &[u8], pw: usize, ph: usize) -> Vec<f32> {
+    let color = |x: usize, y: usize| pattern[(y % ph) * pw + x % pw] as usize;
+    let mut rgb = vec![0f32; w * h * 3];
+    rgb.par_chunks_mut(w * 3).enumerate().for_each(|(y, row)| {
+        for x in 0..w {
+            let own = color(x, y);
+            for ch in 0..3 {
+                row[x * 3 + ch] = if ch == own {
+                    raw[y * w + x]
+                } else {
+                    neighbour_mean(raw, w, h, x, y, ch, 1, &color)
+                        .or_else(|| neighbour_mean(raw, w, h, x, y, ch, 2, &color))
+                        .unwrap_or(0.0)
+                };
+            }
+        }
+    });
+    rgb
+}
+
+#[allow(clippy::too_many_arguments)]
+fn neighbour_mean(
+    raw: &[f32],
+    w: usize,
+    h: usize,
+    x: usize,
+    y: usize,
+    ch: usize,
+    radius: usize,
+    color: &impl Fn(usize, usize) -> usize,
+) -> Option<f32> {
+    let (mut sum, mut n) = (0f32, 0u32);
+    for ny in y.saturating_sub(radius)..(y + radius + 1).min(h) {
+        for nx in x.saturating_sub(radius)..(x + radius + 1).min(w) {
+            if color(nx, ny) == ch {
+                sum += raw[ny * w + nx];
+                n += 1;
+            }
+        }
+    }
+    (n > 0).then(|| sum / n as f32)
+}
+
+/// AsShotNeutral → 各通道增益（最小增益归一为 1）
+fn white_balance(neutral: &[f64]) -> [f32; 3] {
+    let gains = match neutral {
+        [r, g, b, ..] if *r > 0.0 && *g > 0.0 && *b > 0.0 => [1.0 / r, 1.0 / g, 1.0 / b],
+        _ => return [1.0; 3],
+    };
+    let min = gains.iter().copied().fold(f64::MAX, f64::min);
+    gains.map(|g| (g / min) as f32)
+}
+
+/// 相机 → sRGB：cam_rgb = ColorMatrix(XYZ→相机) · XYZ_FROM_SRGB，逐行归一（sRGB 白 → 相机白），再求逆
+fn rgb_from_camera(tiff: &Tiff, raw: &[Entry], root: &[Entry]) -> [[f32; 3]; 3] {
+    const IDENTITY: [[f32; 3]; 3] = [[1.0, 0.0, 0.0], [0.0, 1.0, 0.0], [0.0, 0.0, 1.0]];
+    let illuminant = |tag| tag_reals(tiff, raw, root, tag).first().map(|&v| v as u32);
+    let (cm1, cm2) = (
+        tag_reals(tiff, raw, root, 0xC621),
+        tag_reals(tiff, raw, root, 0xC622),
+    );
+    let cm = if cm2.len() == 9 && illuminant(0xC65B) == Some(ILLUMINANT_D65) {
+        cm2
+    } else if cm1.len() == 9 && illuminant(0xC65A) == Some(ILLUMINANT_D65) {
+        cm1
+    } else if cm2.len() == 9 {
+        cm2
+    } else if cm1.len() == 9 {
+        cm1
+    } else {
+        return IDENTITY;
+    };
+    let mut cam_rgb = [[0f32; 3]; 3];
+    for (i, row) in cam_rgb.iter_mut().enumerate() {
+        for (j, v) in row.iter_mut().enumerate() {
+            *v = (0..3)
+                .map(|k| cm[i * 3 + k] as f32 * XYZ_FROM_SRGB[k][j])
+                .sum();
+        }
+        let sum: f32 = row.iter().sum();
+        if sum.abs() > f32::EPSILON {
+            row.iter_mut().for_each(|v| *v /= sum);
+        }
+    }
+    invert3(&cam_rgb).unwrap_or(IDENTITY)
+}
+
+fn invert3(m: &[[f32; 3]; 3]) -> Option<[[f32; 3]; 3]> {
+    let c = |r: usize, k: usize| {
+        let (r1, r2) = ((r + 1) % 3, (r + 2) % 3);
+        let (k1, k2) = ((k + 1) % 3, (k + 2) % 3);
+        m[r1][k1] * m[r2][k2] - m[r1][k2] * m[r2][k1]
+    };
+    let det = m[0][0] * c(0, 0) + m[0][1] * c(0, 1) + m[0][2] * c(0, 2);
+    if det.abs() < 1e-8 {
+        return None;
+    }
+    let mut inv = [[0f32; 3]; 3];
+    for (i, row) in inv.iter_mut().enumerate() {
+        for (j, v) in row.iter_mut().enumerate() {
+            *v = c(j, i) / det;
+        }
+    }
+    Some(inv)
+}
+
+fn srgb_gamma(v: f32) -> f32 {
+    if v <= 0.003_130_8 {
+        v * 12.92
+    } else {
+        1.055 * v.powf(1.0 / 2.4) - 0.055
+    }
+}
+
+/// 默认色调曲线：sRGB 编码值与 smoothstep 按强度混合（提中间调反差，端点不变）
+fn tone(v: f32) -> f32 {
+    v + TONE_STRENGTH * (v * v * (3.0 - 2.0 * v) - v)
+}
+
+// ---------------------------------------------------------------- 无损 JPEG（ITU T.81 SOF3）
+
+/// 解码无损 JPEG：返回 (样本（行优先、分量交错）, 宽, 高, 分量数)；
+/// 帧头声明的样本数超过 `max_samples`（所属分块的大小）时报错，不按帧头分配
+fn lossless_jpeg(data: &[u8], max_samples: usize) -> Result<(Vec<u16>, usize, usize, usize)> {
+    let bad = || anyhow!("无损 JPEG 数据损坏");
+    if data.get(..2) != Some([0xFF, 0xD8].as_slice()) {
+        return Err(bad());
+    }
+    let mut tables: [Option<Vec<(u8, u8)>>; 4] = Default::default();
+    let mut frame: Option<(u8, usize, usize, Vec<u8>)> = None;
+    let mut pos = 2usize;
+    loop {
+        if *data.get(pos).ok_or_else(bad)? != 0xFF {
+            return Err(bad());
+        }
+        let marker = *data.get(pos + 1).ok_or_else(bad)?;
+        if marker == 0xFF {
+            pos += 1;
+            continue;
+        }
+        let len = u16::from_be_bytes([
+            *data.get(pos + 2).ok_or_else(bad)?,
+            *data.get(pos + 3).ok_or_else(bad)?,
+        ]) as usize;
+        let seg = data.get(pos + 4..pos + 2 + len).ok_or_else(bad)?;
+        pos += 2 + len;
+        match marker {
+            0xC4 => {
+                let mut p = 0usize;
+                while p + 17 <= seg.len() {
+                    let id = (seg[p] & 3) as usize;
+                    let counts = &seg[p + 1..p + 17];
+                    let total: usize = counts.iter().map(|&c| c as usize).sum();
+                    let symbols = seg.get(p + 17..p + 17 + total).ok_or_else(bad)?;
+                    tables[id] = Some(huffman_lut(counts, symbols));
+                    p += 17 + total;
+                }
+            }
+            0xC3 => {
+                if seg.len() < 6 {
+                    return Err(bad());
+                }
+                let precision = seg[0];
+                let h = u16::from_be_bytes([seg[1], seg[2]]) as usize;
+                let w = u16::from_be_bytes([seg[3], seg[4]]) as usize;
+                let n = seg[5] as usize;
+                let ids = (0..n)
+                    .map(|c| seg.get(6 + c * 3).copied())
+                    .collect::<Option<Vec<u8>>>();
+                frame = Some((precision, w, h, ids.ok_or_else(bad)?));
+            }
+            0xC0..=0xCF if marker != 0xC4 && marker != 0xC8 && marker != 0xCC => {
+                return Err(anyhow!("不是无损 JPEG（SOF{:X}）", marker - 0xC0));
+            }
+            0xDD if seg.len() >= 2 && u16::from_be_bytes([seg[0], seg[1]]) != 0 => {
+                return Err(anyhow!("不支持带重启间隔的无损 JPEG"));
+            }
+            0xDA => {
+                let (precision, w, h, ids) = frame.ok_or_else(bad)?;
+                let ns = *seg.first().ok_or_else(bad)? as usize;
+                if ns != ids.len() || w == 0 || h == 0 {
+                    return Err(bad());
+                }
+                if w.saturating_mul(h).saturating_mul(ns) > max_samples {
+                    return Err(anyhow!(
+                        "无损 JPEG 分块 {}x{}x{} 超出 DNG 分块大小",
+                        w,
+                        h,
+                        ns
+                    ));
+                }
+                let mut luts = Vec::with_capacity(ns);
+                for c in 0..ns {
+                    let sel = (*seg.get(2 + c * 2).ok_or_else(bad)? >> 4) as usize;
+                    luts.push(tables[sel & 3].as_ref().ok_or_else(bad)?);
+                }
+                let predictor = *seg.get(1 + ns * 2).ok_or_else(bad)?;
+                let pt = *seg.get(3 + ns * 2).ok_or_else(bad)? & 0x0F;
+                let samples = decode_scan(&data[pos..], &luts, w, h, precision, predictor, pt)?;
+                return Ok((samples, w, h, ns));
+            }
+            _ => {}
+        }
+    }
+}
+
+/// 16 位查找表：下标为码流前 16 位，值为 (码长, 符号)，码长 0 = 非法码
+fn huffman_lut(counts: &[u8], symbols: &[u8]) -> Vec<(u8, u8)> {
+    let mut lut = vec![(0u8, 0u8); 1 << 16];
+    let (mut code, mut k) = (0usize, 0usize);
+    for (i, &count) in counts.iter().enumerate() {
+        let len = i + 1;
+        for _ in 0..count {
+            let span = 1usize << (16 - len);
+            if let Some(slot) = lut.get_mut(code << (16 - len)..(code << (16 - len)) + span) {
+                slot.fill((len as u8, symbols[k]));
+            }
+            code += 1;
+            k += 1;
+        }
+        code <<= 1;
+    }
+    lut
+}
+
+struct BitReader<'a> {
+    data: &'a [u8],
+    pos: usize,
+    acc: u64,
+    bits: u32,
+}
+
+impl BitReader<'_> {
+    /// 补足缓冲（> 56 位，够一次 16 位查表 + 15 位差值）；遇到标记（FF 后非 00）后补 0
+    fn fill(&mut self) {
+        while self.bits <= 56 {
+            let mut byte = 0u8;
+            if let Some(&b) = self.data.get(self.pos) {
+                if b != 0xFF {
+                    byte = b;
+                    self.pos += 1;
+                } else if self.data.get(self.pos + 1) == Some(&0) {
+                    byte = 0xFF;
+                    self.pos += 2;
+                }
+            }
+            self.acc |= (byte as u64) << (56 - self.bits);
+            self.bits += 8;
+        }
+    }
+
+    fn take(&mut self, n: u32) -> u32 {
+        if n == 0 {
+            return 0;
+        }
+        let v = (self.acc >> (64 - n)) as u32;
+        self.acc <<= n;
+        self.bits -= n;
+        v
+    }
+}
+
+fn decode_scan(
+    data: &[u8],
+    luts: &[&Vec<(u8, u8)>],
+    w: usize,
+    h: usize,
+    precision: u8,
+    predictor: u8,
+    pt: u8,
+) -> Result<Vec<u16>> {
+    let n = luts.len();
+    let mut out = vec![0u16; sample_count(w, h, n)?];
+    let mut reader = BitReader {
+        data,
+        pos: 0,
+        acc: 0,
+        bits: 0,
+    };
+    let initial = 1i32 << (precision.saturating_sub(pt + 1));
+    for y in 0..h {
+        for x in 0..w {
+            for (c, lut) in luts.iter().enumerate() {
+                reader.fill();
+                let (len, ssss) = lut[(reader.acc >> 48) as usize];
+                if len == 0 {
+                    return Err(anyhow!("无损 JPEG 霍夫曼码无效"));
+                }
+                reader.take(len as u32);
+                let diff = match ssss {
+                    0 => 0,
+                    16 => 32768,
+                    s => {
+                        let v = reader.take(s as u32) as i32;
+                        if v < 1 << (s - 1) {
+                            v - (1 << s) + 1
+                        } else {
+                            v
+                        }
+                    }
+                };
+                let idx = (y * w + x) * n + c;
+                let left = || out[idx - n] as i32;
+                let up = || out[idx - w * n] as i32;
+                let pred = match (x, y) {
+                    (0, 0) => initial,
+                    (_, 0) => left(),
+                    (0, _) => up(),
+                    _ => {
+                        let (a, b, cc) = (left(), up(), out[idx - w * n - n] as i32);
+                        match predictor {
+                            1 => a,
+                            2 => b,
+                            3 => cc,
+                            4 => a + b - cc,
+                            5 => a + ((b - cc) >> 1),
+                            6 => b + ((a - cc) >> 1),
+                            7 => (a + b) >> 1,
+                            _ => a,
+                        }
+                    }
+                };
+                out[idx] = (pred + diff) as u16;
+            }
+        }
+    }
+    if pt > 0 {
+        out.iter_mut().for_each(|v| *v <<= pt);
+    }
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 测试用无损 JPEG 编码器（预测器 1，ssss 0..=16 均为 5 位码）
+    fn lj92_encode(samples: &[u16], w: usize, h: usize, comps: usize) -> Vec<u8> {
+        let mut out = vec![0xFF, 0xD8, 0xFF, 0xC4, 0, 36, 0x00];
+        let mut counts = [0u8; 16];
+        counts[4] = 17;
+        out.extend_from_slice(&counts);
+        out.extend(0..=16u8);
+        out.extend_from_slice(&[0xFF, 0xC3, 0, (8 + 3 * comps) as u8, 16]);
+        out.extend_from_slice(&(h as u16).to_be_bytes());
+        out.extend_from_slice(&(w as u16).to_be_bytes());
+        out.push(comps as u8);
+        for c in 0..comps {
+            out.extend_from_slice(&[c as u8 + 1, 0x11, 0]);
+        }
+        out.extend_from_slice(&[0xFF, 0xDA, 0, (6 + 2 * comps) as u8, comps as u8]);
+        for c in 0..comps {
+            out.extend_from_slice(&[c as u8 + 1, 0x00]);
+        }
+        out.extend_from_slice(&[1, 0, 0]);
+
+        let (mut acc, mut nbits) = (0u64, 0u32);
+        let mut put = |out: &mut Vec<u8>, v: u32, n: u32| {
+            acc = (acc << n) | v as u64;
+            nbits += n;
+            while nbits >= 8 {
+                let byte = (acc >> (nbits - 8)) as u8;
+                out.push(byte);
+                if byte == 0xFF {
+                    out.push(0);
+                }
+                nbits -= 8;
+            }
+            nbits
+        };
+        let mut pending = 0;
+        for y in 0..h {
+            for x in 0..w {
+                for c in 0..comps {
+                    let idx = (y * w + x) * comps + c;
+                    let pred = match (x, y) {
+                        (0, 0) => 1 << 15,
+                        (0, _) => samples[idx - w * comps] as i32,
+                        _ => samples[idx - comps] as i32,
+                    };
+                    let d = (samples[idx] as i32 - pred) as i16 as i32;
+                    let ssss = if d == 0 {
+                        0
+                    } else if d == -32768 {
+                        16
+                    } else {
+                        32 - d.unsigned_abs().leading_zeros()
+                    };
+                    pending = put(&mut out, ssss, 5);
+                    if (1..16).contains(&ssss) {
+                        let v = if d < 0 { d - 1 } else { d };
+                        pending = put(&mut out, (v as u32) & ((1 << ssss) - 1), ssss);
+                    }
+                }
+            }
+        }
+        // 末字节补 1
+        if pending > 0 {
+            put(&mut out, (1 << (8 - pending)) - 1, 8 - pending);
+        }
+        out.extend_from_slice(&[0xFF, 0xD9]);
+        out
+    }
+
+    /// 小端单 IFD DNG：RGGB、黑 64 / 白 4095、AsShotNeutral (0.5, 1, 0.8)
+    fn build_dng(w: u32, h: u32, payload: &[u8], compression: u16) -> Vec<u8> {
+        let rational = |v: &[(u32, u32)]| -> Vec<u8> {
+            v.iter()
+                .flat_map(|(n, d)| [n.to_le_bytes(), d.to_le_bytes()].concat())
+                .collect()
+        };
+        let entries: Vec<(u16, u16, u32, Vec<u8>)> = vec![
+            (0x00FE, 4, 1, 0u32.to_le_bytes().to_vec()),
+            (0x0100, 4, 1, w.to_le_bytes().to_vec()),
+            (0x0101, 4, 1, h.to_le_bytes().to_vec()),
+            (0x0102, 3, 1, 16u16.to_le_bytes().to_vec()),
+            (0x0103, 3, 1, compression.to_le_bytes().to_vec()),
+            (
+                0x0106,
+                3,
+                1,
+                (PHOTOMETRIC_CFA as u16).to_le_bytes().to_vec(),
+            ),
+            (0x0111, 4, 1, Vec::new()),
+            (0x0115, 3, 1, 1u16.to_le_bytes().to_vec()),
+            (0x0116, 4, 1, h.to_le_bytes().to_vec()),
+            (0x0117, 4, 1, (payload.len() as u32).to_le_bytes().to_vec()),
+            (
+                0x828D,
+                3,
+                2,
+                [2u16.to_le_bytes(), 2u16.to_le_bytes()].concat(),
+            ),
+            (0x828E, 1, 4, vec![0, 1, 1, 2]),
+            (0xC61A, 3, 1, 64u16.to_le_bytes().to_vec()),
+            (0xC61D, 3, 1, 4095u16.to_le_bytes().to_vec()),
+            (0xC628, 5, 3, rational(&[(1, 2), (1, 1), (4, 5)])),
+        ];
+        let ifd_len = 2 + entries.len() * 12 + 4;
+        let extra_at = 8 + ifd_len;
+        let extra: Vec<u8> = entries
+            .iter()
+            .filter(|e| e.3.len() > 4)
+            .flat_map(|e| e.3.clone())
+            .collect();
+        let payload_at = (extra_at + extra.len()) as u32;
+        let mut d = b"II*\0".to_vec();
+        d.extend_from_slice(&8u32.to_le_bytes());
+        d.extend_from_slice(&(entries.len() as u16).to_le_bytes());
+        let mut extra_off = extra_at as u32;
+        for (tag, typ, count, value) in &entries {
+            d.extend_from_slice(&tag.to_le_bytes());
+            d.extend_from_slice(&typ.to_le_bytes());
+            d.extend_from_slice(&count.to_le_bytes());
+            let mut inline = [0u8; 4];
+            if *tag == 0x0111 {
+                inline = payload_at.to_le_bytes();
+            } else if value.len() > 4 {
+                inline = extra_off.to_le_bytes();
+                extra_off += value.len() as u32;
+            } else {
+                inline[..value.len()].copy_from_slice(value);
+            }
+            d.extend_from_slice(&inline);
+        }
+        d.extend_from_slice(&0u32.to_le_bytes());
+        d.extend_from_slice(&extra);
+        d.extend_from_slice(payload);
+        d
+    }
+
+    /// 灰色场景的拜耳马赛克：各通道 = 黑电平 + neutral × 0.4 × 量程，外加渐变扰动
+    fn grey_mosaic(w: usize, h: usize, ramp: bool) -> Vec<u16> {
+        let neutral = [0.5, 1.0, 0.8];
+        (0..h)
+            .flat_map(|y| {
+                (0..w).map(move |x| {
+                    let c = [0, 1, 1, 2][(y % 2) * 2 + x % 2];
+                    let level = if ramp {
+                        0.2 + 0.6 * x as f64 / w as f64
+                    } else {
+                        0.4
+                    };
+                    (64.0 + neutral[c] * level * 4031.0).round() as u16
+                })
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_uncompressed_cfa_white_balanced_grey() {
+        let (w, h) = (8usize, 6usize);
+        let mosaic = grey_mosaic(w, h, false);
+        let payload: Vec<u8> = mosaic.iter().flat_map(|v| v.to_le_bytes()).collect();
+        let img = decode(&build_dng(w as u32, h as u32, &payload, 1)).unwrap();
+        let rgb = img.as_rgb16().unwrap();
+        assert_eq!(rgb.dimensions(), (8, 6));
+        for p in rgb.pixels() {
+            let [r, g, b] = p.0;
+            assert!(r.abs_diff(g) < 64 && b.abs_diff(g) < 64, "{:?}", p.0);
+            // 0.4 线性 → sRGB ≈ 0.665 → 曲线后略升
+            assert!((44_000..47_000).contains(&g), "{}", g);
+        }
+        assert_eq!(
+            unpack(&[0xAB, 0xCD, 0xE0], 2, 1, 12, true),
+            vec![0xABC, 0xDE0]
+        );
+    }
+
+    #[test]
+    fn test_lossless_jpeg_dng_matches_uncompressed() {
+        let (w, h) = (16usize, 4usize);
+        let mosaic = grey_mosaic(w, h, true);
+        // DNG 常见写法：每行拆成 2 分量交错，JPEG 宽度为一半
+        let jpeg = lj92_encode(&mosaic, w / 2, h, 2);
+        let (samples, jw, jh, comps) = lossless_jpeg(&jpeg, usize::MAX).unwrap();
+        assert_eq!((jw, jh, comps), (w / 2, h, 2));
+        assert_eq!(samples, mosaic);
+
+        let plain: Vec<u8> = mosaic.iter().flat_map(|v| v.to_le_bytes()).collect();
+        let a = decode(&build_dng(w as u32, h as u32, &plain, 1)).unwrap();
+        let b = decode(&build_dng(w as u32, h as u32, &jpeg, 7)).unwrap();
+        assert_eq!(
+            a.as_rgb16().unwrap().as_raw(),
+            b.as_rgb16().unwrap().as_raw()
+        );
+        // 渐变从左到右变亮
+        let row = b.as_rgb16().unwrap();
+        assert!(row.get_pixel(14, 1).0[1] > row.get_pixel(1, 1).0[1]);
+    }
+
+    #[test]
+    fn test_oversized_dimensions_rejected() {
+        // IFD 声称 60000x60000：报错而不是按 7 GB 分配
+        let err = decode(&build_dng(60_000, 60_000, &[0; 64], 1)).unwrap_err();
+        assert!(err.to_string().contains("上限"), "{}", err);
+
+        // 无损 JPEG 的 SOF3 改成 65535x65535
+        let mut jpeg = lj92_encode(&grey_mosaic(8, 2, false), 4, 2, 2);
+        let sof = jpeg.windows(2).position(|m| m == [0xFF, 0xC3]).unwrap();
+        jpeg[sof + 5..sof + 9].fill(0xFF);
+        let err = lossless_jpeg(&jpeg, usize::MAX).unwrap_err();
+        assert!(err.to_string().contains("上限"), "{}", err);
+
+        // 帧头大于所属分块：按分块大小拒绝；未压缩数据只按实际字节分配
+        let jpeg = lj92_encode(&grey_mosaic(8, 2, false), 4, 2, 2);
+        assert!(lossless_jpeg(&jpeg, 8 * 2 - 1).is_err());
+        assert!(lossless_jpeg(&jpeg, 8 * 2).is_ok());
+        let few = unpack(&[0xAB, 0xCD], 4096, 4096, 16, true);
+        assert_eq!(few.len(), 1);
+        assert!(few.capacity() < 16);
+    }
+}
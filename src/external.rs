@@ -0,0 +1,487 @@
+//! v4.5：外部解码器后端（按扩展名把解码交给 dcraw_emu / darktable-cli / ImageMagick 等命令行工具）
+//!
+//! - 命令模板为参数数组（不经 shell），`{input}` / `{output}` / `{stem}` 占位符可嵌在参数中
+//!   （如 `--out={output}`）；工具把解码结果写到 `{output}`（临时文件，扩展名由 `output_ext` 决定），
+//!   本模块再按常规格式读回，交给常规管线处理；
+//! - 全进程共用一个计数信号量限制并发（Mutex + Condvar 阻塞等待，许可随作用域释放）；
+//!   macOS 内置的 sips / qlmanage 也走同一信号量与 [`run_with_timeout`]；
+//! - 超时、启动失败、非零退出、无输出、输出不可读各有结构化错误 [`DecoderError`]；
+//! - 配置的扩展名登记到进程级注册表，目录扫描据此把 .heic / .jxl 等纳入待处理文件。
+
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Condvar, Mutex, RwLock};
+use std::time::{Duration, Instant};
+
+/// 缺省超时（秒）
+pub const DEFAULT_TIMEOUT_SECS: u64 = 60;
+/// 缺省并发上限
+pub const DEFAULT_CONCURRENCY: usize = 4;
+
+/// 单个外部解码器
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ExternalDecoder {
+    /// 显示名（缺省取程序名）
+    #[serde(default)]
+    pub name: String,
+    /// 适用扩展名（不含点，大小写不敏感）
+    pub extensions: Vec<String>,
+    /// 命令模板：首项为程序，其余为参数
+    pub command: Vec<String>,
+    /// 工具输出文件扩展名（缺省 tiff）
+    #[serde(default = "default_output_ext")]
+    pub output_ext: String,
+    /// 超时（秒；None = 取全局缺省）
+    #[serde(default)]
+    pub timeout_secs: Option<u64>,
+}
+
+fn default_output_ext() -> String {
+    "tiff".to_string()
+}
+
+impl ExternalDecoder {
+    /// 命令行简写：`ext1,ext2=程序 参数…`（参数按空白切分；含空格的程序路径请用配置文件 / JSON）
+    pub fn parse(spec: &str) -> Result<Self, String> {
+        let (exts, cmd) = spec.split_once('=').ok_or_else(|| {
+            format!(
+                "外部解码器格式应为 ext1,ext2=命令 {{input}} {{output}}: {}",
+                spec
+            )
+        })?;
+        let decoder = Self {
+            name: String::new(),
+            extensions: exts.split(',').map(|e| e.trim().to_string()).collect(),
+            command: cmd.split_whitespace().map(str::to_string).collect(),
+            output_ext: default_output_ext(),
+            timeout_secs: None,
+        };
+        decoder.validate()?;
+        Ok(decoder)
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        let exts: Vec<String> = self.normalized_extensions();
+        if exts.is_empty() || exts.iter().any(|e| e.is_empty()) {
+            return Err(format!("外部解码器 {} 未给出扩展名", self.display_name()));
+        }
+        if self.command.is_empty() || self.command[0].trim().is_empty() {
+            return Err("外部解码器命令为空".to_string());
+        }
+        for placeholder in ["{input}", "{output}"] {
+            if !self.command.iter().any(|a| a.contains(placeholder)) {
+                return Err(format!(
+                    "外部解码器 {} 的命令缺少 {} 占位符",
+                    self.display_name(),
+                    placeholder
+                ));
+            }
+        }
+        if self.output_ext.trim().is_empty() {
+            return Err(format!(
+                "外部解码器 {} 的 output_ext 为空",
+                self.display_name()
+            ));
+        }
+        if let Some(secs) = self.timeout_secs {
+            if !(1..=3600).contains(&secs) {
+                return Err(format!("外部解码器超时须在 1-3600 秒: {}", secs));
+            }
+        }
+        Ok(())
+    }
+
+    pub fn display_name(&self) -> String {
+        if !self.name.is_empty() {
+            return self.name.clone();
+        }
+        self.command
+            .first()
+            .map(|p| {
+                Path::new(p)
+                    .file_name()
+                    .map_or(p.clone(), |n| n.to_string_lossy().to_string())
+            })
+            .unwrap_or_default()
+    }
+
+    fn normalized_extensions(&self) -> Vec<String> {
+        self.extensions
+            .iter()
+            .map(|e| e.trim().trim_start_matches('.').to_ascii_lowercase())
+            .collect()
+    }
+
+    pub fn handles(&self, ext: &str) -> bool {
+        self.normalized_extensions()
+            .iter()
+            .any(|e| e.eq_ignore_ascii_case(ext))
+    }
+
+    /// 运行外部工具解码 `input`；`default_timeout` 用于未单独配置超时的解码器
+    pub fn decode(
+        &self,
+        input: &Path,
+        default_timeout: Duration,
+    ) -> Result<image::DynamicImage, DecoderError> {
+        let program = self.display_name();
+        let stem = input.file_stem().unwrap_or_default().to_string_lossy();
+        let output =
+            TempFile::new(&stem, &self.output_ext).map_err(|source| DecoderError::Spawn {
+                program: program.clone(),
+                source,
+            })?;
+        let input_abs = std::fs::canonicalize(input).unwrap_or_else(|_| input.to_path_buf());
+        let fill = |arg: &str| {
+            arg.replace("{input}", &input_abs.to_string_lossy())
+                .replace("{output}", &output.0.to_string_lossy())
+                .replace("{stem}", &stem)
+        };
+        let mut cmd = Command::new(fill(&self.command[0]));
+        cmd.args(self.command[1..].iter().map(|a| fill(a)));
+        let timeout = self
+            .timeout_secs
+            .map(Duration::from_secs)
+            .unwrap_or(default_timeout);
+
+        let _permit = acquire();
+        run_with_timeout(&mut cmd, &program, timeout)?;
+        match std::fs::metadata(&output.0) {
+            Ok(m) if m.len() > 0 => {}
+            _ => return Err(DecoderError::NoOutput { program }),
+        }
+        // 按内容嗅探格式，工具实际写出的格式未必与 output_ext 一致
+        image::ImageReader::open(&output.0)
+            .and_then(|r| r.with_guessed_format())
+            .map_err(image::ImageError::IoError)
+            .and_then(|r| r.decode())
+            .map_err(|e| DecoderError::BadOutput {
+                program,
+                reason: e.to_string(),
+            })
+    }
+}
+
+/// 按扩展名选解码器（先配置者优先）
+pub fn find<'a>(decoders: &'a [ExternalDecoder], ext: &str) -> Option<&'a ExternalDecoder> {
+    decoders.iter().find(|d| d.handles(ext))
+}
+
+/// 临时输出文件（离开作用域即删除）
+pub(crate) struct TempFile(pub(crate) PathBuf);
+
+impl TempFile {
+    /// 进程内唯一的临时文件名（仅生成路径，不创建文件）
+    pub(crate) fn new(stem: &str, ext: &str) -> std::io::Result<Self> {
+        static SEQ: AtomicUsize = AtomicUsize::new(0);
+        let dir = std::env::temp_dir().join("xtap_external");
+        std::fs::create_dir_all(&dir)?;
+        Ok(TempFile(dir.join(format!(
+            "{}_{}_{}.{}",
+            std::process::id(),
+            SEQ.fetch_add(1, Ordering::Relaxed),
+            stem,
+            ext.trim_start_matches('.')
+        ))))
+    }
+}
+
+impl Drop for TempFile {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.0);
+    }
+}
+
+// ---------------------------------------------------------------- 结构化错误
+
+#[derive(Debug)]
+pub enum DecoderError {
+    /// 无法启动（程序不存在 / 无执行权限）
+    Spawn {
+        program: String,
+        source: std::io::Error,
+    },
+    Timeout {
+        program: String,
+        secs: u64,
+    },
+    /// 非零退出（附 stderr 末尾）
+    Failed {
+        program: String,
+        code: Option<i32>,
+        stderr: String,
+    },
+    /// 正常退出但未写出文件
+    NoOutput {
+        program: String,
+    },
+    /// 输出文件无法解码
+    BadOutput {
+        program: String,
+        reason: String,
+    },
+}
+
+impl fmt::Display for DecoderError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Spawn { program, source } => {
+                write!(f, "外部解码器 {} 无法启动: {}", program, source)
+            }
+            Self::Timeout { program, secs } => {
+                write!(f, "外部解码器 {} 超时（{} 秒）已终止", program, secs)
+            }
+            Self::Failed {
+                program,
+                code,
+                stderr,
+            } => {
+                let code = code.map_or("被信号终止".to_string(), |c| format!("退出码 {}", c));
+                if stderr.is_empty() {
+                    write!(f, "外部解码器 {} 失败（{}）", program, code)
+                } else {
+                    write!(f, "外部解码器 {} 失败（{}）: {}", program, code, stderr)
+                }
+            }
+            Self::NoOutput { program } => write!(f, "外部解码器 {} 未生成输出文件", program),
+            Self::BadOutput { program, reason } => {
+                write!(f, "外部解码器 {} 的输出无法解码: {}", program, reason)
+            }
+        }
+    }
+}
+
+impl std::error::Error for DecoderError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Spawn { source, .. } => Some(source),
+            _ => None,
+        }
+    }
+}
+
+/// 运行命令直到退出或超时（超时即 kill）；stderr 另起线程读取，防管道写满阻塞子进程
+pub fn run_with_timeout(
+    cmd: &mut Command,
+    program: &str,
+    timeout: Duration,
+) -> Result<(), DecoderError> {
+    let mut child = cmd
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|source| DecoderError::Spawn {
+            program: program.to_string(),
+            source,
+        })?;
+    let stderr = child.stderr.take().map(|mut pipe| {
+        std::thread::spawn(move || {
+            let mut buf = Vec::new();
+            let _ = pipe.read_to_end(&mut buf);
+            buf
+        })
+    });
+    let start = Instant::now();
+    let mut nap = Duration::from_millis(5);
+    let status = loop {
+        match child.try_wait() {
+            Ok(Some(status)) => break status,
+            Ok(None) if start.elapsed() >= timeout => {
+                let _ = child.kill();
+                let _ = child.wait();
+                return Err(DecoderError::Timeout {
+                    program: program.to_string(),
+                    secs: timeout.as_secs(),
+                });
+            }
+            Ok(None) => {
+                std::thread::sleep(nap);
+                nap = (nap * 2).min(Duration::from_millis(100));
+            }
+            Err(source) => {
+                return Err(DecoderError::Spawn {
+                    program: program.to_string(),
+                    source,
+                })
+            }
+        }
+    };
+    if status.success() {
+        return Ok(());
+    }
+    let stderr = stderr
+        .and_then(|h| h.join().ok())
+        .map(|b| String::from_utf8_lossy(&b).trim().to_string())
+        .unwrap_or_default();
+    let tail: String = {
+        let chars: Vec<char> = stderr.chars().collect();
+        chars[chars.len().saturating_sub(300)..].iter().collect()
+    };
+    Err(DecoderError::Failed {
+        program: program.to_string(),
+        code: status.code(),
+        stderr: tail,
+    })
+}
+
+// ---------------------------------------------------------------- 并发信号量
+
+struct Gate {
+    /// (占用数, 上限)
+    state: Mutex<(usize, usize)>,
+    freed: Condvar,
+}
+
+static GATE: Gate = Gate {
+    state: Mutex::new((0, DEFAULT_CONCURRENCY)),
+    freed: Condvar::new(),
+};
+
+/// 并发许可（drop 时归还）
+pub struct Permit(());
+
+impl Drop for Permit {
+    fn drop(&mut self) {
+        let mut state = GATE.state.lock().unwrap_or_else(|e| e.into_inner());
+        state.0 -= 1;
+        GATE.freed.notify_one();
+    }
+}
+
+/// 阻塞等待一个许可
+pub fn acquire() -> Permit {
+    let mut state = GATE.state.lock().unwrap_or_else(|e| e.into_inner());
+    while state.0 >= state.1 {
+        state = GATE.freed.wait(state).unwrap_or_else(|e| e.into_inner());
+    }
+    state.0 += 1;
+    Permit(())
+}
+
+/// 设置全进程外部工具并发上限（0 = 缺省 4）
+pub fn set_concurrency(limit: usize) {
+    let mut state = GATE.state.lock().unwrap_or_else(|e| e.into_inner());
+    state.1 = if limit == 0 {
+        DEFAULT_CONCURRENCY
+    } else {
+        limit
+    };
+    GATE.freed.notify_all();
+}
+
+// ---------------------------------------------------------------- 扩展名注册表
+
+static EXTRA_EXTENSIONS: RwLock<Vec<String>> = RwLock::new(Vec::new());
+
+/// 登记外部解码器负责的扩展名（目录扫描据此收录文件）
+pub fn register_extensions(decoders: &[ExternalDecoder]) {
+    let mut exts = EXTRA_EXTENSIONS.write().unwrap_or_else(|e| e.into_inner());
+    for e in decoders.iter().flat_map(|d| d.normalized_extensions()) {
+        if !exts.contains(&e) {
+            exts.push(e);
+        }
+    }
+}
+
+pub fn is_registered_extension(ext: &str) -> bool {
+    EXTRA_EXTENSIONS
+        .read()
+        .unwrap_or_else(|e| e.into_inner())
+        .iter()
+        .any(|e| e.eq_ignore_ascii_case(ext))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_and_validate() {
+        let d = ExternalDecoder::parse(".CR3, nef=dcraw_emu -T -Z {output} {input}").unwrap();
+        assert_eq!(d.extensions, vec![".CR3", "nef"]);
+        assert!(d.handles("cr3") && d.handles("NEF") && !d.handles("jpg"));
+        assert_eq!(d.display_name(), "dcraw_emu");
+        assert!(ExternalDecoder::parse("heic=magick {input} out.png").is_err());
+        assert!(ExternalDecoder::parse("magick {input} {output}").is_err());
+        let d = ExternalDecoder {
+            timeout_secs: Some(0),
+            ..d
+        };
+        assert!(d.validate().is_err());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_stub_script_decode_errors_and_concurrency() {
+        use std::os::unix::fs::PermissionsExt;
+        let dir = std::env::temp_dir().join(format!("xtap_external_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let script = |name: &str, body: &str| {
+            let p = dir.join(name);
+            std::fs::write(&p, format!("#!/bin/sh\n{}\n", body)).unwrap();
+            std::fs::set_permissions(&p, std::fs::Permissions::from_mode(0o755)).unwrap();
+            p.to_string_lossy().to_string()
+        };
+        let input = dir.join("shot.fake");
+        image::RgbImage::from_pixel(12, 8, image::Rgb([10, 200, 30]))
+            .save_with_format(&input, image::ImageFormat::Png)
+            .unwrap();
+        let decoder = |program: String, output_ext: &str| ExternalDecoder {
+            name: String::new(),
+            extensions: vec!["fake".into()],
+            command: vec![program, "{input}".into(), "--out={output}".into()],
+            output_ext: output_ext.into(),
+            timeout_secs: Some(1),
+        };
+        let t = Duration::from_secs(5);
+
+        // 桩脚本：把输入（PNG）拷到 --out= 指定的位置
+        let copy = script("copy.sh", "cp \"$1\" \"${2#--out=}\"");
+        let img = decoder(copy.clone(), "png").decode(&input, t).unwrap();
+        assert_eq!((img.width(), img.height()), (12, 8));
+
+        let fail = script("fail.sh", "echo 'bad raw' >&2; exit 3");
+        match decoder(fail, "png").decode(&input, t) {
+            Err(DecoderError::Failed { code, stderr, .. }) => {
+                assert_eq!(code, Some(3));
+                assert_eq!(stderr, "bad raw");
+            }
+            other => panic!("{:?}", other.map(|_| ())),
+        }
+        let slow = script("slow.sh", "sleep 5");
+        assert!(matches!(
+            decoder(slow, "png").decode(&input, t),
+            Err(DecoderError::Timeout { secs: 1, .. })
+        ));
+        let silent = script("silent.sh", "true");
+        assert!(matches!(
+            decoder(silent, "png").decode(&input, t),
+            Err(DecoderError::NoOutput { .. })
+        ));
+        let missing = decoder(dir.join("nope").to_string_lossy().to_string(), "png");
+        assert!(matches!(
+            missing.decode(&input, t),
+            Err(DecoderError::Spawn { .. })
+        ));
+
+        // 信号量：上限 2 时 4 个并发解码全部成功，且同时占用不超过 2
+        set_concurrency(2);
+        let handles: Vec<_> = (0..4)
+            .map(|_| {
+                let d = decoder(copy.clone(), "png");
+                let input = input.clone();
+                std::thread::spawn(move || d.decode(&input, t).is_ok())
+            })
+            .collect();
+        assert!(handles.into_iter().all(|h| h.join().unwrap()));
+        assert!(GATE.state.lock().unwrap().0 == 0);
+        set_concurrency(0);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,146 @@
+//! v4.5：朋友圈九宫格切图（3×3 / 2×2 / 1×3 等）
+//!
+//! 第三方切图工具的输出再被我们或平台压一遍，画质损失叠加。这里直接在管线内切：
+//! - 整图（裁剪/补边之后）按行列均分成等大切片，切片之间可留 `gutter` 像素缝（缝内像素丢弃，
+//!   朋友圈九宫格之间本来就有白缝，留缝可让拼接处的内容「连得上」）；
+//! - 均分除不尽的余数平均分到四周（网格整体居中）；
+//! - 每个切片独立走缩放/锐化/编码（与单图同一套 Processor 参数），输出按上传顺序编号
+//!   `_1` … `_9`（行优先：左上 → 右下）。
+//!
+//! 朋友圈单条最多 9 张图，所以切片总数上限 9。
+
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+/// 单条朋友圈最多 9 张图
+pub const MAX_TILES: u32 = 9;
+
+/// 九宫格规格
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct GridSpec {
+    pub cols: u32,
+    pub rows: u32,
+    /// 切片间缝宽（像素，按裁剪/补边后的原图尺寸计）
+    pub gutter: u32,
+}
+
+impl GridSpec {
+    /// "3x3" / "2x2" / "1x3"（列x行），切片数须在 1..=9；缝宽另行指定
+    pub fn parse(s: &str, gutter: u32) -> Option<Self> {
+        let (c, r) = s.trim().split_once(['x', 'X', '*', '×'])?;
+        let cols: u32 = c.trim().parse().ok()?;
+        let rows: u32 = r.trim().parse().ok()?;
+        (cols > 0 && rows > 0 && cols * rows <= MAX_TILES).then_some(GridSpec {
+            cols,
+            rows,
+            gutter,
+        })
+    }
+}
+
+/// 单个切片（裁剪/补边后的像素坐标；index 从 1 起，即上传顺序）
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GridTile {
+    pub index: u32,
+    pub row: u32,
+    pub col: u32,
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
+/// 切片输出结果（JSON 输出用）
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TileOutput {
+    #[serde(flatten)]
+    pub tile: GridTile,
+    pub output: PathBuf,
+    pub bytes: u64,
+}
+
+/// 计算切片布局（行优先）。缝宽过大导致切片为 0 时返回空
+pub fn grid_layout(width: u32, height: u32, spec: GridSpec) -> Vec<GridTile> {
+    let gutters_x = spec.gutter.saturating_mul(spec.cols - 1);
+    let gutters_y = spec.gutter.saturating_mul(spec.rows - 1);
+    if width <= gutters_x || height <= gutters_y {
+        return Vec::new();
+    }
+    let tw = (width - gutters_x) / spec.cols;
+    let th = (height - gutters_y) / spec.rows;
+    if tw == 0 || th == 0 {
+        return Vec::new();
+    }
+    // 除不尽的余数平均分到两侧
+    let x0 = (width - gutters_x - tw * spec.cols) / 2;
+    let y0 = (height - gutters_y - th * spec.rows) / 2;
+    let mut tiles = Vec::with_capacity((spec.cols * spec.rows) as usize);
+    for row in 0..spec.rows {
+        for col in 0..spec.cols {
+            tiles.push(GridTile {
+                index: row * spec.cols + col + 1,
+                row,
+                col,
+                x: x0 + col * (tw + spec.gutter),
+                y: y0 + row * (th + spec.gutter),
+                width: tw,
+                height: th,
+            });
+        }
+    }
+    tiles
+}
+
+/// 切片输出路径：在单图输出路径的文件名后追加 `_{index}`（photo_wx.jpg → photo_wx_1.jpg）
+pub fn tile_path(base: &Path, index: u32) -> PathBuf {
+    let stem = base.file_stem().unwrap_or_default().to_string_lossy();
+    let name = match base.extension() {
+        Some(ext) => format!("{}_{}.{}", stem, index, ext.to_string_lossy()),
+        None => format!("{}_{}", stem, index),
+    };
+    base.with_file_name(name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_grid_spec() {
+        assert_eq!(
+            GridSpec::parse("3x3", 0),
+            Some(GridSpec {
+                cols: 3,
+                rows: 3,
+                gutter: 0
+            })
+        );
+        assert_eq!(
+            GridSpec::parse("1x3", 4).map(|g| (g.cols, g.rows)),
+            Some((1, 3))
+        );
+        assert_eq!(GridSpec::parse("4x3", 0), None, "超过 9 张");
+        assert_eq!(GridSpec::parse("0x3", 0), None);
+    }
+
+    #[test]
+    fn test_layout_equal_tiles_with_gutter_centered() {
+        // 1000 宽 3 列、缝 5：可用 990 → 切片 330；高 1001 同理余 1 → 上边距 0
+        let spec = GridSpec::parse("3x3", 5).unwrap();
+        let tiles = grid_layout(1000, 1001, spec);
+        assert_eq!(tiles.len(), 9);
+        assert!(tiles.iter().all(|t| (t.width, t.height) == (330, 330)));
+        assert_eq!((tiles[0].x, tiles[0].y), (0, 0));
+        assert_eq!((tiles[1].x, tiles[2].x), (335, 670));
+        assert_eq!((tiles[8].index, tiles[8].row, tiles[8].col), (9, 2, 2));
+        assert!(tiles[8].y + tiles[8].height <= 1001);
+        // 缝宽吃掉整幅
+        assert!(grid_layout(10, 10, GridSpec::parse("3x3", 5).unwrap()).is_empty());
+    }
+
+    #[test]
+    fn test_tile_path_numbering() {
+        let p = tile_path(Path::new("/out/photo_wx.jpg"), 3);
+        assert_eq!(p, PathBuf::from("/out/photo_wx_3.jpg"));
+    }
+}
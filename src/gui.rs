@@ -23,7 +23,7 @@ use std::path::PathBuf;
 use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
 use std::sync::Arc;
 
-use crate::cli::apply_platform_preset;
+use crate::cli::{apply_platform_preset, builtin_presets, presets};
 use crate::runner::{collect_images, is_large_image, is_supported_image, load_config, save_config};
 use xtap_compress::perceptual::{FocusMode, PerceptualOptions, QuantMode};
 use xtap_compress::{
@@ -85,6 +85,8 @@ impl ImageCompressorApp {
             custom_quality: 95,
             ..Default::default()
         });
+        // v4.5：config.toml 中的 [[external_decoders]]（扩展名登记 + 并发上限）
+        crate::runner::install_external_decoders(&config);
         // 用途/平台/画质从配置记忆恢复（serde default 兜底：旧版配置文件也能正常加载）
 
         // 先设置视觉样式，避免窗口背景闪烁！
@@ -348,6 +350,14 @@ impl ImageCompressorApp {
 
             // P0：panic 兜底——工作线程若崩溃，仍保证发送 ProcessingFinished，UI 不卡「处理中」
             let panic_err = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+                // v4.5：水印素材加载失败 → 整批按失败上报（不输出缺水印的图）
+                if let Some(Err(e)) = config.watermark.as_ref().map(|wm| wm.load()) {
+                    for index in 0..total {
+                        let err_text = Some(e.to_string());
+                        let _ = tx.send(AppEvent::ProcessingProgress(index, false, err_text));
+                    }
+                    return;
+                }
                 let mut processor_config = app_config_to_process_config(&config, custom_output_dir);
                 processor_config.perceptual = if perceptual_on {
                     Some(PerceptualOptions {
@@ -710,14 +720,7 @@ impl eframe::App for ImageCompressorApp {
                                         "archive" => "高清存档",
                                         _ => "自定义",
                                     };
-                                    let plat_label = match self.config.platform.as_str() {
-                                        "wechat" => "微信 (全版本)",
-                                        "wechat-new" => "微信-new (iOS 新)",
-                                        "xiaohongshu" => "小红书",
-                                        "instagram" => "Instagram",
-                                        "general" => "通用 (中画幅)",
-                                        _ => "微信 (全版本)",
-                                    };
+                                    let plat_label = platform_label(&self.config.platform);
                                     let quality_label =
                                         match self.config.quality_mode.as_str() {
                                             "max" => "画质优先",
@@ -859,14 +862,7 @@ impl eframe::App for ImageCompressorApp {
                                                         .color(egui::Color32::from_rgb(30, 41, 59)),
                                                 );
                                                 let plat_label =
-                                                    match self.config.platform.as_str() {
-                                                        "wechat" => "微信 (全版本)",
-                                                        "wechat-new" => "微信-new (iOS 新)",
-                                                        "xiaohongshu" => "小红书",
-                                                        "instagram" => "Instagram",
-                                                        "general" => "通用 (中画幅)",
-                                                        _ => "微信 (全版本)",
-                                                    };
+                                                    platform_label(&self.config.platform);
                                                 egui::ComboBox::from_id_salt("platform_combo")
                                                     .selected_text(
                                                         egui::RichText::new(plat_label).color(
@@ -899,6 +895,21 @@ impl eframe::App for ImageCompressorApp {
                                                             "general".to_string(),
                                                             "通用 (中画幅)",
                                                         );
+                                                        // presets.toml 自定义预设（内置之后按名排序）
+                                                        let builtin = builtin_presets();
+                                                        for (name, preset) in presets() {
+                                                            if builtin.contains_key(name) {
+                                                                continue;
+                                                            }
+                                                            ui.selectable_value(
+                                                                &mut self.config.platform,
+                                                                name.clone(),
+                                                                preset
+                                                                    .label
+                                                                    .clone()
+                                                                    .unwrap_or_else(|| name.clone()),
+                                                            );
+                                                        }
                                                     });
                                                 ui.add_space(12.0);
                                                 ui.label(
@@ -939,6 +950,17 @@ impl eframe::App for ImageCompressorApp {
                                                         );
                                                     });
                                             });
+                                            // v4.5：预设带画幅约束（小红书 3:4 / IG 4:5）时提供智能裁剪
+                                            if let Some(aspect) = presets()
+                                                .get(&self.config.platform)
+                                                .and_then(|p| p.aspect.clone())
+                                            {
+                                                ui.add_space(4.0);
+                                                ui.checkbox(
+                                                    &mut self.config.smart_crop,
+                                                    format!("智能裁剪到 {}（自动避开主体/人脸）", aspect),
+                                                );
+                                            }
                                             ui.add_space(4.0);
                                             ui.label(
                                                 egui::RichText::new(
@@ -1249,6 +1271,149 @@ impl eframe::App for ImageCompressorApp {
                                             .color(egui::Color32::GRAY),
                                         );
                                     });
+
+                                    ui.add_space(15.0);
+                                    ui.separator();
+                                    ui.add_space(10.0);
+
+                                    // v4.5：水印（缩放后、锐化与编码前叠加）
+                                    ui.label(
+                                        egui::RichText::new("💧 水印")
+                                            .size(14.0)
+                                            .strong()
+                                            .color(egui::Color32::from_rgb(37, 99, 235)),
+                                    );
+                                    ui.add_space(5.0);
+
+                                    let mut wm_enabled = self.config.watermark.is_some();
+                                    if ui.checkbox(&mut wm_enabled, "添加水印").changed() {
+                                        self.config.watermark = wm_enabled
+                                            .then(xtap_compress::watermark::WatermarkConfig::default);
+                                    }
+                                    if let Some(wm) = self.config.watermark.as_mut() {
+                                        egui::Grid::new("watermark_grid")
+                                            .num_columns(2)
+                                            .spacing([10.0, 8.0])
+                                            .show(ui, |ui| {
+                                                ui.label(
+                                                    egui::RichText::new("标志 (PNG):")
+                                                        .color(egui::Color32::from_rgb(71, 85, 105)),
+                                                );
+                                                ui.horizontal(|ui| {
+                                                    ui.label(
+                                                        egui::RichText::new(
+                                                            wm.image.as_deref().unwrap_or("(未选择)"),
+                                                        )
+                                                        .size(12.0),
+                                                    );
+                                                    if ui.button("选择").clicked() {
+                                                        if let Some(path) = rfd::FileDialog::new()
+                                                            .add_filter("PNG", &["png"])
+                                                            .pick_file()
+                                                        {
+                                                            wm.image =
+                                                                Some(path.to_string_lossy().to_string());
+                                                        }
+                                                    }
+                                                    if wm.image.is_some() && ui.button("清除").clicked() {
+                                                        wm.image = None;
+                                                    }
+                                                });
+                                                ui.end_row();
+
+                                                ui.label(
+                                                    egui::RichText::new("文字:")
+                                                        .color(egui::Color32::from_rgb(71, 85, 105)),
+                                                )
+                                                .on_hover_text("未选标志时使用文字水印，需指定字体");
+                                                let mut text = wm.text.clone().unwrap_or_default();
+                                                if ui.text_edit_singleline(&mut text).changed() {
+                                                    wm.text = (!text.is_empty()).then_some(text);
+                                                }
+                                                ui.end_row();
+
+                                                ui.label(
+                                                    egui::RichText::new("字体 (TTF/OTF):")
+                                                        .color(egui::Color32::from_rgb(71, 85, 105)),
+                                                );
+                                                ui.horizontal(|ui| {
+                                                    ui.label(
+                                                        egui::RichText::new(
+                                                            wm.font.as_deref().unwrap_or("(未选择)"),
+                                                        )
+                                                        .size(12.0),
+                                                    );
+                                                    if ui.button("选择").clicked() {
+                                                        if let Some(path) = rfd::FileDialog::new()
+                                                            .add_filter("字体", &["ttf", "otf"])
+                                                            .pick_file()
+                                                        {
+                                                            wm.font =
+                                                                Some(path.to_string_lossy().to_string());
+                                                        }
+                                                    }
+                                                });
+                                                ui.end_row();
+
+                                                ui.label(
+                                                    egui::RichText::new("位置:")
+                                                        .color(egui::Color32::from_rgb(71, 85, 105)),
+                                                );
+                                                egui::ComboBox::from_id_salt("watermark_position")
+                                                    .selected_text(wm.position.clone())
+                                                    .show_ui(ui, |ui| {
+                                                        for pos in [
+                                                            "top-left",
+                                                            "top",
+                                                            "top-right",
+                                                            "left",
+                                                            "center",
+                                                            "right",
+                                                            "bottom-left",
+                                                            "bottom",
+                                                            "bottom-right",
+                                                            "tile",
+                                                        ] {
+                                                            ui.selectable_value(
+                                                                &mut wm.position,
+                                                                pos.to_string(),
+                                                                pos,
+                                                            );
+                                                        }
+                                                    });
+                                                ui.end_row();
+
+                                                ui.label(
+                                                    egui::RichText::new("不透明度:")
+                                                        .color(egui::Color32::from_rgb(71, 85, 105)),
+                                                );
+                                                ui.add(egui::Slider::new(&mut wm.opacity, 0.05..=1.0));
+                                                ui.end_row();
+
+                                                ui.label(
+                                                    egui::RichText::new("尺寸 (短边比例):")
+                                                        .color(egui::Color32::from_rgb(71, 85, 105)),
+                                                )
+                                                .on_hover_text("标志宽度 / 文字字号占输出短边的比例");
+                                                let default_scale =
+                                                    if wm.image.is_some() { 0.2 } else { 0.05 };
+                                                let mut scale = wm.scale.unwrap_or(default_scale);
+                                                if ui
+                                                    .add(egui::Slider::new(&mut scale, 0.01..=0.5))
+                                                    .changed()
+                                                {
+                                                    wm.scale = Some(scale);
+                                                }
+                                                ui.end_row();
+
+                                                ui.label(
+                                                    egui::RichText::new("边距 (短边比例):")
+                                                        .color(egui::Color32::from_rgb(71, 85, 105)),
+                                                );
+                                                ui.add(egui::Slider::new(&mut wm.margin, 0.0..=0.2));
+                                                ui.end_row();
+                                            });
+                                    }
                                 });
                             });
                     }
@@ -1318,7 +1483,7 @@ impl eframe::App for ImageCompressorApp {
                                 ui.add_space(5.0);
                                 ui.label(
                                     egui::RichText::new(
-                                        "支持 JPG, PNG, WEBP, TIFF, DNG, RAW 等格式",
+                                        "支持 JPG, PNG, WEBP, TIFF, HEIC, RAW 等格式",
                                     )
                                     .size(12.0)
                                     .color(egui::Color32::from_rgb(100, 116, 139)),
@@ -1349,7 +1514,8 @@ impl eframe::App for ImageCompressorApp {
                                                 &[
                                                     "jpg", "jpeg", "png", "webp", "bmp", "tif",
                                                     "tiff", "dng", "cr2", "cr3", "nef", "arw",
-                                                    "orf", "raf", "rw2", "pef", "srw",
+                                                    "orf", "raf", "rw2", "pef", "srw", "heic",
+                                                    "heif", "jxl", "gif", "hdr", "exr",
                                                 ],
                                             )
                                             .pick_files()
@@ -1370,6 +1536,7 @@ impl eframe::App for ImageCompressorApp {
                                 &[
                                     "jpg", "jpeg", "png", "webp", "bmp", "tif", "tiff", "dng",
                                     "cr2", "cr3", "nef", "arw", "orf", "raf", "rw2", "pef", "srw",
+                                    "heic", "heif", "jxl", "gif", "hdr", "exr",
                                 ],
                             )
                             .pick_files()
@@ -1505,6 +1672,14 @@ impl eframe::App for ImageCompressorApp {
     }
 }
 
+/// 平台显示名：取预设 label（内置均有中文名），自定义预设未写 label 时显示预设名
+fn platform_label(platform: &str) -> String {
+    presets()
+        .get(platform)
+        .and_then(|p| p.label.clone())
+        .unwrap_or_else(|| platform.to_string())
+}
+
 fn load_icon() -> Option<IconData> {
     match ::image::load_from_memory(include_bytes!("../icon.png")) {
         Ok(img) => {
@@ -0,0 +1,820 @@
+//! v4.5：HEIC/HEIF 输入（iPhone 照片一步转 JPEG）
+//!
+//! - 容器解析纯 Rust：pitm 主图 → iinf/iloc 定位条目数据 → ipma/ipco 关联属性
+//!   （ispe 尺寸、irot/imir 方向、colr ICC），grid 主图读出行列与拼接画布尺寸；
+//!   主图经 cdsc 引用的 Exif / XMP(mime) 条目即原图元数据；
+//! - 像素解码走 libheif（cargo feature `heic`，需系统 libheif ≥ 1.17）：HEVC 主图、grid 分块拼接、
+//!   irot/imir 摆正均由 libheif 完成；未启用时可配外部解码器
+//!   （`--external-decoder "heic,heif=heif-convert {input} {output}"`）；
+//! - 输出 JPEG 写回 EXIF / XMP / ICC：像素已按 irot/imir 摆正，EXIF Orientation 复位为 1，防止二次旋转。
+
+use crate::raw_preview::{boxes, child, Tiff};
+use anyhow::{anyhow, bail, Result};
+use bytes::Bytes;
+use img_parts::jpeg::{markers, JpegSegment};
+
+/// 支持的 HEIF 扩展名（小写）
+pub const HEIF_EXTENSIONS: [&str; 3] = ["heic", "heif", "hif"];
+
+/// HEVC 编码的 HEIF 品牌（ftyp 主品牌或兼容品牌之一）
+const BRANDS: [&[u8; 4]; 6] = [b"heic", b"heix", b"heim", b"heis", b"mif1", b"msf1"];
+/// APP 段内容上限（64KB 减去 2 字节段长）
+const MAX_SEGMENT: usize = 65_533;
+const EXIF_HEADER: &[u8] = b"Exif\0\0";
+const XMP_HEADER: &[u8] = b"http://ns.adobe.com/xap/1.0/\0";
+/// ICC APP2 头："ICC_PROFILE\0" + 序号 + 总段数
+const ICC_HEADER_LEN: usize = 14;
+
+pub fn is_heif_extension(ext: &str) -> bool {
+    HEIF_EXTENSIONS.iter().any(|e| ext.eq_ignore_ascii_case(e))
+}
+
+/// 主图几何与元数据
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct HeifInfo {
+    /// 摆正后的主图尺寸（已计入 irot 旋转）
+    pub width: u32,
+    pub height: u32,
+    /// grid 主图的 (行, 列)；单幅编码为 None
+    pub grid: Option<(u32, u32)>,
+    /// irot 逆时针旋转角（0 / 90 / 180 / 270）
+    pub rotation: u16,
+    /// imir 镜像轴（0 = 竖轴左右翻转，1 = 横轴上下翻转）
+    pub mirror: Option<u8>,
+    /// EXIF（TIFF 结构，不含 "Exif\0\0" 头）
+    pub exif: Option<Vec<u8>>,
+    pub xmp: Option<Vec<u8>>,
+    pub icc: Option<Vec<u8>>,
+}
+
+/// 解析 HEIF 容器：主图几何 + 元数据（不解码像素）
+pub fn parse(data: &[u8]) -> Result<HeifInfo> {
+    let (_, ftyp) = child(data, b"ftyp").ok_or_else(|| anyhow!("不是 HEIF 文件（缺少 ftyp）"))?;
+    let is_heif = ftyp.len() >= 8
+        && (0..ftyp.len() / 4)
+            .filter(|&i| i != 1)
+            .any(|i| BRANDS.iter().any(|b| &ftyp[i * 4..i * 4 + 4] == *b));
+    if !is_heif {
+        bail!("不是 HEVC 编码的 HEIF 文件");
+    }
+    let (_, meta) = child(data, b"meta").ok_or_else(|| anyhow!("HEIF 缺少 meta"))?;
+    let meta = Meta::parse(meta.get(4..).unwrap_or_default())?;
+
+    let primary = meta.primary;
+    let primary_item = meta
+        .items
+        .iter()
+        .find(|i| i.id == primary)
+        .ok_or_else(|| anyhow!("HEIF 主图条目 {} 不存在", primary))?;
+
+    let mut info = HeifInfo::default();
+    for prop in meta.properties(primary) {
+        match &prop.kind {
+            b"ispe" if prop.data.len() >= 12 => {
+                info.width = be32(prop.data, 4);
+                info.height = be32(prop.data, 8);
+            }
+            b"irot" if !prop.data.is_empty() => info.rotation = (prop.data[0] & 3) as u16 * 90,
+            b"imir" if !prop.data.is_empty() => info.mirror = Some(prop.data[0] & 1),
+            b"colr" if prop.data.len() > 4 && matches!(&prop.data[..4], b"prof" | b"rICC") => {
+                info.icc = Some(prop.data[4..].to_vec());
+            }
+            _ => {}
+        }
+    }
+    if &primary_item.kind == b"grid" {
+        let desc = meta.item_data(data, primary)?;
+        let (rows, cols, w, h) = grid_descriptor(&desc)?;
+        let tiles = meta.references(b"dimg", primary).len() as u32;
+        if tiles != rows * cols {
+            bail!("HEIF grid {}x{} 仅有 {} 个分块", rows, cols, tiles);
+        }
+        info.grid = Some((rows, cols));
+        (info.width, info.height) = (w, h);
+    }
+    if info.width == 0 || info.height == 0 {
+        bail!("HEIF 主图缺少尺寸（ispe）");
+    }
+    if info.rotation % 180 == 90 {
+        std::mem::swap(&mut info.width, &mut info.height);
+    }
+
+    // 描述主图的元数据条目优先；没有 cdsc 引用的旧文件取首个同类条目
+    let described: Vec<u32> = meta
+        .items
+        .iter()
+        .filter(|i| meta.references(b"cdsc", i.id).contains(&primary))
+        .map(|i| i.id)
+        .collect();
+    let pick = |want: &dyn Fn(&Item) -> bool| {
+        let mut candidates: Vec<&Item> = meta.items.iter().filter(|i| want(i)).collect();
+        candidates.sort_by_key(|i| !described.contains(&i.id));
+        candidates
+            .into_iter()
+            .find_map(|i| meta.item_data(data, i.id).ok())
+    };
+    info.exif = pick(&|i| &i.kind == b"Exif").and_then(|payload| exif_tiff(&payload));
+    info.xmp = pick(&|i| &i.kind == b"mime" && i.content_type == "application/rdf+xml");
+    Ok(info)
+}
+
+/// 像素解码（libheif：主图 / grid 拼接 / irot·imir 摆正），输出 8-bit RGB(A)
+#[cfg(feature = "heic")]
+pub fn decode(data: &[u8]) -> Result<image::DynamicImage> {
+    use libheif_rs::{ColorSpace, HeifContext, LibHeif, RgbChroma};
+
+    parse(data)?;
+    let lib = LibHeif::new();
+    let ctx = HeifContext::read_from_bytes(data)?;
+    let handle = ctx.primary_image_handle()?;
+    let alpha = handle.has_alpha_channel();
+    let chroma = if alpha {
+        RgbChroma::Rgba
+    } else {
+        RgbChroma::Rgb
+    };
+    let image = lib.decode(&handle, ColorSpace::Rgb(chroma), None)?;
+    let plane = image
+        .planes()
+        .interleaved
+        .ok_or_else(|| anyhow!("libheif 未返回交错 RGB 平面"))?;
+    let channels = if alpha { 4 } else { 3 };
+    let row = plane.width as usize * channels;
+    let mut pixels = Vec::with_capacity(row * plane.height as usize);
+    for y in 0..plane.height as usize {
+        pixels.extend_from_slice(&plane.data[y * plane.stride..y * plane.stride + row]);
+    }
+    let (w, h) = (plane.width, plane.height);
+    let img = if alpha {
+        image::RgbaImage::from_raw(w, h, pixels).map(image::DynamicImage::ImageRgba8)
+    } else {
+        image::RgbImage::from_raw(w, h, pixels).map(image::DynamicImage::ImageRgb8)
+    };
+    img.ok_or_else(|| anyhow!("libheif 平面尺寸异常"))
+}
+
+/// 未启用 `heic` 特性：先校验容器，再提示启用方式
+#[cfg(not(feature = "heic"))]
+pub fn decode(data: &[u8]) -> Result<image::DynamicImage> {
+    parse(data)?;
+    bail!(
+        "HEIC 解码需以 `--features heic` 编译（依赖系统 libheif），\
+         或配置外部解码器：--external-decoder \"heic,heif=heif-convert {{input}} {{output}}\""
+    )
+}
+
+/// 输出 JPEG 的元数据段：EXIF(APP1，Orientation 复位) / XMP(APP1) / ICC(APP2，按需分段)
+pub fn jpeg_segments(info: &HeifInfo) -> Vec<JpegSegment> {
+    let mut out = Vec::new();
+    if let Some(exif) = &info.exif {
+        let mut tiff = exif.clone();
+        reset_orientation(&mut tiff);
+        if EXIF_HEADER.len() + tiff.len() <= MAX_SEGMENT {
+            out.push(segment(markers::APP1, [EXIF_HEADER, &tiff].concat()));
+        }
+    }
+    if let Some(xmp) = &info.xmp {
+        if XMP_HEADER.len() + xmp.len() <= MAX_SEGMENT {
+            out.push(segment(markers::APP1, [XMP_HEADER, xmp].concat()));
+        }
+    }
+    if let Some(icc) = &info.icc {
+        let chunks: Vec<&[u8]> = icc.chunks(MAX_SEGMENT - ICC_HEADER_LEN).collect();
+        if !icc.is_empty() && chunks.len() <= 255 {
+            for (i, chunk) in chunks.iter().enumerate() {
+                let mut body = b"ICC_PROFILE\0".to_vec();
+                body.extend_from_slice(&[i as u8 + 1, chunks.len() as u8]);
+                body.extend_from_slice(chunk);
+                out.push(segment(markers::APP2, body));
+            }
+        }
+    }
+    out
+}
+
+fn segment(marker: u8, body: Vec<u8>) -> JpegSegment {
+    JpegSegment::new_with_contents(marker, Bytes::from(body))
+}
+
+/// IFD0 的 Orientation(0x0112) 改写为 1（像素已摆正）
+fn reset_orientation(tiff: &mut [u8]) {
+    let pos = {
+        let Some(t) = Tiff::new(tiff) else { return };
+        let Some((entries, _)) = t.first_ifd().and_then(|off| t.ifd(off)) else {
+            return;
+        };
+        match entries.iter().find(|e| e.tag == 0x0112 && e.typ == 3) {
+            Some(e) => (e.value_off, t.le),
+            None => return,
+        }
+    };
+    let (off, le) = pos;
+    let one = if le {
+        1u16.to_le_bytes()
+    } else {
+        1u16.to_be_bytes()
+    };
+    tiff[off..off + 2].copy_from_slice(&one);
+}
+
+/// Exif 条目：4 字节 TIFF 头偏移 + 数据（偏移之前常是 "Exif\0\0"）
+fn exif_tiff(payload: &[u8]) -> Option<Vec<u8>> {
+    let skip = u32::from_be_bytes(payload.get(..4)?.try_into().ok()?) as usize;
+    let tiff = payload.get(4usize.checked_add(skip)?..)?;
+    Tiff::new(tiff).map(|_| tiff.to_vec())
+}
+
+/// grid 描述：(行, 列, 画布宽, 画布高)
+fn grid_descriptor(desc: &[u8]) -> Result<(u32, u32, u32, u32)> {
+    let bad = || anyhow!("HEIF grid 描述损坏");
+    let flags = *desc.get(1).ok_or_else(bad)?;
+    let rows = *desc.get(2).ok_or_else(bad)? as u32 + 1;
+    let cols = *desc.get(3).ok_or_else(bad)? as u32 + 1;
+    let (w, h) = if flags & 1 == 0 {
+        let b = desc.get(4..8).ok_or_else(bad)?;
+        (
+            u16::from_be_bytes([b[0], b[1]]) as u32,
+            u16::from_be_bytes([b[2], b[3]]) as u32,
+        )
+    } else {
+        desc.get(4..12).ok_or_else(bad)?;
+        (be32(desc, 4), be32(desc, 8))
+    };
+    Ok((rows, cols, w, h))
+}
+
+// ---------------------------------------------------------------- meta
+
+struct Item {
+    id: u32,
+    kind: [u8; 4],
+    content_type: String,
+}
+
+struct Location {
+    id: u32,
+    /// 0 = 文件偏移，1 = idat 内偏移
+    method: u8,
+    extents: Vec<(u64, u64)>,
+}
+
+struct Property<'a> {
+    kind: [u8; 4],
+    data: &'a [u8],
+}
+
+struct Meta<'a> {
+    primary: u32,
+    items: Vec<Item>,
+    locations: Vec<Location>,
+    properties: Vec<Property<'a>>,
+    /// 条目 → 1 起的属性序号
+    associations: Vec<(u32, Vec<u16>)>,
+    /// (类型, 源条目, 目标条目)
+    references: Vec<([u8; 4], u32, Vec<u32>)>,
+    idat: &'a [u8],
+}
+
+impl<'a> Meta<'a> {
+    fn parse(meta: &'a [u8]) -> Result<Self> {
+        let mut out = Meta {
+            primary: 0,
+            items: Vec::new(),
+            locations: Vec::new(),
+            properties: Vec::new(),
+            associations: Vec::new(),
+            references: Vec::new(),
+            idat: &[],
+        };
+        let mut has_pitm = false;
+        for (kind, _, body) in boxes(meta) {
+            let mut r = Reader::new(body);
+            match &kind {
+                b"pitm" => {
+                    let version = r.u8()?;
+                    r.skip(3)?;
+                    out.primary = r.id(version == 0)?;
+                    has_pitm = true;
+                }
+                b"iinf" => out.items = parse_iinf(&mut r)?,
+                b"iloc" => out.locations = parse_iloc(&mut r)?,
+                b"iref" => {
+                    let version = r.u8()?;
+                    for (kind, _, refs) in boxes(body.get(4..).unwrap_or_default()) {
+                        let mut r = Reader::new(refs);
+                        let from = r.id(version == 0)?;
+                        let count = r.u16()?;
+                        let to = (0..count)
+                            .map(|_| r.id(version == 0))
+                            .collect::<Result<Vec<_>>>()?;
+                        out.references.push((kind, from, to));
+                    }
+                }
+                b"iprp" => {
+                    for (kind, _, body) in boxes(body) {
+                        match &kind {
+                            b"ipco" => {
+                                out.properties = boxes(body)
+                                    .into_iter()
+                                    .map(|(kind, _, data)| Property { kind, data })
+                                    .collect();
+                            }
+                            b"ipma" => out.associations.extend(parse_ipma(&mut Reader::new(body))?),
+                            _ => {}
+                        }
+                    }
+                }
+                b"idat" => out.idat = body,
+                _ => {}
+            }
+        }
+        if !has_pitm {
+            bail!("HEIF 缺少主图声明（pitm）");
+        }
+        Ok(out)
+    }
+
+    fn properties(&self, item: u32) -> impl Iterator<Item = &Property<'a>> {
+        self.associations
+            .iter()
+            .filter(move |(id, _)| *id == item)
+            .flat_map(|(_, idx)| idx.iter())
+            .filter_map(|&i| self.properties.get((i as usize).checked_sub(1)?))
+    }
+
+    fn references(&self, kind: &[u8; 4], from: u32) -> Vec<u32> {
+        self.references
+            .iter()
+            .filter(|(k, f, _)| k == kind && *f == from)
+            .flat_map(|(_, _, to)| to.iter().copied())
+            .collect()
+    }
+
+    /// 条目数据（按 iloc 拼接各段；长度 0 表示到文件 / idat 末尾）
+    fn item_data(&self, data: &[u8], item: u32) -> Result<Vec<u8>> {
+        let loc = self
+            .locations
+            .iter()
+            .find(|l| l.id == item)
+            .ok_or_else(|| anyhow!("HEIF 条目 {} 缺少位置信息", item))?;
+        let source = match loc.method {
+            0 => data,
+            1 => self.idat,
+            m => bail!("HEIF 条目构造方式 {} 不支持", m),
+        };
+        let mut out = Vec::new();
+        for &(offset, len) in &loc.extents {
+            let start = usize::try_from(offset)?;
+            let end = if len == 0 {
+                source.len()
+            } else {
+                start.saturating_add(usize::try_from(len)?)
+            };
+            let chunk = source
+                .get(start..end)
+                .ok_or_else(|| anyhow!("HEIF 条目 {} 数据越界", item))?;
+            out.extend_from_slice(chunk);
+        }
+        Ok(out)
+    }
+}
+
+fn parse_iinf(r: &mut Reader) -> Result<Vec<Item>> {
+    let version = r.u8()?;
+    r.skip(3)?;
+    let _count = r.id(version == 0)?;
+    let mut items = Vec::new();
+    for (kind, _, body) in boxes(r.rest()) {
+        if &kind != b"infe" {
+            continue;
+        }
+        let mut r = Reader::new(body);
+        let version = r.u8()?;
+        r.skip(3)?;
+        // v0/v1 不带条目类型（HEIC 不使用）
+        if version < 2 {
+            continue;
+        }
+        let id = r.id(version == 2)?;
+        r.skip(2)?;
+        let kind: [u8; 4] = r.bytes(4)?.try_into()?;
+        r.cstr()?;
+        let content_type = if &kind == b"mime" {
+            r.cstr().unwrap_or_default()
+        } else {
+            String::new()
+        };
+        items.push(Item {
+            id,
+            kind,
+            content_type,
+        });
+    }
+    Ok(items)
+}
+
+fn parse_iloc(r: &mut Reader) -> Result<Vec<Location>> {
+    let version = r.u8()?;
+    r.skip(3)?;
+    let sizes = r.u8()?;
+    let (offset_size, length_size) = (sizes >> 4, sizes & 15);
+    let sizes = r.u8()?;
+    let base_size = sizes >> 4;
+    let index_size = if version >= 1 { sizes & 15 } else { 0 };
+    let count = r.id(version < 2)?;
+    let mut out = Vec::new();
+    for _ in 0..count {
+        let id = r.id(version < 2)?;
+        let method = if version >= 1 {
+            (r.u16()? & 15) as u8
+        } else {
+            0
+        };
+        r.skip(2)?;
+        let base = r.sized(base_size)?;
+        let extents = r.u16()?;
+        let mut list = Vec::with_capacity(extents as usize);
+        for _ in 0..extents {
+            r.sized(index_size)?;
+            let offset = base
+                .checked_add(r.sized(offset_size)?)
+                .ok_or_else(|| anyhow!("HEIF iloc 偏移溢出"))?;
+            list.push((offset, r.sized(length_size)?));
+        }
+        out.push(Location {
+            id,
+            method,
+            extents: list,
+        });
+    }
+    Ok(out)
+}
+
+fn parse_ipma(r: &mut Reader) -> Result<Vec<(u32, Vec<u16>)>> {
+    let version = r.u8()?;
+    r.skip(2)?;
+    let wide = r.u8()? & 1 == 1;
+    let count = r.u32()?;
+    let mut out = Vec::new();
+    for _ in 0..count {
+        let id = r.id(version == 0)?;
+        let n = r.u8()?;
+        let idx = (0..n)
+            .map(|_| {
+                Ok(if wide {
+                    r.u16()? & 0x7FFF
+                } else {
+                    (r.u8()? & 0x7F) as u16
+                })
+            })
+            .collect::<Result<Vec<_>>>()?;
+        out.push((id, idx));
+    }
+    Ok(out)
+}
+
+fn be32(data: &[u8], off: usize) -> u32 {
+    u32::from_be_bytes(data[off..off + 4].try_into().unwrap())
+}
+
+/// 大端顺序读取器（越界即报错）
+struct Reader<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn new(data: &'a [u8]) -> Self {
+        Reader { data, pos: 0 }
+    }
+
+    fn bytes(&mut self, n: usize) -> Result<&'a [u8]> {
+        let out = self
+            .pos
+            .checked_add(n)
+            .and_then(|end| self.data.get(self.pos..end))
+            .ok_or_else(|| anyhow!("HEIF 盒子截断"))?;
+        self.pos += n;
+        Ok(out)
+    }
+
+    fn skip(&mut self, n: usize) -> Result<()> {
+        self.bytes(n).map(|_| ())
+    }
+
+    fn rest(&self) -> &'a [u8] {
+        &self.data[self.pos..]
+    }
+
+    fn u8(&mut self) -> Result<u8> {
+        Ok(self.bytes(1)?[0])
+    }
+
+    fn u16(&mut self) -> Result<u16> {
+        Ok(u16::from_be_bytes(self.bytes(2)?.try_into()?))
+    }
+
+    fn u32(&mut self) -> Result<u32> {
+        Ok(u32::from_be_bytes(self.bytes(4)?.try_into()?))
+    }
+
+    /// 条目 ID：旧版本 16 位，新版本 32 位
+    fn id(&mut self, short: bool) -> Result<u32> {
+        if short {
+            self.u16().map(u32::from)
+        } else {
+            self.u32()
+        }
+    }
+
+    /// iloc 的 0 / 4 / 8 字节变长字段
+    fn sized(&mut self, size: u8) -> Result<u64> {
+        match size {
+            0 => Ok(0),
+            4 => self.u32().map(u64::from),
+            8 => Ok(u64::from_be_bytes(self.bytes(8)?.try_into()?)),
+            s => bail!("HEIF iloc 字段长度 {} 不支持", s),
+        }
+    }
+
+    fn cstr(&mut self) -> Result<String> {
+        let rest = self.rest();
+        let len = rest
+            .iter()
+            .position(|&b| b == 0)
+            .ok_or_else(|| anyhow!("HEIF 字符串未结束"))?;
+        self.pos += len + 1;
+        Ok(String::from_utf8_lossy(&rest[..len]).into_owned())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bx(kind: &[u8; 4], body: &[u8]) -> Vec<u8> {
+        [&((body.len() + 8) as u32).to_be_bytes()[..], kind, body].concat()
+    }
+
+    fn full(kind: &[u8; 4], version: u8, flags: u32, body: &[u8]) -> Vec<u8> {
+        let mut head = flags.to_be_bytes();
+        head[0] = version;
+        bx(kind, &[&head[..], body].concat())
+    }
+
+    fn infe(id: u16, kind: &[u8; 4], content_type: &str) -> Vec<u8> {
+        let mut body = [&id.to_be_bytes()[..], &[0, 0], kind, b"\0"].concat();
+        if !content_type.is_empty() {
+            body.extend_from_slice(content_type.as_bytes());
+            body.push(0);
+        }
+        full(b"infe", 2, 0, &body)
+    }
+
+    /// 2x2 grid 主图（1..=4 为 hvc1 分块、5 为 grid、6 为 Exif、7 为 XMP），irot 90° + ICC
+    fn build_grid_heic(exif_tiff: &[u8], xmp: &[u8], icc: &[u8]) -> Vec<u8> {
+        let grid_desc = [0u8, 0, 1, 1, 0x0F, 0xA0, 0x0B, 0xB8]; // 2x2，4000x3000
+        let exif_payload = [&6u32.to_be_bytes()[..], b"Exif\0\0", exif_tiff].concat();
+        let payloads: Vec<(u16, Vec<u8>)> = vec![
+            (5, grid_desc.to_vec()),
+            (6, exif_payload),
+            (7, xmp.to_vec()),
+        ];
+
+        let mut items: Vec<u8> = 7u16.to_be_bytes().to_vec();
+        for id in 1..=4 {
+            items.extend(infe(id, b"hvc1", ""));
+        }
+        items.extend(infe(5, b"grid", ""));
+        items.extend(infe(6, b"Exif", ""));
+        items.extend(infe(7, b"mime", "application/rdf+xml"));
+
+        let mut ipco = bx(
+            b"ispe",
+            &[
+                &[0u8; 4][..],
+                &4000u32.to_be_bytes(),
+                &3000u32.to_be_bytes(),
+            ]
+            .concat(),
+        );
+        ipco.extend(bx(b"irot", &[1]));
+        ipco.extend(bx(b"colr", &[b"prof".as_slice(), icc].concat()));
+        let ipma = full(
+            b"ipma",
+            0,
+            0,
+            &[
+                &1u32.to_be_bytes()[..],
+                &5u16.to_be_bytes(),
+                &[3, 0x81, 0x82, 0x83],
+            ]
+            .concat(),
+        );
+        let iprp = bx(b"iprp", &[bx(b"ipco", &ipco), ipma].concat());
+
+        let refs = [
+            bx(
+                b"dimg",
+                &[
+                    &5u16.to_be_bytes()[..],
+                    &4u16.to_be_bytes(),
+                    &[0, 1, 0, 2, 0, 3, 0, 4],
+                ]
+                .concat(),
+            ),
+            bx(
+                b"cdsc",
+                &[
+                    &6u16.to_be_bytes()[..],
+                    &1u16.to_be_bytes(),
+                    &5u16.to_be_bytes(),
+                ]
+                .concat(),
+            ),
+            bx(
+                b"cdsc",
+                &[
+                    &7u16.to_be_bytes()[..],
+                    &1u16.to_be_bytes(),
+                    &5u16.to_be_bytes(),
+                ]
+                .concat(),
+            ),
+        ]
+        .concat();
+
+        let ftyp = bx(b"ftyp", b"heic\0\0\0\0mif1heic");
+        // iloc 偏移需 meta 长度：先以占位偏移构造一次量出长度，再回填
+        let build_meta = |base: u32| {
+            let mut iloc = vec![0x44, 0x00];
+            iloc.extend(3u16.to_be_bytes());
+            let mut off = base;
+            for (id, data) in &payloads {
+                iloc.extend(id.to_be_bytes());
+                iloc.extend([0, 0, 0, 1]);
+                iloc.extend(off.to_be_bytes());
+                iloc.extend((data.len() as u32).to_be_bytes());
+                off += data.len() as u32;
+            }
+            full(
+                b"meta",
+                0,
+                0,
+                &[
+                    full(b"hdlr", 0, 0, b"\0\0\0\0pict\0\0\0\0\0\0\0\0\0\0\0\0\0"),
+                    full(b"pitm", 0, 0, &5u16.to_be_bytes()),
+                    full(b"iinf", 0, 0, &items),
+                    full(b"iloc", 0, 0, &iloc),
+                    bx(b"iref", &[&[0u8; 4][..], &refs].concat()),
+                    iprp.clone(),
+                ]
+                .concat(),
+            )
+        };
+        let meta_len = build_meta(0).len();
+        let base = (ftyp.len() + meta_len + 8) as u32;
+        let mdat: Vec<u8> = payloads.iter().flat_map(|(_, d)| d.clone()).collect();
+        [ftyp, build_meta(base), bx(b"mdat", &mdat)].concat()
+    }
+
+    #[test]
+    fn test_parse_grid_metadata_and_segments() {
+        // 大端 TIFF：IFD0 = Make("Apple") + Orientation(6)
+        let mut tiff = b"MM\0\x2a\0\0\0\x08\0\x02".to_vec();
+        tiff.extend([0x01, 0x0F, 0, 2, 0, 0, 0, 6, 0, 0, 0, 38]);
+        tiff.extend([0x01, 0x12, 0, 3, 0, 0, 0, 1, 0, 6, 0, 0]);
+        tiff.extend([0, 0, 0, 0]);
+        tiff.extend(b"Apple\0");
+        let xmp = b"<x:xmpmeta xmlns:x=\"adobe:ns:meta/\"/>";
+        let icc = vec![7u8; 70_000];
+        let file = build_grid_heic(&tiff, xmp, &icc);
+
+        let info = parse(&file).unwrap();
+        assert_eq!(info.grid, Some((2, 2)));
+        assert_eq!(info.rotation, 90);
+        assert_eq!((info.width, info.height), (3000, 4000));
+        assert_eq!(info.exif.as_deref(), Some(tiff.as_slice()));
+        assert_eq!(info.xmp.as_deref(), Some(xmp.as_slice()));
+        assert_eq!(info.icc.as_deref(), Some(icc.as_slice()));
+
+        let segs = jpeg_segments(&info);
+        // EXIF + XMP + ICC 两段（70000 > 65519）
+        assert_eq!(segs.len(), 4);
+        let exif = segs[0].contents();
+        assert!(exif.starts_with(b"Exif\0\0"));
+        assert_eq!(&exif[6 + 30..6 + 32], &[0, 1], "Orientation 应复位为 1");
+        assert!(segs[1].contents().starts_with(XMP_HEADER));
+        assert_eq!(segs[2].marker(), markers::APP2);
+        assert_eq!(&segs[3].contents()[12..14], &[2, 2]);
+        let total: usize = segs[2..].iter().map(|s| s.contents().len() - 14).sum();
+        assert_eq!(total, icc.len());
+
+        // 截断文件报错而非 panic
+        for cut in [20, 200, file.len() - 10] {
+            let _ = parse(&file[..cut]);
+        }
+        assert!(parse(b"\0\0\0\x10ftypisom\0\0\0\0").is_err());
+    }
+
+    #[test]
+    fn test_parse_single_image_with_alpha() {
+        // 256x256 hvc1 主图 + auxl 引用的 alpha 辅助图（auxC），无元数据；HEVC 码流留空
+        let mut items: Vec<u8> = 2u16.to_be_bytes().to_vec();
+        items.extend(infe(1, b"hvc1", ""));
+        items.extend(infe(2, b"hvc1", ""));
+        let mut ipco = bx(
+            b"ispe",
+            &[&[0u8; 4][..], &256u32.to_be_bytes(), &256u32.to_be_bytes()].concat(),
+        );
+        ipco.extend(full(b"auxC", 0, 0, b"urn:mpeg:hevc:2015:auxid:1\0"));
+        let ipma = full(
+            b"ipma",
+            0,
+            0,
+            &[
+                &2u32.to_be_bytes()[..],
+                &1u16.to_be_bytes(),
+                &[1, 0x81],
+                &2u16.to_be_bytes(),
+                &[2, 0x81, 0x82],
+            ]
+            .concat(),
+        );
+        let auxl = bx(
+            b"auxl",
+            &[
+                &2u16.to_be_bytes()[..],
+                &1u16.to_be_bytes(),
+                &1u16.to_be_bytes(),
+            ]
+            .concat(),
+        );
+        let meta = full(
+            b"meta",
+            0,
+            0,
+            &[
+                full(b"hdlr", 0, 0, b"\0\0\0\0pict\0\0\0\0\0\0\0\0\0\0\0\0\0"),
+                full(b"pitm", 0, 0, &1u16.to_be_bytes()),
+                full(b"iinf", 0, 0, &items),
+                bx(b"iref", &[&[0u8; 4][..], &auxl].concat()),
+                bx(b"iprp", &[bx(b"ipco", &ipco), ipma].concat()),
+            ]
+            .concat(),
+        );
+        let file = [bx(b"ftyp", b"heic\0\0\0\0mif1heic"), meta].concat();
+
+        let info = parse(&file).unwrap();
+        assert_eq!((info.width, info.height), (256, 256));
+        assert_eq!(info.grid, None);
+        assert_eq!(info.rotation, 0);
+        assert!(info.exif.is_none() && info.xmp.is_none() && info.icc.is_none());
+        assert!(jpeg_segments(&info).is_empty());
+
+        // 无 HEVC 码流：解码报错而非 panic；未启用 feature 时提示启用方式
+        let err = decode(&file).unwrap_err().to_string();
+        if !cfg!(feature = "heic") {
+            assert!(err.contains("heic"));
+        }
+    }
+
+    fn fixture(name: &str) -> std::path::PathBuf {
+        std::path::PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+            .join("tests/fixtures/heic")
+            .join(name)
+    }
+
+    #[test]
+    fn test_parse_rotated_fixture() {
+        let data = std::fs::read(fixture("quadrants-rot90.heic")).unwrap();
+        let info = parse(&data).unwrap();
+        // 64x48 编码、irot 逆时针 270°（即顺时针 90°）：摆正后为竖图
+        assert_eq!((info.width, info.height), (48, 64));
+        assert_eq!(info.rotation, 270);
+        // libheif 编码器把主图封装为 1x1 grid
+        assert_eq!(info.grid, Some((1, 1)));
+    }
+
+    #[cfg(feature = "heic")]
+    #[test]
+    fn test_decode_rotated_fixture() {
+        use image::GenericImageView;
+
+        let img = crate::load_image_safe(&fixture("quadrants-rot90.heic")).unwrap();
+        assert_eq!(img.dimensions(), (48, 64));
+        // 顺时针转 90° 后：左上蓝、右上红、左下白、右下绿
+        let expect = [
+            ((12, 16), [40, 60, 220]),
+            ((36, 16), [220, 30, 30]),
+            ((12, 48), [240, 240, 240]),
+            ((36, 48), [30, 200, 40]),
+        ];
+        let rgb = img.to_rgb8();
+        for ((x, y), want) in expect {
+            let got = rgb.get_pixel(x, y).0;
+            let close = got.iter().zip(want).all(|(&g, w)| g.abs_diff(w) <= 12);
+            assert!(close, "({}, {}) = {:?}，期望 {:?}", x, y, got, want);
+        }
+    }
+}
@@ -0,0 +1,298 @@
+//! v4.5：应用图标 / 网站图标组（一张方图出 favicon.ico + 各尺寸 PNG + site.webmanifest）
+//!
+//! - 源图非正方形时居中补透明边成正方形（不裁切，标志完整保留）；
+//! - 每个尺寸都从源图直接缩放（不逐级缩小，避免小图糊上加糊），再按 `padding` 留白、
+//!   `radius` 切圆角（抗锯齿遮罩）；`background` 给出时先铺底色再切圆角；
+//! - favicon.ico 内含 16/24/32/48/64/128/256 多分辨率（PNG 帧）；
+//! - apple-touch-icon.png（180）：iOS 自带圆角遮罩且透明区会变黑，故不切圆角、按底色（缺省白）铺满；
+//! - android-chrome-192/512 列入 site.webmanifest（清单由调用方写盘，与响应式图片组清单同样处理）。
+
+use anyhow::{anyhow, Result};
+use image::codecs::ico::{IcoEncoder, IcoFrame};
+use image::imageops::{self, FilterType};
+use image::{ExtendedColorType, Rgba, RgbaImage};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+/// favicon.ico 内的分辨率
+pub const ICO_SIZES: [u32; 7] = [16, 24, 32, 48, 64, 128, 256];
+/// 单独输出的 PNG：(文件名, 边长, 是否列入 webmanifest)
+const PNG_ICONS: [(&str, u32, bool); 4] = [
+    ("favicon-16x16.png", 16, false),
+    ("favicon-32x32.png", 32, false),
+    ("android-chrome-192x192.png", 192, true),
+    ("android-chrome-512x512.png", 512, true),
+];
+const APPLE_TOUCH_ICON: (&str, u32) = ("apple-touch-icon.png", 180);
+
+/// 图标组规格
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct IconSpec {
+    /// 四周留白，占边长比例（0-0.4）
+    pub padding: f32,
+    /// 圆角半径，占边长比例（0 = 直角，0.5 = 圆形）
+    pub radius: f32,
+    /// 底色（None = 透明；apple-touch-icon 缺省白底）
+    pub background: Option<[u8; 3]>,
+}
+
+impl Default for IconSpec {
+    fn default() -> Self {
+        Self {
+            padding: 0.0,
+            radius: 0.0,
+            background: None,
+        }
+    }
+}
+
+impl IconSpec {
+    pub fn validate(&self) -> Result<()> {
+        if !(0.0..=0.4).contains(&self.padding) {
+            return Err(anyhow!(
+                "图标留白须在 0-0.4（占边长比例）: {}",
+                self.padding
+            ));
+        }
+        if !(0.0..=0.5).contains(&self.radius) {
+            return Err(anyhow!("图标圆角须在 0-0.5（占边长比例）: {}", self.radius));
+        }
+        Ok(())
+    }
+}
+
+/// 图标组输出目录：单图输出路径旁的 `{名}_icons/`
+pub fn icon_dir(base: &Path) -> PathBuf {
+    let stem = base.file_stem().unwrap_or_default().to_string_lossy();
+    base.with_file_name(format!("{}_icons", stem))
+}
+
+/// 单个图标文件
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct IconFile {
+    pub path: PathBuf,
+    /// 边长（ICO 为其中最大一帧）
+    pub size: u32,
+    pub bytes: u64,
+}
+
+/// site.webmanifest 中的一项
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ManifestIcon {
+    pub src: String,
+    pub sizes: String,
+    #[serde(rename = "type")]
+    pub mime: String,
+}
+
+/// site.webmanifest 内容
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WebManifest {
+    pub name: String,
+    pub short_name: String,
+    pub icons: Vec<ManifestIcon>,
+    pub theme_color: String,
+    pub background_color: String,
+    pub display: String,
+}
+
+/// 图标组报告（随 JSON 结果输出）
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct IconSet {
+    pub dir: PathBuf,
+    pub files: Vec<IconFile>,
+    /// site.webmanifest 路径（调用方按 `webmanifest` 写盘）
+    pub manifest: PathBuf,
+    pub webmanifest: WebManifest,
+    /// `<head>` 片段（路径按站点根目录）
+    pub html: String,
+}
+
+/// 生成图标组：写 favicon.ico 与各尺寸 PNG，返回报告（webmanifest 由调用方写盘）
+pub fn write_icon_set(src: &RgbaImage, spec: &IconSpec, dir: &Path, name: &str) -> Result<IconSet> {
+    spec.validate()?;
+    std::fs::create_dir_all(dir)?;
+    let square = make_square(src);
+    let mut files = Vec::new();
+
+    let frames: Vec<RgbaImage> = ICO_SIZES
+        .iter()
+        .map(|&s| render_icon(&square, s, spec, false))
+        .collect();
+    let ico_frames = frames
+        .iter()
+        .map(|f| IcoFrame::as_png(f.as_raw(), f.width(), f.height(), ExtendedColorType::Rgba8))
+        .collect::<image::ImageResult<Vec<_>>>()?;
+    let ico_path = dir.join("favicon.ico");
+    let mut ico = Vec::new();
+    IcoEncoder::new(&mut ico).encode_images(&ico_frames)?;
+    std::fs::write(&ico_path, &ico)?;
+    files.push(IconFile {
+        path: ico_path,
+        size: ICO_SIZES[ICO_SIZES.len() - 1],
+        bytes: ico.len() as u64,
+    });
+
+    let mut manifest_icons = Vec::new();
+    let pngs = PNG_ICONS
+        .iter()
+        .map(|&(file, size, listed)| (file, size, listed, false))
+        .chain(std::iter::once((
+            APPLE_TOUCH_ICON.0,
+            APPLE_TOUCH_ICON.1,
+            false,
+            true,
+        )));
+    for (file, size, listed, apple) in pngs {
+        let icon = render_icon(&square, size, spec, apple);
+        let path = dir.join(file);
+        icon.save(&path)
+            .map_err(|e| anyhow!("图标写入失败 {}: {}", path.display(), e))?;
+        files.push(IconFile {
+            bytes: std::fs::metadata(&path)?.len(),
+            path,
+            size,
+        });
+        if listed {
+            manifest_icons.push(ManifestIcon {
+                src: format!("/{}", file),
+                sizes: format!("{}x{}", size, size),
+                mime: "image/png".to_string(),
+            });
+        }
+    }
+
+    let color = spec
+        .background
+        .map(|[r, g, b]| format!("#{:02x}{:02x}{:02x}", r, g, b))
+        .unwrap_or_else(|| "#ffffff".to_string());
+    Ok(IconSet {
+        dir: dir.to_path_buf(),
+        files,
+        manifest: dir.join("site.webmanifest"),
+        webmanifest: WebManifest {
+            name: name.to_string(),
+            short_name: name.to_string(),
+            icons: manifest_icons,
+            theme_color: color.clone(),
+            background_color: color,
+            display: "standalone".to_string(),
+        },
+        html: concat!(
+            "<link rel=\"icon\" href=\"/favicon.ico\" sizes=\"any\">\n",
+            "<link rel=\"icon\" type=\"image/png\" sizes=\"32x32\" href=\"/favicon-32x32.png\">\n",
+            "<link rel=\"icon\" type=\"image/png\" sizes=\"16x16\" href=\"/favicon-16x16.png\">\n",
+            "<link rel=\"apple-touch-icon\" sizes=\"180x180\" href=\"/apple-touch-icon.png\">\n",
+            "<link rel=\"manifest\" href=\"/site.webmanifest\">\n",
+        )
+        .to_string(),
+    })
+}
+
+/// 非正方形源图居中补透明边成正方形
+fn make_square(src: &RgbaImage) -> RgbaImage {
+    let (w, h) = src.dimensions();
+    if w == h {
+        return src.clone();
+    }
+    let side = w.max(h);
+    let mut canvas = RgbaImage::new(side, side);
+    imageops::replace(
+        &mut canvas,
+        src,
+        ((side - w) / 2) as i64,
+        ((side - h) / 2) as i64,
+    );
+    canvas
+}
+
+/// 渲染单个尺寸：底色 → 留白缩放贴图 → 圆角遮罩；apple 版按底色（缺省白）铺满、不切圆角
+fn render_icon(square: &RgbaImage, size: u32, spec: &IconSpec, apple: bool) -> RgbaImage {
+    let pad = (spec.padding * size as f32).round() as u32;
+    let inner = size.saturating_sub(pad * 2).max(1);
+    let scaled = imageops::resize(square, inner, inner, FilterType::Lanczos3);
+    let background = match (spec.background, apple) {
+        (Some([r, g, b]), _) => Rgba([r, g, b, 255]),
+        (None, true) => Rgba([255, 255, 255, 255]),
+        (None, false) => Rgba([0, 0, 0, 0]),
+    };
+    let mut canvas = RgbaImage::from_pixel(size, size, background);
+    imageops::overlay(&mut canvas, &scaled, pad as i64, pad as i64);
+    if apple {
+        // 透明残留（底色透明的源图边缘）一律按白底合成
+        for p in canvas.pixels_mut() {
+            let a = p[3] as u32;
+            for c in 0..3 {
+                p[c] = ((p[c] as u32 * a + 255 * (255 - a) + 127) / 255) as u8;
+            }
+            p[3] = 255;
+        }
+    } else if spec.radius > 0.0 {
+        apply_round_mask(&mut canvas, spec.radius * size as f32);
+    }
+    canvas
+}
+
+/// 圆角遮罩：角上按像素中心到圆心的距离做 1px 抗锯齿过渡
+fn apply_round_mask(img: &mut RgbaImage, radius: f32) {
+    let (w, h) = img.dimensions();
+    let r = radius.min(w.min(h) as f32 / 2.0);
+    if r <= 0.0 {
+        return;
+    }
+    for (x, y, p) in img.enumerate_pixels_mut() {
+        let (px, py) = (x as f32 + 0.5, y as f32 + 0.5);
+        let cx = px.clamp(r, w as f32 - r);
+        let cy = py.clamp(r, h as f32 - r);
+        let d = ((px - cx).powi(2) + (py - cy).powi(2)).sqrt();
+        let coverage = (r + 0.5 - d).clamp(0.0, 1.0);
+        p[3] = (p[3] as f32 * coverage).round() as u8;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_icon_set_files_and_masks() {
+        let dir = std::env::temp_dir().join(format!("xtap_icon_set_{}", std::process::id()));
+        let src = RgbaImage::from_pixel(300, 200, Rgba([200, 30, 30, 255]));
+        let spec = IconSpec {
+            padding: 0.1,
+            radius: 0.25,
+            background: Some([0, 0, 255]),
+        };
+        let set = write_icon_set(&src, &spec, &dir, "logo").unwrap();
+        assert_eq!(set.files.len(), 1 + PNG_ICONS.len() + 1);
+        assert!(set.files.iter().all(|f| f.path.is_file() && f.bytes > 0));
+        assert_eq!(set.webmanifest.icons.len(), 2);
+        assert_eq!(set.webmanifest.icons[0].sizes, "192x192");
+        assert_eq!(set.webmanifest.background_color, "#0000ff");
+
+        // ICO 解码取最大帧 256
+        let ico = image::open(dir.join("favicon.ico")).unwrap();
+        assert_eq!((ico.width(), ico.height()), (256, 256));
+        // 192：圆角外透明；留白处为底色；中心为源图
+        let png = image::open(dir.join("android-chrome-192x192.png"))
+            .unwrap()
+            .to_rgba8();
+        assert_eq!(png.get_pixel(0, 0)[3], 0);
+        assert_eq!(png.get_pixel(96, 5).0, [0, 0, 255, 255]);
+        assert_eq!(png.get_pixel(96, 96).0, [200, 30, 30, 255]);
+        // apple-touch-icon 不切圆角、不透明
+        let apple = image::open(dir.join("apple-touch-icon.png"))
+            .unwrap()
+            .to_rgba8();
+        assert_eq!(apple.dimensions(), (180, 180));
+        assert_eq!(apple.get_pixel(0, 0)[3], 255);
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert!(IconSpec {
+            radius: 0.6,
+            ..Default::default()
+        }
+        .validate()
+        .is_err());
+    }
+}
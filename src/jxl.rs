@@ -0,0 +1,226 @@
+//! v4.5：JPEG XL 输入 / 输出
+//!
+//! - 解码：cargo feature `jxl` 启用时进程内经 jxl-oxide（纯 Rust）解码，EXIF 方向由 jxl-oxide 摆正，
+//!   8/16 位与浮点样本、透明通道原样保留；未启用时 .jxl 按不支持格式处理，
+//!   可登记外部解码器 `jxl=djxl {input} {output}`（输出 PNG）。用户为 jxl 配置的外部解码器优先；
+//! - 编码（不需要特性）：像素写临时 PNG 交给 libjxl 的 `cjxl -q`，有体积上限时按 JPEG 路径同样的方式二分质量；
+//! - JPEG 无损重压缩：JPEG 输入 + `jxl_lossless_jpeg` 时不解码像素，直接 `cjxl --lossless_jpeg=1`，
+//!   体积通常小约 20%，且可经 `djxl x.jxl x.jpg` 逐字节还原原 JPEG（EXIF 等元数据随位流保留）。
+
+use crate::external::{self, DecoderError, TempFile};
+use anyhow::{anyhow, bail, Result};
+use std::path::Path;
+use std::process::{Command, Stdio};
+use std::time::Duration;
+
+pub const CJXL: &str = "cjxl";
+
+pub fn is_jxl_extension(ext: &str) -> bool {
+    ext.eq_ignore_ascii_case("jxl")
+}
+
+/// 像素解码（jxl-oxide，方向已摆正）；分配上限与 image 通用解码一致
+#[cfg(feature = "jxl")]
+pub fn decode(data: &[u8]) -> Result<image::DynamicImage> {
+    use image::ImageDecoder;
+    use jxl_oxide::integration::JxlDecoder;
+
+    let mut decoder =
+        JxlDecoder::new(std::io::Cursor::new(data)).map_err(|e| anyhow!("JXL 解码失败: {}", e))?;
+    decoder.set_limits(image::Limits::default())?;
+    image::DynamicImage::from_decoder(decoder).map_err(|e| anyhow!("JXL 解码失败: {}", e))
+}
+
+#[cfg(not(feature = "jxl"))]
+pub fn decode(_data: &[u8]) -> Result<image::DynamicImage> {
+    bail!(
+        "JPEG XL 解码需以 `--features jxl` 编译，\
+         或配置外部解码器：--external-decoder \"jxl=djxl {{input}} {{output}}\"（输出 PNG）"
+    )
+}
+
+/// 处理前预检 cjxl 可否启动（`--output-format jxl` 时调用，失败按参数错误退出）
+pub fn check_encoder() -> Result<()> {
+    probe(CJXL)
+}
+
+fn probe(program: &str) -> Result<()> {
+    Command::new(program)
+        .arg("--version")
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .map(|_| ())
+        .map_err(|e| {
+            anyhow!(
+                "--output-format jxl 需要 libjxl 的 {} 命令（{}）",
+                program,
+                e
+            )
+        })
+}
+
+/// 像素编码为 JXL：`quality` 对应 `cjxl -q`（1-100）；给定 `limit`（字节）时取不超限的最高质量，
+/// 全部超限取质量 1。返回 (数据, 实际质量)
+pub fn encode(
+    img: &image::DynamicImage,
+    quality: u8,
+    limit: Option<usize>,
+    timeout: Duration,
+) -> Result<(Vec<u8>, u8)> {
+    encode_with(CJXL, img, quality, limit, timeout)
+}
+
+/// JPEG → JXL 无损重压缩（不解码像素），返回输出字节数
+pub fn transcode_jpeg(input: &Path, output: &Path, timeout: Duration) -> Result<u64> {
+    let data = cjxl(CJXL, input, &["--lossless_jpeg=1"], timeout)?;
+    std::fs::write(output, &data)?;
+    Ok(data.len() as u64)
+}
+
+fn encode_with(
+    program: &str,
+    img: &image::DynamicImage,
+    quality: u8,
+    limit: Option<usize>,
+    timeout: Duration,
+) -> Result<(Vec<u8>, u8)> {
+    use image::codecs::png::{CompressionType, FilterType, PngEncoder};
+    use image::DynamicImage;
+
+    let source = TempFile::new("jxl_src", "png")?;
+    // PNG 只收 8/16 位整数，浮点缓冲（HDR）先转 16 位
+    let owned;
+    let img = match img {
+        DynamicImage::ImageRgb32F(_) | DynamicImage::ImageRgba32F(_) => {
+            owned = DynamicImage::ImageRgba16(img.to_rgba16());
+            &owned
+        }
+        _ => img,
+    };
+    let file = std::io::BufWriter::new(std::fs::File::create(&source.0)?);
+    img.write_with_encoder(PngEncoder::new_with_quality(
+        file,
+        CompressionType::Fast,
+        FilterType::NoFilter,
+    ))?;
+
+    let run = |q: u8| cjxl(program, &source.0, &["-q", &q.to_string()], timeout);
+    let data = run(quality)?;
+    let Some(limit) = limit else {
+        return Ok((data, quality));
+    };
+    if data.len() <= limit {
+        return Ok((data, quality));
+    }
+    let (mut low, mut high) = (1u8, quality.saturating_sub(1));
+    let mut best = None;
+    while low <= high {
+        let mid = (low + high) / 2;
+        let data = run(mid)?;
+        if data.len() <= limit {
+            best = Some((data, mid));
+            low = mid + 1;
+        } else if mid == 1 {
+            break;
+        } else {
+            high = mid - 1;
+        }
+    }
+    match best {
+        Some(found) => Ok(found),
+        None => Ok((run(1)?, 1)),
+    }
+}
+
+fn cjxl(program: &str, input: &Path, args: &[&str], timeout: Duration) -> Result<Vec<u8>> {
+    let output = TempFile::new("jxl_out", "jxl")?;
+    let mut cmd = Command::new(program);
+    cmd.arg(input).arg(&output.0).args(args);
+    let _permit = external::acquire();
+    external::run_with_timeout(&mut cmd, program, timeout).map_err(|e| match e {
+        DecoderError::Spawn { .. } => anyhow!("JXL 编码需要 libjxl 的 {} 命令（{}）", program, e),
+        e => anyhow!("JXL 编码失败: {}", e),
+    })?;
+    let data = std::fs::read(&output.0).unwrap_or_default();
+    if data.is_empty() {
+        bail!("JXL 编码失败: {} 未生成输出文件", program);
+    }
+    Ok(data)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fixture() -> Vec<u8> {
+        let path = std::path::PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+            .join("tests/fixtures/jxl/quadrants.jxl");
+        std::fs::read(path).unwrap()
+    }
+
+    #[cfg(feature = "jxl")]
+    #[test]
+    fn test_decode_fixture() {
+        use image::GenericImageView;
+
+        let img = decode(&fixture()).unwrap();
+        assert_eq!(img.dimensions(), (64, 48));
+        // 无损编码：四个象限逐像素还原
+        let rgb = img.to_rgb8();
+        let expect = [[220, 30, 30], [30, 200, 40], [40, 60, 220], [240, 240, 240]];
+        for (x, y, p) in rgb.enumerate_pixels() {
+            let quadrant = (y >= 24) as usize * 2 + (x >= 32) as usize;
+            assert_eq!(p.0, expect[quadrant], "({}, {})", x, y);
+        }
+        assert!(decode(&fixture()[..40]).is_err());
+    }
+
+    #[cfg(not(feature = "jxl"))]
+    #[test]
+    fn test_decode_without_feature_hints() {
+        let err = decode(&fixture()).unwrap_err().to_string();
+        assert!(err.contains("--features jxl") && err.contains("djxl"));
+        assert!(is_jxl_extension("JXL") && !is_jxl_extension("jpg"));
+    }
+
+    #[test]
+    fn test_probe_missing_encoder() {
+        let err = probe("/nonexistent/cjxl").unwrap_err();
+        assert!(err.to_string().contains("libjxl"));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_encode_quality_search() {
+        use std::os::unix::fs::PermissionsExt;
+
+        // 桩 cjxl：参数为 输入 输出 -q Q，输出 Q×100 字节
+        let dir = std::env::temp_dir().join(format!("xtap_jxl_test_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let stub = dir.join("cjxl_stub.sh");
+        std::fs::write(
+            &stub,
+            "#!/bin/sh\n[ -s \"$1\" ] || exit 2\nhead -c $(( $4 * 100 )) /dev/zero > \"$2\"\n",
+        )
+        .unwrap();
+        std::fs::set_permissions(&stub, std::fs::Permissions::from_mode(0o755)).unwrap();
+        let program = stub.to_string_lossy().to_string();
+        let img = image::DynamicImage::new_rgba8(8, 8);
+        let timeout = Duration::from_secs(10);
+
+        let (data, q) = encode_with(&program, &img, 90, None, timeout).unwrap();
+        assert_eq!((data.len(), q), (9000, 90));
+        // 上限 5050 字节 → 不超限的最高质量 50
+        let (data, q) = encode_with(&program, &img, 90, Some(5050), timeout).unwrap();
+        assert_eq!((data.len(), q), (5000, 50));
+        // 全部超限 → 质量 1
+        let (_, q) = encode_with(&program, &img, 90, Some(10), timeout).unwrap();
+        assert_eq!(q, 1);
+
+        let err = encode_with("/nonexistent/cjxl", &img, 90, None, timeout).unwrap_err();
+        assert!(err.to_string().contains("libjxl"));
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
@@ -1,28 +1,44 @@
+pub mod animation;
+pub mod budget;
 pub mod cas;
+pub mod cmyk;
+pub mod contact_sheet;
+pub mod crop;
+pub mod dither;
+pub mod dng;
+pub mod external;
+pub mod grid;
+pub mod heif;
+pub mod icon;
+pub mod jxl;
+pub mod pad;
 pub mod perceptual;
+pub mod placeholder;
+pub mod platform_sim;
+pub mod print;
+pub mod raw_preview;
+pub mod slice;
+pub mod srcset;
+#[cfg(feature = "svg")]
+pub mod svg;
+pub mod tiff_pages;
+pub mod watermark;
 
 use anyhow::Result;
 use bytes::Bytes;
 use fast_image_resize as fr;
 use image::GenericImageView;
-use img_parts::jpeg::Jpeg;
+use img_parts::jpeg::{markers, Jpeg, JpegSegment};
 use memmap2::Mmap;
 use perceptual::{FocusMode, PerceptualMetrics, PerceptualOptions};
 use serde::{Deserialize, Serialize};
 use std::fs;
 use std::path::{Path, PathBuf};
-#[cfg(target_os = "macos")]
-use std::sync::atomic::{AtomicUsize, Ordering};
-
+use std::sync::Arc;
 // 摄影级优化导入
 use image::ImageBuffer;
 use image::Rgba;
 
-#[cfg(target_os = "macos")]
-static SIPS_CONCURRENCY: AtomicUsize = AtomicUsize::new(0);
-#[cfg(target_os = "macos")]
-const MAX_SIPS_CONCURRENCY: usize = 4;
-
 #[derive(Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
 pub enum ProcessMode {
     WeChat,
@@ -36,6 +52,8 @@ pub enum OutputFormat {
     KeepOriginal,
     /// v4.3.1：WebP 输出（网络分发/网页内嵌更省体积，支持透明通道）
     WebP,
+    /// v4.5：JPEG XL 输出（经 libjxl 的 cjxl，未安装时处理前按参数错误拒绝）
+    Jxl,
 }
 
 #[derive(Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
@@ -92,6 +110,97 @@ pub struct AppConfig {
     // v4.4.1：GUI 自定义导出目录记忆（用户通过文件选择器指定的输出目录，None=默认原文件旁）
     #[serde(default)]
     pub custom_output_dir: Option<String>,
+    // v4.5：智能裁剪到画幅（crop_aspect 为空时由平台预设 aspect 填充，如小红书 3:4 / IG 4:5）
+    #[serde(default)]
+    pub smart_crop: bool,
+    #[serde(default)]
+    pub crop_aspect: Option<String>,
+    // v4.5：补边到画布（与智能裁剪二选一，补边优先）。pad_canvas："1080x1350" 精确像素 / "1:1" 画幅比；
+    // pad_fill："blur"（默认）/ "#RRGGBB" / white / black。为空时由平台预设填充
+    #[serde(default)]
+    pub pad: bool,
+    #[serde(default)]
+    pub pad_canvas: Option<String>,
+    #[serde(default)]
+    pub pad_fill: Option<String>,
+    // v4.5：九宫格切图（"3x3" / "2x2" / "1x3"，None = 不切）；grid_gutter 切片间缝宽 px
+    #[serde(default)]
+    pub grid: Option<String>,
+    #[serde(default)]
+    pub grid_gutter: u32,
+    // v4.5：长图分页页高（输出像素，0 = 不分页）；宽缩到 custom_max_dim 后按此高度切页
+    #[serde(default)]
+    pub slice_height: u32,
+    // v4.5：水印叠加（None = 不加；PNG 标志或 TTF/OTF 文字，尺寸/边距相对输出短边）
+    #[serde(default)]
+    pub watermark: Option<watermark::WatermarkConfig>,
+    // v4.5：冲印尺寸（"6x4in@300" / "A4@200" / "15x10cm"，None = 不启用）；按纸张像素框缩放并写入密度
+    #[serde(default)]
+    pub print_size: Option<String>,
+    // v4.5：响应式图片组宽度档（"320,640,1280,2560"，None = 不启用）；srcset_formats："jpeg,webp"
+    // （缺省跟随 output_format）；srcset_sizes：<img sizes>（缺省 100vw）
+    #[serde(default)]
+    pub srcset: Option<String>,
+    #[serde(default)]
+    pub srcset_formats: Option<String>,
+    #[serde(default)]
+    pub srcset_sizes: Option<String>,
+    // v4.5：低清占位图（BlurHash + 主色 + 小缩略图）；placeholder_format：jpeg（默认）/ webp；
+    // placeholder_size：缩略图长边（0 = 默认 32，上限 32）
+    #[serde(default)]
+    pub placeholder: bool,
+    #[serde(default)]
+    pub placeholder_format: Option<String>,
+    #[serde(default)]
+    pub placeholder_size: u32,
+    // v4.5：图标组模式（favicon.ico + 各尺寸 PNG + site.webmanifest）；icon_padding / icon_radius
+    // 占边长比例，icon_background："#RRGGBB"（None = 透明）
+    #[serde(default)]
+    pub icon: bool,
+    #[serde(default)]
+    pub icon_padding: f32,
+    #[serde(default)]
+    pub icon_radius: f32,
+    #[serde(default)]
+    pub icon_background: Option<String>,
+    // v4.5：外部解码器（按扩展名交给命令行工具解码，优先于内置 RAW / sips 路径）；
+    // decoder_timeout_secs 为未单独配置超时的解码器及内置 sips/qlmanage 的超时，
+    // decoder_concurrency 为全进程外部工具并发上限（0 = 缺省 4）
+    #[serde(default)]
+    pub external_decoders: Vec<external::ExternalDecoder>,
+    #[serde(default = "default_decoder_timeout_secs")]
+    pub decoder_timeout_secs: u64,
+    #[serde(default)]
+    pub decoder_concurrency: usize,
+    // v4.5：JXL 输出时 JPEG 输入走无损重压缩（不解码像素、跳过缩放等处理；需 cjxl）
+    #[serde(default)]
+    pub jxl_lossless_jpeg: bool,
+    // v4.5：动图（GIF / APNG / 动态 WebP）逐帧处理；first_frame_only 时只取首帧按静态图处理。
+    // animation_format：auto / gif / webp；帧数与总时长上限 0 = 不限
+    #[serde(default)]
+    pub first_frame_only: bool,
+    #[serde(default = "default_animation_format")]
+    pub animation_format: String,
+    #[serde(default = "default_animation_max_frames")]
+    pub animation_max_frames: u32,
+    #[serde(default = "default_animation_max_duration_ms")]
+    pub animation_max_duration_ms: u32,
+    // v4.5：SVG 栅格化（feature svg；缺省关闭，SVG 仍按不支持格式透传）。svg_size：长边像素 "2048" 或 "300dpi"
+    // （None = 长边 2048）；svg_background：#RRGGBB / white / black / transparent（None = 透明）
+    #[serde(default)]
+    pub svg_rasterize: bool,
+    #[serde(default)]
+    pub svg_size: Option<String>,
+    #[serde(default)]
+    pub svg_background: Option<String>,
+    // v4.5：16 位 / 浮点源量化到 8 位的抖动（blue-noise / ordered / none）与浮点源色调映射（none / reinhard / aces）
+    #[serde(default = "default_dither")]
+    pub dither: String,
+    #[serde(default = "default_tone_map")]
+    pub tone_map: String,
+    // v4.5：多页 TIFF 取页方式（first / all / largest）
+    #[serde(default = "default_tiff_pages")]
+    pub tiff_pages: String,
 }
 
 fn default_usage_mode() -> String {
@@ -107,6 +216,34 @@ fn default_platform() -> String {
     "wechat".to_string()
 }
 
+fn default_decoder_timeout_secs() -> u64 {
+    external::DEFAULT_TIMEOUT_SECS
+}
+
+fn default_dither() -> String {
+    "blue-noise".to_string()
+}
+
+fn default_tone_map() -> String {
+    "none".to_string()
+}
+
+fn default_tiff_pages() -> String {
+    "first".to_string()
+}
+
+fn default_animation_format() -> String {
+    "auto".to_string()
+}
+
+fn default_animation_max_frames() -> u32 {
+    animation::DEFAULT_MAX_FRAMES
+}
+
+fn default_animation_max_duration_ms() -> u32 {
+    animation::DEFAULT_MAX_DURATION_MS
+}
+
 fn default_subsampling() -> String {
     "420".to_string()
 }
@@ -138,6 +275,40 @@ impl Default for AppConfig {
             output_suffix: None,
             cas_strength: 0.0,
             custom_output_dir: None,
+            smart_crop: false,
+            crop_aspect: None,
+            pad: false,
+            pad_canvas: None,
+            pad_fill: None,
+            grid: None,
+            grid_gutter: 0,
+            slice_height: 0,
+            watermark: None,
+            print_size: None,
+            srcset: None,
+            srcset_formats: None,
+            srcset_sizes: None,
+            placeholder: false,
+            placeholder_format: None,
+            placeholder_size: 0,
+            icon: false,
+            icon_padding: 0.0,
+            icon_radius: 0.0,
+            icon_background: None,
+            external_decoders: Vec::new(),
+            decoder_timeout_secs: external::DEFAULT_TIMEOUT_SECS,
+            decoder_concurrency: 0,
+            jxl_lossless_jpeg: false,
+            first_frame_only: false,
+            animation_format: default_animation_format(),
+            animation_max_frames: animation::DEFAULT_MAX_FRAMES,
+            animation_max_duration_ms: animation::DEFAULT_MAX_DURATION_MS,
+            svg_rasterize: false,
+            svg_size: None,
+            svg_background: None,
+            dither: default_dither(),
+            tone_map: default_tone_map(),
+            tiff_pages: default_tiff_pages(),
         }
     }
 }
@@ -169,8 +340,91 @@ pub struct ProcessConfig {
     pub output_suffix: Option<String>,
     // v4.4.0：CAS 锐化强度（0=关闭；感知模式已有 USM 时自动跳过防双重锐化）
     pub cas_strength: f32,
+    // v4.5：智能裁剪目标画幅 (宽, 高)；None = 不裁
+    pub crop_aspect: Option<(u32, u32)>,
+    // v4.5：补边到画布（优先于 crop_aspect）；None = 不补边
+    pub pad: Option<pad::PadSpec>,
+    // v4.5：九宫格切图（画幅适配之后切，每片独立缩放编码）；None = 不切
+    pub grid: Option<grid::GridSpec>,
+    // v4.5：长图分页页高（输出像素，与 grid 互斥、grid 优先）；None = 不分页
+    pub slice_height: Option<u32>,
+    // v4.5：批量总预算分到本文件的份额（KB），覆盖 target_kb / 感知 budget_kb；由调用方按文件注入
+    pub file_budget_kb: Option<u32>,
+    // v4.5：水印（缩放后、锐化与编码前叠加；素材只加载一次，多图 / 多线程共享）
+    pub watermark: Option<Arc<watermark::Watermark>>,
+    // v4.5：冲印尺寸（仅单图输出；覆盖 max_dim，补边精确画布也按纸张重新缩放）
+    pub print: Option<print::PrintSize>,
+    // v4.5：响应式图片组（一次解码、多档宽度 × 多格式；与切图/分页/冲印互斥）
+    pub srcset: Option<srcset::SrcsetSpec>,
+    // v4.5：低清占位图（由编码前的已缩放缓冲生成，多输出时取第一个输出）；None = 不生成
+    pub placeholder: Option<placeholder::PlaceholderSpec>,
+    // v4.5：图标组模式（取代压缩输出，不做画幅适配/切图）；None = 不启用
+    pub icon: Option<icon::IconSpec>,
+    // v4.5：外部解码器（空 = 不启用）与其缺省超时（秒，亦用于 macOS sips/qlmanage）
+    pub external_decoders: Vec<external::ExternalDecoder>,
+    pub decoder_timeout_secs: u64,
+    // v4.5：JXL 输出时 JPEG 输入无损重压缩（仅 output_format = Jxl 时生效）
+    pub jxl_lossless_jpeg: bool,
+    // v4.5：动图逐帧处理（与图标组/切图/分页/响应式/冲印互斥，这些模式只取首帧）；None = 只取首帧
+    pub animation: Option<animation::AnimationSpec>,
+    // v4.5：SVG 栅格化（渲染后走常规管线）；None = 不处理 SVG
+    #[cfg(feature = "svg")]
+    pub svg: Option<svg::SvgSpec>,
+    // v4.5：高位深源的抖动量化与色调映射（8 位源不受影响）
+    pub dither: dither::DitherSpec,
+    // v4.5：多页 TIFF 取页方式（图标组 / 切图 / 分页 / 响应式模式下 all 按 first 处理）
+    pub tiff_pages: tiff_pages::PageMode,
+}
+
+/// v4.5：单文件处理报告（输出路径 + 感知指标 + 几何变换），供 CLI/JSON 输出
+#[derive(Debug, Clone, Default)]
+pub struct ProcessReport {
+    pub output: PathBuf,
+    /// 感知指标（perceptual=None 时恒为 None）
+    pub metrics: Option<PerceptualMetrics>,
+    /// 智能裁剪窗口（原图坐标；未裁剪为 None）
+    pub crop: Option<crop::CropRect>,
+    /// 补边画布与原图放置区域（未补边为 None）
+    pub pad: Option<pad::PadPlacement>,
+    /// 九宫格切片 / 长图分页（按上传顺序；未切图为空，切图时 output 为第 1 片）
+    pub tiles: Vec<grid::TileOutput>,
+    /// 冲印尺寸换算结果（未启用冲印模式为 None）
+    pub print: Option<print::PrintFit>,
+    /// 响应式图片组（全部变体 + `<picture>` 片段；未启用为 None，启用时 output 为第一个变体）
+    pub srcset: Option<srcset::SrcsetManifest>,
+    /// 低清占位图（未启用为 None）
+    pub placeholder: Option<placeholder::Placeholder>,
+    /// 图标组（未启用为 None，启用时 output 为 favicon.ico）
+    pub icons: Option<icon::IconSet>,
+    /// 动图输出（未按动图处理为 None）
+    pub animation: Option<animation::AnimationInfo>,
+    /// 多页 TIFF 的总页数与实际输出的页（单页 TIFF 与其他格式为 None；多页输出时 output 为第 1 页）
+    pub tiff_pages: Option<tiff_pages::TiffPages>,
+}
+
+/// v4.5：打开的输入文件（续跑判定与处理共用）。多页 TIFF 的页表在打开时只扫描一次；
+/// 多预设扇出时由 [`Processor::decode_source`] 预先解码，多个处理器共享同一份源图
+pub struct Source {
+    /// 自愈后的输入路径
+    path: PathBuf,
+    /// 多页 TIFF 的文件映射与页表（非 TIFF、外部解码器接管或只有 1 页时为 None）
+    tiff: Option<(Mmap, Vec<tiff_pages::PageInfo>)>,
+    /// 已解码的源图：多页 TIFF 按页下标，其他格式下标恒为 0
+    decoded: Vec<(usize, image::DynamicImage)>,
 }
 
+impl Source {
+    fn decoded(&self, index: usize) -> Option<&image::DynamicImage> {
+        self.decoded
+            .iter()
+            .find(|(i, _)| *i == index)
+            .map(|(_, img)| img)
+    }
+}
+
+/// 扇出预解码多页 TIFF 时共享页面的内存上限（按 RGBA8 估算），超出的页由各处理器按需解码
+const SHARED_PAGES_BUDGET: u64 = 1 << 30;
+
 pub struct Processor {
     config: ProcessConfig,
 }
@@ -187,7 +441,110 @@ impl Processor {
     /// - `preserve_structure=true` 时，按 `structure_base` 复刻源目录相对层级到 output_dir（修 D2 拍平）。
     /// - `output_suffix` 可覆盖默认 `_wx/_hd/_da`；空串=无后缀。`keep_original_name` 优先级最高（无后缀）。
     /// - 支持 WebP 输出扩展名。
+    ///
+    /// v4.5：九宫格 / 长图分页模式返回第 1 片路径（`_1` / `_01`），续跑以首片是否存在判定；
+    /// 图标组模式返回 `{名}_icons/favicon.ico`；多帧动图按动图输出格式改扩展名为 `.gif` / `.webp`。
     pub fn expected_output_path(&self, input_path: &Path) -> PathBuf {
+        self.expected_output_path_for(&self.open_source(input_path))
+    }
+
+    /// 同 [`Processor::expected_output_path`]，复用已打开源的 TIFF 页表
+    pub fn expected_output_path_for(&self, source: &Source) -> PathBuf {
+        let input_path = source.path.as_path();
+        let base = self.single_output_path(input_path);
+        if self.config.icon.is_some() {
+            icon::icon_dir(&base).join("favicon.ico")
+        } else if self.config.grid.is_some() {
+            grid::tile_path(&base, 1)
+        } else if self.config.slice_height.is_some() {
+            slice::page_path(&base, 1)
+        } else if let Some(spec) = &self.config.srcset {
+            let (ext, _) = srcset::format_info(spec.formats[0]);
+            srcset::variant_path(&base, spec.widths[0], ext)
+        } else if let Some(ext) = self.animation_output_ext(input_path) {
+            base.with_extension(ext)
+        } else if self.tiff_page_count(source) > 1 {
+            tiff_pages::page_path(&base, 1)
+        } else {
+            base
+        }
+    }
+
+    /// v4.5：实际生效的取页方式——图标组 / 切图 / 分页 / 响应式本身即多输出，`all` 按 `first` 处理
+    fn tiff_page_mode(&self) -> tiff_pages::PageMode {
+        let multi_output = self.config.icon.is_some()
+            || self.config.grid.is_some()
+            || self.config.slice_height.is_some()
+            || self.config.srcset.is_some();
+        match self.config.tiff_pages {
+            tiff_pages::PageMode::All if multi_output => tiff_pages::PageMode::First,
+            mode => mode,
+        }
+    }
+
+    /// v4.5：打开输入文件：路径自愈，多页 TIFF 扫描页表（每个输入只扫一次）
+    pub fn open_source(&self, input_path: &Path) -> Source {
+        let path = path_self_healing(input_path);
+        let tiff = self.tiff_page_table(&path);
+        Source {
+            path,
+            tiff,
+            decoded: Vec::new(),
+        }
+    }
+
+    /// v4.5：多页 TIFF 的页表（非 TIFF、外部解码器接管或只有 1 页时为 None）
+    fn tiff_page_table(&self, healed: &Path) -> Option<(Mmap, Vec<tiff_pages::PageInfo>)> {
+        let ext = healed.extension().and_then(|e| e.to_str()).unwrap_or("");
+        if !tiff_pages::is_tiff_extension(ext)
+            || external::find(&self.config.external_decoders, ext).is_some()
+        {
+            return None;
+        }
+        let file = fs::File::open(healed).ok()?;
+        let data = unsafe { Mmap::map(&file) }.ok()?;
+        let pages = tiff_pages::scan(&data).ok()?;
+        (pages.len() > 1).then_some((data, pages))
+    }
+
+    /// v4.5：`all` 模式下多页 TIFF 将输出的页数（其他模式恒为 1）
+    fn tiff_page_count(&self, source: &Source) -> usize {
+        if self.tiff_page_mode() != tiff_pages::PageMode::All {
+            return 1;
+        }
+        source.tiff.as_ref().map_or(1, |(_, pages)| {
+            tiff_pages::select(pages, tiff_pages::PageMode::All).len()
+        })
+    }
+
+    /// v4.5：多帧动图的输出扩展名（gif / webp）。关闭动图处理、多输出 / 图标组 / 冲印模式、
+    /// 外部解码器接管的扩展名以及单帧文件均为 None（按静态图处理）
+    fn animation_output_ext(&self, input_path: &Path) -> Option<&'static str> {
+        let spec = self.config.animation.as_ref()?;
+        if self.config.icon.is_some()
+            || self.config.grid.is_some()
+            || self.config.slice_height.is_some()
+            || self.config.srcset.is_some()
+            || self.config.print.is_some()
+        {
+            return None;
+        }
+        let healed = path_self_healing(input_path);
+        let ext = healed.extension().and_then(|e| e.to_str()).unwrap_or("");
+        if !animation::is_animation_extension(ext)
+            || external::find(&self.config.external_decoders, ext).is_some()
+        {
+            return None;
+        }
+        let file = fs::File::open(&healed).ok()?;
+        let data = unsafe { Mmap::map(&file) }.ok()?;
+        let prefer_webp =
+            self.config.output_format == OutputFormat::WebP || ext.eq_ignore_ascii_case("webp");
+        animation::is_animated(&data).then(|| spec.format.extension(prefer_webp))
+    }
+
+    /// 单图输出路径（九宫格切片在此基础上追加 `_{序号}`）
+    fn single_output_path(&self, input_path: &Path) -> PathBuf {
         let healed_path = path_self_healing(input_path);
         let file_stem = healed_path
             .file_stem()
@@ -203,8 +560,9 @@ impl Processor {
         let output_ext = match self.config.output_format {
             OutputFormat::Jpeg => "jpg",
             OutputFormat::WebP => "webp",
+            OutputFormat::Jxl => "jxl",
             OutputFormat::KeepOriginal => match extension.as_str() {
-                "png" => "png",
+                "png" | "svg" => "png",
                 "webp" => "webp",
                 _ => "jpg",
             },
@@ -301,7 +659,80 @@ impl Processor {
         &self,
         input_path: &Path,
     ) -> Result<(PathBuf, Option<PerceptualMetrics>)> {
-        let healed_path = path_self_healing(input_path);
+        self.process_image_with_report(input_path)
+            .map(|r| (r.output, r.metrics))
+    }
+
+    /// v4.5：处理并返回完整报告（感知指标 + 裁剪窗口等）
+    pub fn process_image_with_report(&self, input_path: &Path) -> Result<ProcessReport> {
+        self.process_source(&self.open_source(input_path))
+    }
+
+    /// v4.5：处理已打开的源（续跑判定与处理共用一次页表扫描；多预设扇出时共享
+    /// [`Processor::decode_source`] 的解码结果）。macOS 上 RAW 仍由 sips 自行处理
+    pub fn process_source_with_report(&self, source: &Source) -> Result<ProcessReport> {
+        self.process_source(source)
+    }
+
+    /// v4.5：按本处理器的解码配置读入源图（多页 TIFF 按取页方式取第 1 个选中页），供批量预算估算与平台二压模拟对照
+    pub fn load_source(&self, input_path: &Path) -> Result<image::DynamicImage> {
+        let source = self.open_source(input_path);
+        match &source.tiff {
+            Some((data, pages)) => {
+                let index = tiff_pages::select(pages, self.tiff_page_mode())[0];
+                tiff_pages::decode_page(data, &pages[index])
+            }
+            None => self.decode_single(&source.path),
+        }
+    }
+
+    /// v4.5：预先解码源图（多预设扇出一次解码喂给多个处理器）。多页 TIFF 解码选中的页，
+    /// 合计超出 [`SHARED_PAGES_BUDGET`] 的页留给处理时按需解码
+    pub fn decode_source(&self, source: &mut Source) -> Result<()> {
+        let Some((data, pages)) = &source.tiff else {
+            let img = self.decode_single(&source.path)?;
+            source.decoded = vec![(0, img)];
+            return Ok(());
+        };
+        let mut used = 0u64;
+        for index in tiff_pages::select(pages, self.tiff_page_mode()) {
+            let page = &pages[index];
+            used += page.width as u64 * page.height as u64 * 4;
+            if used > SHARED_PAGES_BUDGET && !source.decoded.is_empty() {
+                break;
+            }
+            source
+                .decoded
+                .push((index, tiff_pages::decode_page(data, page)?));
+        }
+        Ok(())
+    }
+
+    /// 单图解码：外部解码器优先，其次 SVG 栅格化、内置解码
+    fn decode_single(&self, healed: &Path) -> Result<image::DynamicImage> {
+        let ext = healed.extension().and_then(|e| e.to_str()).unwrap_or("");
+        if let Some(decoder) = external::find(&self.config.external_decoders, ext) {
+            return Ok(decoder.decode(healed, self.decoder_timeout())?);
+        }
+        #[cfg(feature = "svg")]
+        if let Some(spec) = self.svg_spec_for(ext) {
+            return svg::rasterize(healed, spec);
+        }
+        load_image_safe(healed)
+    }
+
+    /// v4.5：开启栅格化且为 .svg 时返回渲染参数
+    #[cfg(feature = "svg")]
+    fn svg_spec_for(&self, ext: &str) -> Option<&svg::SvgSpec> {
+        self.config
+            .svg
+            .as_ref()
+            .filter(|_| svg::is_svg_extension(ext))
+    }
+
+    fn process_source(&self, source: &Source) -> Result<ProcessReport> {
+        let healed_path = source.path.as_path();
+        let decoded = source.decoded(0).filter(|_| source.tiff.is_none());
         let file_name_os = healed_path
             .file_name()
             .unwrap_or_default()
@@ -316,41 +747,73 @@ impl Processor {
             .unwrap_or("")
             .to_lowercase();
 
-        let raw_extensions = [
-            "dng", "cr2", "cr3", "nef", "arw", "orf", "raf", "rw2", "pef", "srw", "3fr",
-        ];
-        let is_raw = raw_extensions.contains(&extension.as_str());
-
         // 输出路径与 expected_output_path 严格同源；按最终输出路径的父目录建目录（兼容保结构子目录）
-        let output_path = self.expected_output_path(&healed_path);
+        let output_path = self.expected_output_path_for(source);
         if let Some(parent) = output_path.parent() {
             fs::create_dir_all(parent)?;
         }
 
-        let metrics: Option<PerceptualMetrics>;
+        let mut report = ProcessReport {
+            output: output_path.clone(),
+            ..Default::default()
+        };
+
+        // v4.5：JXL 输出 + JPEG 输入：无损重压缩，原 JPEG 位流（含元数据）整体转存
+        if self.config.output_format == OutputFormat::Jxl
+            && self.config.jxl_lossless_jpeg
+            && matches!(extension.as_str(), "jpg" | "jpeg")
+        {
+            jxl::transcode_jpeg(healed_path, &output_path, self.decoder_timeout())?;
+            return Ok(report);
+        }
+
+        // v4.5：外部解码器优先（任意平台），SVG 栅格化同走此路；扇出时调用方已按 `decode_source` 解码
+        #[cfg(feature = "svg")]
+        let rasterize_svg = self.svg_spec_for(&extension).is_some();
+        #[cfg(not(feature = "svg"))]
+        let rasterize_svg = false;
+        if external::find(&self.config.external_decoders, &extension).is_some() || rasterize_svg {
+            let owned;
+            let img = match decoded {
+                Some(img) => img,
+                None => {
+                    owned = self.decode_single(healed_path)?;
+                    &owned
+                }
+            };
+            self.process_normal(
+                healed_path,
+                &output_path,
+                &extension,
+                Some(img),
+                &mut report,
+            )?;
+            return Ok(report);
+        }
+
+        // v4.5：多页 TIFF 按打开时扫描的页表逐页处理（扇出时已预解码的页直接复用）
+        if source.tiff.is_some() {
+            self.process_tiff_pages(source, &output_path, &extension, &mut report)?;
+            return Ok(report);
+        }
 
         #[cfg(target_os = "macos")]
         {
             let file_stem = healed_path.file_stem().unwrap().to_string_lossy();
-            if is_raw {
-                self.process_raw(&healed_path, &output_path, &file_stem, &file_name_os)?;
-                metrics = None;
+            if raw_preview::is_raw_extension(&extension) {
+                self.process_raw(healed_path, &output_path, &file_stem, &file_name_os)?;
             } else {
-                metrics = self.process_normal(&healed_path, &output_path, &extension)?;
+                self.process_normal(healed_path, &output_path, &extension, decoded, &mut report)?;
             }
         }
 
+        // v4.5：其他平台的 RAW 取内嵌 JPEG 预览（load_image_safe 内完成），走常规管线并迁移 EXIF
         #[cfg(not(target_os = "macos"))]
-        if is_raw {
-            return Err(anyhow::anyhow!(
-                "RAW 格式 ({}) 仅在 macOS 系统上支持。请先在 Mac 上处理,或转成 JPG/PNG 后使用。",
-                extension
-            ));
-        } else {
-            metrics = self.process_normal(&healed_path, &output_path, &extension)?;
+        {
+            self.process_normal(healed_path, &output_path, &extension, decoded, &mut report)?;
         }
 
-        Ok((output_path, metrics))
+        Ok(report)
     }
 
     /// 返回当前感知压缩配置（None = 走 v4.1.0 旧路径），供 CLI/JSON 输出 metrics 元数据
@@ -360,12 +823,75 @@ impl Processor {
 
     /// 实际生效的体积预算（KB）：感知模式 budget_kb 覆盖 target_kb
     pub fn effective_target_kb(&self) -> u32 {
+        if let Some(kb) = self.config.file_budget_kb {
+            return kb;
+        }
         match &self.config.perceptual {
             Some(p) => p.budget_kb.unwrap_or(self.config.target_kb),
             None => self.config.target_kb,
         }
     }
 
+    /// v4.5：派生一个带批量预算份额的处理器（其余参数不变）
+    pub fn with_file_budget_kb(&self, kb: u32) -> Processor {
+        let mut config = self.config.clone();
+        config.file_budget_kb = Some(kb.max(1));
+        Processor::new(config)
+    }
+
+    /// v4.5：批量预算分配权重 = 输出像素（按 max_dim 长边缩放）× (底噪 + 内容复杂度)。
+    /// JPEG 体积大致正比于像素数与纹理量；底噪项保证纯色图也分到份额。
+    /// 内置解码的格式只读文件头取尺寸、按源文件每像素字节数估复杂度，不解码像素（处理时才解码）；
+    /// RAW / HEIC / SVG / 外部解码器接管的格式读不出可靠文件头，回落完整解码
+    pub fn budget_weight(&self, input_path: &Path) -> Result<f64> {
+        let source = self.open_source(input_path);
+        let (w, h, complexity) = match self.probe_dimensions(&source) {
+            Some((w, h)) => {
+                let bytes = fs::metadata(&source.path)?.len();
+                (w, h, file_complexity(&source.path, bytes, w, h))
+            }
+            None => {
+                let img = self.load_source(&source.path)?;
+                let (w, h) = img.dimensions();
+                (w, h, estimate_image_complexity(&img))
+            }
+        };
+        let max_dim = self.config.max_dim;
+        let scale = if max_dim > 0 && w.max(h) > max_dim {
+            max_dim as f64 / w.max(h) as f64
+        } else {
+            1.0
+        };
+        let pixels = w as f64 * h as f64 * scale * scale;
+        Ok(pixels * (0.15 + complexity as f64))
+    }
+
+    /// v4.5：只读文件头取源图尺寸（多页 TIFF 取选中的第 1 页）；需完整解码才知道尺寸的格式为 None
+    fn probe_dimensions(&self, source: &Source) -> Option<(u32, u32)> {
+        if let Some((_, pages)) = &source.tiff {
+            let page = &pages[tiff_pages::select(pages, self.tiff_page_mode())[0]];
+            return Some((page.width, page.height));
+        }
+        let ext = source
+            .path
+            .extension()
+            .and_then(|e| e.to_str())
+            .unwrap_or("");
+        // RAW 的 TIFF 头是内嵌缩略图尺寸，HEIC / SVG / 外部解码格式内置解码器不认
+        if raw_preview::is_raw_extension(ext)
+            || heif::is_heif_extension(ext)
+            || external::find(&self.config.external_decoders, ext).is_some()
+        {
+            return None;
+        }
+        image::ImageReader::open(&source.path)
+            .ok()?
+            .with_guessed_format()
+            .ok()?
+            .into_dimensions()
+            .ok()
+    }
+
     #[cfg(target_os = "macos")]
     fn process_raw(
         &self,
@@ -374,10 +900,9 @@ impl Processor {
         file_stem: &str,
         file_name: &str,
     ) -> Result<()> {
-        while SIPS_CONCURRENCY.load(Ordering::SeqCst) >= MAX_SIPS_CONCURRENCY {
-            std::thread::sleep(std::time::Duration::from_millis(100));
-        }
-        SIPS_CONCURRENCY.fetch_add(1, Ordering::SeqCst);
+        // v4.5：与外部解码器共用全进程信号量（阻塞等待许可，离开作用域归还）
+        let _permit = external::acquire();
+        let timeout = self.decoder_timeout();
 
         let input_path_abs =
             fs::canonicalize(input_path).unwrap_or_else(|_| input_path.to_path_buf());
@@ -416,24 +941,8 @@ impl Processor {
             }
             cmd.arg(&input_path_abs).arg("--out").arg(output_path);
 
-            let mut child = cmd.spawn()?;
-            let timeout = std::time::Duration::from_secs(30);
-            let start = std::time::Instant::now();
-
-            let status = loop {
-                match child.try_wait()? {
-                    Some(status) => break status,
-                    None => {
-                        if start.elapsed() > timeout {
-                            let _ = child.kill();
-                            break std::process::ExitStatus::default();
-                        }
-                        std::thread::sleep(std::time::Duration::from_millis(100));
-                    }
-                }
-            };
-
-            if status.success() && output_path.exists() {
+            let converted = external::run_with_timeout(&mut cmd, "sips", timeout);
+            if converted.is_ok() && output_path.exists() {
                 return Ok(());
             }
 
@@ -449,19 +958,7 @@ impl Processor {
             }
             ql_cmd.arg("-o").arg(&temp_dir).arg(&input_path_abs);
 
-            if let Ok(mut child) = ql_cmd.spawn() {
-                let start = std::time::Instant::now();
-                loop {
-                    if let Ok(Some(_)) = child.try_wait() {
-                        break;
-                    }
-                    if start.elapsed().as_secs() > 30 {
-                        let _ = child.kill();
-                        break;
-                    }
-                    std::thread::sleep(std::time::Duration::from_millis(100));
-                }
-            }
+            let _ = external::run_with_timeout(&mut ql_cmd, "qlmanage", timeout);
 
             let ql_file_1 = temp_dir.join(format!("{}.png", file_stem));
             let ql_file_2 = temp_dir.join(format!("{}.png", file_name));
@@ -489,21 +986,317 @@ impl Processor {
                 }
             }
 
-            Err(anyhow::anyhow!("该机型 RAW 暂不支持"))
+            // sips 的结构化错误（超时 / 退出码）优先于笼统提示
+            Err(match converted {
+                Err(e) => anyhow::anyhow!("该机型 RAW 暂不支持（{}）", e),
+                Ok(()) => anyhow::anyhow!("该机型 RAW 暂不支持"),
+            })
         })();
 
-        SIPS_CONCURRENCY.fetch_sub(1, Ordering::SeqCst);
         result?;
         Ok(())
     }
 
+    fn decoder_timeout(&self) -> std::time::Duration {
+        std::time::Duration::from_secs(self.config.decoder_timeout_secs.max(1))
+    }
+
+    /// v4.5：动图逐帧缩放、叠水印后编码为优化 GIF（质量决定调色板精度）或动态 WebP（逐帧无损）。
+    /// 补边 / 裁剪 / 锐化 / 体积预算只作用于静态图
+    fn process_animation(
+        &self,
+        input_path: &Path,
+        output_path: &Path,
+        ext: &str,
+        report: &mut ProcessReport,
+    ) -> Result<()> {
+        let spec = self.config.animation.unwrap_or_default();
+        let file = fs::File::open(input_path)?;
+        let data = unsafe { Mmap::map(&file)? };
+        let mut anim = animation::decode(&data, &spec, self.config.max_dim)?;
+        if let Some(mark) = &self.config.watermark {
+            for frame in &mut anim.frames {
+                mark.apply(&mut frame.image);
+            }
+        }
+        let (data, info) = match ext {
+            "webp" => animation::encode_webp(&anim)?,
+            _ => animation::encode_gif(&anim, self.config.quality)?,
+        };
+        fs::write(output_path, data)?;
+        report.animation = Some(info);
+        Ok(())
+    }
+
+    /// v4.5：多页 TIFF——选中的页各自走常规管线；多页输出时 report.output 为第 1 页，
+    /// 感知指标取最差的一页（同九宫格）
+    fn process_tiff_pages(
+        &self,
+        source: &Source,
+        output_path: &Path,
+        extension: &str,
+        report: &mut ProcessReport,
+    ) -> Result<()> {
+        let Some((data, pages)) = &source.tiff else {
+            return Ok(());
+        };
+        let input_path = source.path.as_path();
+        let mode = self.tiff_page_mode();
+        let selected = tiff_pages::select(pages, mode);
+        let base = self.single_output_path(input_path);
+        let mut outputs = Vec::with_capacity(selected.len());
+        for (n, &index) in selected.iter().enumerate() {
+            let page = &pages[index];
+            let owned;
+            let img = match source.decoded(index) {
+                Some(img) => img,
+                None => {
+                    owned = tiff_pages::decode_page(data, page)?;
+                    &owned
+                }
+            };
+            let out = match n {
+                0 => output_path.to_path_buf(),
+                _ => tiff_pages::page_path(&base, n as u32 + 1),
+            };
+            let mut part = ProcessReport::default();
+            let target = if n == 0 { &mut *report } else { &mut part };
+            self.process_normal(input_path, &out, extension, Some(img), target)?;
+            if let (Some(m), Some(cur)) = (part.metrics, report.metrics.as_ref()) {
+                if m.ssim_vs_source < cur.ssim_vs_source {
+                    report.metrics = Some(m);
+                }
+            }
+            outputs.push(tiff_pages::PageOutput {
+                page: index as u32 + 1,
+                width: page.width,
+                height: page.height,
+                bytes: fs::metadata(&out).map(|m| m.len()).unwrap_or(0),
+                output: out,
+            });
+        }
+        // first 模式下未输出的整页（不含缩小分辨率子图），供批处理告警
+        let skipped = match mode {
+            tiff_pages::PageMode::First => tiff_pages::select(pages, tiff_pages::PageMode::All)
+                .iter()
+                .filter(|i| !selected.contains(i))
+                .count() as u32,
+            _ => 0,
+        };
+        report.tiff_pages = Some(tiff_pages::TiffPages {
+            count: pages.len() as u32,
+            skipped,
+            outputs,
+        });
+        Ok(())
+    }
+
     fn process_normal(
         &self,
         input_path: &Path,
         output_path: &Path,
         extension: &str,
-    ) -> Result<Option<PerceptualMetrics>> {
-        let img = load_image_safe(input_path)?;
+        decoded: Option<&image::DynamicImage>,
+        report: &mut ProcessReport,
+    ) -> Result<()> {
+        // v4.5：多帧动图逐帧处理（输出扩展名已由 expected_output_path 定为 .gif / .webp）
+        if let Some(ext) = self.animation_output_ext(input_path) {
+            return self.process_animation(input_path, output_path, ext, report);
+        }
+        let mut img = match decoded {
+            Some(img) => img.clone(),
+            None => load_image_safe(input_path)?,
+        };
+        // v4.5：图标组——整组从源图直接生成，不走画幅适配与压缩编码
+        if let Some(spec) = &self.config.icon {
+            let dir = output_path.parent().unwrap_or(Path::new("."));
+            let name = input_path.file_stem().unwrap_or_default().to_string_lossy();
+            report.icons = Some(icon::write_icon_set(&img.to_rgba8(), spec, dir, &name)?);
+            return Ok(());
+        }
+        // v4.5：画幅适配（先适配后缩放）。补边与智能裁剪二选一、补边优先；
+        // 精确像素画布即最终输出尺寸，跳过后面的长边缩放
+        let mut fit = Fit::LongEdge;
+        if let Some(spec) = self.config.pad {
+            if let Some((canvas, placement)) = pad::pad_to_canvas(&img, &spec)? {
+                img = canvas;
+                report.pad = Some(placement);
+            }
+            if matches!(spec.canvas, pad::PadCanvas::Exact(..)) {
+                fit = Fit::Keep;
+            }
+        } else if let Some(aspect) = self.config.crop_aspect {
+            if let Some(r) = crop::smart_crop_rect(&img.to_rgba8(), aspect) {
+                img = img.crop_imm(r.x, r.y, r.width, r.height);
+                report.crop = Some(r);
+            }
+        }
+
+        // v4.5：九宫格——每片独立走缩放/锐化/编码，按上传顺序编号输出
+        if let Some(spec) = self.config.grid {
+            let (w, h) = img.dimensions();
+            let tiles = grid::grid_layout(w, h, spec);
+            if tiles.is_empty() {
+                return Err(anyhow::anyhow!(
+                    "九宫格切图失败：{}x{} 的图放不下 {}x{} 切片（缝宽 {}px）",
+                    w,
+                    h,
+                    spec.cols,
+                    spec.rows,
+                    spec.gutter
+                ));
+            }
+            let base = self.single_output_path(input_path);
+            let parts = tiles.len() as u32;
+            for t in tiles {
+                let tile_img = img.crop_imm(t.x, t.y, t.width, t.height);
+                let out = grid::tile_path(&base, t.index);
+                let enc = self.encode_image(tile_img, input_path, &out, extension, fit, parts)?;
+                record_part(report, enc, t, out);
+            }
+            report.output = report.tiles[0].output.clone();
+            return Ok(());
+        }
+
+        // v4.5：长图分页——宽缩到平台宽度，按换算后的页高找低细节行切开，每页独立卡预算
+        if let Some(page_height) = self.config.slice_height {
+            let (w, h) = img.dimensions();
+            let scale = if self.config.max_dim > 0 && w > self.config.max_dim {
+                self.config.max_dim as f64 / w as f64
+            } else {
+                1.0
+            };
+            let src_page = ((page_height as f64 / scale).round() as u32).max(1);
+            // 先按页数下限预检，超限图不做 RGBA 拷贝与切线搜索
+            let min_pages = slice::min_pages(h, src_page);
+            if min_pages > slice::MAX_PAGES {
+                return Err(anyhow::anyhow!(
+                    "长图分页失败：{}x{} 按页高 {}px 至少需 {} 页，超过上限 {}",
+                    w,
+                    h,
+                    page_height,
+                    min_pages,
+                    slice::MAX_PAGES
+                ));
+            }
+            let pages = slice::slice_pages(&img.to_rgba8(), src_page);
+            if pages.len() as u32 > slice::MAX_PAGES {
+                return Err(anyhow::anyhow!(
+                    "长图分页失败：{}x{} 按页高 {}px 需 {} 页，超过上限 {}",
+                    w,
+                    h,
+                    page_height,
+                    pages.len(),
+                    slice::MAX_PAGES
+                ));
+            }
+            let base = self.single_output_path(input_path);
+            let parts = pages.len() as u32;
+            for (i, p) in pages.iter().enumerate() {
+                let index = i as u32 + 1;
+                let page_img = img.crop_imm(0, p.y, w, p.height);
+                let out = slice::page_path(&base, index);
+                let enc =
+                    self.encode_image(page_img, input_path, &out, extension, Fit::Width, parts)?;
+                let part = grid::GridTile {
+                    index,
+                    row: i as u32,
+                    col: 0,
+                    x: 0,
+                    y: p.y,
+                    width: w,
+                    height: p.height,
+                };
+                record_part(report, enc, part, out);
+            }
+            report.output = report.tiles[0].output.clone();
+            return Ok(());
+        }
+
+        // v4.5：响应式图片组——同一张解码图按各档宽度 × 各格式分别缩放/编码
+        if let Some(spec) = &self.config.srcset {
+            let (w, h) = img.dimensions();
+            let widths = srcset::effective_widths(&spec.widths, w);
+            let base = self.single_output_path(input_path);
+            let parts = (widths.len() * spec.formats.len()) as u32;
+            let mut variants = Vec::with_capacity(parts as usize);
+            for &format in &spec.formats {
+                let (ext, mime) = srcset::format_info(format);
+                for &width in &widths {
+                    let variant = Processor::new(ProcessConfig {
+                        output_format: format,
+                        max_dim: width,
+                        srcset: None,
+                        ..self.config.clone()
+                    });
+                    let out = srcset::variant_path(&base, width, ext);
+                    let enc = variant.encode_image(
+                        img.clone(),
+                        input_path,
+                        &out,
+                        extension,
+                        Fit::Width,
+                        parts,
+                    )?;
+                    let bytes = enc.bytes;
+                    keep_part_outputs(report, enc);
+                    // 与 encode_image 的 Fit::Width 缩放同一算法
+                    let height = if w > width {
+                        (h as f32 * (width as f32 / w as f32)) as u32
+                    } else {
+                        h
+                    };
+                    variants.push(srcset::SrcsetVariant {
+                        width: width.min(w),
+                        height,
+                        format: mime.to_string(),
+                        output: out,
+                        bytes,
+                    });
+                }
+            }
+            let alt = input_path.file_stem().unwrap_or_default().to_string_lossy();
+            report.output = variants[0].output.clone();
+            report.srcset = Some(srcset::SrcsetManifest {
+                html: srcset::picture_html(&variants, &spec.sizes, &alt),
+                sizes: spec.sizes.clone(),
+                manifest: srcset::sidecar_path(&base, "json"),
+                placeholder: report.placeholder.clone(),
+                variants,
+            });
+            return Ok(());
+        }
+
+        // v4.5：冲印尺寸——按纸张像素框缩放（不放大），源图密度随报告输出
+        if let Some(size) = self.config.print {
+            let (w, h) = img.dimensions();
+            let mut pf = size.fit(w, h);
+            pf.source_dpi = fs::read(input_path).ok().and_then(|d| print::read_dpi(&d));
+            fit = Fit::Print {
+                width: pf.width_px,
+                height: pf.height_px,
+                dpi: pf.dpi,
+            };
+            report.print = Some(pf);
+        }
+
+        let enc = self.encode_image(img, input_path, output_path, extension, fit, 1)?;
+        report.metrics = enc.metrics;
+        report.placeholder = enc.placeholder;
+        Ok(())
+    }
+
+    /// 缩放 → 降噪/锐化 → 编码 → 写盘（单图、九宫格切片、长图分页共用）。
+    /// 缩放方式见 [`Fit`]；`parts` 为同一输入的输出片数（批量预算按片均分）。返回感知指标、输出字节数与占位图
+    fn encode_image(
+        &self,
+        img: image::DynamicImage,
+        input_path: &Path,
+        output_path: &Path,
+        extension: &str,
+        fit: Fit,
+        parts: u32,
+    ) -> Result<Encoded> {
         let (width, height) = img.dimensions();
         // v4.3.1：提前判定源图是否含 alpha 通道（PNG/WebP 透明图）。JPEG 输出不支持透明，
         // 含 alpha 时把透明区域填白底（修 D4：透明 PNG→JPEG 丢透明变黑）。
@@ -512,42 +1305,67 @@ impl Processor {
         let mut pm = PerceptualMetrics::default();
 
         // max_dim == 0 表示「不缩放，保持原图尺寸」（高清存档/投稿场景）
-        let scale = if self.config.max_dim > 0
-            && (width > self.config.max_dim || height > self.config.max_dim)
-        {
-            let ratio_w = self.config.max_dim as f32 / width as f32;
-            let ratio_h = self.config.max_dim as f32 / height as f32;
-            ratio_w.min(ratio_h)
-        } else {
-            1.0
+        let max_dim = self.config.max_dim;
+        let scale = match fit {
+            Fit::LongEdge if max_dim > 0 && (width > max_dim || height > max_dim) => {
+                let ratio_w = max_dim as f32 / width as f32;
+                let ratio_h = max_dim as f32 / height as f32;
+                ratio_w.min(ratio_h)
+            }
+            Fit::Width if max_dim > 0 && width > max_dim => max_dim as f32 / width as f32,
+            Fit::Print { width: w, .. } => w as f32 / width as f32,
+            _ => 1.0,
         };
 
-        let new_width = (width as f32 * scale) as u32;
-        let new_height = (height as f32 * scale) as u32;
-
-        let img_rgba = img.to_rgba8();
+        let (new_width, new_height) = match fit {
+            Fit::Print {
+                width: w,
+                height: h,
+                ..
+            } => (w, h),
+            _ => (
+                (width as f32 * scale) as u32,
+                (height as f32 * scale) as u32,
+            ),
+        };
 
         let t_down = std::time::Instant::now();
-        let src_image = fr::images::Image::from_vec_u8(
-            width,
-            height,
-            img_rgba.into_raw(),
-            fr::PixelType::U8x4,
-        )?;
+        // v4.5：16 位 / 浮点源按原精度缩放，再抖动量化到 8 位（直接 to_rgba8 会让渐变出台阶）
+        let rgba = if dither::is_high_depth(&img) {
+            dither::resize_to_rgba8(&img, new_width, new_height, &self.config.dither)?
+        } else {
+            let src_image = fr::images::Image::from_vec_u8(
+                width,
+                height,
+                img.to_rgba8().into_raw(),
+                fr::PixelType::U8x4,
+            )?;
 
-        let mut dst_image = fr::images::Image::new(new_width, new_height, fr::PixelType::U8x4);
+            let mut dst_image = fr::images::Image::new(new_width, new_height, fr::PixelType::U8x4);
 
-        let mut resizer = fr::Resizer::new();
-        resizer.resize(&src_image, &mut dst_image, None)?;
+            let mut resizer = fr::Resizer::new();
+            resizer.resize(&src_image, &mut dst_image, None)?;
 
-        let rgba_buf = dst_image.buffer();
+            image::ImageBuffer::from_raw(new_width, new_height, dst_image.into_vec())
+                .ok_or_else(|| anyhow::anyhow!("Failed to create image buffer"))?
+        };
         pm.downscale_ms = t_down.elapsed().as_millis() as u64;
 
         // 转换为 DynamicImage 以便后续处理
-        let mut dynamic_img = image::DynamicImage::ImageRgba8(
-            image::ImageBuffer::from_raw(new_width, new_height, rgba_buf.to_vec())
-                .ok_or_else(|| anyhow::anyhow!("Failed to create image buffer"))?,
-        );
+        let mut dynamic_img = image::DynamicImage::ImageRgba8(rgba);
+
+        // v4.5：水印叠加（按输出尺寸排版；先于参考帧，感知指标不把水印算作失真）
+        if let Some(mark) = &self.config.watermark {
+            let mut rgba = dynamic_img.to_rgba8();
+            mark.apply(&mut rgba);
+            dynamic_img = image::DynamicImage::ImageRgba8(rgba);
+        }
+
+        // v4.5：低清占位图取已缩放（含水印）的输出缓冲，锐化前即可（占位图本就极糊）
+        let placeholder = match &self.config.placeholder {
+            Some(spec) => Some(placeholder::generate(&dynamic_img.to_rgba8(), spec)?),
+            None => None,
+        };
 
         // 感知指标参考帧：降采样后、锐化编码前的灰度图
         let reference_gray = perceptual.map(|_| perceptual::to_gray(&dynamic_img));
@@ -556,7 +1374,9 @@ impl Processor {
             // 降噪在「目标分辨率」上做（§3 防御性修正：超大图全分辨率降噪会卡死/爆内存，
             // 统一放降采样后；高质量重采样已抑制高频噪点，目标分辨率降噪足以满足分享/存档画质）
             // JPG 输入禁降噪（放大块效应）；超过 4000px 的超大图直接跳过降噪（防冻结）
-            let is_jpeg_input = matches!(extension, "jpg" | "jpeg");
+            // RAW 内嵌预览同为 JPEG
+            let is_jpeg_input =
+                matches!(extension, "jpg" | "jpeg") || raw_preview::is_raw_extension(extension);
             if p.denoise_strength > 0 && !is_jpeg_input && new_width.max(new_height) <= 4000 {
                 let t = std::time::Instant::now();
                 dynamic_img = image::DynamicImage::ImageRgba8(perceptual::bilateral_denoise(
@@ -607,8 +1427,9 @@ impl Processor {
         let output_ext = match self.config.output_format {
             OutputFormat::Jpeg => "jpg",
             OutputFormat::WebP => "webp",
+            OutputFormat::Jxl => "jxl",
             OutputFormat::KeepOriginal => match extension {
-                "png" => "png",
+                "png" | "svg" => "png",
                 "webp" => "webp",
                 _ => "jpg",
             },
@@ -631,6 +1452,22 @@ impl Processor {
                 dynamic_img.write_to(&mut cursor, image::ImageFormat::WebP)?;
                 result_data = cursor.into_inner();
             }
+            "jxl" => {
+                let t_encode = std::time::Instant::now();
+                let target_kb = match self.config.file_budget_kb {
+                    Some(kb) => (kb / parts.max(1)).max(1),
+                    None => self.effective_target_kb(),
+                };
+                let limit = (target_kb > 0).then_some(target_kb as usize * 1024);
+                let quality = match perceptual {
+                    Some(p) => self.config.quality.min(p.quality_ceil),
+                    None => self.config.quality,
+                };
+                let (data, q) = jxl::encode(&dynamic_img, quality, limit, self.decoder_timeout())?;
+                pm.final_quality = q;
+                pm.encode_ms = t_encode.elapsed().as_millis() as u64;
+                result_data = data;
+            }
             _ => {
                 let t_encode = std::time::Instant::now();
                 // 转换为 RGB 格式；JPEG 不支持透明 → 含 alpha 的源图先按白底合成（修 D4 透明丢失）
@@ -641,9 +1478,10 @@ impl Processor {
                 };
 
                 // 感知模式：budget_kb 覆盖 target_kb；质量上限 quality_ceil
-                let effective_target_kb = match perceptual {
-                    Some(p) => p.budget_kb.unwrap_or(self.config.target_kb),
-                    None => self.config.target_kb,
+                // v4.5：批量预算份额优先（多片输出按片均分）
+                let effective_target_kb = match self.config.file_budget_kb {
+                    Some(kb) => (kb / parts.max(1)).max(1),
+                    None => self.effective_target_kb(),
                 };
                 let limit_bytes = if effective_target_kb > 0 {
                     Some((effective_target_kb as usize) * 1024)
@@ -745,14 +1583,79 @@ impl Processor {
                     }
                 }
 
-                if extension == "jpg" || extension == "jpeg" {
+                if extension == "jpg"
+                    || extension == "jpeg"
+                    || raw_preview::is_raw_extension(extension)
+                    || heif::is_heif_extension(extension)
+                {
                     result_data = preserve_exif_safe(input_path, &result_data);
                 }
             }
         }
 
+        // v4.5：冲印模式写入输出密度（在保留原图元数据之后，覆盖原图的旧密度）
+        if let Fit::Print { dpi, .. } = fit {
+            match output_ext {
+                "png" => print::set_png_dpi(&mut result_data, dpi),
+                "webp" | "jxl" => {}
+                _ => print::set_jpeg_dpi(&mut result_data, dpi),
+            }
+        }
+
+        let bytes = result_data.len() as u64;
         fs::write(output_path, result_data)?;
-        Ok(perceptual.map(|_| pm))
+        Ok(Encoded {
+            metrics: perceptual.map(|_| pm),
+            bytes,
+            placeholder,
+        })
+    }
+}
+
+/// v4.5：编码前的缩放方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Fit {
+    /// 长边缩到 max_dim（默认）
+    LongEdge,
+    /// 宽缩到 max_dim（长图分页：短边对齐平台宽度）
+    Width,
+    /// 不缩放（精确像素画布即最终尺寸）
+    Keep,
+    /// 缩放到冲印像素尺寸并写入密度
+    Print { width: u32, height: u32, dpi: u32 },
+}
+
+/// 单次编码结果
+struct Encoded {
+    metrics: Option<PerceptualMetrics>,
+    bytes: u64,
+    placeholder: Option<placeholder::Placeholder>,
+}
+
+/// 记录一个切片/分页的输出；感知指标取最差的一片（SSIM 最低），保守反映整组画质
+fn record_part(report: &mut ProcessReport, enc: Encoded, tile: grid::GridTile, output: PathBuf) {
+    let bytes = enc.bytes;
+    keep_part_outputs(report, enc);
+    report.tiles.push(grid::TileOutput {
+        tile,
+        output,
+        bytes,
+    });
+}
+
+/// 多输出时感知指标只保留最差的一份（SSIM 最低），占位图取第一个输出
+fn keep_part_outputs(report: &mut ProcessReport, enc: Encoded) {
+    if report.placeholder.is_none() {
+        report.placeholder = enc.placeholder;
+    }
+    if let Some(m) = enc.metrics {
+        let worse = match &report.metrics {
+            Some(cur) => m.ssim_vs_source < cur.ssim_vs_source,
+            None => true,
+        };
+        if worse {
+            report.metrics = Some(m);
+        }
     }
 }
 
@@ -771,22 +1674,32 @@ fn preserve_exif_safe(input_path: &Path, result_data: &[u8]) -> Vec<u8> {
         return result_data.to_vec();
     }
 
-    let input_jpeg = match Jpeg::from_bytes(Bytes::copy_from_slice(&input_mmap)) {
-        Ok(jpeg) => jpeg,
-        Err(_) => return result_data.to_vec(),
+    // v4.5：RAW 输入无 APP 段可拷，按 RAW 的 IFD 重建 EXIF 写入 APP1；
+    // HEIC 取容器内的 Exif / XMP 条目与 colr ICC
+    let extension = input_path.extension().and_then(|e| e.to_str());
+    let is_raw = extension.is_some_and(raw_preview::is_raw_extension);
+    let meta_segments = if extension.is_some_and(heif::is_heif_extension) {
+        match heif::parse(&input_mmap) {
+            Ok(info) => heif::jpeg_segments(&info),
+            Err(_) => return result_data.to_vec(),
+        }
+    } else if is_raw {
+        match raw_preview::raw_exif(&input_mmap) {
+            Some(tiff) => vec![JpegSegment::new_with_contents(
+                markers::APP1,
+                Bytes::from([b"Exif\0\0".as_slice(), &tiff].concat()),
+            )],
+            None => return result_data.to_vec(),
+        }
+    } else {
+        match jpeg_meta_segments(&input_mmap) {
+            // v4.5：CMYK / YCCK 源已转 RGB，原 CMYK ICC 与 APP14 不再适用，改标 sRGB
+            Some(segments) if cmyk::probe(&input_mmap).is_some() => cmyk::rgb_segments(segments),
+            Some(segments) => segments,
+            None => return result_data.to_vec(),
+        }
     };
 
-    // P4（升级 v4.3.0）：保留所有 APP 段（marker 0xE0–0xEF），零元数据损失。
-    // 覆盖 EXIF(APP1)、XMP(APP1)、ICC 色彩配置(APP2 ICC_PROFILE)、MPF(APP2)、
-    // Adobe IRB(APP13)/Adobe(APP14) 等。v4.2.0 只取首个 APP1(0xE1) 会丢 XMP 与
-    // 宽色域 ICC 配置（ICC 在 APP2），违背"保证原图色彩"。
-    // 注意：DQT/DHT/SOF/SOS 等不在 0xE0–0xEF 范围，不会被重复插入。
-    let meta_segments: Vec<_> = input_jpeg
-        .segments()
-        .iter()
-        .filter(|s| (0xE0..=0xEF).contains(&s.marker()))
-        .cloned()
-        .collect();
     if meta_segments.is_empty() {
         return result_data.to_vec();
     }
@@ -801,14 +1714,29 @@ fn preserve_exif_safe(input_path: &Path, result_data: &[u8]) -> Vec<u8> {
 
     let mut output_jpeg = output_jpeg;
     // 从位置 1 起依次插入每个 APP 段（保持原顺序：EXIF / XMP / ICC 均保留）
-    let mut insert_pos = 1;
-    for seg in &meta_segments {
+    for (insert_pos, seg) in (1..).zip(meta_segments.iter()) {
         output_jpeg.segments_mut().insert(insert_pos, seg.clone());
-        insert_pos += 1;
     }
     output_jpeg.encoder().bytes().to_vec()
 }
 
+fn jpeg_meta_segments(input: &[u8]) -> Option<Vec<JpegSegment>> {
+    let input_jpeg = Jpeg::from_bytes(Bytes::copy_from_slice(input)).ok()?;
+    // P4（升级 v4.3.0）：保留所有 APP 段（marker 0xE0–0xEF），零元数据损失。
+    // 覆盖 EXIF(APP1)、XMP(APP1)、ICC 色彩配置(APP2 ICC_PROFILE)、MPF(APP2)、
+    // Adobe IRB(APP13)/Adobe(APP14) 等。v4.2.0 只取首个 APP1(0xE1) 会丢 XMP 与
+    // 宽色域 ICC 配置（ICC 在 APP2），违背"保证原图色彩"。
+    // 注意：DQT/DHT/SOF/SOS 等不在 0xE0–0xEF 范围，不会被重复插入。
+    Some(
+        input_jpeg
+            .segments()
+            .iter()
+            .filter(|s| (0xE0..=0xEF).contains(&s.marker()))
+            .cloned()
+            .collect(),
+    )
+}
+
 /// 用途驱动的三方统一配置构造（GUI / CLI / AI JSON 同一套语义）
 ///
 /// - social：平台预设值（已写入 `custom_*`）→ 社交分享最优体积/画质
@@ -868,6 +1796,115 @@ pub fn app_config_to_process_config(
         sharpening_radius: config.sharpening_radius,
         sharpening_amount: config.sharpening_amount,
         perceptual: None,
+        // v4.5：智能裁剪（画幅由调用方或平台预设填入 crop_aspect）
+        crop_aspect: if config.smart_crop {
+            config.crop_aspect.as_deref().and_then(crop::parse_aspect)
+        } else {
+            None
+        },
+        pad: if config.pad {
+            config
+                .pad_canvas
+                .as_deref()
+                .and_then(pad::PadCanvas::parse)
+                .map(|canvas| pad::PadSpec {
+                    canvas,
+                    fill: config
+                        .pad_fill
+                        .as_deref()
+                        .and_then(pad::PadFill::parse)
+                        .unwrap_or(pad::PadFill::Blur),
+                })
+        } else {
+            None
+        },
+        // v4.5：九宫格切图
+        grid: config
+            .grid
+            .as_deref()
+            .and_then(|g| grid::GridSpec::parse(g, config.grid_gutter)),
+        // v4.5：长图分页
+        slice_height: (config.slice_height > 0).then_some(config.slice_height),
+        file_budget_kb: None,
+        // v4.5：水印素材在此加载一次；调用方（CLI / AI-JSON / GUI）已用 WatermarkConfig::load 预检并报错
+        watermark: config
+            .watermark
+            .as_ref()
+            .and_then(|wm| wm.load().ok())
+            .map(Arc::new),
+        // v4.5：冲印尺寸
+        print: config
+            .print_size
+            .as_deref()
+            .and_then(print::PrintSize::parse),
+        // v4.5：响应式图片组（格式缺省跟随导出格式）
+        srcset: config
+            .srcset
+            .as_deref()
+            .and_then(srcset::parse_widths)
+            .map(|widths| srcset::SrcsetSpec {
+                widths,
+                formats: config
+                    .srcset_formats
+                    .as_deref()
+                    .and_then(srcset::parse_formats)
+                    .unwrap_or_else(|| match config.output_format {
+                        OutputFormat::WebP => vec![OutputFormat::WebP],
+                        _ => vec![OutputFormat::Jpeg],
+                    }),
+                sizes: config
+                    .srcset_sizes
+                    .clone()
+                    .unwrap_or_else(|| srcset::DEFAULT_SIZES.to_string()),
+            }),
+        // v4.5：图标组
+        icon: config.icon.then(|| icon::IconSpec {
+            padding: config.icon_padding,
+            radius: config.icon_radius,
+            background: config
+                .icon_background
+                .as_deref()
+                .and_then(pad::parse_hex_color),
+        }),
+        // v4.5：低清占位图（格式名无法识别时按 JPEG）
+        placeholder: config.placeholder.then(|| {
+            placeholder::PlaceholderSpec::parse(
+                config.placeholder_format.as_deref(),
+                config.placeholder_size,
+            )
+            .unwrap_or_default()
+        }),
+        external_decoders: config.external_decoders.clone(),
+        decoder_timeout_secs: config.decoder_timeout_secs,
+        jxl_lossless_jpeg: config.jxl_lossless_jpeg,
+        // v4.5：动图（格式名无法识别时按 auto）
+        animation: (!config.first_frame_only).then(|| animation::AnimationSpec {
+            format: animation::AnimationFormat::parse(&config.animation_format)
+                .unwrap_or(animation::AnimationFormat::Auto),
+            max_frames: config.animation_max_frames,
+            max_duration_ms: config.animation_max_duration_ms,
+        }),
+        // v4.5：SVG 栅格化（尺寸 / 底色无法解析时按缺省）
+        #[cfg(feature = "svg")]
+        svg: config.svg_rasterize.then(|| svg::SvgSpec {
+            size: config
+                .svg_size
+                .as_deref()
+                .and_then(svg::SvgSize::parse)
+                .unwrap_or(svg::SvgSize::LongEdge(svg::DEFAULT_LONG_EDGE)),
+            background: config
+                .svg_background
+                .as_deref()
+                .and_then(svg::parse_background)
+                .flatten(),
+        }),
+        // v4.5：高位深抖动（名称无法识别时按缺省）
+        dither: dither::DitherSpec {
+            mode: dither::DitherMode::parse(&config.dither)
+                .unwrap_or(dither::DitherMode::BlueNoise),
+            tone_map: dither::ToneMap::parse(&config.tone_map).unwrap_or(dither::ToneMap::Clip),
+        },
+        tiff_pages: tiff_pages::PageMode::parse(&config.tiff_pages).unwrap_or_default(),
     }
 }
 
@@ -980,6 +2017,23 @@ fn should_apply_sharpening(image: &image::DynamicImage, _max_dim: u32) -> bool {
     true
 }
 
+/// v4.5：按源文件每像素字节数估算复杂度（0.0-1.0，不解码像素）：纹理越多压缩后越大。
+/// 有损格式按 4 bpp、其他按 16 bpp 记满分
+fn file_complexity(path: &Path, bytes: u64, width: u32, height: u32) -> f32 {
+    let pixels = (width as f64 * height as f64).max(1.0);
+    let bpp = bytes as f64 * 8.0 / pixels;
+    let full = match path
+        .extension()
+        .and_then(|e| e.to_str())
+        .map(|e| e.to_ascii_lowercase())
+        .as_deref()
+    {
+        Some("jpg" | "jpeg" | "webp") => 4.0,
+        _ => 16.0,
+    };
+    (bpp / full).clamp(0.0, 1.0) as f32
+}
+
 /// 估算图像复杂度（0.0-1.0）
 /// 简单场景（天空、纯色背景）返回低值，复杂场景（纹理、细节）返回高值
 fn estimate_image_complexity(image: &image::DynamicImage) -> f32 {
@@ -1436,21 +2490,6 @@ fn manage_color_space(
     image
 }
 
-/// 检测图片是否为 300dpi 或更高分辨率
-#[allow(dead_code)]
-fn is_high_resolution_dpi(input_path: &Path) -> bool {
-    // 尝试读取 EXIF 中的 DPI 信息
-    if let Ok(file) = fs::File::open(input_path) {
-        if let Ok(mmap) = unsafe { Mmap::map(&file) } {
-            // 简单判断：如果文件很大且尺寸不大，可能是高 DPI
-            if mmap.len() > 5 * 1024 * 1024 {
-                return true;
-            }
-        }
-    }
-    false
-}
-
 pub fn path_self_healing(input_path: &Path) -> PathBuf {
     let path_str = input_path.to_string_lossy();
 
@@ -1504,6 +2543,51 @@ pub fn path_self_healing(input_path: &Path) -> PathBuf {
 fn load_image_safe(input_path: &Path) -> Result<image::DynamicImage> {
     // 注意: 调用者(process_image)已做 path_self_healing,此处直接用输入路径,
     // 避免重复 stat 调用
+    // v4.5：HEIC/HEIF 走 libheif（feature heic），未启用时返回启用提示
+    if input_path
+        .extension()
+        .and_then(|e| e.to_str())
+        .is_some_and(heif::is_heif_extension)
+    {
+        let file = fs::File::open(input_path)?;
+        let mmap = unsafe { Mmap::map(&file)? };
+        return heif::decode(&mmap);
+    }
+    // v4.5：JPEG XL 走 jxl-oxide（feature jxl），未启用时返回启用提示
+    if input_path
+        .extension()
+        .and_then(|e| e.to_str())
+        .is_some_and(jxl::is_jxl_extension)
+    {
+        let file = fs::File::open(input_path)?;
+        let mmap = unsafe { Mmap::map(&file)? };
+        return jxl::decode(&mmap);
+    }
+    // v4.5：RAW 取内嵌 JPEG 预览（须先于通用解码：DNG 的 IFD0 常是可被 TIFF 解码器读出的小缩略图）
+    if input_path
+        .extension()
+        .and_then(|e| e.to_str())
+        .is_some_and(raw_preview::is_raw_extension)
+    {
+        let file = fs::File::open(input_path)?;
+        let mmap = unsafe { Mmap::map(&file)? };
+        // DNG 先走全分辨率显影；不支持的编码（有损 DNG / 特殊 CFA 等）回落内嵌预览
+        let is_dng = input_path
+            .extension()
+            .is_some_and(|e| e.eq_ignore_ascii_case("dng"));
+        let developed = if is_dng {
+            match dng::decode(&mmap) {
+                Ok(img) => return Ok(img),
+                Err(e) => Some(e),
+            }
+        } else {
+            None
+        };
+        return raw_preview::decode_preview(&mmap).map_err(|e| match developed {
+            Some(dng_err) => anyhow::anyhow!("{}（DNG 显影失败: {}）", e, dng_err),
+            None => e,
+        });
+    }
     if let Ok(img) = load_image_mmap(input_path) {
         return Ok(img);
     }
@@ -1517,12 +2601,19 @@ fn load_image_mmap(input_path: &Path) -> Result<image::DynamicImage> {
 
     if file_size > 200 * 1024 * 1024 {
         let mmap = unsafe { Mmap::map(&file)? };
+        if let Some(info) = cmyk::probe(&mmap) {
+            return cmyk::decode(&mmap, &info);
+        }
         return image::load_from_memory(&mmap)
             .map_err(|e| anyhow::anyhow!("Failed to decode with mmap: {}", e));
     }
 
     drop(file);
     let bytes = fs::read(input_path)?;
+    // v4.5：CMYK / YCCK JPEG 按 Adobe 标志与内嵌 ICC 转 RGB（通用解码会偏色）
+    if let Some(info) = cmyk::probe(&bytes) {
+        return cmyk::decode(&bytes, &info);
+    }
     image::load_from_memory(&bytes)
         .map_err(|e| anyhow::anyhow!("Failed to decode from memory: {}", e))
 }
@@ -1664,6 +2755,24 @@ mod tests {
             structure_base: None,
             output_suffix: None,
             cas_strength: 0.0,
+            crop_aspect: None,
+            pad: None,
+            grid: None,
+            slice_height: None,
+            file_budget_kb: None,
+            watermark: None,
+            print: None,
+            srcset: None,
+            placeholder: None,
+            icon: None,
+            external_decoders: Vec::new(),
+            decoder_timeout_secs: external::DEFAULT_TIMEOUT_SECS,
+            jxl_lossless_jpeg: false,
+            animation: None,
+            #[cfg(feature = "svg")]
+            svg: None,
+            dither: dither::DitherSpec::default(),
+            tiff_pages: tiff_pages::PageMode::First,
         };
 
         let wx = Processor::new(ProcessConfig {
@@ -1704,6 +2813,93 @@ mod tests {
         assert!(ow.to_string_lossy().ends_with("b.jpg"));
     }
 
+    #[test]
+    fn test_budget_weight_reads_header_only() {
+        // 权重只读文件头：细节多的图权重更高；截掉像素数据的 PNG 照样给出权重
+        let dir = tmp_dir("budget_weight");
+        let flat = dir.join("flat.jpg");
+        let busy = dir.join("busy.jpg");
+        image::RgbImage::from_pixel(320, 240, image::Rgb([90, 120, 150]))
+            .save(&flat)
+            .unwrap();
+        image::RgbImage::from_fn(320, 240, |x, y| {
+            let v = ((x * 7919 + y * 104_729) % 251) as u8;
+            image::Rgb([v, v.wrapping_mul(3), 255 - v])
+        })
+        .save(&busy)
+        .unwrap();
+        let p = Processor::new(app_config_to_process_config(&AppConfig::default(), None));
+        assert!(p.budget_weight(&busy).unwrap() > p.budget_weight(&flat).unwrap());
+
+        let png = dir.join("busy.png");
+        image::open(&busy).unwrap().save(&png).unwrap();
+        let data = fs::read(&png).unwrap();
+        let cut = dir.join("cut.png");
+        fs::write(&cut, &data[..data.len() / 3]).unwrap();
+        assert!(image::open(&cut).is_err());
+        assert!(p.budget_weight(&cut).unwrap() > 0.0);
+        fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn test_watermark_loaded_once_and_shared() {
+        // 水印素材在配置转换时加载一次，克隆出的处理配置共享同一份
+        let dir = tmp_dir("watermark_once");
+        let logo = dir.join("logo.png");
+        make_test_image(&logo, 40, 20);
+        let cfg = AppConfig {
+            watermark: Some(watermark::WatermarkConfig {
+                image: Some(logo.to_string_lossy().into_owned()),
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+        let pc = app_config_to_process_config(&cfg, None);
+        let copy = pc.clone();
+        assert!(Arc::ptr_eq(
+            pc.watermark.as_ref().unwrap(),
+            copy.watermark.as_ref().unwrap()
+        ));
+        fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn test_multipage_tiff_source_shared() {
+        // 多页 TIFF：打开时扫描一次页表，预解码的页供两个处理器共用
+        use tiff::encoder::{colortype, TiffEncoder};
+        let dir = tmp_dir("tiff_source");
+        let src = dir.join("scan.tif");
+        let mut enc = TiffEncoder::new(fs::File::create(&src).unwrap()).unwrap();
+        enc.write_image::<colortype::Gray8>(120, 80, &vec![90; 120 * 80])
+            .unwrap();
+        enc.write_image::<colortype::Gray8>(60, 140, &vec![30; 60 * 140])
+            .unwrap();
+        drop(enc);
+
+        let processor_for = |suffix: &str| {
+            let mut pc = app_config_to_process_config(&AppConfig::default(), Some(dir.join("out")));
+            pc.output_suffix = Some(suffix.to_string());
+            pc.tiff_pages = tiff_pages::PageMode::All;
+            Processor::new(pc)
+        };
+        let (a, b) = (processor_for("a"), processor_for("b"));
+        let mut source = a.open_source(&src);
+        assert_eq!(source.tiff.as_ref().map(|(_, p)| p.len()), Some(2));
+        a.decode_source(&mut source).unwrap();
+        assert_eq!(source.decoded.len(), 2);
+        let first = a.expected_output_path_for(&source);
+        assert!(first.to_string_lossy().ends_with("scan_a_p01.jpg"));
+
+        for p in [&a, &b] {
+            let report = p.process_source_with_report(&source).unwrap();
+            let pages = report.tiff_pages.unwrap();
+            assert_eq!(pages.outputs.len(), 2);
+            let last = image::open(&pages.outputs[1].output).unwrap();
+            assert_eq!((last.width(), last.height()), (60, 140));
+        }
+        fs::remove_dir_all(&dir).ok();
+    }
+
     #[test]
     fn test_path_self_healing_case_insensitive() {
         let dir = tmp_dir("heal");
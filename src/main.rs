@@ -1,4 +1,6 @@
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
+// capabilities 的 json_input_schema 是单个 serde_json::json! 大对象，字段多了会触发宏展开递归上限
+#![recursion_limit = "256"]
 
 //! 入口路由（三层解耦）：
 //! - src/lib.rs    压缩内核（EXIF 保留 / 智能锐化 / 色彩空间 / 感知压缩 / 原子写）
@@ -42,6 +44,77 @@ fn main() -> Result<()> {
         // 并行并发数：AI 可经 --max-workers 限流（在全局池首次使用前生效）
         runner::apply_max_workers(cli.max_workers);
 
+        // 平台预设注册表：内置 + 系统/用户 presets.toml + --presets（须在任何预设查询之前）
+        if let Err(e) = cli::init_presets(cli.presets.as_deref()) {
+            eprintln!("❌ {}", e);
+            std::process::exit(2);
+        }
+        if let Some(ref plats) = cli.platform {
+            // v4.5：逗号分隔多个预设 = 多预设扇出，逐个校验
+            for p in cli::split_platforms(plats) {
+                if !cli::presets().contains_key(&p) {
+                    eprintln!(
+                        "❌ 未知平台预设 '{}'。可用：{}",
+                        p,
+                        cli::presets()
+                            .keys()
+                            .cloned()
+                            .collect::<Vec<_>>()
+                            .join(" / ")
+                    );
+                    std::process::exit(2);
+                }
+            }
+        }
+        let conflicts = cli.fanout_conflicts();
+        if !conflicts.is_empty() {
+            eprintln!("❌ 多预设扇出不能与 {} 同用", conflicts.join(" / "));
+            std::process::exit(2);
+        }
+
+        // v4.5：JXL 输出依赖 cjxl，缺失时在处理前按参数错误退出
+        if cli.output_format == cli::CliOutputFormat::Jxl {
+            if let Err(e) = xtap_compress::jxl::check_encoder() {
+                eprintln!("❌ {}", e);
+                std::process::exit(2);
+            }
+        }
+
+        // 水印素材/参数预检：坏路径、坏字体在处理前就按参数错误退出
+        if let Some(wm) = cli.watermark_config() {
+            if let Err(e) = wm.load() {
+                eprintln!("❌ {}", e);
+                std::process::exit(2);
+            }
+        }
+
+        // 图标组参数预检
+        if cli.icon {
+            let spec = xtap_compress::icon::IconSpec {
+                padding: cli.icon_padding,
+                radius: cli.icon_radius,
+                background: None,
+            };
+            if let Err(e) = spec.validate() {
+                eprintln!("❌ {}", e);
+                std::process::exit(2);
+            }
+            if let Some(ref bg) = cli.icon_background {
+                if xtap_compress::pad::parse_hex_color(bg).is_none() {
+                    eprintln!("❌ 图标底色须为 #RRGGBB: '{}'", bg);
+                    std::process::exit(2);
+                }
+            }
+        }
+
+        // 联系表参数/字体预检
+        if let Some(cs) = cli.contact_sheet_config() {
+            if let Err(e) = cs.validate() {
+                eprintln!("❌ {}", e);
+                std::process::exit(2);
+            }
+        }
+
         // --capabilities 优先：输出版本支持的全部参数 schema
         if cli.capabilities {
             let caps = build_capabilities();
@@ -0,0 +1,288 @@
+//! v4.5：补边到固定画布（letterbox），智能裁剪之外的另一种画幅适配
+//!
+//! 商品图 / 作品图不能裁掉任何内容，只能把整图放进固定画布（如 IG 竖版 1080×1350 或 1:1）：
+//! - 画布两种写法：`1080x1350` = 精确像素画布（输出即该尺寸，不再按长边缩放）；
+//!   `1:1` / `4:5` = 画幅比（画布按原图补足短缺的一轴，之后照常走长边缩放）；
+//! - 底色两种：纯色（`#RRGGBB` / white / black），或模糊放大的原图铺底（复用 lib.rs 的高斯核）。
+//!
+//! 模糊底在 1/8 画布尺寸上做（高斯核是朴素二维卷积，全尺寸会很慢），放大回画布后观感无差。
+//! 画布按原图精度合成（16 位 / 浮点源不在这里量化，留给缩放后的抖动），画布像素设上限。
+
+use crate::crop::parse_aspect;
+use anyhow::{anyhow, Result};
+use image::{imageops, DynamicImage, ImageBuffer, Pixel, Rgba, RgbaImage};
+use serde::{Deserialize, Serialize};
+
+/// 模糊底的工作分辨率（相对画布的缩小倍数）
+const BLUR_DOWNSCALE: u32 = 8;
+/// 工作分辨率上的高斯 sigma
+const BLUR_SIGMA: f32 = 4.0;
+/// 画布像素上限（1 亿）：极端画幅比（如长图补成 1:50）会让画布远大于原图
+pub const MAX_CANVAS_PIXELS: u64 = 100_000_000;
+
+/// 画布规格
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PadCanvas {
+    /// 精确像素画布 (宽, 高)
+    Exact(u32, u32),
+    /// 画幅比 (宽, 高)
+    Aspect(u32, u32),
+}
+
+impl PadCanvas {
+    /// "1080x1350" → Exact（不超过 [`MAX_CANVAS_PIXELS`]）；"1:1" / "4/5" → Aspect
+    pub fn parse(s: &str) -> Option<Self> {
+        let t = s.trim();
+        if t.contains(['x', 'X']) {
+            let (w, h) = t.split_once(['x', 'X'])?;
+            let w: u32 = w.trim().parse().ok()?;
+            let h: u32 = h.trim().parse().ok()?;
+            (w > 0 && h > 0 && w as u64 * h as u64 <= MAX_CANVAS_PIXELS)
+                .then_some(PadCanvas::Exact(w, h))
+        } else {
+            parse_aspect(t).map(|(w, h)| PadCanvas::Aspect(w, h))
+        }
+    }
+}
+
+/// 底色
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PadFill {
+    /// 模糊放大的原图铺底
+    Blur,
+    Solid([u8; 3]),
+}
+
+impl PadFill {
+    /// "blur" / "white" / "black" / "#RRGGBB"（# 可省）
+    pub fn parse(s: &str) -> Option<Self> {
+        let t = s.trim().to_lowercase();
+        match t.as_str() {
+            "blur" => return Some(PadFill::Blur),
+            "white" => return Some(PadFill::Solid([255, 255, 255])),
+            "black" => return Some(PadFill::Solid([0, 0, 0])),
+            _ => {}
+        }
+        parse_hex_color(&t).map(PadFill::Solid)
+    }
+}
+
+/// "#RRGGBB" / "RRGGBB" → [r, g, b]（水印文字颜色共用）
+pub fn parse_hex_color(s: &str) -> Option<[u8; 3]> {
+    let hex = s.trim().trim_start_matches('#');
+    if hex.len() != 6 {
+        return None;
+    }
+    let v = u32::from_str_radix(hex, 16).ok()?;
+    Some([(v >> 16) as u8, (v >> 8) as u8, v as u8])
+}
+
+/// 补边配置
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PadSpec {
+    pub canvas: PadCanvas,
+    pub fill: PadFill,
+}
+
+/// 补边结果：画布尺寸 + 原图在画布中的放置区域（JSON 输出用，坐标为补边后、长边缩放前）
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PadPlacement {
+    pub canvas_width: u32,
+    pub canvas_height: u32,
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
+/// 计算画布尺寸与放置区域。精确画布只缩不放（小图居中留更宽的边），画幅比画布按原图补足。
+/// 已符合画幅（误差 <1%）且非精确画布时返回 None；画布超过 [`MAX_CANVAS_PIXELS`] 时报错。
+pub fn pad_layout(w: u32, h: u32, canvas: PadCanvas) -> Result<Option<PadPlacement>> {
+    if w == 0 || h == 0 {
+        return Ok(None);
+    }
+    let (cw, ch, iw, ih) = match canvas {
+        PadCanvas::Exact(cw, ch) => {
+            let scale = (cw as f64 / w as f64).min(ch as f64 / h as f64).min(1.0);
+            let iw = ((w as f64 * scale).round() as u32).clamp(1, cw);
+            let ih = ((h as f64 * scale).round() as u32).clamp(1, ch);
+            (cw, ch, iw, ih)
+        }
+        PadCanvas::Aspect(aw, ah) => {
+            let target = aw as f64 / ah as f64;
+            let current = w as f64 / h as f64;
+            if (current / target - 1.0).abs() < 0.01 {
+                return Ok(None);
+            }
+            if current > target {
+                (w, (w as f64 / target).round() as u32, w, h)
+            } else {
+                ((h as f64 * target).round() as u32, h, w, h)
+            }
+        }
+    };
+    if cw as u64 * ch as u64 > MAX_CANVAS_PIXELS {
+        return Err(anyhow!(
+            "补边画布 {}x{} 超出上限（{} 像素），请换用更接近原图的画幅比",
+            cw,
+            ch,
+            MAX_CANVAS_PIXELS
+        ));
+    }
+    Ok(Some(PadPlacement {
+        canvas_width: cw,
+        canvas_height: ch,
+        x: (cw - iw) / 2,
+        y: (ch - ih) / 2,
+        width: iw,
+        height: ih,
+    }))
+}
+
+/// 执行补边：返回画布图与放置区域；无需补边时返回 None。
+/// 8 位源输出 RGBA8，16 位源输出 RGBA16，浮点源输出 RGBA32F。
+pub fn pad_to_canvas(
+    img: &DynamicImage,
+    spec: &PadSpec,
+) -> Result<Option<(DynamicImage, PadPlacement)>> {
+    let Some(p) = pad_layout(img.width(), img.height(), spec.canvas)? else {
+        return Ok(None);
+    };
+    // 底图按 8 位生成（纯色为 1 像素，模糊底为 1/8 画布的小图），再转成原图精度
+    let backdrop = DynamicImage::ImageRgba8(match spec.fill {
+        PadFill::Solid([r, g, b]) => RgbaImage::from_pixel(1, 1, Rgba([r, g, b, 255])),
+        PadFill::Blur => blurred_backdrop(&img.to_rgba8(), p.canvas_width, p.canvas_height),
+    });
+    let canvas = match img {
+        DynamicImage::ImageRgb32F(_) | DynamicImage::ImageRgba32F(_) => {
+            DynamicImage::ImageRgba32F(place(&img.to_rgba32f(), &backdrop.to_rgba32f(), &p))
+        }
+        _ if crate::dither::is_high_depth(img) => {
+            DynamicImage::ImageRgba16(place(&img.to_rgba16(), &backdrop.to_rgba16(), &p))
+        }
+        _ => DynamicImage::ImageRgba8(place(&img.to_rgba8(), &backdrop.to_rgba8(), &p)),
+    };
+    Ok(Some((canvas, p)))
+}
+
+/// 底图铺满画布（1 像素底直接填色，小图放大），原图缩到放置区域后叠上
+fn place<P: Pixel + 'static>(
+    img: &ImageBuffer<P, Vec<P::Subpixel>>,
+    backdrop: &ImageBuffer<P, Vec<P::Subpixel>>,
+    p: &PadPlacement,
+) -> ImageBuffer<P, Vec<P::Subpixel>> {
+    let (cw, ch) = (p.canvas_width, p.canvas_height);
+    let mut canvas = if backdrop.dimensions() == (1, 1) {
+        ImageBuffer::from_pixel(cw, ch, *backdrop.get_pixel(0, 0))
+    } else {
+        imageops::resize(backdrop, cw, ch, imageops::FilterType::Triangle)
+    };
+    let (x, y) = (p.x as i64, p.y as i64);
+    if (p.width, p.height) == img.dimensions() {
+        imageops::overlay(&mut canvas, img, x, y);
+    } else {
+        let placed = imageops::resize(img, p.width, p.height, imageops::FilterType::Lanczos3);
+        imageops::overlay(&mut canvas, &placed, x, y);
+    }
+    canvas
+}
+
+/// 模糊铺底：原图等比放大到覆盖画布（居中裁），在 1/8 分辨率上高斯模糊，返回该小图（由调用方放大）
+fn blurred_backdrop(img: &RgbaImage, cw: u32, ch: u32) -> RgbaImage {
+    let (w, h) = img.dimensions();
+    let sw = (cw / BLUR_DOWNSCALE).max(1);
+    let sh = (ch / BLUR_DOWNSCALE).max(1);
+    let cover = (sw as f64 / w as f64).max(sh as f64 / h as f64);
+    let rw = ((w as f64 * cover).ceil() as u32).max(sw);
+    let rh = ((h as f64 * cover).ceil() as u32).max(sh);
+    let mut small = imageops::resize(img, rw, rh, imageops::FilterType::Triangle);
+    let small = imageops::crop(&mut small, (rw - sw) / 2, (rh - sh) / 2, sw, sh).to_image();
+    let mut blurred = crate::gaussian_blur(&small, BLUR_SIGMA);
+    // 铺底不透明（透明 PNG 的透明区在模糊底上按不透明处理）
+    for px in blurred.pixels_mut() {
+        px[3] = 255;
+    }
+    blurred
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn red_image(w: u32, h: u32) -> DynamicImage {
+        DynamicImage::ImageRgba8(RgbaImage::from_pixel(w, h, Rgba([200, 30, 30, 255])))
+    }
+
+    #[test]
+    fn test_parse_canvas_and_fill() {
+        assert_eq!(
+            PadCanvas::parse("1080x1350"),
+            Some(PadCanvas::Exact(1080, 1350))
+        );
+        assert_eq!(PadCanvas::parse("1:1"), Some(PadCanvas::Aspect(1, 1)));
+        assert_eq!(PadCanvas::parse("0x10"), None);
+        assert_eq!(PadFill::parse("blur"), Some(PadFill::Blur));
+        assert_eq!(
+            PadFill::parse("#FF8000"),
+            Some(PadFill::Solid([255, 128, 0]))
+        );
+        assert_eq!(PadFill::parse("#12"), None);
+    }
+
+    #[test]
+    fn test_exact_canvas_solid_fill_letterboxes_centered() {
+        let spec = PadSpec {
+            canvas: PadCanvas::Exact(1080, 1350),
+            fill: PadFill::Solid([255, 255, 255]),
+        };
+        let (out, p) = pad_to_canvas(&red_image(2000, 1000), &spec)
+            .unwrap()
+            .unwrap();
+        let out = out.as_rgba8().unwrap();
+        assert_eq!(out.dimensions(), (1080, 1350));
+        assert_eq!((p.width, p.height), (1080, 540));
+        assert_eq!((p.x, p.y), (0, 405));
+        assert_eq!(out.get_pixel(540, 10), &Rgba([255, 255, 255, 255]));
+        assert_eq!(out.get_pixel(540, 675), &Rgba([200, 30, 30, 255]));
+    }
+
+    #[test]
+    fn test_aspect_canvas_blur_fill_uses_image_colors() {
+        let spec = PadSpec {
+            canvas: PadCanvas::Aspect(1, 1),
+            fill: PadFill::Blur,
+        };
+        let (out, p) = pad_to_canvas(&red_image(400, 200), &spec).unwrap().unwrap();
+        let out = out.as_rgba8().unwrap();
+        assert_eq!(out.dimensions(), (400, 400));
+        assert_eq!((p.x, p.y, p.width, p.height), (0, 100, 400, 200));
+        // 纯红原图的模糊底仍是红色
+        let bg = out.get_pixel(200, 20);
+        assert!(bg[0] > 150 && bg[1] < 80, "模糊底应取自原图颜色: {:?}", bg);
+        // 已是目标画幅：无需补边
+        assert!(pad_to_canvas(&red_image(300, 300), &spec)
+            .unwrap()
+            .is_none());
+    }
+
+    #[test]
+    fn test_high_depth_kept_and_canvas_capped() {
+        // 16 位源按 16 位合成：8 位量化不了的细微灰阶原样保留
+        let src = image::ImageBuffer::from_pixel(300, 100, Rgba([1000u16, 1001, 1002, 65535]));
+        let spec = PadSpec {
+            canvas: PadCanvas::Aspect(1, 1),
+            fill: PadFill::Solid([0, 0, 0]),
+        };
+        let (out, _) = pad_to_canvas(&DynamicImage::ImageRgba16(src), &spec)
+            .unwrap()
+            .unwrap();
+        let out = out.as_rgba16().expect("应保持 16 位");
+        assert_eq!(out.get_pixel(150, 150).0, [1000, 1001, 1002, 65535]);
+        assert_eq!(out.get_pixel(150, 10).0, [0, 0, 0, 65535]);
+
+        // 4000px 宽补成 1:50 → 4000x200000，超出上限报错
+        let err = pad_layout(4000, 3000, PadCanvas::Aspect(1, 50)).unwrap_err();
+        assert!(err.to_string().contains("上限"), "{}", err);
+        assert_eq!(PadCanvas::parse("20000x20000"), None);
+    }
+}
@@ -0,0 +1,288 @@
+//! v4.5：低清占位图（LQIP：BlurHash + 主色 + ≤32px base64 缩略图）
+//!
+//! - 由编码前的「已缩放」缓冲生成（与输出同画幅、同水印），CMS 入库无需再解码输出图；
+//! - BlurHash 按官方算法在 ≤64px 的小图上计算（线性光加权余弦基，默认 4x3 分量）；
+//! - 主色取 4bit/通道量化直方图的最大桶（桶内像素取均值），忽略近全透明像素；
+//! - 缩略图长边 ≤32px，JPEG（含透明时按白底合成，与正式输出一致）或 WebP（保留透明），
+//!   以 `data:` URI 给出，可直接写进 `<img src>` / CSS `background-image`。
+
+use crate::OutputFormat;
+use anyhow::Result;
+use image::imageops::FilterType;
+use image::RgbaImage;
+use serde::{Deserialize, Serialize};
+
+/// 缩略图长边上限（px）
+pub const MAX_THUMB_EDGE: u32 = 32;
+/// BlurHash 分量（横 x 纵）
+const BLURHASH_COMPONENTS: (u32, u32) = (4, 3);
+/// BlurHash / 主色的计算尺寸上限（px）：占位图本就极糊，更大无收益
+const ANALYSIS_EDGE: u32 = 64;
+
+/// 占位图规格
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PlaceholderSpec {
+    /// 缩略图格式（仅 Jpeg / WebP）
+    pub format: OutputFormat,
+    /// 缩略图长边（1..=32）
+    pub size: u32,
+}
+
+impl Default for PlaceholderSpec {
+    fn default() -> Self {
+        Self {
+            format: OutputFormat::Jpeg,
+            size: MAX_THUMB_EDGE,
+        }
+    }
+}
+
+impl PlaceholderSpec {
+    /// 格式名（jpeg/jpg/webp，缺省 jpeg）+ 长边（0 = 缺省 32，超出按 32 截断）
+    pub fn parse(format: Option<&str>, size: u32) -> Option<Self> {
+        let format = match format.map(|f| f.trim().to_ascii_lowercase()).as_deref() {
+            None | Some("jpeg") | Some("jpg") => OutputFormat::Jpeg,
+            Some("webp") => OutputFormat::WebP,
+            Some(_) => return None,
+        };
+        let size = if size == 0 {
+            MAX_THUMB_EDGE
+        } else {
+            size.min(MAX_THUMB_EDGE)
+        };
+        Some(Self { format, size })
+    }
+}
+
+/// 占位信息（随 FileResult / 清单输出）
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Placeholder {
+    pub blurhash: String,
+    /// 主色 `#rrggbb`
+    pub dominant_color: String,
+    /// 缩略图 `data:image/jpeg;base64,…` / `data:image/webp;base64,…`
+    pub thumbnail: String,
+    pub thumbnail_width: u32,
+    pub thumbnail_height: u32,
+}
+
+/// 由已缩放的输出缓冲生成占位信息
+pub fn generate(img: &RgbaImage, spec: &PlaceholderSpec) -> Result<Placeholder> {
+    let small = shrink(img, ANALYSIS_EDGE);
+    let (cx, cy) = BLURHASH_COMPONENTS;
+    let thumb = shrink(img, spec.size.clamp(1, MAX_THUMB_EDGE));
+    let (tw, th) = thumb.dimensions();
+    let (mime, data) = match spec.format {
+        OutputFormat::WebP => {
+            let mut cursor = std::io::Cursor::new(Vec::new());
+            image::DynamicImage::ImageRgba8(thumb)
+                .write_to(&mut cursor, image::ImageFormat::WebP)?;
+            ("image/webp", cursor.into_inner())
+        }
+        _ => {
+            let rgb: Vec<u8> = flatten_white(&thumb).into_iter().flatten().collect();
+            let data = mozjpeg_rs::Encoder::default()
+                .quality(70)
+                .encode_rgb(&rgb, tw, th)
+                .map_err(|e| anyhow::anyhow!("占位缩略图编码失败: {}", e))?;
+            ("image/jpeg", data)
+        }
+    };
+    Ok(Placeholder {
+        blurhash: blurhash(&small, cx, cy),
+        dominant_color: dominant_color(&small),
+        thumbnail: format!("data:{};base64,{}", mime, base64(&data)),
+        thumbnail_width: tw,
+        thumbnail_height: th,
+    })
+}
+
+/// 长边缩到 `edge`（不放大）
+fn shrink(img: &RgbaImage, edge: u32) -> RgbaImage {
+    let (w, h) = img.dimensions();
+    if w.max(h) <= edge {
+        return img.clone();
+    }
+    let scale = edge as f64 / w.max(h) as f64;
+    let nw = ((w as f64 * scale).round() as u32).max(1);
+    let nh = ((h as f64 * scale).round() as u32).max(1);
+    image::imageops::resize(img, nw, nh, FilterType::Triangle)
+}
+
+/// 按白底合成透明像素（与 JPEG 正式输出的透明处理一致）
+fn flatten_white(img: &RgbaImage) -> Vec<[u8; 3]> {
+    img.pixels()
+        .map(|p| {
+            let a = p[3] as u32;
+            let mix = |c: u8| ((c as u32 * a + 255 * (255 - a) + 127) / 255) as u8;
+            [mix(p[0]), mix(p[1]), mix(p[2])]
+        })
+        .collect()
+}
+
+/// BlurHash 编码（https://github.com/woltapp/blurhash 算法）
+pub fn blurhash(img: &RgbaImage, cx: u32, cy: u32) -> String {
+    let (w, h) = img.dimensions();
+    let linear: Vec<[f64; 3]> = flatten_white(img)
+        .into_iter()
+        .map(|p| p.map(srgb_to_linear))
+        .collect();
+    let mut factors = Vec::with_capacity((cx * cy) as usize);
+    for j in 0..cy {
+        for i in 0..cx {
+            let norm = if i == 0 && j == 0 { 1.0 } else { 2.0 };
+            let mut f = [0.0f64; 3];
+            for y in 0..h {
+                let by = (std::f64::consts::PI * j as f64 * y as f64 / h as f64).cos();
+                for x in 0..w {
+                    let basis = by * (std::f64::consts::PI * i as f64 * x as f64 / w as f64).cos();
+                    let p = linear[(y * w + x) as usize];
+                    for c in 0..3 {
+                        f[c] += basis * p[c];
+                    }
+                }
+            }
+            let scale = norm / (w as f64 * h as f64);
+            factors.push(f.map(|v| v * scale));
+        }
+    }
+
+    let mut hash = String::new();
+    push_base83(&mut hash, (cx - 1) + (cy - 1) * 9, 1);
+    let ac = &factors[1..];
+    let max_value = if ac.is_empty() {
+        push_base83(&mut hash, 0, 1);
+        1.0
+    } else {
+        let actual = ac
+            .iter()
+            .flat_map(|f| f.iter())
+            .fold(0.0f64, |m, v| m.max(v.abs()));
+        let quantised = ((actual * 166.0 - 0.5).floor()).clamp(0.0, 82.0) as u32;
+        push_base83(&mut hash, quantised, 1);
+        (quantised + 1) as f64 / 166.0
+    };
+    let dc = factors[0];
+    let dc_value =
+        (linear_to_srgb(dc[0]) << 16) + (linear_to_srgb(dc[1]) << 8) + linear_to_srgb(dc[2]);
+    push_base83(&mut hash, dc_value, 4);
+    for f in ac {
+        let q = |v: f64| {
+            let s = v / max_value;
+            (s.signum() * s.abs().sqrt() * 9.0 + 9.5)
+                .floor()
+                .clamp(0.0, 18.0) as u32
+        };
+        push_base83(&mut hash, q(f[0]) * 19 * 19 + q(f[1]) * 19 + q(f[2]), 2);
+    }
+    hash
+}
+
+/// 主色：4bit/通道量化直方图的最大桶，桶内像素取均值；近全透明像素不计（全透明图按白色）
+pub fn dominant_color(img: &RgbaImage) -> String {
+    let mut buckets = vec![(0u32, [0u64; 3]); 4096];
+    for p in img.pixels().filter(|p| p[3] >= 16) {
+        let key = ((p[0] as usize >> 4) << 8) | ((p[1] as usize >> 4) << 4) | (p[2] as usize >> 4);
+        let b = &mut buckets[key];
+        b.0 += 1;
+        for c in 0..3 {
+            b.1[c] += p[c] as u64;
+        }
+    }
+    let (count, sum) = buckets
+        .iter()
+        .max_by_key(|b| b.0)
+        .copied()
+        .unwrap_or_default();
+    if count == 0 {
+        return "#ffffff".to_string();
+    }
+    let avg = sum.map(|s| (s / count as u64) as u8);
+    format!("#{:02x}{:02x}{:02x}", avg[0], avg[1], avg[2])
+}
+
+fn srgb_to_linear(c: u8) -> f64 {
+    let v = c as f64 / 255.0;
+    if v <= 0.04045 {
+        v / 12.92
+    } else {
+        ((v + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+fn linear_to_srgb(v: f64) -> u32 {
+    let v = v.clamp(0.0, 1.0);
+    let s = if v <= 0.003_130_8 {
+        v * 12.92
+    } else {
+        1.055 * v.powf(1.0 / 2.4) - 0.055
+    };
+    (s * 255.0 + 0.5) as u32
+}
+
+fn push_base83(out: &mut String, value: u32, digits: u32) {
+    const CHARS: &[u8] =
+        b"0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz#$%*+,-.:;=?@[]^_{|}~";
+    for i in 1..=digits {
+        let digit = (value / 83u32.pow(digits - i)) % 83;
+        out.push(CHARS[digit as usize] as char);
+    }
+}
+
+fn base64(data: &[u8]) -> String {
+    const CHARS: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut out = String::with_capacity(data.len().div_ceil(3) * 4);
+    for chunk in data.chunks(3) {
+        let b = [
+            chunk[0],
+            *chunk.get(1).unwrap_or(&0),
+            *chunk.get(2).unwrap_or(&0),
+        ];
+        let n = ((b[0] as u32) << 16) | ((b[1] as u32) << 8) | b[2] as u32;
+        for i in 0..4 {
+            if i <= chunk.len() {
+                out.push(CHARS[(n >> (18 - 6 * i) & 63) as usize] as char);
+            } else {
+                out.push('=');
+            }
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_blurhash_and_dominant_color() {
+        // 4x3 分量 → 尺寸位 "L"；纯红 DC 0xFF0000 = "TI:j"；总长 1+1+4+2x11
+        let red = RgbaImage::from_pixel(40, 30, image::Rgba([255, 0, 0, 255]));
+        let hash = blurhash(&red, 4, 3);
+        assert_eq!(hash.len(), 28);
+        assert!(hash.starts_with('L'));
+        assert_eq!(&hash[2..6], "TI:j");
+
+        let mut img = RgbaImage::from_pixel(8, 8, image::Rgba([10, 20, 250, 255]));
+        for y in 0..2 {
+            for x in 0..8 {
+                img.put_pixel(x, y, image::Rgba([0, 200, 0, 255]));
+            }
+        }
+        assert_eq!(dominant_color(&img), "#0a14fa");
+        assert_eq!(base64(b"Man"), "TWFu");
+        assert_eq!(base64(b"Ma"), "TWE=");
+    }
+
+    #[test]
+    fn test_generate_tiny_thumbnail() {
+        let img = RgbaImage::from_pixel(400, 100, image::Rgba([30, 60, 90, 255]));
+        let spec = PlaceholderSpec::parse(Some("jpg"), 100).unwrap();
+        assert_eq!(spec.size, MAX_THUMB_EDGE);
+        let ph = generate(&img, &spec).unwrap();
+        assert_eq!((ph.thumbnail_width, ph.thumbnail_height), (32, 8));
+        assert!(ph.thumbnail.starts_with("data:image/jpeg;base64,/9j/"));
+        assert_eq!(ph.dominant_color, "#1e3c5a");
+        assert!(PlaceholderSpec::parse(Some("avif"), 0).is_none());
+    }
+}
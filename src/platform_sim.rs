@@ -0,0 +1,425 @@
+//! 平台二压模拟器：验证「防二压」预设到底能不能扛住平台重编码
+//!
+//! `platform_preset_max` 的全部前提是卡住微信/小红书的二压阈值，但此前没有任何验证手段。
+//! 本模块按各平台公开/实测的重编码行为建模——长边缩放阈值、重编码质量、色度子采样、
+//! 免二压体积线、重编码后体积上限——把我们的输出再「过一遍平台」，报告最终画面相对原图的 SSIM/PSNR。
+//!
+//! 模型参数是经验值（平台随时可能调整），全部字段可由调用方覆盖（CLI `--simulate-config`）。
+//! 编码复用 mozjpeg-rs，指标复用 `perceptual::to_gray/ssim_gray/psnr_gray`，零新依赖。
+
+use crate::crop::CropRect;
+use crate::pad::PadPlacement;
+use anyhow::Result;
+use image::{DynamicImage, GenericImageView};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+/// 单个平台的二压模型（全部字段可由配置覆盖）
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PlatformModel {
+    pub name: String,
+    /// 长边阈值 px：超出即等比缩到该值（0 = 平台不缩放）
+    pub max_long_edge: u32,
+    /// 平台重编码 JPEG 质量
+    pub quality: u8,
+    /// 重编码色度子采样："420" / "422" / "444"
+    pub subsampling: String,
+    /// 免二压体积线 KB：JPEG 且尺寸未超阈值、体积 ≤ 该值时平台保留原文件（0 = 一律重编码）
+    pub passthrough_kb: u32,
+    /// 重编码后体积上限 KB：超出逐级降 Q（0 = 不限）
+    pub size_cap_kb: u32,
+}
+
+impl Default for PlatformModel {
+    /// 通用模型：不缩放、Q75 + 4:2:0、一律重编码（用于配置里新增的未知平台补齐缺省字段）
+    fn default() -> Self {
+        Self {
+            name: "custom".to_string(),
+            max_long_edge: 0,
+            quality: 75,
+            subsampling: "420".to_string(),
+            passthrough_kb: 0,
+            size_cap_kb: 0,
+        }
+    }
+}
+
+/// 内置模型覆盖的平台名（与 CLI `--platform` 取值一致，general 不是真实平台故不建模）
+pub const BUILTIN_PLATFORMS: &[&str] = &["wechat", "wechat-new", "xiaohongshu", "instagram"];
+
+/// 内置平台二压模型（经验值，与 cli::platform_preset 的阈值注释同源）
+pub fn builtin_platform_model(name: &str) -> Option<PlatformModel> {
+    let name = name.to_lowercase();
+    let (max_long_edge, quality, passthrough_kb, size_cap_kb) = match name.as_str() {
+        // 朋友圈：长边 >1080 必缩；≤1MB 的 JPEG 原样保留，否则 ~Q75 重编码
+        "wechat" => (1080, 75, 1024, 0),
+        // iOS 8.0.64+ 宽幅：长边 2560、2MB 线
+        "wechat-new" => (2560, 75, 2048, 0),
+        // 小红书：竖版 1242×1660 主流，单图 ≤5MB 保留，超出重编码并卡 5MB
+        "xiaohongshu" => (1660, 80, 5120, 5120),
+        // Instagram：一律重编码（宽 1080，~Q75）
+        "instagram" => (1080, 75, 0, 0),
+        _ => return None,
+    };
+    Some(PlatformModel {
+        name,
+        max_long_edge,
+        quality,
+        subsampling: "420".to_string(),
+        passthrough_kb,
+        size_cap_kb,
+    })
+}
+
+/// 过平台后的结果（相对原图的最终画质）
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct PlatformSimResult {
+    pub platform: String,
+    /// 平台是否重编码（false = 命中免二压，原文件被保留）
+    pub recompressed: bool,
+    pub width: u32,
+    pub height: u32,
+    /// 平台重编码最终质量（未重编码时缺省）
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub quality: Option<u8>,
+    /// 平台侧最终文件体积
+    pub bytes: u64,
+    /// 最终画面 vs 原图（降采样到最终尺寸对齐）
+    pub ssim_vs_original: f64,
+    pub psnr_vs_original: f64,
+    /// 多输出（切片 / 响应式变体 / 扇出目标）时为指标最差（SSIM 最低）的那份输出；单输出缺省
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub output: Option<String>,
+    /// 参与模拟的输出数（单输出缺省）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub outputs_simulated: Option<u32>,
+}
+
+/// 把一份已编码的输出「过一遍平台」：返回平台侧最终画面与结果（指标字段留 0，由调用方核算）
+pub fn simulate_platform(
+    model: &PlatformModel,
+    encoded: &[u8],
+) -> Result<(DynamicImage, PlatformSimResult)> {
+    let img = image::load_from_memory(encoded)
+        .map_err(|e| anyhow::anyhow!("平台模拟：输出解码失败: {}", e))?;
+    let (w, h) = img.dimensions();
+    let long_edge = w.max(h);
+    let oversized = model.max_long_edge > 0 && long_edge > model.max_long_edge;
+    let is_jpeg = encoded.starts_with(&[0xFF, 0xD8]);
+
+    // 免二压：JPEG + 尺寸未超阈值 + 体积在线内 → 平台保留原文件
+    if is_jpeg
+        && !oversized
+        && model.passthrough_kb > 0
+        && encoded.len() as u64 <= model.passthrough_kb as u64 * 1024
+    {
+        let result = PlatformSimResult {
+            platform: model.name.clone(),
+            recompressed: false,
+            width: w,
+            height: h,
+            quality: None,
+            bytes: encoded.len() as u64,
+            ..Default::default()
+        };
+        return Ok((img, result));
+    }
+
+    let resized = if oversized {
+        let scale = model.max_long_edge as f64 / long_edge as f64;
+        let nw = ((w as f64 * scale).round() as u32).max(1);
+        let nh = ((h as f64 * scale).round() as u32).max(1);
+        // 平台侧普遍是双线性缩放，不做锐化补偿
+        DynamicImage::ImageRgb8(image::imageops::resize(
+            &img.to_rgb8(),
+            nw,
+            nh,
+            image::imageops::FilterType::Triangle,
+        ))
+    } else {
+        DynamicImage::ImageRgb8(img.to_rgb8())
+    };
+    let (nw, nh) = resized.dimensions();
+    let rgb = resized.to_rgb8();
+
+    let encode = |quality: u8| -> Result<Vec<u8>> {
+        use mozjpeg_rs::{Encoder, Subsampling};
+        let sub = match model.subsampling.as_str() {
+            "444" => Subsampling::S444,
+            "422" => Subsampling::S422,
+            _ => Subsampling::S420,
+        };
+        Encoder::default()
+            .quality(quality)
+            .subsampling(sub)
+            .encode_rgb(rgb.as_raw(), nw, nh)
+            .map_err(|e| anyhow::anyhow!("平台模拟：重编码失败: {}", e))
+    };
+
+    // 体积上限：超出每次降 5 个 Q，最低 40（平台不会无底线压）
+    let mut quality = model.quality.clamp(1, 100);
+    let mut data = encode(quality)?;
+    if model.size_cap_kb > 0 {
+        let cap = model.size_cap_kb as usize * 1024;
+        while data.len() > cap && quality > 40 {
+            quality = quality.saturating_sub(5).max(40);
+            data = encode(quality)?;
+        }
+    }
+
+    let final_img = image::load_from_memory(&data)
+        .map_err(|e| anyhow::anyhow!("平台模拟：重编码结果解码失败: {}", e))?;
+    let result = PlatformSimResult {
+        platform: model.name.clone(),
+        recompressed: true,
+        width: nw,
+        height: nh,
+        quality: Some(quality),
+        bytes: data.len() as u64,
+        ..Default::default()
+    };
+    Ok((final_img, result))
+}
+
+/// v4.5：输出画面在原图上的对应区域（画幅适配后只比较同一块画面，不把裁掉/补出的部分算进指标）
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SimRegion {
+    /// 智能裁剪窗口（原图坐标）
+    pub crop: Option<CropRect>,
+    /// 补边画布与原图放置区域
+    pub pad: Option<PadPlacement>,
+    /// 该输出覆盖的切片 / 分页（画幅适配后的画面坐标；None = 整张）
+    pub tile: Option<CropRect>,
+}
+
+impl SimRegion {
+    /// 原图上参与比较的矩形，以及它在输出画面中的位置（占输出宽高的比例 x, y, w, h）。
+    /// 先换算到画幅适配后的画面：切片与原图内容（补边时为放置区域）取交集，再映射回原图
+    fn rects(&self, src_w: u32, src_h: u32) -> Option<(CropRect, [f64; 4])> {
+        let rect = |x: u32, y: u32, width: u32, height: u32| CropRect {
+            x,
+            y,
+            width,
+            height,
+        };
+        if src_w == 0 || src_h == 0 {
+            return None;
+        }
+        let (fitted, content) = match (self.pad, self.crop) {
+            (Some(p), _) => (
+                rect(0, 0, p.canvas_width, p.canvas_height),
+                rect(p.x, p.y, p.width, p.height),
+            ),
+            (None, Some(c)) => {
+                let inside =
+                    c.x.saturating_add(c.width) <= src_w && c.y.saturating_add(c.height) <= src_h;
+                if !inside {
+                    return None;
+                }
+                (rect(0, 0, c.width, c.height), rect(0, 0, c.width, c.height))
+            }
+            (None, None) => (rect(0, 0, src_w, src_h), rect(0, 0, src_w, src_h)),
+        };
+        let tile = self.tile.unwrap_or(fitted);
+        let x0 = tile.x.max(content.x);
+        let y0 = tile.y.max(content.y);
+        let x1 = (tile.x + tile.width).min(content.x + content.width);
+        let y1 = (tile.y + tile.height).min(content.y + content.height);
+        if x1 <= x0 || y1 <= y0 || content.width == 0 || content.height == 0 {
+            return None;
+        }
+        let (sx, sy) = (
+            src_w as f64 / content.width as f64,
+            src_h as f64 / content.height as f64,
+        );
+        let offset = self.crop.filter(|_| self.pad.is_none());
+        let src = match offset {
+            Some(c) => rect(c.x + x0, c.y + y0, x1 - x0, y1 - y0),
+            None => {
+                let x = (((x0 - content.x) as f64 * sx).round() as u32).min(src_w - 1);
+                let y = (((y0 - content.y) as f64 * sy).round() as u32).min(src_h - 1);
+                let w = (((x1 - x0) as f64 * sx).round() as u32).clamp(1, src_w - x);
+                let h = (((y1 - y0) as f64 * sy).round() as u32).clamp(1, src_h - y);
+                rect(x, y, w, h)
+            }
+        };
+        let (tw, th) = (tile.width as f64, tile.height as f64);
+        let placed = [
+            (x0 - tile.x) as f64 / tw,
+            (y0 - tile.y) as f64 / th,
+            (x1 - x0) as f64 / tw,
+            (y1 - y0) as f64 / th,
+        ];
+        Some((src, placed))
+    }
+}
+
+/// 完整模拟：读取我们的输出文件过平台，与原图对应区域（降采样到最终画面对齐）核算 SSIM/PSNR。
+/// `original` 由调用方按处理管线解码（TIFF 页 / RAW / HEIC / 外部解码器与处理时一致）
+pub fn simulate_against_original(
+    model: &PlatformModel,
+    original: &DynamicImage,
+    region: &SimRegion,
+    output: &Path,
+) -> Result<PlatformSimResult> {
+    let encoded = std::fs::read(output)
+        .map_err(|e| anyhow::anyhow!("平台模拟：读取输出失败 {}: {}", output.display(), e))?;
+    let (final_img, mut result) = simulate_platform(model, &encoded)?;
+    let (src_w, src_h) = original.dimensions();
+    let (src, [fx, fy, fw, fh]) = region
+        .rects(src_w, src_h)
+        .ok_or_else(|| anyhow::anyhow!("平台模拟：输出区域超出原图 {}x{}", src_w, src_h))?;
+    // 最终画面上对应原图的区域（补边时去掉补出的边）
+    let (w, h) = final_img.dimensions();
+    let ox = ((fx * w as f64).round() as u32).min(w - 1);
+    let oy = ((fy * h as f64).round() as u32).min(h - 1);
+    let ow = ((fw * w as f64).round() as u32).clamp(1, w - ox);
+    let oh = ((fh * h as f64).round() as u32).clamp(1, h - oy);
+    let out_region = final_img.crop_imm(ox, oy, ow, oh);
+    let orig_region = original.crop_imm(src.x, src.y, src.width, src.height);
+    let orig_resized = DynamicImage::ImageRgb8(image::imageops::resize(
+        &orig_region.to_rgb8(),
+        ow,
+        oh,
+        image::imageops::FilterType::Triangle,
+    ));
+    let (ref_gray, gw, gh) = crate::perceptual::to_gray(&orig_resized);
+    let (out_gray, _, _) = crate::perceptual::to_gray(&out_region);
+    result.ssim_vs_original = crate::perceptual::ssim_gray(&ref_gray, &out_gray, gw, gh);
+    result.psnr_vs_original = crate::perceptual::psnr_gray(&ref_gray, &out_gray);
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn jpeg_bytes(w: u32, h: u32, quality: u8) -> Vec<u8> {
+        let mut img = image::RgbImage::new(w, h);
+        for (x, y, p) in img.enumerate_pixels_mut() {
+            *p = image::Rgb([
+                (x * 255 / w) as u8,
+                (y * 255 / h) as u8,
+                ((x ^ y) & 0xFF) as u8,
+            ]);
+        }
+        mozjpeg_rs::Encoder::default()
+            .quality(quality)
+            .encode_rgb(img.as_raw(), w, h)
+            .unwrap()
+    }
+
+    #[test]
+    fn test_builtin_models_cover_platform_names() {
+        for name in BUILTIN_PLATFORMS {
+            let m = builtin_platform_model(name).expect("内置平台必须有模型");
+            assert_eq!(&m.name, name);
+            assert!(m.quality > 0 && m.quality <= 100);
+        }
+        assert!(builtin_platform_model("general").is_none());
+    }
+
+    #[test]
+    fn test_oversized_output_is_resized_and_recompressed() {
+        let model = builtin_platform_model("wechat").unwrap();
+        let data = jpeg_bytes(2000, 1500, 90);
+        let (img, r) = simulate_platform(&model, &data).unwrap();
+        assert!(r.recompressed);
+        assert_eq!((r.width, r.height), (1080, 810));
+        assert_eq!(img.dimensions(), (1080, 810));
+        assert_eq!(r.quality, Some(75));
+    }
+
+    #[test]
+    fn test_small_jpeg_within_limits_passes_through() {
+        let model = builtin_platform_model("wechat").unwrap();
+        let data = jpeg_bytes(800, 600, 85);
+        let (_, r) = simulate_platform(&model, &data).unwrap();
+        assert!(!r.recompressed, "≤1MB 且长边 ≤1080 的 JPEG 应免二压");
+        assert_eq!(r.bytes, data.len() as u64);
+
+        // 同一张图在「一律重编码」的平台上必然二压
+        let ig = builtin_platform_model("instagram").unwrap();
+        let (_, r) = simulate_platform(&ig, &data).unwrap();
+        assert!(r.recompressed);
+    }
+
+    #[test]
+    fn test_crop_and_pad_regions_align_with_original() {
+        let dir = std::env::temp_dir().join(format!("xtap_platform_sim_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let model = PlatformModel {
+            quality: 95,
+            subsampling: "444".to_string(),
+            ..Default::default()
+        };
+        let orig = DynamicImage::ImageRgb8(image::RgbImage::from_fn(400, 300, |x, y| {
+            image::Rgb([
+                (x * 255 / 400) as u8,
+                ((x ^ y) & 0xFF) as u8,
+                (y * 255 / 300) as u8,
+            ])
+        }));
+
+        // 智能裁剪：输出只是右半幅，按裁剪窗口比较才对得上
+        let crop = CropRect {
+            x: 200,
+            y: 0,
+            width: 200,
+            height: 300,
+        };
+        let cropped = dir.join("cropped.png");
+        orig.crop_imm(200, 0, 200, 300).save(&cropped).unwrap();
+        let region = SimRegion {
+            crop: Some(crop),
+            ..Default::default()
+        };
+        let aligned = simulate_against_original(&model, &orig, &region, &cropped).unwrap();
+        let stretched =
+            simulate_against_original(&model, &orig, &SimRegion::default(), &cropped).unwrap();
+        assert!(aligned.ssim_vs_original > 0.9, "{:?}", aligned);
+        assert!(aligned.ssim_vs_original > stretched.ssim_vs_original + 0.2);
+
+        // 补边：上下各补 50px 黑边，只比较原图所在区域
+        let mut canvas = image::RgbImage::new(400, 400);
+        image::imageops::replace(&mut canvas, &orig.to_rgb8(), 0, 50);
+        let padded = dir.join("padded.png");
+        canvas.save(&padded).unwrap();
+        let region = SimRegion {
+            pad: Some(PadPlacement {
+                canvas_width: 400,
+                canvas_height: 400,
+                x: 0,
+                y: 50,
+                width: 400,
+                height: 300,
+            }),
+            ..Default::default()
+        };
+        let aligned = simulate_against_original(&model, &orig, &region, &padded).unwrap();
+        let stretched =
+            simulate_against_original(&model, &orig, &SimRegion::default(), &padded).unwrap();
+        assert!(aligned.ssim_vs_original > 0.9, "{:?}", aligned);
+        assert!(aligned.ssim_vs_original > stretched.ssim_vs_original + 0.2);
+
+        // 补边后切片：下半片只含原图下半部分与底边，按交集比较
+        let lower = dir.join("lower.png");
+        image::DynamicImage::ImageRgb8(canvas)
+            .crop_imm(0, 200, 400, 200)
+            .save(&lower)
+            .unwrap();
+        let region = SimRegion {
+            tile: Some(CropRect {
+                x: 0,
+                y: 200,
+                width: 400,
+                height: 200,
+            }),
+            ..region
+        };
+        let aligned = simulate_against_original(&model, &orig, &region, &lower).unwrap();
+        assert!(aligned.ssim_vs_original > 0.9, "{:?}", aligned);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,487 @@
+//! v4.5：冲印尺寸模式（「6×4 in @ 300 dpi」「A4 @ 200 dpi」）
+//!
+//! 冲印店按物理尺寸 × DPI 收图，像素不够会糊、密度标错会按 72dpi 印成海报。这里：
+//! - 由纸张尺寸和 DPI 算出目标像素框，纸张方向自动跟随图片方向（横图配横纸）；
+//! - 图片等比缩进像素框（不裁不补；要铺满纸面先配合 `--smart-crop` / `--pad` 调画幅）；
+//!   原图像素不够时不放大，改为降低写入的 DPI，保证印出来仍是所选纸张大小；
+//! - 输出写入正确密度：JPEG 改写 JFIF APP0 与 EXIF IFD0 的 X/YResolution，PNG 写 pHYs。
+//!   WebP 没有通用的密度字段，只输出像素。
+//!
+//! 源图密度按 EXIF → JFIF（JPEG）、pHYs（PNG）、IFD0（TIFF）读取，随结果报告。
+
+use serde::{Deserialize, Serialize};
+
+/// 未指定 DPI 时的冲印默认值
+pub const DEFAULT_PRINT_DPI: u32 = 300;
+/// DPI 允许范围（JFIF 密度字段为 u16）
+const DPI_RANGE: std::ops::RangeInclusive<u32> = 50..=2400;
+const MM_PER_INCH: f64 = 25.4;
+
+/// 常用纸张 / 相纸（英寸，竖向）
+const PAPERS: &[(&str, f64, f64)] = &[
+    ("a3", 297.0 / MM_PER_INCH, 420.0 / MM_PER_INCH),
+    ("a4", 210.0 / MM_PER_INCH, 297.0 / MM_PER_INCH),
+    ("a5", 148.0 / MM_PER_INCH, 210.0 / MM_PER_INCH),
+    ("a6", 105.0 / MM_PER_INCH, 148.0 / MM_PER_INCH),
+    ("b5", 176.0 / MM_PER_INCH, 250.0 / MM_PER_INCH),
+    ("letter", 8.5, 11.0),
+    ("legal", 8.5, 14.0),
+    ("3r", 3.5, 5.0),
+    ("4r", 4.0, 6.0),
+    ("5r", 5.0, 7.0),
+    ("6r", 6.0, 8.0),
+    ("8r", 8.0, 10.0),
+];
+
+/// 冲印尺寸（英寸）+ DPI
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PrintSize {
+    pub width_in: f64,
+    pub height_in: f64,
+    pub dpi: u32,
+}
+
+impl PrintSize {
+    /// "A4@200" / "6x4in@300" / "15x10cm@300dpi" / "4R"（缺省单位 in，缺省 300dpi）
+    pub fn parse(s: &str) -> Option<Self> {
+        let s = s.trim().to_ascii_lowercase();
+        let (size, dpi) = match s.split_once('@') {
+            Some((size, dpi)) => {
+                let dpi: u32 = dpi.trim().trim_end_matches("dpi").trim().parse().ok()?;
+                (size.trim(), dpi)
+            }
+            None => (s.as_str(), DEFAULT_PRINT_DPI),
+        };
+        if !DPI_RANGE.contains(&dpi) {
+            return None;
+        }
+        let (width_in, height_in) = match PAPERS.iter().find(|(name, ..)| *name == size) {
+            Some(&(_, w, h)) => (w, h),
+            None => {
+                let (num, per_inch) = if let Some(v) = size.strip_suffix("mm") {
+                    (v, MM_PER_INCH)
+                } else if let Some(v) = size.strip_suffix("cm") {
+                    (v, MM_PER_INCH / 10.0)
+                } else {
+                    (size.strip_suffix("in").unwrap_or(size), 1.0)
+                };
+                let (w, h) = num.split_once(['x', '*', '×'])?;
+                let w: f64 = w.trim().parse().ok()?;
+                let h: f64 = h.trim().parse().ok()?;
+                (w / per_inch, h / per_inch)
+            }
+        };
+        (width_in > 0.0 && height_in > 0.0).then_some(PrintSize {
+            width_in,
+            height_in,
+            dpi,
+        })
+    }
+
+    /// 计算图片在纸面上的像素尺寸与写入的 DPI（纸张方向跟随图片，不放大）
+    pub fn fit(&self, width: u32, height: u32) -> PrintFit {
+        let (long, short) = (
+            self.width_in.max(self.height_in),
+            self.width_in.min(self.height_in),
+        );
+        let (paper_w, paper_h) = if width >= height {
+            (long, short)
+        } else {
+            (short, long)
+        };
+        let box_w = paper_w * self.dpi as f64;
+        let box_h = paper_h * self.dpi as f64;
+        let scale = (box_w / width.max(1) as f64).min(box_h / height.max(1) as f64);
+        let (width_px, height_px, dpi) = if scale < 1.0 {
+            (
+                ((width as f64 * scale).round() as u32).clamp(1, box_w.round() as u32),
+                ((height as f64 * scale).round() as u32).clamp(1, box_h.round() as u32),
+                self.dpi,
+            )
+        } else {
+            // 像素不够：保持原像素，按贴边的那一侧反算 DPI（向上取整，印出来不超出纸面）
+            let dpi = (width as f64 / paper_w)
+                .max(height as f64 / paper_h)
+                .ceil()
+                .max(1.0) as u32;
+            (width, height, dpi)
+        };
+        PrintFit {
+            width_px,
+            height_px,
+            dpi,
+            width_in: round2(width_px as f64 / dpi as f64),
+            height_in: round2(height_px as f64 / dpi as f64),
+            source_dpi: None,
+        }
+    }
+}
+
+/// 冲印模式结果（JSON 输出 print 字段）
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct PrintFit {
+    pub width_px: u32,
+    pub height_px: u32,
+    /// 写入输出的 DPI（原图像素不够时低于所选 DPI）
+    pub dpi: u32,
+    /// 按写入 DPI 换算的实际印刷尺寸（英寸）
+    pub width_in: f64,
+    pub height_in: f64,
+    /// 源图标注的密度（无标注为 None）
+    pub source_dpi: Option<f64>,
+}
+
+fn round2(v: f64) -> f64 {
+    (v * 100.0).round() / 100.0
+}
+
+// ============================================================================
+// 密度读取
+// ============================================================================
+
+/// 读取源图标注的密度（DPI，取水平方向）。JPEG：EXIF 优先、JFIF 次之；PNG：pHYs；TIFF：IFD0
+pub fn read_dpi(data: &[u8]) -> Option<f64> {
+    if data.starts_with(&[0xFF, 0xD8]) {
+        let mut jfif = None;
+        for (marker, body) in jpeg_segments(data) {
+            if marker == 0xE1 && body.starts_with(b"Exif\0\0") {
+                if let Some(dpi) = tiff_dpi(&body[6..]) {
+                    return Some(dpi);
+                }
+            } else if marker == 0xE0 && body.starts_with(b"JFIF\0") && body.len() >= 12 {
+                let density = u16::from_be_bytes([body[8], body[9]]) as f64;
+                jfif = match body[7] {
+                    1 if density > 0.0 => Some(density),
+                    2 if density > 0.0 => Some(density * 2.54),
+                    _ => jfif,
+                };
+            }
+        }
+        return jfif;
+    }
+    if data.starts_with(PNG_SIGNATURE) {
+        return png_chunks(data)
+            .find(|(ty, body)| ty == b"pHYs" && body.len() >= 9)
+            .and_then(|(_, body)| {
+                let ppm = u32::from_be_bytes([body[0], body[1], body[2], body[3]]) as f64;
+                (body[8] == 1 && ppm > 0.0).then_some(ppm * 0.0254)
+            });
+    }
+    if data.starts_with(b"II*\0") || data.starts_with(b"MM\0*") {
+        return tiff_dpi(data);
+    }
+    None
+}
+
+/// JPEG 段遍历（SOS 之前的带长度段）：(marker, 段体不含长度字段)
+fn jpeg_segments(data: &[u8]) -> impl Iterator<Item = (u8, &[u8])> + '_ {
+    let mut pos = 2;
+    std::iter::from_fn(move || {
+        if pos + 4 > data.len() || data[pos] != 0xFF || data[pos + 1] == 0xDA {
+            return None;
+        }
+        let marker = data[pos + 1];
+        let len = u16::from_be_bytes([data[pos + 2], data[pos + 3]]) as usize;
+        let end = (pos + 2 + len).min(data.len());
+        let body = &data[(pos + 4).min(end)..end];
+        pos += 2 + len.max(2);
+        Some((marker, body))
+    })
+}
+
+/// TIFF 字节序读取器
+#[derive(Clone, Copy)]
+struct Tiff<'a> {
+    data: &'a [u8],
+    le: bool,
+}
+
+impl<'a> Tiff<'a> {
+    fn new(data: &'a [u8]) -> Option<Self> {
+        let le = match data.get(..2)? {
+            b"II" => true,
+            b"MM" => false,
+            _ => return None,
+        };
+        Some(Tiff { data, le })
+    }
+
+    fn u16(&self, at: usize) -> Option<u16> {
+        let b: [u8; 2] = self.data.get(at..at + 2)?.try_into().ok()?;
+        Some(if self.le {
+            u16::from_le_bytes(b)
+        } else {
+            u16::from_be_bytes(b)
+        })
+    }
+
+    fn u32(&self, at: usize) -> Option<u32> {
+        let b: [u8; 4] = self.data.get(at..at + 4)?.try_into().ok()?;
+        Some(if self.le {
+            u32::from_le_bytes(b)
+        } else {
+            u32::from_be_bytes(b)
+        })
+    }
+
+    /// IFD0 条目：(tag, type, 条目起始偏移)
+    fn ifd0(&self) -> Vec<(u16, u16, usize)> {
+        let Some(ifd) = self.u32(4).map(|o| o as usize) else {
+            return Vec::new();
+        };
+        let count = self.u16(ifd).unwrap_or(0) as usize;
+        (0..count)
+            .map(|i| ifd + 2 + i * 12)
+            .filter_map(|e| Some((self.u16(e)?, self.u16(e + 2)?, e)))
+            .collect()
+    }
+}
+
+const TAG_X_RESOLUTION: u16 = 0x011A;
+const TAG_Y_RESOLUTION: u16 = 0x011B;
+const TAG_RESOLUTION_UNIT: u16 = 0x0128;
+const TYPE_SHORT: u16 = 3;
+const TYPE_RATIONAL: u16 = 5;
+
+/// TIFF/EXIF IFD0 的 XResolution（按 ResolutionUnit 换算成 DPI；unit=1 无单位时返回 None）
+fn tiff_dpi(data: &[u8]) -> Option<f64> {
+    let t = Tiff::new(data)?;
+    let entries = t.ifd0();
+    let unit = entries
+        .iter()
+        .find(|(tag, ty, _)| *tag == TAG_RESOLUTION_UNIT && *ty == TYPE_SHORT)
+        .and_then(|&(_, _, e)| t.u16(e + 8))
+        .unwrap_or(2);
+    let (_, _, e) = *entries
+        .iter()
+        .find(|(tag, ty, _)| *tag == TAG_X_RESOLUTION && *ty == TYPE_RATIONAL)?;
+    let at = t.u32(e + 8)? as usize;
+    let (num, den) = (t.u32(at)? as f64, t.u32(at + 4)? as f64);
+    if den == 0.0 || num == 0.0 {
+        return None;
+    }
+    match unit {
+        2 => Some(num / den),
+        3 => Some(num / den * 2.54),
+        _ => None,
+    }
+}
+
+// ============================================================================
+// 密度写入
+// ============================================================================
+
+/// JPEG：改写所有 JFIF APP0 与 EXIF IFD0 的密度；没有 JFIF 段时在 SOI 后补一个
+pub fn set_jpeg_dpi(data: &mut Vec<u8>, dpi: u32) {
+    if !data.starts_with(&[0xFF, 0xD8]) {
+        return;
+    }
+    let dpi16 = dpi.min(u16::MAX as u32) as u16;
+    let mut jfif_found = false;
+    let mut pos = 2;
+    while pos + 4 <= data.len() && data[pos] == 0xFF && data[pos + 1] != 0xDA {
+        let marker = data[pos + 1];
+        let len = u16::from_be_bytes([data[pos + 2], data[pos + 3]]) as usize;
+        let body = pos + 4;
+        let end = (pos + 2 + len).min(data.len());
+        if marker == 0xE0 && data[body..end].starts_with(b"JFIF\0") && end - body >= 12 {
+            // JFIF\0 | 版本(2) | 单位(1) | X 密度(2) | Y 密度(2)
+            data[body + 7] = 1;
+            data[body + 8..body + 10].copy_from_slice(&dpi16.to_be_bytes());
+            data[body + 10..body + 12].copy_from_slice(&dpi16.to_be_bytes());
+            jfif_found = true;
+        } else if marker == 0xE1 && data[body..end].starts_with(b"Exif\0\0") {
+            set_tiff_dpi(&mut data[body + 6..end], dpi);
+        }
+        pos += 2 + len.max(2);
+    }
+    if !jfif_found {
+        let mut app0 = vec![0xFF, 0xE0, 0x00, 0x10];
+        app0.extend_from_slice(b"JFIF\0\x01\x01\x01");
+        app0.extend_from_slice(&dpi16.to_be_bytes());
+        app0.extend_from_slice(&dpi16.to_be_bytes());
+        app0.extend_from_slice(&[0, 0]);
+        data.splice(2..2, app0);
+    }
+}
+
+/// 原位改写 EXIF IFD0 的 X/YResolution 与 ResolutionUnit（缺失的字段不补，JFIF 已承载密度）
+fn set_tiff_dpi(tiff: &mut [u8], dpi: u32) {
+    let Some(t) = Tiff::new(tiff) else {
+        return;
+    };
+    let le = t.le;
+    let mut patches: Vec<(usize, Vec<u8>)> = Vec::new();
+    let put32 = |v: u32| if le { v.to_le_bytes() } else { v.to_be_bytes() };
+    for (tag, ty, e) in t.ifd0() {
+        match (tag, ty) {
+            (TAG_X_RESOLUTION | TAG_Y_RESOLUTION, TYPE_RATIONAL) => {
+                if let Some(at) = t.u32(e + 8) {
+                    let mut v = put32(dpi).to_vec();
+                    v.extend_from_slice(&put32(1));
+                    patches.push((at as usize, v));
+                }
+            }
+            (TAG_RESOLUTION_UNIT, TYPE_SHORT) => {
+                let unit: u16 = 2;
+                let v = if le {
+                    unit.to_le_bytes()
+                } else {
+                    unit.to_be_bytes()
+                };
+                patches.push((e + 8, v.to_vec()));
+            }
+            _ => {}
+        }
+    }
+    for (at, bytes) in patches {
+        if let Some(dst) = tiff.get_mut(at..at + bytes.len()) {
+            dst.copy_from_slice(&bytes);
+        }
+    }
+}
+
+const PNG_SIGNATURE: &[u8] = b"\x89PNG\r\n\x1a\n";
+
+/// PNG 块遍历：(类型, 数据)
+fn png_chunks(data: &[u8]) -> impl Iterator<Item = ([u8; 4], &[u8])> + '_ {
+    let mut pos = PNG_SIGNATURE.len();
+    std::iter::from_fn(move || {
+        let len = u32::from_be_bytes(data.get(pos..pos + 4)?.try_into().ok()?) as usize;
+        let ty: [u8; 4] = data.get(pos + 4..pos + 8)?.try_into().ok()?;
+        let body = data.get(pos + 8..pos + 8 + len)?;
+        pos += 12 + len;
+        Some((ty, body))
+    })
+}
+
+/// PNG：去掉旧 pHYs，在 IHDR 之后写入新的 pHYs（像素/米）
+pub fn set_png_dpi(data: &mut Vec<u8>, dpi: u32) {
+    if !data.starts_with(PNG_SIGNATURE) {
+        return;
+    }
+    let ppm = (dpi as f64 / 0.0254).round() as u32;
+    let mut out = PNG_SIGNATURE.to_vec();
+    for (ty, body) in png_chunks(data) {
+        if &ty == b"pHYs" {
+            continue;
+        }
+        push_png_chunk(&mut out, &ty, body);
+        if &ty == b"IHDR" {
+            let mut phys = Vec::with_capacity(9);
+            phys.extend_from_slice(&ppm.to_be_bytes());
+            phys.extend_from_slice(&ppm.to_be_bytes());
+            phys.push(1);
+            push_png_chunk(&mut out, b"pHYs", &phys);
+        }
+    }
+    *data = out;
+}
+
+fn push_png_chunk(out: &mut Vec<u8>, ty: &[u8; 4], body: &[u8]) {
+    out.extend_from_slice(&(body.len() as u32).to_be_bytes());
+    out.extend_from_slice(ty);
+    out.extend_from_slice(body);
+    let crc = crc32(ty.iter().chain(body));
+    out.extend_from_slice(&crc.to_be_bytes());
+}
+
+/// PNG 块校验用 CRC-32（IEEE，逐位计算；块很小，无需查表）
+fn crc32<'a>(bytes: impl Iterator<Item = &'a u8>) -> u32 {
+    let mut crc = 0xFFFF_FFFFu32;
+    for &b in bytes {
+        crc ^= b as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 != 0 {
+                (crc >> 1) ^ 0xEDB8_8320
+            } else {
+                crc >> 1
+            };
+        }
+    }
+    !crc
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_print_size() {
+        let a4 = PrintSize::parse("A4@200").unwrap();
+        assert!((a4.width_in - 8.27).abs() < 0.01 && a4.dpi == 200);
+        let p = PrintSize::parse("6x4in@300dpi").unwrap();
+        assert_eq!((p.width_in, p.height_in, p.dpi), (6.0, 4.0, 300));
+        let cm = PrintSize::parse("15x10cm").unwrap();
+        assert!((cm.width_in - 5.906).abs() < 0.001 && cm.dpi == DEFAULT_PRINT_DPI);
+        assert!(PrintSize::parse("4R").is_some());
+        assert!(PrintSize::parse("6x4@10").is_none(), "DPI 过低");
+        assert!(PrintSize::parse("huge").is_none());
+    }
+
+    #[test]
+    fn test_fit_follows_orientation_and_never_upscales() {
+        let p = PrintSize::parse("6x4in@300").unwrap();
+        // 竖图 3000x4500 → 纸张转竖 4x6 → 1200x1800
+        let f = p.fit(3000, 4500);
+        assert_eq!((f.width_px, f.height_px, f.dpi), (1200, 1800, 300));
+        assert_eq!((f.width_in, f.height_in), (4.0, 6.0));
+        // 横图 4000x2000（比纸更扁）→ 宽贴边 1800
+        let f = p.fit(4000, 2000);
+        assert_eq!((f.width_px, f.height_px), (1800, 900));
+        // 像素不够：不放大，降 DPI 保证仍印满 6 英寸
+        let f = p.fit(900, 600);
+        assert_eq!((f.width_px, f.height_px, f.dpi), (900, 600, 150));
+        assert_eq!((f.width_in, f.height_in), (6.0, 4.0));
+    }
+
+    #[test]
+    fn test_density_roundtrip_jpeg_and_png() {
+        let img = image::RgbImage::from_pixel(16, 16, image::Rgb([90, 120, 200]));
+        let mut jpeg = Vec::new();
+        image::codecs::jpeg::JpegEncoder::new(&mut jpeg)
+            .encode_image(&img)
+            .unwrap();
+        set_jpeg_dpi(&mut jpeg, 300);
+        assert_eq!(read_dpi(&jpeg), Some(300.0));
+        assert!(image::load_from_memory(&jpeg).is_ok());
+
+        let mut png = Vec::new();
+        image::DynamicImage::ImageRgb8(img)
+            .write_to(&mut std::io::Cursor::new(&mut png), image::ImageFormat::Png)
+            .unwrap();
+        assert_eq!(read_dpi(&png), None);
+        set_png_dpi(&mut png, 200);
+        let dpi = read_dpi(&png).unwrap();
+        assert!((dpi - 200.0).abs() < 0.1, "{}", dpi);
+        set_png_dpi(&mut png, 600);
+        assert_eq!(png_chunks(&png).filter(|(t, _)| t == b"pHYs").count(), 1);
+        assert!(image::load_from_memory(&png).is_ok());
+    }
+
+    #[test]
+    fn test_exif_resolution_read_and_patched() {
+        // 最小 EXIF：II，IFD0 三项（XRes/YRes RATIONAL 指向尾部，Unit=inch）
+        let mut tiff = b"II*\0\x08\0\0\0".to_vec();
+        tiff.extend_from_slice(&3u16.to_le_bytes());
+        let data_at = 8 + 2 + 3 * 12 + 4;
+        for (i, tag) in [TAG_X_RESOLUTION, TAG_Y_RESOLUTION].iter().enumerate() {
+            tiff.extend_from_slice(&tag.to_le_bytes());
+            tiff.extend_from_slice(&TYPE_RATIONAL.to_le_bytes());
+            tiff.extend_from_slice(&1u32.to_le_bytes());
+            tiff.extend_from_slice(&((data_at + i * 8) as u32).to_le_bytes());
+        }
+        tiff.extend_from_slice(&TAG_RESOLUTION_UNIT.to_le_bytes());
+        tiff.extend_from_slice(&TYPE_SHORT.to_le_bytes());
+        tiff.extend_from_slice(&1u32.to_le_bytes());
+        tiff.extend_from_slice(&[2, 0, 0, 0]);
+        tiff.extend_from_slice(&[0; 4]);
+        for _ in 0..2 {
+            tiff.extend_from_slice(&72u32.to_le_bytes());
+            tiff.extend_from_slice(&1u32.to_le_bytes());
+        }
+        assert_eq!(tiff_dpi(&tiff), Some(72.0));
+        set_tiff_dpi(&mut tiff, 350);
+        assert_eq!(tiff_dpi(&tiff), Some(350.0));
+    }
+}
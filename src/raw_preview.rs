@@ -0,0 +1,762 @@
+//! v4.5：RAW 内嵌预览提取（纯 Rust，跨平台，无需 sips / libraw）
+//!
+//! - DNG/CR2/NEF/ARW/PEF/SRW/3FR/RW2/ORF 为 TIFF 结构：遍历 IFD0 → SubIFDs(0x014A) → 后继 IFD 链，
+//!   收集 JPEGInterchangeFormat(0x0201/0x0202)、JPEG 压缩(6/7)的单条带图像、RW2 的 JpgFromRaw(0x002E)；
+//! - CR3 为 ISO-BMFF：取 moov 首轨（整幅 JPEG）、PRVW 预览与 THMB 缩略图；RAF 取文件头中的 JPEG 偏移；
+//! - 每个候选都按 JPEG 段结构校验并读出 SOF 尺寸（无损 JPEG 等非基线/渐进编码视为原始数据，不当预览），
+//!   按像素数从大到小排序；结构化候选都偏小（长边 < 1024）时再全文扫描 SOI 兜底（ORF 预览藏在厂商 MakerNote 中）；
+//! - EXIF 迁移：按 IFD0（相机/方向/时间等白名单）+ Exif IFD（去 MakerNote）+ GPS IFD 重建紧凑 TIFF，
+//!   交由调用方写入输出 JPEG 的 APP1；CR3 取 CMT1/CMT2/CMT4，RAF 取预览 JPEG 自带的 EXIF。
+
+use anyhow::{anyhow, Result};
+use std::collections::HashSet;
+
+/// 支持的 RAW 扩展名（小写）
+pub const RAW_EXTENSIONS: [&str; 11] = [
+    "dng", "cr2", "cr3", "nef", "arw", "orf", "raf", "rw2", "pef", "srw", "3fr",
+];
+
+/// 结构化候选的长边低于此值时追加全文扫描
+const SCAN_FALLBACK_EDGE: u32 = 1024;
+/// IFD 遍历上限（防环 / 防恶意文件）
+const MAX_IFDS: usize = 64;
+/// APP1 段内容上限（64KB 减去段长与 "Exif\0\0" 头）
+const MAX_EXIF_LEN: usize = 65_533 - 6;
+
+const CR3_CANON_UUID: [u8; 16] = [
+    0x85, 0xc0, 0xb6, 0x87, 0x82, 0x0f, 0x11, 0xe0, 0x81, 0x11, 0xf4, 0xce, 0x46, 0x2b, 0x6a, 0x48,
+];
+const CR3_PREVIEW_UUID: [u8; 16] = [
+    0xea, 0xf4, 0x2b, 0x5e, 0x1c, 0x98, 0x4b, 0x88, 0xb9, 0xfb, 0xb7, 0xdc, 0x40, 0x6e, 0x4d, 0x16,
+];
+
+pub fn is_raw_extension(ext: &str) -> bool {
+    RAW_EXTENSIONS.contains(&ext.to_ascii_lowercase().as_str())
+}
+
+/// 内嵌 JPEG 预览在文件中的位置与尺寸
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Preview {
+    pub offset: usize,
+    pub len: usize,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl Preview {
+    pub fn bytes<'a>(&self, data: &'a [u8]) -> &'a [u8] {
+        &data[self.offset..self.offset + self.len]
+    }
+}
+
+/// 列出全部可用预览（按像素数从大到小）
+pub fn find_previews(data: &[u8]) -> Vec<Preview> {
+    let mut found = if is_cr3(data) {
+        cr3_previews(data)
+    } else if data.starts_with(b"FUJIFILMCCD-RAW") {
+        raf_previews(data)
+    } else if let Some(tiff) = Tiff::new(data) {
+        tiff_previews(&tiff)
+    } else {
+        Vec::new()
+    };
+    if found
+        .iter()
+        .all(|p| p.width.max(p.height) < SCAN_FALLBACK_EDGE)
+    {
+        found.extend(scan_jpegs(data, 0));
+    }
+    let mut seen = HashSet::new();
+    found.retain(|p| seen.insert(p.offset));
+    found.sort_by_key(|p| std::cmp::Reverse(p.width as u64 * p.height as u64));
+    found
+}
+
+/// 解码最大的可解码预览（损坏的候选依次跳过）
+pub fn decode_preview(data: &[u8]) -> Result<image::DynamicImage> {
+    let previews = find_previews(data);
+    if previews.is_empty() {
+        return Err(anyhow!("RAW 文件中未找到内嵌 JPEG 预览"));
+    }
+    let mut last_err = None;
+    for p in &previews {
+        match image::load_from_memory_with_format(p.bytes(data), image::ImageFormat::Jpeg) {
+            Ok(img) => return Ok(img),
+            Err(e) => last_err = Some(e),
+        }
+    }
+    Err(anyhow!(
+        "RAW 内嵌预览解码失败: {}",
+        last_err.map(|e| e.to_string()).unwrap_or_default()
+    ))
+}
+
+/// 从 RAW 重建 EXIF（TIFF 结构，不含 "Exif\0\0" 头）；过大或无元数据时返回 None
+pub fn raw_exif(data: &[u8]) -> Option<Vec<u8>> {
+    let exif = if is_cr3(data) {
+        cr3_exif(data)
+    } else if let Some(tiff) = Tiff::new(data) {
+        let ifd0 = tiff.first_ifd()?;
+        let exif_ifd = tiff.pointer(ifd0, 0x8769).map(|o| (&tiff, o));
+        let gps_ifd = tiff.pointer(ifd0, 0x8825).map(|o| (&tiff, o));
+        build_exif((&tiff, ifd0), exif_ifd, gps_ifd)
+    } else {
+        // RAF 等：预览 JPEG 自带 EXIF
+        find_previews(data)
+            .first()
+            .and_then(|p| jpeg_app1_exif(p.bytes(data)))
+            .map(<[u8]>::to_vec)
+    }?;
+    (exif.len() <= MAX_EXIF_LEN).then_some(exif)
+}
+
+// ---------------------------------------------------------------- TIFF
+
+#[derive(Clone, Copy)]
+pub(crate) struct Entry {
+    pub(crate) tag: u16,
+    pub(crate) typ: u16,
+    pub(crate) count: u32,
+    /// 值数据在 TIFF 内的偏移（≤4 字节时为条目内联位置）
+    pub(crate) value_off: usize,
+    pub(crate) value_len: usize,
+}
+
+/// TIFF 读取器（DNG 解码复用）
+pub(crate) struct Tiff<'a> {
+    pub(crate) data: &'a [u8],
+    pub(crate) le: bool,
+}
+
+impl<'a> Tiff<'a> {
+    /// TIFF / ORF("RO"/"RS") / RW2(0x55) 头
+    pub(crate) fn new(data: &'a [u8]) -> Option<Self> {
+        let le = match data.get(..2)? {
+            b"II" => true,
+            b"MM" => false,
+            _ => return None,
+        };
+        let tiff = Tiff { data, le };
+        matches!(tiff.u16(2)?, 42 | 0x4F52 | 0x5352 | 0x55).then_some(tiff)
+    }
+
+    pub(crate) fn u16(&self, off: usize) -> Option<u16> {
+        let b: [u8; 2] = self.data.get(off..off + 2)?.try_into().ok()?;
+        Some(if self.le {
+            u16::from_le_bytes(b)
+        } else {
+            u16::from_be_bytes(b)
+        })
+    }
+
+    pub(crate) fn u32(&self, off: usize) -> Option<u32> {
+        let b: [u8; 4] = self.data.get(off..off + 4)?.try_into().ok()?;
+        Some(if self.le {
+            u32::from_le_bytes(b)
+        } else {
+            u32::from_be_bytes(b)
+        })
+    }
+
+    pub(crate) fn first_ifd(&self) -> Option<usize> {
+        Some(self.u32(4)? as usize)
+    }
+
+    /// IFD 条目与后继 IFD 偏移（0 = 无）
+    pub(crate) fn ifd(&self, off: usize) -> Option<(Vec<Entry>, usize)> {
+        let n = self.u16(off)? as usize;
+        let mut entries = Vec::with_capacity(n);
+        for i in 0..n {
+            let e = off + 2 + i * 12;
+            let typ = self.u16(e + 2)?;
+            let count = self.u32(e + 4)?;
+            let unit = match typ {
+                1 | 2 | 6 | 7 => 1,
+                3 | 8 => 2,
+                4 | 9 | 11 | 13 => 4,
+                5 | 10 | 12 => 8,
+                _ => continue,
+            };
+            let value_len = (count as usize).checked_mul(unit)?;
+            let value_off = if value_len <= 4 {
+                e + 8
+            } else {
+                self.u32(e + 8)? as usize
+            };
+            if value_off.checked_add(value_len)? > self.data.len() {
+                continue;
+            }
+            entries.push(Entry {
+                tag: self.u16(e)?,
+                typ,
+                count,
+                value_off,
+                value_len,
+            });
+        }
+        let next = self.u32(off + 2 + n * 12).unwrap_or(0) as usize;
+        Some((entries, next))
+    }
+
+    /// SHORT / LONG 数值
+    pub(crate) fn values(&self, e: &Entry) -> Vec<u32> {
+        (0..e.count as usize)
+            .filter_map(|i| match e.typ {
+                3 => self.u16(e.value_off + i * 2).map(u32::from),
+                4 | 13 => self.u32(e.value_off + i * 4),
+                _ => None,
+            })
+            .collect()
+    }
+
+    pub(crate) fn value(&self, entries: &[Entry], tag: u16) -> Option<u32> {
+        let e = entries.iter().find(|e| e.tag == tag)?;
+        self.values(e).first().copied()
+    }
+
+    /// 数值（整数 / 有理数 / 浮点统一为 f64）
+    pub(crate) fn reals(&self, e: &Entry) -> Vec<f64> {
+        (0..e.count as usize)
+            .filter_map(|i| match e.typ {
+                1 => self.data.get(e.value_off + i).map(|&b| b as f64),
+                3 => self.u16(e.value_off + i * 2).map(f64::from),
+                8 => self.u16(e.value_off + i * 2).map(|v| v as i16 as f64),
+                4 | 13 => self.u32(e.value_off + i * 4).map(f64::from),
+                9 => self.u32(e.value_off + i * 4).map(|v| v as i32 as f64),
+                5 | 10 => {
+                    let (n, d) = (
+                        self.u32(e.value_off + i * 8)?,
+                        self.u32(e.value_off + i * 8 + 4)?,
+                    );
+                    let (n, d) = if e.typ == 10 {
+                        (n as i32 as f64, d as i32 as f64)
+                    } else {
+                        (n as f64, d as f64)
+                    };
+                    (d != 0.0).then(|| n / d)
+                }
+                11 => self
+                    .u32(e.value_off + i * 4)
+                    .map(|v| f32::from_bits(v) as f64),
+                12 => {
+                    let (a, b) = (
+                        self.u32(e.value_off + i * 8)? as u64,
+                        self.u32(e.value_off + i * 8 + 4)? as u64,
+                    );
+                    let bits = if self.le {
+                        (b << 32) | a
+                    } else {
+                        (a << 32) | b
+                    };
+                    Some(f64::from_bits(bits))
+                }
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// 子 IFD 指针（Exif 0x8769 / GPS 0x8825）
+    pub(crate) fn pointer(&self, ifd: usize, tag: u16) -> Option<usize> {
+        let (entries, _) = self.ifd(ifd)?;
+        self.value(&entries, tag).map(|v| v as usize)
+    }
+}
+
+fn tiff_previews(tiff: &Tiff) -> Vec<Preview> {
+    let mut found = Vec::new();
+    let mut queue: Vec<usize> = tiff.first_ifd().into_iter().collect();
+    let mut visited = HashSet::new();
+    while let Some(off) = queue.pop() {
+        if off == 0 || visited.len() >= MAX_IFDS || !visited.insert(off) {
+            continue;
+        }
+        let Some((entries, next)) = tiff.ifd(off) else {
+            continue;
+        };
+        queue.push(next);
+        if let Some(e) = entries.iter().find(|e| e.tag == 0x014A) {
+            queue.extend(tiff.values(e).into_iter().map(|v| v as usize));
+        }
+        let mut ranges = Vec::new();
+        if let (Some(o), Some(l)) = (tiff.value(&entries, 0x0201), tiff.value(&entries, 0x0202)) {
+            ranges.push((o as usize, l as usize));
+        }
+        if matches!(tiff.value(&entries, 0x0103), Some(6 | 7)) {
+            let offsets = entries.iter().find(|e| e.tag == 0x0111);
+            let counts = entries.iter().find(|e| e.tag == 0x0117);
+            if let (Some(o), Some(c)) = (offsets, counts) {
+                if let ([o], [c]) = (&tiff.values(o)[..], &tiff.values(c)[..]) {
+                    ranges.push((*o as usize, *c as usize));
+                }
+            }
+        }
+        // RW2：JpgFromRaw 以 UNDEFINED 值直接内嵌整个 JPEG
+        if let Some(e) = entries.iter().find(|e| e.tag == 0x002E && e.value_len > 4) {
+            ranges.push((e.value_off, e.value_len));
+        }
+        found.extend(
+            ranges
+                .into_iter()
+                .filter_map(|(o, l)| preview_at(tiff.data, o, l)),
+        );
+    }
+    found
+}
+
+/// 校验 `data[offset..offset+len]` 为可用 JPEG 并读出尺寸
+fn preview_at(data: &[u8], offset: usize, len: usize) -> Option<Preview> {
+    let slice = data.get(offset..offset.checked_add(len)?)?;
+    let (len, width, height) = jpeg_info(slice)?;
+    Some(Preview {
+        offset,
+        len,
+        width,
+        height,
+    })
+}
+
+// ---------------------------------------------------------------- EXIF 重建
+
+/// IFD0 保留的标签：描述 / 厂商 / 机型 / 方向 / 分辨率 / 软件 / 时间 / 作者 / 版权
+const IFD0_TAGS: [u16; 11] = [
+    0x010E, 0x010F, 0x0110, 0x0112, 0x011A, 0x011B, 0x0128, 0x0131, 0x0132, 0x013B, 0x8298,
+];
+/// Exif IFD 丢弃的标签：MakerNote（内部偏移随原文件失效）、Interop 指针
+const EXIF_DROP_TAGS: [u16; 2] = [0x927C, 0xA005];
+
+struct OutEntry<'a> {
+    tag: u16,
+    typ: u16,
+    count: u32,
+    value: &'a [u8],
+}
+
+fn collect<'a>(tiff: &Tiff<'a>, ifd: usize, keep: impl Fn(u16) -> bool) -> Vec<OutEntry<'a>> {
+    let Some((entries, _)) = tiff.ifd(ifd) else {
+        return Vec::new();
+    };
+    let mut out: Vec<OutEntry> = entries
+        .iter()
+        .filter(|e| keep(e.tag) && !matches!(e.tag, 0x8769 | 0x8825))
+        .map(|e| OutEntry {
+            tag: e.tag,
+            typ: e.typ,
+            count: e.count,
+            value: &tiff.data[e.value_off..e.value_off + e.value_len],
+        })
+        .collect();
+    out.sort_by_key(|e| e.tag);
+    out
+}
+
+/// 按源字节序写出 IFD0 (+ Exif IFD + GPS IFD)；各源须同字节序（值字节原样拷贝）
+fn build_exif(
+    ifd0: (&Tiff, usize),
+    exif: Option<(&Tiff, usize)>,
+    gps: Option<(&Tiff, usize)>,
+) -> Option<Vec<u8>> {
+    let le = ifd0.0.le;
+    let root = collect(ifd0.0, ifd0.1, |t| IFD0_TAGS.contains(&t));
+    let exif = exif
+        .filter(|(t, _)| t.le == le)
+        .map(|(t, o)| collect(t, o, |tag| !EXIF_DROP_TAGS.contains(&tag)))
+        .filter(|e| !e.is_empty());
+    let gps = gps
+        .filter(|(t, _)| t.le == le)
+        .map(|(t, o)| collect(t, o, |_| true))
+        .filter(|e| !e.is_empty());
+    if root.is_empty() && exif.is_none() {
+        return None;
+    }
+
+    let mut out = Vec::new();
+    out.extend_from_slice(if le { b"II" } else { b"MM" });
+    put_u16(&mut out, 42, le);
+    put_u32(&mut out, 8, le);
+    let pointers: Vec<u16> = [(0x8769, exif.is_some()), (0x8825, gps.is_some())]
+        .into_iter()
+        .filter_map(|(tag, present)| present.then_some(tag))
+        .collect();
+    let slots = write_ifd(&mut out, &root, &pointers, le);
+    for (slot, entries) in slots.into_iter().zip([exif, gps].into_iter().flatten()) {
+        let at = out.len() as u32;
+        out[slot..slot + 4].copy_from_slice(&if le {
+            at.to_le_bytes()
+        } else {
+            at.to_be_bytes()
+        });
+        write_ifd(&mut out, &entries, &[], le);
+    }
+    Some(out)
+}
+
+/// 写一个 IFD（条目按标签升序，指针条目值先占位）+ 其外置数据，返回各指针值的位置
+fn write_ifd(out: &mut Vec<u8>, entries: &[OutEntry], pointers: &[u16], le: bool) -> Vec<usize> {
+    let n = entries.len() + pointers.len();
+    let mut data_off = out.len() + 2 + n * 12 + 4;
+    let mut data = Vec::new();
+    let mut rows: Vec<(u16, u16, u32, [u8; 4], bool)> = Vec::with_capacity(n);
+    for e in entries {
+        let mut inline = [0u8; 4];
+        if e.value.len() <= 4 {
+            inline[..e.value.len()].copy_from_slice(e.value);
+        } else {
+            let at = data_off as u32;
+            inline = if le {
+                at.to_le_bytes()
+            } else {
+                at.to_be_bytes()
+            };
+            data.extend_from_slice(e.value);
+            if e.value.len() % 2 == 1 {
+                data.push(0);
+            }
+            data_off += e.value.len().div_ceil(2) * 2;
+        }
+        rows.push((e.tag, e.typ, e.count, inline, false));
+    }
+    rows.extend(pointers.iter().map(|&tag| (tag, 4, 1, [0u8; 4], true)));
+    rows.sort_by_key(|r| r.0);
+
+    put_u16(out, n as u16, le);
+    let mut slots = Vec::new();
+    for (tag, typ, count, inline, pointer) in rows {
+        put_u16(out, tag, le);
+        put_u16(out, typ, le);
+        put_u32(out, count, le);
+        if pointer {
+            slots.push(out.len());
+        }
+        out.extend_from_slice(&inline);
+    }
+    put_u32(out, 0, le);
+    out.extend_from_slice(&data);
+    slots
+}
+
+fn put_u16(out: &mut Vec<u8>, v: u16, le: bool) {
+    out.extend_from_slice(&if le { v.to_le_bytes() } else { v.to_be_bytes() });
+}
+
+fn put_u32(out: &mut Vec<u8>, v: u32, le: bool) {
+    out.extend_from_slice(&if le { v.to_le_bytes() } else { v.to_be_bytes() });
+}
+
+// ---------------------------------------------------------------- CR3 / RAF
+
+fn is_cr3(data: &[u8]) -> bool {
+    data.get(4..12) == Some(b"ftypcrx ".as_slice())
+}
+
+/// ISO-BMFF 盒子：(类型, 盒内数据起点, 盒内数据)
+pub(crate) fn boxes(data: &[u8]) -> Vec<([u8; 4], usize, &[u8])> {
+    let mut out = Vec::new();
+    let mut pos = 0usize;
+    while pos + 8 <= data.len() {
+        let size32 = u32::from_be_bytes(data[pos..pos + 4].try_into().unwrap()) as usize;
+        let kind: [u8; 4] = data[pos + 4..pos + 8].try_into().unwrap();
+        let (header, size) = match size32 {
+            0 => (8, data.len() - pos),
+            1 => match data.get(pos + 8..pos + 16) {
+                Some(b) => (16, u64::from_be_bytes(b.try_into().unwrap()) as usize),
+                None => break,
+            },
+            s => (8, s),
+        };
+        if size < header || pos.checked_add(size).map_or(true, |end| end > data.len()) {
+            break;
+        }
+        out.push((kind, pos + header, &data[pos + header..pos + size]));
+        pos += size;
+    }
+    out
+}
+
+pub(crate) fn child<'a>(data: &'a [u8], kind: &[u8; 4]) -> Option<(usize, &'a [u8])> {
+    boxes(data)
+        .into_iter()
+        .find(|(k, _, _)| k == kind)
+        .map(|(_, start, d)| (start, d))
+}
+
+/// 指定 UUID 的盒子（返回 UUID 之后的内容）
+fn uuid_box<'a>(data: &'a [u8], uuid: &[u8; 16]) -> Option<(usize, &'a [u8])> {
+    boxes(data)
+        .into_iter()
+        .find(|(k, _, d)| k == b"uuid" && d.starts_with(uuid))
+        .map(|(_, start, d)| (start + 16, &d[16..]))
+}
+
+fn cr3_previews(data: &[u8]) -> Vec<Preview> {
+    let mut found = Vec::new();
+    // 首轨：整幅 JPEG（stbl 中的 co64/stco 偏移 + stsz 大小）
+    if let Some((moov_start, moov)) = child(data, b"moov") {
+        let stbl = child(moov, b"trak")
+            .and_then(|(_, t)| child(t, b"mdia"))
+            .and_then(|(_, m)| child(m, b"minf"))
+            .and_then(|(_, m)| child(m, b"stbl"));
+        if let Some((_, stbl)) = stbl {
+            let offset = child(stbl, b"co64")
+                .and_then(|(_, b)| b.get(8..16))
+                .map(|b| u64::from_be_bytes(b.try_into().unwrap()) as usize)
+                .or_else(|| {
+                    child(stbl, b"stco")
+                        .and_then(|(_, b)| b.get(8..12))
+                        .map(|b| u32::from_be_bytes(b.try_into().unwrap()) as usize)
+                });
+            let size = child(stbl, b"stsz").and_then(|(_, b)| {
+                let fixed = u32::from_be_bytes(b.get(4..8)?.try_into().ok()?);
+                let size = if fixed != 0 {
+                    fixed
+                } else {
+                    u32::from_be_bytes(b.get(12..16)?.try_into().ok()?)
+                };
+                Some(size as usize)
+            });
+            if let (Some(o), Some(s)) = (offset, size) {
+                found.extend(preview_at(data, o, s));
+            }
+        }
+        if let Some((start, canon)) = uuid_box(moov, &CR3_CANON_UUID) {
+            if let Some((s, thmb)) = child(canon, b"THMB") {
+                found.extend(scan_jpegs(thmb, moov_start + start + s));
+            }
+        }
+    }
+    if let Some((start, prvw_uuid)) = uuid_box(data, &CR3_PREVIEW_UUID) {
+        // uuid 内容：8 字节头 + PRVW 盒
+        if let Some(rest) = prvw_uuid.get(8..) {
+            if let Some((s, prvw)) = child(rest, b"PRVW") {
+                found.extend(scan_jpegs(prvw, start + 8 + s));
+            }
+        }
+    }
+    found
+}
+
+fn cr3_exif(data: &[u8]) -> Option<Vec<u8>> {
+    let (_, moov) = child(data, b"moov")?;
+    let (_, canon) = uuid_box(moov, &CR3_CANON_UUID)?;
+    let cmt = |kind: &[u8; 4]| child(canon, kind).and_then(|(_, d)| Tiff::new(d));
+    let ifd0 = cmt(b"CMT1")?;
+    let exif = cmt(b"CMT2");
+    let gps = cmt(b"CMT4");
+    build_exif(
+        (&ifd0, ifd0.first_ifd()?),
+        exif.as_ref().and_then(|t| Some((t, t.first_ifd()?))),
+        gps.as_ref().and_then(|t| Some((t, t.first_ifd()?))),
+    )
+}
+
+/// RAF：文件头 84/88 字节处为 JPEG 偏移 / 长度（大端）
+fn raf_previews(data: &[u8]) -> Vec<Preview> {
+    let be = |off: usize| {
+        data.get(off..off + 4)
+            .map(|b| u32::from_be_bytes(b.try_into().unwrap()) as usize)
+    };
+    match (be(84), be(88)) {
+        (Some(o), Some(l)) => preview_at(data, o, l).into_iter().collect(),
+        _ => Vec::new(),
+    }
+}
+
+// ---------------------------------------------------------------- JPEG
+
+/// 全文扫描 SOI（FF D8 FF），逐个校验；`base` 为 `data` 在文件中的起点
+fn scan_jpegs(data: &[u8], base: usize) -> Vec<Preview> {
+    let mut found = Vec::new();
+    let mut pos = 0usize;
+    while pos + 3 <= data.len() {
+        if data[pos] == 0xFF && data[pos + 1] == 0xD8 && data[pos + 2] == 0xFF {
+            if let Some((len, width, height)) = jpeg_info(&data[pos..]) {
+                found.push(Preview {
+                    offset: base + pos,
+                    len,
+                    width,
+                    height,
+                });
+                pos += len;
+                continue;
+            }
+        }
+        pos += 1;
+    }
+    found
+}
+
+/// 按段结构遍历 JPEG：返回 (到 EOI 为止的长度, 宽, 高)。
+/// 仅接受基线/扩展/渐进（SOF0/1/2）；无损等其他 SOF 多为原始传感器数据，图像库也无法解码
+fn jpeg_info(data: &[u8]) -> Option<(usize, u32, u32)> {
+    if data.get(..2)? != [0xFF, 0xD8] {
+        return None;
+    }
+    let mut dims = None;
+    let mut pos = 2usize;
+    loop {
+        if *data.get(pos)? != 0xFF {
+            return None;
+        }
+        while *data.get(pos + 1)? == 0xFF {
+            pos += 1;
+        }
+        let marker = data[pos + 1];
+        pos += 2;
+        match marker {
+            0xD9 => {
+                let (w, h) = dims?;
+                return Some((pos, w, h));
+            }
+            0xD0..=0xD7 | 0x01 => continue,
+            _ => {}
+        }
+        let seg_len = u16::from_be_bytes(data.get(pos..pos + 2)?.try_into().ok()?) as usize;
+        if seg_len < 2 {
+            return None;
+        }
+        match marker {
+            0xC0..=0xC2 => {
+                let h = u16::from_be_bytes(data.get(pos + 3..pos + 5)?.try_into().ok()?);
+                let w = u16::from_be_bytes(data.get(pos + 5..pos + 7)?.try_into().ok()?);
+                if w == 0 || h == 0 {
+                    return None;
+                }
+                dims = Some((w as u32, h as u32));
+            }
+            0xC3 | 0xC5..=0xC7 | 0xC9..=0xCB | 0xCD..=0xCF => return None,
+            _ => {}
+        }
+        pos += seg_len;
+        if marker == 0xDA {
+            // 熵编码数据：跳过填充字节 FF00 与 RSTn，停在下一个真正的标记上
+            loop {
+                let ff = pos + data.get(pos..)?.iter().position(|&b| b == 0xFF)?;
+                match *data.get(ff + 1)? {
+                    0x00 | 0xD0..=0xD7 | 0xFF => pos = ff + 1,
+                    _ => {
+                        pos = ff;
+                        break;
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// JPEG 的 APP1 EXIF 内容（不含 "Exif\0\0"）
+fn jpeg_app1_exif(jpeg: &[u8]) -> Option<&[u8]> {
+    let mut pos = 2usize;
+    while pos + 4 <= jpeg.len() && jpeg[pos] == 0xFF {
+        let marker = jpeg[pos + 1];
+        if marker == 0xDA || marker == 0xD9 {
+            break;
+        }
+        let len = u16::from_be_bytes([jpeg[pos + 2], jpeg[pos + 3]]) as usize;
+        let body = jpeg.get(pos + 4..pos + 2 + len)?;
+        if marker == 0xE1 && body.starts_with(b"Exif\0\0") {
+            return Some(&body[6..]);
+        }
+        pos += 2 + len;
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn jpeg(w: u32, h: u32) -> Vec<u8> {
+        let img = image::RgbImage::from_pixel(w, h, image::Rgb([40, 120, 200]));
+        let mut out = std::io::Cursor::new(Vec::new());
+        image::DynamicImage::ImageRgb8(img)
+            .write_to(&mut out, image::ImageFormat::Jpeg)
+            .unwrap();
+        out.into_inner()
+    }
+
+    /// 小端 TIFF：IFD0（Make/Orientation/缩略图 0x0201/0x0202/Exif 指针）→ SubIFD（大预览，JPEG 压缩条带）
+    fn synthetic_raw(thumb: &[u8], big: &[u8]) -> Vec<u8> {
+        let mut d = Vec::new();
+        d.extend_from_slice(b"II*\0");
+        d.extend_from_slice(&8u32.to_le_bytes());
+        let ifd0_entries = 6u16;
+        let ifd0_len = 2 + 12 * ifd0_entries as usize + 4;
+        let exif_at = 8 + ifd0_len;
+        let exif_len = 2 + 12 + 4;
+        let sub_at = exif_at + exif_len;
+        let sub_len = 2 + 12 * 3 + 4;
+        let make_at = sub_at + sub_len;
+        let thumb_at = make_at + 8;
+        let big_at = thumb_at + thumb.len();
+        let entry = |d: &mut Vec<u8>, tag: u16, typ: u16, count: u32, value: u32| {
+            d.extend_from_slice(&tag.to_le_bytes());
+            d.extend_from_slice(&typ.to_le_bytes());
+            d.extend_from_slice(&count.to_le_bytes());
+            d.extend_from_slice(&value.to_le_bytes());
+        };
+        d.extend_from_slice(&ifd0_entries.to_le_bytes());
+        entry(&mut d, 0x010F, 2, 8, make_at as u32);
+        entry(&mut d, 0x0112, 3, 1, 6);
+        entry(&mut d, 0x014A, 4, 1, sub_at as u32);
+        entry(&mut d, 0x0201, 4, 1, thumb_at as u32);
+        entry(&mut d, 0x0202, 4, 1, thumb.len() as u32);
+        entry(&mut d, 0x8769, 4, 1, exif_at as u32);
+        d.extend_from_slice(&0u32.to_le_bytes());
+        d.extend_from_slice(&1u16.to_le_bytes());
+        entry(&mut d, 0x8827, 3, 1, 400);
+        d.extend_from_slice(&0u32.to_le_bytes());
+        d.extend_from_slice(&3u16.to_le_bytes());
+        entry(&mut d, 0x0103, 3, 1, 7);
+        entry(&mut d, 0x0111, 4, 1, big_at as u32);
+        entry(&mut d, 0x0117, 4, 1, big.len() as u32);
+        d.extend_from_slice(&0u32.to_le_bytes());
+        d.extend_from_slice(b"XTAPCAM\0");
+        d.extend_from_slice(thumb);
+        d.extend_from_slice(big);
+        d
+    }
+
+    #[test]
+    fn test_tiff_raw_largest_preview_and_exif() {
+        let (thumb, big) = (jpeg(160, 120), jpeg(1200, 800));
+        let raw = synthetic_raw(&thumb, &big);
+        let previews = find_previews(&raw);
+        assert_eq!(previews.len(), 2);
+        assert_eq!((previews[0].width, previews[0].height), (1200, 800));
+        assert_eq!(previews[0].bytes(&raw), &big[..]);
+        assert_eq!(decode_preview(&raw).unwrap().width(), 1200);
+
+        // 重建 EXIF：Make / Orientation 保留，SubIFD / 缩略图指针丢弃，Exif IFD 带 ISO
+        let exif = raw_exif(&raw).unwrap();
+        let tiff = Tiff::new(&exif).unwrap();
+        let (entries, _) = tiff.ifd(tiff.first_ifd().unwrap()).unwrap();
+        let tags: Vec<u16> = entries.iter().map(|e| e.tag).collect();
+        assert_eq!(tags, vec![0x010F, 0x0112, 0x8769]);
+        assert_eq!(tiff.value(&entries, 0x0112), Some(6));
+        let make = entries[0];
+        assert_eq!(
+            &exif[make.value_off..make.value_off + make.value_len],
+            b"XTAPCAM\0"
+        );
+        let (exif_entries, _) = tiff.ifd(tiff.pointer(8, 0x8769).unwrap()).unwrap();
+        assert_eq!(tiff.value(&exif_entries, 0x8827), Some(400));
+    }
+
+    #[test]
+    fn test_jpeg_info_rejects_lossless_and_scans_fallback() {
+        let j = jpeg(64, 48);
+        assert_eq!(jpeg_info(&j), Some((j.len(), 64, 48)));
+        // SOF3（无损 JPEG，DNG/CR2 原始数据常见）不当预览
+        let lossless = [
+            0xFF, 0xD8, 0xFF, 0xC3, 0x00, 0x0B, 8, 0, 16, 0, 16, 1, 1, 0x11, 0,
+        ];
+        assert_eq!(jpeg_info(&lossless), None);
+        // 非 TIFF 容器：全文扫描兜底
+        let mut blob = b"ORF-like header....".to_vec();
+        blob.extend_from_slice(&j);
+        blob.extend_from_slice(&[0u8; 32]);
+        let found = find_previews(&blob);
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].offset, 19);
+        assert!(is_raw_extension("NEF") && !is_raw_extension("jpg"));
+    }
+}
@@ -13,12 +13,25 @@ use std::io::Read;
 use std::path::{Path, PathBuf};
 
 use crate::cli::{
-    apply_platform_preset, build_envelope, Cli, CliQualityMode, FileResult, JsonInput,
-    PerceptualMetricsOut, StepTimings,
+    apply_platform_preset, benchmark_regressions, build_envelope, fanout_target_names,
+    split_platforms, tiff_page_warnings, BenchmarkEntry, BenchmarkReport, Cli, CliFanoutLayout,
+    CliQualityMode, FanoutOutput, FileResult, JsonEnvelope, JsonInput, PerceptualMetricsOut,
+    StepTimings,
 };
+use xtap_compress::budget::{
+    allocate_budgets, rebalance_budgets, BatchBudgetReport, MAX_REBALANCE_PASSES,
+};
+use xtap_compress::contact_sheet::{self, ContactSheetConfig, SheetEntry};
+use xtap_compress::crop::CropRect;
+use xtap_compress::icon::IconSet;
 use xtap_compress::perceptual::{FocusMode, PerceptualMetrics, PerceptualOptions, QuantMode};
+use xtap_compress::platform_sim::{
+    builtin_platform_model, simulate_against_original, PlatformModel, PlatformSimResult, SimRegion,
+};
+use xtap_compress::srcset::SrcsetManifest;
 use xtap_compress::{
-    app_config_to_process_config, AppConfig, ColorSpace, OutputFormat, ProcessMode, Processor,
+    app_config_to_process_config, AppConfig, ColorSpace, OutputFormat, ProcessConfig, ProcessMode,
+    Processor, Source,
 };
 
 // ============================================================================
@@ -53,6 +66,14 @@ pub(crate) fn load_config() -> Result<AppConfig> {
         config.quality_mode = "max".to_string();
     }
     config.config_version = 2;
+    // v4.5：无效的外部解码器条目丢弃（不影响其余配置加载）
+    config.external_decoders.retain(|d| match d.validate() {
+        Ok(()) => true,
+        Err(e) => {
+            eprintln!("[WARN] 忽略无效的外部解码器配置: {}", e);
+            false
+        }
+    });
     if did_migrate {
         if let Err(e) = save_config(&config) {
             eprintln!(
@@ -90,7 +111,7 @@ fn perceptual_options_from_cli(cli: &Cli) -> Option<PerceptualOptions> {
         focus_mode: cli.focus_mode.into(),
         quant_mode: cli.quant_mode.into(),
         quality_ceil: cli.quality_ceil.unwrap_or(95),
-        platform: cli.platform.map(|p| p.as_str().to_string()),
+        platform: cli.platform.as_ref().map(|p| p.to_lowercase()),
         ..Default::default()
     })
 }
@@ -161,6 +182,7 @@ where
 }
 
 pub(crate) fn run_cli(cli: &Cli) -> Result<()> {
+    install_external_decoders(&cli.to_app_config());
     // 先收集文件列表(目录默认递归;记录被拒路径用于提示)
     let mut files = Vec::new();
     let mut rejected: Vec<(String, String)> = Vec::new();
@@ -230,6 +252,11 @@ pub(crate) fn run_cli(cli: &Cli) -> Result<()> {
         }
     }
 
+    // 基准模式且完全没给输入：用内置生成语料离线跑（CI 回归门禁无需外部图片）
+    if cli.benchmark_mode() && cli.input.is_empty() && cli.positional.is_empty() {
+        files = generate_benchmark_corpus()?;
+    }
+
     // 普通 CLI 模式：输入无效时给出明确提示并退出(非 0),避免 AI/脚本误判为成功
     if files.is_empty() && (!cli.input.is_empty() || !cli.positional.is_empty()) {
         eprintln!("\n⚠️ 没有处理任何文件。你给出的路径均无效：");
@@ -287,7 +314,7 @@ pub(crate) fn run_cli(cli: &Cli) -> Result<()> {
     }
 
     // A/B 对照 / 基准对比模式：旧路径(v4.1.0) vs 新感知路径 双输出 + 对比表
-    if cli.ab || cli.benchmark {
+    if cli.ab || cli.benchmark_mode() {
         return run_compare_mode(cli, &files);
     }
 
@@ -317,6 +344,8 @@ pub(crate) fn run_cli(cli: &Cli) -> Result<()> {
     } else {
         None
     };
+    // v4.5：多预设扇出（--platform a,b / --platform a --usage-mode archive），非扇出为空
+    let targets = cli_fanout_targets(cli, &effective_output_dir, &structure_base);
     let mut process_config = app_config_to_process_config(&app_config, effective_output_dir);
     process_config.structure_base = structure_base;
     process_config.perceptual = perceptual_options_from_cli(cli);
@@ -327,11 +356,19 @@ pub(crate) fn run_cli(cli: &Cli) -> Result<()> {
     let force = cli.force;
     let overwrite = cli.overwrite;
     let jsonl = cli.jsonl;
-    let results: Vec<FileResult> = map_bucketed(
+    let sim_model = platform_model_from_cli(cli);
+    let (results, budget_report) = run_batch(
+        &processor,
         &files,
-        |f| is_large_image(f),
-        |file| {
-            let r = process_or_passthrough(&processor, file, force, overwrite, passthrough);
+        |f| f.as_path(),
+        cli.batch_budget_kb,
+        |processor, file, rerun| {
+            let mut r = if targets.is_empty() {
+                process_or_passthrough(processor, file, force || rerun, overwrite, passthrough)
+            } else {
+                process_fanout(&targets, file, force || rerun, overwrite, passthrough)
+            };
+            attach_platform_sim(&mut r, sim_model.as_ref(), processor, file);
             if jsonl {
                 emit_jsonl(&r);
             }
@@ -342,15 +379,63 @@ pub(crate) fn run_cli(cli: &Cli) -> Result<()> {
     let completed = results.iter().filter(|r| r.success).count();
     let failed = results.len() - completed;
 
+    for w in tiff_page_warnings(&results) {
+        eprintln!("[WARN] {}", w);
+    }
     if !quiet && !jsonl {
         for r in &results {
             if r.success {
                 println!("  ✅ Success: {}", r.output.clone().unwrap_or_default());
+                if let Some(pages) = r.tiff_pages.as_ref().filter(|p| p.outputs.len() > 1) {
+                    for o in &pages.outputs[1..] {
+                        println!("     ↳ [源第 {} 页] {}", o.page, o.output.display());
+                    }
+                }
+                for o in r.outputs.iter().flatten().skip(1) {
+                    println!(
+                        "     ↳ [{}] {}",
+                        o.target,
+                        o.result.output.clone().unwrap_or_default()
+                    );
+                }
+                if let Some(ps) = &r.platform_sim {
+                    println!(
+                        "     📱 过 {} 后：SSIM {:.4} / PSNR {:.2} dB（{}，{}x{}，{:.1} KB）",
+                        ps.platform,
+                        ps.ssim_vs_original,
+                        ps.psnr_vs_original,
+                        if ps.recompressed {
+                            "被二压"
+                        } else {
+                            "免二压"
+                        },
+                        ps.width,
+                        ps.height,
+                        ps.bytes as f64 / 1024.0
+                    );
+                    if let (Some(n), Some(o)) = (ps.outputs_simulated, &ps.output) {
+                        println!("        共模拟 {} 份输出，以上为最差的一份：{}", n, o);
+                    }
+                }
             } else {
                 println!("  ❌ Failed: {}", r.error.clone().unwrap_or_default());
             }
         }
         println!("\n✅ 处理完成！成功: {}, 失败: {}", completed, failed);
+        if let Some(report) = &budget_report {
+            print_batch_budget(report);
+        }
+    }
+    if let Some(cs) = cli.contact_sheet_config() {
+        match write_contact_sheet(&cs, &results) {
+            Ok(pages) if !quiet => {
+                for p in &pages {
+                    println!("🗂  联系表已输出: {}", p.display());
+                }
+            }
+            Ok(_) => {}
+            Err(e) => eprintln!("⚠️ 联系表生成失败: {}", e),
+        }
     }
 
     if failed > 0 {
@@ -360,6 +445,204 @@ pub(crate) fn run_cli(cli: &Cli) -> Result<()> {
     Ok(())
 }
 
+/// v4.5：CLI 两条通路的多预设扇出目标（非扇出返回空）
+fn cli_fanout_targets(
+    cli: &Cli,
+    output_dir: &Option<PathBuf>,
+    structure_base: &Option<PathBuf>,
+) -> Vec<FanoutTarget> {
+    cli.fanout_targets()
+        .unwrap_or_default()
+        .into_iter()
+        .map(|(name, cfg)| {
+            let mut pc = app_config_to_process_config(&cfg, output_dir.clone());
+            pc.structure_base = structure_base.clone();
+            pc.perceptual = perceptual_options_from_cli(cli);
+            FanoutTarget {
+                processor: Processor::new(fanout_process_config(pc, &name, cli.fanout_layout)),
+                name,
+            }
+        })
+        .collect()
+}
+
+/// v4.5：批量执行（三条通路共用）。给了批量总预算时：
+/// 1. 按 `Processor::budget_weight` 权重切分总预算，每个文件用自己的份额处理；
+/// 2. 余量分给顶到份额的文件强制重压（最多 `MAX_REBALANCE_PASSES` 轮，JSONL 会为重压文件再输出一行）。
+///
+/// 无法计算权重的条目（不存在/不支持/损坏）不分份额、照常处理，其成功输出体积计入合计。
+fn run_batch<T, P, F>(
+    processor: &Processor,
+    items: &[T],
+    path_of: P,
+    batch_budget_kb: Option<u32>,
+    run: F,
+) -> (Vec<FileResult>, Option<BatchBudgetReport>)
+where
+    T: Sync,
+    P: Fn(&T) -> &Path + Sync,
+    F: Fn(&Processor, &T, bool) -> FileResult + Sync,
+{
+    let Some(budget_kb) = batch_budget_kb else {
+        let results = map_bucketed(
+            items,
+            |it| is_large_image(path_of(it)),
+            |it| run(processor, it, false),
+        );
+        return (results, None);
+    };
+
+    let indices: Vec<usize> = (0..items.len()).collect();
+    let big = |&i: &usize| is_large_image(path_of(&items[i]));
+    let weights: Vec<Option<f64>> = map_bucketed(&indices, big, |&i| {
+        let path = path_of(&items[i]);
+        if !path.is_file() || !is_supported_image(path) || is_system_hidden(path) {
+            return None;
+        }
+        processor.budget_weight(path).ok()
+    });
+    let budgeted: Vec<usize> = indices
+        .iter()
+        .copied()
+        .filter(|&i| weights[i].is_some())
+        .collect();
+    let budget_weights: Vec<f64> = budgeted
+        .iter()
+        .map(|&i| weights[i].unwrap_or(0.0))
+        .collect();
+    let mut budgets = allocate_budgets(budget_kb, &budget_weights);
+    let mut share: Vec<Option<u32>> = vec![None; items.len()];
+    for (k, &i) in budgeted.iter().enumerate() {
+        share[i] = Some(budgets[k]);
+    }
+    let process = |share: &[Option<u32>], i: usize, force: bool| match share[i] {
+        Some(kb) => run(&processor.with_file_budget_kb(kb), &items[i], force),
+        None => run(processor, &items[i], force),
+    };
+    let mut results = map_bucketed(&indices, big, |&i| process(&share, i, false));
+
+    let output_bytes = |r: &FileResult| {
+        if r.success {
+            r.compressed_size.unwrap_or(0)
+        } else {
+            0
+        }
+    };
+    let mut report = BatchBudgetReport {
+        budget_kb,
+        ..Default::default()
+    };
+    let mut rebalanced = vec![false; items.len()];
+    while report.rebalance_passes < MAX_REBALANCE_PASSES {
+        // 份额外文件（无权重）的体积先从总预算里扣掉
+        let fixed: u64 = indices
+            .iter()
+            .filter(|&&i| share[i].is_none())
+            .map(|&i| output_bytes(&results[i]))
+            .sum();
+        let available_kb = (budget_kb as u64 * 1024).saturating_sub(fixed) / 1024;
+        let actual: Vec<u64> = budgeted
+            .iter()
+            .map(|&i| output_bytes(&results[i]))
+            .collect();
+        let Some(next) = rebalance_budgets(available_kb as u32, &budgets, &actual, &budget_weights)
+        else {
+            break;
+        };
+        let rerun: Vec<usize> = budgeted
+            .iter()
+            .enumerate()
+            .filter(|&(k, _)| next[k] > budgets[k])
+            .map(|(_, &i)| i)
+            .collect();
+        if rerun.is_empty() {
+            break;
+        }
+        for (k, &i) in budgeted.iter().enumerate() {
+            share[i] = Some(next[k]);
+        }
+        budgets = next;
+        let redone = map_bucketed(&rerun, big, |&i| process(&share, i, true));
+        for (i, r) in rerun.iter().zip(redone) {
+            results[*i] = r;
+            rebalanced[*i] = true;
+        }
+        report.rebalance_passes += 1;
+    }
+
+    report.total_bytes = results.iter().map(output_bytes).sum();
+    report.fits = report.total_bytes <= budget_kb as u64 * 1024;
+    report.rebalanced_files = rebalanced.iter().filter(|&&b| b).count();
+    (results, Some(report))
+}
+
+/// 批量预算结果摘要（人类可读）
+fn print_batch_budget(report: &BatchBudgetReport) {
+    println!(
+        "📦 批量预算：合计 {:.1} KB / {} KB（{}；重平衡 {} 轮，重压 {} 个文件）",
+        report.total_bytes as f64 / 1024.0,
+        report.budget_kb,
+        if report.fits { "达标" } else { "超出" },
+        report.rebalance_passes,
+        report.rebalanced_files
+    );
+}
+
+/// 批量预算写入 JSON 信封；超出时追加告警
+fn attach_batch_budget(envelope: &mut JsonEnvelope, report: Option<BatchBudgetReport>) {
+    if let Some(r) = &report {
+        if !r.fits {
+            envelope.warnings.push(format!(
+                "批量预算未达标：合计 {:.1} KB > {} KB（最低质量仍超份额，或含 PNG/WebP 输出）",
+                r.total_bytes as f64 / 1024.0,
+                r.budget_kb
+            ));
+        }
+    }
+    envelope.data.batch_budget = report;
+}
+
+/// v4.5：按输入顺序把整批结果排成联系表（缩略图取压缩输出）
+fn write_contact_sheet(
+    config: &ContactSheetConfig,
+    results: &[FileResult],
+) -> Result<Vec<PathBuf>> {
+    let entries: Vec<SheetEntry> = results
+        .iter()
+        .map(|r| SheetEntry {
+            image: r.output.as_ref().map(PathBuf::from),
+            caption: Path::new(&r.input)
+                .file_name()
+                .map(|n| n.to_string_lossy().to_string())
+                .unwrap_or_else(|| r.input.clone()),
+            original_bytes: r.original_size,
+            compressed_bytes: r.compressed_size,
+            failed: !r.success,
+        })
+        .collect();
+    contact_sheet::render(config, &entries)
+}
+
+/// 联系表写入 JSON 信封；生成失败只记告警，不影响批次结果
+fn attach_contact_sheet(
+    envelope: &mut JsonEnvelope,
+    config: Option<&ContactSheetConfig>,
+    results: &[FileResult],
+) {
+    let Some(config) = config else {
+        return;
+    };
+    match write_contact_sheet(config, results) {
+        Ok(pages) => {
+            envelope.data.contact_sheets = pages
+                .iter()
+                .map(|p| p.display().to_string().replace('\\', "/"))
+                .collect();
+        }
+        Err(e) => envelope.warnings.push(format!("联系表生成失败: {}", e)),
+    }
+}
+
 /// CLI 输入无效时的通用「第一次用」避坑提示(给人类与 AI 看)
 fn print_cli_hint() {
     eprintln!("\n💡 正确用法(避坑):");
@@ -368,7 +651,7 @@ fn print_cli_hint() {
     eprintln!("        macOS/Linux:  ./图片高速压缩 \"~/图片/我的照片.jpg\"");
     eprintln!("      未加引号会被 shell 按空格拆成多段 → 找不到文件。");
     eprintln!("   2) 目录默认递归处理子目录;空目录会得到 0 个文件。");
-    eprintln!("   3) RAW(.cr3/.nef/.arw…) 仅在 macOS 支持;Windows 请先转 JPG/PNG。");
+    eprintln!("   3) RAW(.cr3/.nef/.arw…) 在 macOS 外取内嵌 JPEG 预览,尺寸以相机预览为准。");
     eprintln!("   4) 给 AI/脚本最稳:用 --json 从 stdin 传路径数组(绕开 shell 分词);");
     eprintln!("      或 Python subprocess 用列表传参(走 Unicode 命令行)。");
     eprintln!();
@@ -460,6 +743,8 @@ fn run_cli_with_json_output(cli: &Cli, files: &[PathBuf]) -> Result<()> {
     } else {
         None
     };
+    // v4.5：多预设扇出（--platform a,b / --platform a --usage-mode archive），非扇出为空
+    let targets = cli_fanout_targets(cli, &effective_output_dir, &structure_base);
     let mut process_config = app_config_to_process_config(&app_config, effective_output_dir);
     process_config.structure_base = structure_base;
     process_config.perceptual = perceptual_options_from_cli(cli);
@@ -469,12 +754,20 @@ fn run_cli_with_json_output(cli: &Cli, files: &[PathBuf]) -> Result<()> {
     let jsonl = cli.jsonl;
     let overwrite = cli.overwrite;
     let passthrough = cli.passthrough_unsupported;
+    let sim_model = platform_model_from_cli(cli);
     // 分桶调度（与 GUI 同款 OOM 护栏）：小图并行、大图串行；统一走 process_or_passthrough（隐藏跳过/透传收口）
-    let results: Vec<FileResult> = map_bucketed(
+    let (results, budget_report) = run_batch(
+        &processor,
         files,
-        |f| is_large_image(f),
-        |file| {
-            let result = process_or_passthrough(&processor, file, force, overwrite, passthrough);
+        |f| f.as_path(),
+        cli.batch_budget_kb,
+        |processor, file, rerun| {
+            let mut result = if targets.is_empty() {
+                process_or_passthrough(processor, file, force || rerun, overwrite, passthrough)
+            } else {
+                process_fanout(&targets, file, force || rerun, overwrite, passthrough)
+            };
+            attach_platform_sim(&mut result, sim_model.as_ref(), processor, file);
             if jsonl {
                 // 流式 JSONL：每处理完一个文件立即输出一行（println! 自带行级锁）
                 emit_jsonl(&result);
@@ -483,7 +776,9 @@ fn run_cli_with_json_output(cli: &Cli, files: &[PathBuf]) -> Result<()> {
         },
     );
 
-    let envelope = build_envelope(&results, start);
+    let mut envelope = build_envelope(&results, start);
+    attach_batch_budget(&mut envelope, budget_report);
+    attach_contact_sheet(&mut envelope, cli.contact_sheet_config().as_ref(), &results);
     println!("{}", serde_json::to_string(&envelope)?);
 
     // 有失败时退出码 1，让 AI 脚本能检测
@@ -494,6 +789,162 @@ fn run_cli_with_json_output(cli: &Cli, files: &[PathBuf]) -> Result<()> {
     Ok(())
 }
 
+/// 解析平台二压模型：内置模型为底，config（TOML，表名=平台名）按字段覆盖；
+/// 配置里新增的平台以 PlatformModel::default() 补齐缺省字段
+pub(crate) fn resolve_platform_model(name: &str, config: Option<&Path>) -> Result<PlatformModel> {
+    let name = name.to_lowercase();
+    let overrides = match config {
+        Some(path) => {
+            let text = fs::read_to_string(path)
+                .map_err(|e| anyhow::anyhow!("平台模型配置读取失败 {}: {}", path.display(), e))?;
+            let table: toml::Table = toml::from_str(&text)
+                .map_err(|e| anyhow::anyhow!("平台模型配置解析失败 {}: {}", path.display(), e))?;
+            table.get(&name).cloned()
+        }
+        None => None,
+    };
+    let base = match (builtin_platform_model(&name), &overrides) {
+        (Some(m), _) => m,
+        (None, Some(_)) => PlatformModel {
+            name: name.clone(),
+            ..Default::default()
+        },
+        (None, None) => {
+            return Err(anyhow::anyhow!(
+                "未知的模拟平台 '{}'（内置：{}；自定义平台请在 --simulate-config 中定义）",
+                name,
+                xtap_compress::platform_sim::BUILTIN_PLATFORMS.join(" / ")
+            ))
+        }
+    };
+    let Some(overrides) = overrides else {
+        return Ok(base);
+    };
+    let toml::Value::Table(overrides) = overrides else {
+        return Err(anyhow::anyhow!("平台模型配置 [{}] 必须是表", name));
+    };
+    let mut merged = toml::Table::try_from(&base)?;
+    merged.extend(overrides);
+    merged.insert("name".to_string(), toml::Value::String(name.clone()));
+    toml::Value::Table(merged)
+        .try_into()
+        .map_err(|e| anyhow::anyhow!("平台模型配置 [{}] 字段无效: {}", name, e))
+}
+
+/// CLI 通路：--simulate-platform 未给时返回 None；平台未知/配置无效属参数错误（退出码 2）
+fn platform_model_from_cli(cli: &Cli) -> Option<PlatformModel> {
+    let name = cli.simulate_platform.as_deref()?;
+    match resolve_platform_model(name, cli.simulate_config.as_deref()) {
+        Ok(m) => Some(m),
+        Err(e) => {
+            eprintln!("❌ {}", e);
+            std::process::exit(2);
+        }
+    }
+}
+
+/// 成功产出的结果追加「过平台后」指标（透传结果跳过）；模拟失败只告警，不影响成功/退出码。
+/// 原图按处理器的解码配置读入一次，按结果里的裁剪/补边/切片区域对齐后再比较。
+/// 切片、响应式变体与扇出目标逐份模拟，platform_sim 取最差的一份；多页 TIFF 只比较第 1 个输出页
+fn attach_platform_sim(
+    r: &mut FileResult,
+    model: Option<&PlatformModel>,
+    processor: &Processor,
+    input: &Path,
+) {
+    let Some(model) = model else {
+        return;
+    };
+    if r.passthrough == Some(true) || (!r.success && r.outputs.is_none()) {
+        return;
+    }
+    let orig = match processor.load_source(input) {
+        Ok(img) => img,
+        Err(e) => {
+            eprintln!("[WARN] 平台二压模拟失败 {}: {}", r.input, e);
+            return;
+        }
+    };
+    let Some(targets) = r.outputs.as_mut() else {
+        r.platform_sim = simulate_outputs(r, model, &orig);
+        return;
+    };
+    let mut sims = Vec::new();
+    for t in targets.iter_mut().filter(|t| t.result.success) {
+        t.result.platform_sim = simulate_outputs(&t.result, model, &orig);
+        if let Some(sim) = &t.result.platform_sim {
+            let output = sim.output.clone().or_else(|| t.result.output.clone());
+            let count = sim.outputs_simulated.unwrap_or(1);
+            sims.push((output.unwrap_or_default(), count, sim.clone()));
+        }
+    }
+    r.platform_sim = worst_platform_sim(sims);
+}
+
+/// 一份结果的全部输出逐份过平台（切片 / 分页、响应式变体，否则只有主输出）
+fn simulate_outputs(
+    r: &FileResult,
+    model: &PlatformModel,
+    orig: &image::DynamicImage,
+) -> Option<PlatformSimResult> {
+    let region = SimRegion {
+        crop: r.crop,
+        pad: r.pad,
+        tile: None,
+    };
+    let outputs: Vec<(SimRegion, String)> = match (&r.tiles, &r.srcset) {
+        (Some(tiles), _) => tiles
+            .iter()
+            .map(|t| {
+                let tile = CropRect {
+                    x: t.tile.x,
+                    y: t.tile.y,
+                    width: t.tile.width,
+                    height: t.tile.height,
+                };
+                let region = SimRegion {
+                    tile: Some(tile),
+                    ..region
+                };
+                (region, t.output.display().to_string())
+            })
+            .collect(),
+        (None, Some(set)) => set
+            .variants
+            .iter()
+            .map(|v| (region, v.output.display().to_string()))
+            .collect(),
+        (None, None) => r.output.iter().map(|o| (region, o.clone())).collect(),
+    };
+    let sims = outputs
+        .into_iter()
+        .filter_map(|(region, output)| {
+            match simulate_against_original(model, orig, &region, Path::new(&output)) {
+                Ok(sim) => Some((output.replace('\\', "/"), 1, sim)),
+                Err(e) => {
+                    eprintln!("[WARN] 平台二压模拟失败 {}: {}", output, e);
+                    None
+                }
+            }
+        })
+        .collect();
+    worst_platform_sim(sims)
+}
+
+/// 多份模拟结果取 SSIM 最低的一份，记下其输出路径与参与模拟的输出总数（只有一份时原样返回）
+fn worst_platform_sim(sims: Vec<(String, u32, PlatformSimResult)>) -> Option<PlatformSimResult> {
+    let total: u32 = sims.iter().map(|(_, n, _)| n).sum();
+    if sims.len() == 1 {
+        return sims.into_iter().next().map(|(_, _, sim)| sim);
+    }
+    let (output, _, mut worst) = sims
+        .into_iter()
+        .min_by(|a, b| a.2.ssim_vs_original.total_cmp(&b.2.ssim_vs_original))?;
+    worst.output.get_or_insert(output);
+    worst.outputs_simulated = Some(total);
+    Some(worst)
+}
+
 /// 由 Processor 的感知配置 + 实测指标 组装 JSON 输出用的感知指标块（perceptual=None 且无指标时返回 None）
 fn build_perceptual_out(
     processor: &Processor,
@@ -538,12 +989,24 @@ fn process_one_file(
     file: &Path,
     force: bool,
     overwrite: bool,
+) -> FileResult {
+    let source = processor.open_source(file);
+    process_one_file_with(processor, file, force, overwrite, &source)
+}
+
+/// 同 [`process_one_file`]，用已打开的源（续跑判定与处理共用页表；多预设扇出共享一次解码）
+fn process_one_file_with(
+    processor: &Processor,
+    file: &Path,
+    force: bool,
+    overwrite: bool,
+    source: &Source,
 ) -> FileResult {
     let file_str = file.display().to_string().replace('\\', "/");
 
     // 幂等续跑：输出已存在且未 force → 跳过（success=true, skipped=true）
     if !force && !overwrite {
-        let expected = processor.expected_output_path(file);
+        let expected = processor.expected_output_path_for(source);
         if expected.exists() {
             let original_size = fs::metadata(file).ok().map(|m| m.len());
             let compressed_size = fs::metadata(&expected).ok().map(|m| m.len());
@@ -564,15 +1027,28 @@ fn process_one_file(
                 skipped: Some(true),
                 passthrough: None,
                 perceptual: build_perceptual_out(processor, None),
+                crop: None,
+                pad: None,
+                tiles: None,
+                print: None,
+                srcset: None,
+                placeholder: None,
+                icons: None,
+                animation: None,
+                tiff_pages: None,
+                outputs: None,
+                platform_sim: None,
             };
         }
     }
 
     let original_size = fs::metadata(file).ok().map(|m| m.len());
-    let (success, output, error, percept) = match processor.process_image_with_metrics(file) {
-        Ok((output_path, m)) => (true, Some(output_path.display().to_string()), None, m),
-        Err(e) => (false, None, Some(e.to_string()), None),
+    let (report, error) = match processor.process_source_with_report(source) {
+        Ok(r) => (Some(r), None),
+        Err(e) => (None, Some(e.to_string())),
     };
+    let success = report.is_some();
+    let output = report.as_ref().map(|r| r.output.display().to_string());
     // v4.3.1：失败原因分类，便于 agent 决策重试还是跳过
     let error_type = if success {
         None
@@ -580,15 +1056,52 @@ fn process_one_file(
         Some(classify_error(error.as_deref().unwrap_or("")))
     };
 
-    let compressed_size = output
+    // v4.5：九宫格按全部切片合计体积
+    let tiles = report
         .as_ref()
-        .and_then(|p| fs::metadata(Path::new(p)).ok().map(|m| m.len()));
+        .filter(|r| !r.tiles.is_empty())
+        .map(|r| r.tiles.clone());
+    // v4.5：响应式图片组按全部变体合计体积；清单与 <picture> 片段写在图片旁
+    let srcset = report.as_ref().and_then(|r| r.srcset.clone());
+    if let Some(ref m) = srcset {
+        if let Err(e) = write_srcset_sidecars(m) {
+            eprintln!("[WARN] 响应式清单写入失败 {}: {}", m.manifest.display(), e);
+        }
+    }
+    // v4.5：图标组按全部文件合计体积；site.webmanifest 写在图标目录内
+    let icons = report.as_ref().and_then(|r| r.icons.clone());
+    if let Some(ref set) = icons {
+        if let Err(e) = write_webmanifest(set) {
+            eprintln!(
+                "[WARN] site.webmanifest 写入失败 {}: {}",
+                set.manifest.display(),
+                e
+            );
+        }
+    }
+    // v4.5：多页 TIFF 逐页输出时按全部页合计体积
+    let tiff_pages = report.as_ref().and_then(|r| r.tiff_pages.clone());
+    let page_bytes = tiff_pages
+        .as_ref()
+        .filter(|p| p.outputs.len() > 1)
+        .map(|p| p.outputs.iter().map(|o| o.bytes).sum());
+    let compressed_size = match (&tiles, &srcset, &icons) {
+        (Some(t), _, _) => Some(t.iter().map(|t| t.bytes).sum()),
+        (None, Some(m), _) => Some(m.variants.iter().map(|v| v.bytes).sum()),
+        (None, None, Some(set)) => Some(set.files.iter().map(|f| f.bytes).sum()),
+        (None, None, None) => page_bytes.or_else(|| {
+            output
+                .as_ref()
+                .and_then(|p| fs::metadata(Path::new(p)).ok().map(|m| m.len()))
+        }),
+    };
     let compression_ratio = match (original_size, compressed_size) {
         (Some(o), Some(c)) if c > 0 => Some(o as f64 / c as f64),
         _ => None,
     };
 
-    let perceptual = build_perceptual_out(processor, percept.as_ref());
+    let perceptual =
+        build_perceptual_out(processor, report.as_ref().and_then(|r| r.metrics.as_ref()));
 
     FileResult {
         input: file_str,
@@ -602,9 +1115,135 @@ fn process_one_file(
         skipped: None,
         passthrough: None,
         perceptual,
+        crop: report.as_ref().and_then(|r| r.crop),
+        pad: report.as_ref().and_then(|r| r.pad),
+        tiles,
+        print: report.as_ref().and_then(|r| r.print),
+        srcset,
+        placeholder: report.as_ref().and_then(|r| r.placeholder.clone()),
+        icons,
+        animation: report.as_ref().and_then(|r| r.animation.clone()),
+        tiff_pages,
+        outputs: None,
+        platform_sim: None,
+    }
+}
+
+/// v4.5：多预设扇出的一个目标（预设名 / archive + 对应处理器）
+pub(crate) struct FanoutTarget {
+    pub name: String,
+    pub processor: Processor,
+}
+
+/// v4.5：按扇出布局改写单个目标的输出命名——suffix 追加 `_<目标名>` 后缀，subdir 输出到 `<输出目录>/<目标名>/`。
+/// 保留原文件名（keep_original_name）时后缀不生效，一律按子目录区分
+fn fanout_process_config(
+    mut config: ProcessConfig,
+    name: &str,
+    layout: CliFanoutLayout,
+) -> ProcessConfig {
+    if layout == CliFanoutLayout::Subdir || config.keep_original_name {
+        config.output_dir = config.output_dir.map(|d| d.join(name));
+    } else {
+        config.output_suffix = Some(match config.output_suffix.as_deref() {
+            Some(s) if !s.is_empty() => format!("{}_{}", s, name),
+            _ => name.to_string(),
+        });
+    }
+    if let Some(p) = config.perceptual.as_mut() {
+        p.platform = (name != "archive").then(|| name.to_string());
+    }
+    config
+}
+
+/// v4.5：多预设扇出单文件入口：源图只解码一次，依次交给每个目标处理器，合并为一条结果。
+/// 隐藏文件/不支持格式按第一个目标走 [`process_or_passthrough`]；全部目标都可跳过时不解码
+fn process_fanout(
+    targets: &[FanoutTarget],
+    file: &Path,
+    force: bool,
+    overwrite: bool,
+    passthrough: bool,
+) -> FileResult {
+    if is_system_hidden(file) || !is_supported_image(file) {
+        return process_or_passthrough(&targets[0].processor, file, force, overwrite, passthrough);
+    }
+    // 页表只扫一次，续跑判定与各目标处理共用
+    let mut source = targets[0].processor.open_source(file);
+    let all_exist = !force
+        && !overwrite
+        && targets
+            .iter()
+            .all(|t| t.processor.expected_output_path_for(&source).exists());
+    // 解码失败（RAW / 损坏）交给各处理器自行解码，错误信息照常分类
+    if !all_exist {
+        let _ = targets[0].processor.decode_source(&mut source);
+    }
+    let outputs: Vec<FanoutOutput> = targets
+        .iter()
+        .map(|t| FanoutOutput {
+            target: t.name.clone(),
+            result: process_one_file_with(&t.processor, file, force, overwrite, &source),
+        })
+        .collect();
+    merge_fanout(file, outputs)
+}
+
+/// 扇出结果合并：全部成功才算成功；output 取第一份成功输出，compressed_size 为成功输出合计
+fn merge_fanout(file: &Path, outputs: Vec<FanoutOutput>) -> FileResult {
+    let ok: Vec<&FileResult> = outputs
+        .iter()
+        .map(|o| &o.result)
+        .filter(|r| r.success)
+        .collect();
+    let failed: Vec<&FanoutOutput> = outputs.iter().filter(|o| !o.result.success).collect();
+    let original_size = fs::metadata(file).ok().map(|m| m.len());
+    let compressed_size = (!ok.is_empty()).then(|| {
+        ok.iter()
+            .map(|r| r.compressed_size.unwrap_or(0))
+            .sum::<u64>()
+    });
+    let compression_ratio = match (original_size, compressed_size) {
+        (Some(o), Some(c)) if c > 0 => Some(o as f64 / c as f64),
+        _ => None,
+    };
+    FileResult {
+        input: file.display().to_string().replace('\\', "/"),
+        output: ok.first().and_then(|r| r.output.clone()),
+        success: failed.is_empty(),
+        error: (!failed.is_empty()).then(|| {
+            failed
+                .iter()
+                .map(|o| format!("[{}] {}", o.target, o.result.error.as_deref().unwrap_or("")))
+                .collect::<Vec<_>>()
+                .join("; ")
+        }),
+        error_type: failed.first().and_then(|o| o.result.error_type.clone()),
+        original_size,
+        compressed_size,
+        compression_ratio,
+        skipped: outputs
+            .iter()
+            .all(|o| o.result.skipped == Some(true))
+            .then_some(true),
+        outputs: Some(outputs),
+        ..Default::default()
     }
 }
 
+/// v4.5：写图标组的 site.webmanifest
+fn write_webmanifest(set: &IconSet) -> Result<()> {
+    fs::write(&set.manifest, serde_json::to_vec_pretty(&set.webmanifest)?)?;
+    Ok(())
+}
+
+/// v4.5：写响应式图片组清单（.srcset.json）与 <picture> 片段（.srcset.html）
+fn write_srcset_sidecars(manifest: &SrcsetManifest) -> Result<()> {
+    fs::write(&manifest.manifest, serde_json::to_vec_pretty(manifest)?)?;
+    fs::write(manifest.manifest.with_extension("html"), &manifest.html)?;
+    Ok(())
+}
+
 /// v4.3.1（修 D1）：判定是否为 macOS 系统隐藏资源叉文件（._ 前缀）。
 /// 这类文件会被 `is_supported_image` 误判为可压缩图片（.jpg 扩展名），
 /// 必须归类为 skipped 而非 failed，否则会污染退出码、误导自动化重试。
@@ -787,10 +1426,12 @@ fn run_compare_mode(cli: &Cli, files: &[PathBuf]) -> Result<()> {
     new_cfg.perceptual = perceptual_options_from_cli(cli);
     let new_proc = Processor::new(new_cfg);
 
-    let force = cli.force;
+    // 落盘/门禁时必须真实重压：跳过已存在输出会拿旧文件体积冒充本次结果
+    let force = cli.force || cli.benchmark_json.is_some() || cli.benchmark_baseline.is_some();
     let overwrite = cli.overwrite;
+    let benchmark = cli.benchmark_mode();
 
-    if cli.benchmark {
+    if benchmark {
         println!("\n=== 感知压缩 A/B 基准对比（旧 v4.1.0 vs 新感知路径）===");
         println!(
             "{:<24} {:>9} {:>9} {:>8} {:>8} {:>8} {:>8} {:>6} {:>7}",
@@ -799,6 +1440,7 @@ fn run_compare_mode(cli: &Cli, files: &[PathBuf]) -> Result<()> {
     }
 
     let mut montage_rows: Vec<String> = Vec::new();
+    let mut bench_entries: Vec<BenchmarkEntry> = Vec::new();
     for file in files {
         let t0 = std::time::Instant::now();
         let old_r = process_one_file(&old_proc, file, force, overwrite);
@@ -825,12 +1467,14 @@ fn run_compare_mode(cli: &Cli, files: &[PathBuf]) -> Result<()> {
             .and_then(|m| m.final_quality)
             .unwrap_or(0);
 
-        if cli.benchmark {
+        if benchmark {
+            let file_name = file
+                .file_name()
+                .map(|s| s.to_string_lossy().to_string())
+                .unwrap_or_default();
             println!(
                 "{:<24} {:>9.1} {:>9.1} {:>8.4} {:>8.4} {:>8.2} {:>8.2} {:>6} {:>7}",
-                file.file_name()
-                    .map(|s| s.to_string_lossy())
-                    .unwrap_or_default(),
+                file_name,
                 old_size as f64 / 1024.0,
                 new_size as f64 / 1024.0,
                 old_ssim,
@@ -840,6 +1484,18 @@ fn run_compare_mode(cli: &Cli, files: &[PathBuf]) -> Result<()> {
                 elapsed_ms,
                 new_q
             );
+            let finite = |v: f64| if v.is_finite() { Some(v) } else { None };
+            bench_entries.push(BenchmarkEntry {
+                file: file_name,
+                old_bytes: old_size,
+                new_bytes: new_size,
+                old_ssim: finite(old_ssim),
+                new_ssim: finite(new_ssim),
+                old_psnr: finite(old_psnr),
+                new_psnr: finite(new_psnr),
+                new_quality: new_q,
+                elapsed_ms: elapsed_ms as u64,
+            });
         }
 
         // 并排 montage（--ab）
@@ -866,8 +1522,39 @@ fn run_compare_mode(cli: &Cli, files: &[PathBuf]) -> Result<()> {
             println!("{}", r);
         }
     }
-    if cli.benchmark {
+    if benchmark {
         println!("=== 对比结束（盲测请放大 200% 看睫毛/暗部）===\n");
+        let report = BenchmarkReport::new(bench_entries);
+        if let Some(ref json_path) = cli.benchmark_json {
+            fs::write(json_path, serde_json::to_string_pretty(&report)?).map_err(|e| {
+                anyhow::anyhow!("基准 JSON 写入失败 {}: {}", json_path.display(), e)
+            })?;
+            println!("📝 基准结果已写入: {}", json_path.display());
+        }
+        if let Some(ref baseline_path) = cli.benchmark_baseline {
+            let text = fs::read_to_string(baseline_path)
+                .map_err(|e| anyhow::anyhow!("基线读取失败 {}: {}", baseline_path.display(), e))?;
+            let baseline: BenchmarkReport = serde_json::from_str(&text).map_err(|e| {
+                anyhow::anyhow!("基线 JSON 解析失败 {}: {}", baseline_path.display(), e)
+            })?;
+            let regressions = benchmark_regressions(&baseline, &report, cli.max_regression);
+            if !regressions.is_empty() {
+                eprintln!(
+                    "❌ 相对基线出现 {} 项回归（容差 {:.2}%）：",
+                    regressions.len(),
+                    cli.max_regression * 100.0
+                );
+                for r in &regressions {
+                    eprintln!("   • {}", r);
+                }
+                std::process::exit(1);
+            }
+            println!(
+                "✅ 未超出基线容差（{:.2}%）：{}",
+                cli.max_regression * 100.0,
+                baseline_path.display()
+            );
+        }
     }
 
     Ok(())
@@ -939,6 +1626,40 @@ fn generate_test_image(width: u32, height: u32) -> image::RgbImage {
     img
 }
 
+/// 内置基准语料版本：generate_test_image 或尺寸表改动时递增，旧语料文件不再复用，
+/// 旧版本的基线按文件名对不上，回归门禁会提示重新生成基线
+const BENCH_CORPUS_VERSION: u32 = 1;
+
+/// 内置离线基准语料：generate_test_image 生成横/竖/方/大图若干尺寸，落盘到临时目录。
+/// 文件名只含语料版本与尺寸，跨机器运行的基线可按文件名直接对比。
+fn generate_benchmark_corpus() -> Result<Vec<PathBuf>> {
+    const SIZES: [(u32, u32); 5] = [
+        (640, 480),
+        (1080, 1920),
+        (1600, 1200),
+        (2048, 2048),
+        (4000, 3000),
+    ];
+    let dir = std::env::temp_dir().join("xtap_compress_bench_corpus");
+    fs::create_dir_all(&dir)?;
+    let mut files = Vec::with_capacity(SIZES.len());
+    for (w, h) in SIZES {
+        let path = dir.join(format!("bench_v{}_{}x{}.png", BENCH_CORPUS_VERSION, w, h));
+        if !path.exists() {
+            image::DynamicImage::ImageRgb8(generate_test_image(w, h))
+                .save(&path)
+                .map_err(|e| anyhow::anyhow!("基准语料生成失败 {}: {}", path.display(), e))?;
+        }
+        files.push(path);
+    }
+    eprintln!(
+        "[INFO] 未指定输入，使用内置生成语料 {} 张：{}",
+        files.len(),
+        dir.display()
+    );
+    Ok(files)
+}
+
 /// 环境自检：内置生成测试图，完整走一遍压缩管线，输出健康报告后退出
 pub(crate) fn run_self_check() -> Result<()> {
     let start = std::time::Instant::now();
@@ -1092,9 +1813,17 @@ pub(crate) fn run_json_mode(json_input: &JsonInput) -> Result<()> {
             "jpeg" | "jpg" => app_config.output_format = OutputFormat::Jpeg,
             "original" | "keep" => app_config.output_format = OutputFormat::KeepOriginal,
             "webp" => app_config.output_format = OutputFormat::WebP, // v4.3.1：JSON 路径 webp 输出
+            "jxl" => app_config.output_format = OutputFormat::Jxl,
             _ => {}
         }
     }
+    // v4.5：JXL 输出依赖 cjxl，缺失时按参数错误退出（而非每个文件各报一次）
+    if app_config.output_format == OutputFormat::Jxl {
+        if let Err(e) = xtap_compress::jxl::check_encoder() {
+            eprintln!("[ERROR] {}", e);
+            std::process::exit(2);
+        }
+    }
 
     // 摄影级优化参数
     if let Some(v) = json_input.enable_sharpening {
@@ -1118,6 +1847,252 @@ pub(crate) fn run_json_mode(json_input: &JsonInput) -> Result<()> {
             _ => app_config.color_space = ColorSpace::KeepOriginal,
         }
     }
+    // v4.5：智能裁剪（crop_aspect 隐含 smart_crop；画幅缺省由平台预设填充）
+    if let Some(ref a) = json_input.crop_aspect {
+        if xtap_compress::crop::parse_aspect(a).is_some() {
+            app_config.smart_crop = true;
+            app_config.crop_aspect = Some(a.clone());
+        } else {
+            eprintln!("[WARN] 无法解析 crop_aspect '{}'，忽略", a);
+        }
+    }
+    if let Some(sc) = json_input.smart_crop {
+        app_config.smart_crop = sc || app_config.crop_aspect.is_some();
+    }
+    // v4.5：九宫格切图
+    if let Some(ref g) = json_input.grid {
+        let gutter = json_input.grid_gutter.unwrap_or(0);
+        if xtap_compress::grid::GridSpec::parse(g, gutter).is_some() {
+            app_config.grid = Some(g.clone());
+            app_config.grid_gutter = gutter;
+        } else {
+            eprintln!(
+                "[WARN] 无法解析 grid '{}'（示例：3x3 / 2x2 / 1x3），忽略",
+                g
+            );
+        }
+    }
+    // v4.5：水印（素材加载失败按参数错误退出，避免每个文件各报一次）
+    if let Some(ref wm) = json_input.watermark {
+        if let Err(e) = wm.load() {
+            eprintln!("[ERROR] {}", e);
+            std::process::exit(2);
+        }
+        app_config.watermark = Some(wm.clone());
+    }
+    // v4.5：联系表（参数/字体无效按参数错误退出）
+    if let Some(ref cs) = json_input.contact_sheet {
+        if let Err(e) = cs.validate() {
+            eprintln!("[ERROR] {}", e);
+            std::process::exit(2);
+        }
+    }
+    // v4.5：长图分页（与 grid 互斥，grid 优先）
+    if let Some(sh) = json_input.slice_height {
+        if app_config.grid.is_some() {
+            eprintln!("[WARN] grid 与 slice_height 互斥，按九宫格处理");
+        } else {
+            app_config.slice_height = sh;
+        }
+    }
+    // v4.5：冲印尺寸（切图/分页时不生效）
+    if let Some(ref ps) = json_input.print_size {
+        if xtap_compress::print::PrintSize::parse(ps).is_none() {
+            eprintln!(
+                "[WARN] 无法解析 print_size '{}'（示例：A4@200 / 6x4in@300），忽略",
+                ps
+            );
+        } else if app_config.grid.is_some() || app_config.slice_height > 0 {
+            eprintln!("[WARN] print_size 与 grid / slice_height 互斥，按切图处理");
+        } else {
+            app_config.print_size = Some(ps.clone());
+        }
+    }
+    // v4.5：响应式图片组（切图/分页优先；启用后冲印尺寸不生效）
+    if let Some(ref widths) = json_input.srcset {
+        let joined = widths
+            .iter()
+            .map(|w| w.to_string())
+            .collect::<Vec<_>>()
+            .join(",");
+        if xtap_compress::srcset::parse_widths(&joined).is_none() {
+            eprintln!("[WARN] srcset 宽度档须为正整数，忽略");
+        } else if app_config.grid.is_some() || app_config.slice_height > 0 {
+            eprintln!("[WARN] srcset 与 grid / slice_height 互斥，按切图处理");
+        } else {
+            if app_config.print_size.take().is_some() {
+                eprintln!("[WARN] srcset 与 print_size 互斥，按响应式图片组处理");
+            }
+            app_config.srcset = Some(joined);
+            if let Some(ref formats) = json_input.srcset_formats {
+                let joined = formats.join(",");
+                if xtap_compress::srcset::parse_formats(&joined).is_some() {
+                    app_config.srcset_formats = Some(joined);
+                } else {
+                    eprintln!(
+                        "[WARN] 无法解析 srcset_formats {:?}（可选 jpeg / webp），跟随 output_format",
+                        formats
+                    );
+                }
+            }
+            app_config.srcset_sizes = json_input.srcset_sizes.clone();
+        }
+    }
+    // v4.5：低清占位图
+    if json_input.placeholder.unwrap_or(false) {
+        app_config.placeholder = true;
+        if let Some(ref f) = json_input.placeholder_format {
+            if xtap_compress::placeholder::PlaceholderSpec::parse(Some(f), 0).is_some() {
+                app_config.placeholder_format = Some(f.clone());
+            } else {
+                eprintln!(
+                    "[WARN] 无法解析 placeholder_format '{}'（可选 jpeg / webp），按 jpeg",
+                    f
+                );
+            }
+        }
+        app_config.placeholder_size = json_input.placeholder_size.unwrap_or(0);
+    }
+    // v4.5：图标组（比例越界按参数错误退出；底色无法解析按透明）
+    if json_input.icon.unwrap_or(false) {
+        let spec = xtap_compress::icon::IconSpec {
+            padding: json_input.icon_padding.unwrap_or(0.0),
+            radius: json_input.icon_radius.unwrap_or(0.0),
+            background: None,
+        };
+        if let Err(e) = spec.validate() {
+            eprintln!("[ERROR] {}", e);
+            std::process::exit(2);
+        }
+        if app_config.grid.is_some()
+            || app_config.slice_height > 0
+            || app_config.print_size.is_some()
+            || app_config.srcset.is_some()
+        {
+            eprintln!(
+                "[WARN] icon 与 grid / slice_height / print_size / srcset 互斥，按图标组处理"
+            );
+        }
+        app_config.icon = true;
+        app_config.icon_padding = spec.padding;
+        app_config.icon_radius = spec.radius;
+        if let Some(ref bg) = json_input.icon_background {
+            if xtap_compress::pad::parse_hex_color(bg).is_some() {
+                app_config.icon_background = Some(bg.clone());
+            } else {
+                eprintln!(
+                    "[WARN] 无法解析 icon_background '{}'（#RRGGBB），按透明",
+                    bg
+                );
+            }
+        }
+    }
+    // v4.5：外部解码器（配置无效按参数错误退出）
+    if let Some(ref decoders) = json_input.external_decoders {
+        for d in decoders {
+            if let Err(e) = d.validate() {
+                eprintln!("[ERROR] {}", e);
+                std::process::exit(2);
+            }
+        }
+        app_config.external_decoders = decoders.clone();
+    }
+    if let Some(secs) = json_input.decoder_timeout_secs {
+        if !(1..=3600).contains(&secs) {
+            eprintln!("[ERROR] decoder_timeout_secs 须在 1-3600: {}", secs);
+            std::process::exit(2);
+        }
+        app_config.decoder_timeout_secs = secs;
+    }
+    app_config.decoder_concurrency = json_input.decoder_concurrency.unwrap_or(0);
+    app_config.jxl_lossless_jpeg = json_input.jxl_lossless_jpeg.unwrap_or(false);
+    // v4.5：动图（格式名无法识别按参数错误退出）
+    app_config.first_frame_only = json_input.first_frame_only.unwrap_or(false);
+    if let Some(ref f) = json_input.animation_format {
+        if xtap_compress::animation::AnimationFormat::parse(f).is_none() {
+            eprintln!("[ERROR] animation_format 须为 auto / gif / webp: {}", f);
+            std::process::exit(2);
+        }
+        app_config.animation_format = f.clone();
+    }
+    if let Some(n) = json_input.animation_max_frames {
+        app_config.animation_max_frames = n;
+    }
+    if let Some(ms) = json_input.animation_max_duration_ms {
+        app_config.animation_max_duration_ms = ms;
+    }
+    // v4.5：SVG 栅格化（尺寸 / 底色无法解析按参数错误退出；未启用 feature svg 时开启即报错）
+    app_config.svg_rasterize = json_input.svg_rasterize.unwrap_or(false);
+    #[cfg(not(feature = "svg"))]
+    if app_config.svg_rasterize {
+        eprintln!("[ERROR] svg_rasterize 需以 --features svg 编译");
+        std::process::exit(2);
+    }
+    #[cfg(feature = "svg")]
+    if let Some(ref size) = json_input.svg_size {
+        if xtap_compress::svg::SvgSize::parse(size).is_none() {
+            eprintln!(
+                "[ERROR] 无法解析 svg_size '{}'（示例：2048 / 300dpi）",
+                size
+            );
+            std::process::exit(2);
+        }
+        app_config.svg_size = Some(size.clone());
+    }
+    #[cfg(feature = "svg")]
+    if let Some(ref bg) = json_input.svg_background {
+        if xtap_compress::svg::parse_background(bg).is_none() {
+            eprintln!(
+                "[ERROR] 无法解析 svg_background '{}'（transparent / white / black / #RRGGBB）",
+                bg
+            );
+            std::process::exit(2);
+        }
+        app_config.svg_background = Some(bg.clone());
+    }
+    // v4.5：高位深抖动 / 色调映射（名称无法识别按参数错误退出）
+    if let Some(ref d) = json_input.dither {
+        if xtap_compress::dither::DitherMode::parse(d).is_none() {
+            eprintln!("[ERROR] dither 须为 blue-noise / ordered / none: {}", d);
+            std::process::exit(2);
+        }
+        app_config.dither = d.clone();
+    }
+    if let Some(ref t) = json_input.tone_map {
+        if xtap_compress::dither::ToneMap::parse(t).is_none() {
+            eprintln!("[ERROR] tone_map 须为 none / reinhard / aces: {}", t);
+            std::process::exit(2);
+        }
+        app_config.tone_map = t.clone();
+    }
+    // v4.5：多页 TIFF 取页方式
+    if let Some(ref m) = json_input.tiff_pages {
+        if xtap_compress::tiff_pages::PageMode::parse(m).is_none() {
+            eprintln!("[ERROR] tiff_pages 须为 first / all / largest: {}", m);
+            std::process::exit(2);
+        }
+        app_config.tiff_pages = m.clone();
+    }
+    install_external_decoders(&app_config);
+    // v4.5：补边（pad_canvas 隐含 pad；画布/底色缺省由平台预设填充）
+    if let Some(ref c) = json_input.pad_canvas {
+        if xtap_compress::pad::PadCanvas::parse(c).is_some() {
+            app_config.pad = true;
+            app_config.pad_canvas = Some(c.clone());
+        } else {
+            eprintln!("[WARN] 无法解析 pad_canvas '{}'，忽略", c);
+        }
+    }
+    if let Some(pd) = json_input.pad {
+        app_config.pad = pd || app_config.pad_canvas.is_some();
+    }
+    if let Some(ref f) = json_input.pad_fill {
+        if xtap_compress::pad::PadFill::parse(f).is_some() {
+            app_config.pad_fill = Some(f.clone());
+        } else {
+            eprintln!("[WARN] 无法解析 pad_fill '{}'，按 blur 处理", f);
+        }
+    }
     // v4.3.1：保结构 / 后缀可控
     if let Some(ps) = json_input.preserve_structure {
         app_config.preserve_structure = ps;
@@ -1138,23 +2113,75 @@ pub(crate) fn run_json_mode(json_input: &JsonInput) -> Result<()> {
 
     // 平台阈值预设（§2）+ 体积线覆盖（与 CLI 同逻辑）。
     // v4.4.0：统一收口 apply_platform_preset——quality_mode=="max" 走画质优先表（Q96+444+CAS）
-    if let Some(plat) = &json_input.platform {
-        apply_platform_preset(&mut app_config, plat);
-    }
-    if let Some(kb) = json_input.target_budget_kb {
-        app_config.custom_target_kb = kb;
-    }
-    // v4.3.0：色彩子采样（显式字段优先级最高，预设后覆盖）
-    if let Some(s) = &json_input.subsampling {
-        let s = s.to_lowercase();
-        if s == "444" || s == "422" || s == "420" {
-            app_config.subsampling = s;
+    let finish_preset = |cfg: &mut AppConfig, platform: Option<&str>| {
+        if let Some(plat) = platform {
+            if !crate::cli::presets().contains_key(&plat.to_lowercase()) {
+                eprintln!(
+                    "[WARN] 未知平台预设 '{}'，忽略预设（可用 --capabilities 查看 platform_presets）",
+                    plat
+                );
+            }
+            apply_platform_preset(cfg, plat);
         }
+        if let Some(kb) = json_input.target_budget_kb {
+            cfg.custom_target_kb = kb;
+        }
+        // v4.3.0：色彩子采样（显式字段优先级最高，预设后覆盖）
+        if let Some(s) = &json_input.subsampling {
+            let s = s.to_lowercase();
+            if s == "444" || s == "422" || s == "420" {
+                cfg.subsampling = s;
+            }
+        }
+        // v4.4.0：CAS 强度显式覆盖（0=强制关闭画质优先档的默认 CAS）
+        if let Some(cs) = json_input.cas_strength {
+            cfg.cas_strength = cs.clamp(0.0, 1.0);
+        }
+    };
+    // v4.5：多预设扇出（platforms 多个，或 platforms + usage_mode=archive）：每个目标各自套预设
+    let platforms = split_platforms(&json_input.platforms.clone().unwrap_or_default().join(","));
+    let fanout = fanout_target_names(&platforms, app_config.usage_mode == "archive");
+    if fanout.is_none() && !platforms.is_empty() && json_input.platform.is_none() {
+        // 单个 platforms 等同 platform
+        app_config.usage_mode = "social".to_string();
+        app_config.platform = platforms[0].clone();
+    }
+    let fanout_configs: Vec<(String, AppConfig)> = fanout
+        .unwrap_or_default()
+        .into_iter()
+        .map(|name| {
+            let mut cfg = app_config.clone();
+            if name == "archive" {
+                cfg.usage_mode = "archive".to_string();
+                finish_preset(&mut cfg, None);
+            } else {
+                cfg.usage_mode = "social".to_string();
+                cfg.platform = name.clone();
+                finish_preset(&mut cfg, Some(&name));
+            }
+            (name, cfg)
+        })
+        .collect();
+    if !fanout_configs.is_empty() && (app_config.overwrite || json_input.batch_budget_kb.is_some())
+    {
+        eprintln!("[ERROR] 多预设扇出不支持 overwrite / batch_budget_kb");
+        std::process::exit(2);
     }
-    // v4.4.0：CAS 强度显式覆盖（0=强制关闭画质优先档的默认 CAS）
-    if let Some(cs) = json_input.cas_strength {
-        app_config.cas_strength = cs.clamp(0.0, 1.0);
-    }
+    let single_platform = if json_input.platform.is_some() {
+        json_input.platform.clone()
+    } else if fanout_configs.is_empty() {
+        platforms.first().cloned()
+    } else {
+        None
+    };
+    finish_preset(&mut app_config, single_platform.as_deref());
+    let fanout_layout = match json_input.fanout_layout.as_deref() {
+        None => CliFanoutLayout::Suffix,
+        Some(l) => CliFanoutLayout::parse(l).unwrap_or_else(|| {
+            eprintln!("[WARN] 未知 fanout_layout '{}'，按 suffix 处理", l);
+            CliFanoutLayout::Suffix
+        }),
+    };
 
     // 输出目录：未指定时默认 ./compressed/，不污染源目录
     let output_dir = json_input
@@ -1229,7 +2256,6 @@ pub(crate) fn run_json_mode(json_input: &JsonInput) -> Result<()> {
     // 并行并发数：AI 可经 max_workers 限流（仅在全局池尚未初始化时生效）
     apply_max_workers(json_input.max_workers);
 
-    let mut process_config = app_config_to_process_config(&app_config, output_dir);
     // 感知开关三态契约（与 CLI/GUI 对齐）：
     // - 显式 quality_mode="perceptual" → 开；"normal" → 关
     // - 缺失但显式 usage_mode="social" → 开（新式调用，与 GUI 社交分享默认一致）
@@ -1245,8 +2271,8 @@ pub(crate) fn run_json_mode(json_input: &JsonInput) -> Result<()> {
             false
         }
     };
-    process_config.perceptual = if perceptual_on {
-        Some(PerceptualOptions {
+    let perceptual = |platform: Option<String>| {
+        perceptual_on.then(|| PerceptualOptions {
             denoise_strength: json_input.denoise_strength.unwrap_or(25).min(100),
             focus_mode: match json_input.focus_mode.as_deref() {
                 Some("center") => FocusMode::Center,
@@ -1256,10 +2282,8 @@ pub(crate) fn run_json_mode(json_input: &JsonInput) -> Result<()> {
             quant_mode: QuantMode::Csf,
             quality_ceil: json_input.quality_ceil.unwrap_or(95),
             budget_kb: json_input.target_budget_kb,
-            platform: json_input.platform.clone(),
+            platform,
         })
-    } else {
-        None
     };
     // v4.3.1：保结构输出——以所有展开条目的公共祖先为层级基准
     let structure_base = if app_config.preserve_structure {
@@ -1268,21 +2292,45 @@ pub(crate) fn run_json_mode(json_input: &JsonInput) -> Result<()> {
     } else {
         None
     };
-    process_config.structure_base = structure_base;
+    let mut process_config = app_config_to_process_config(&app_config, output_dir.clone());
+    process_config.perceptual = perceptual(single_platform.clone());
+    process_config.structure_base = structure_base.clone();
     let processor = Processor::new(process_config);
+    let targets: Vec<FanoutTarget> = fanout_configs
+        .iter()
+        .map(|(name, cfg)| {
+            let mut pc = app_config_to_process_config(cfg, output_dir.clone());
+            pc.perceptual = perceptual(None);
+            pc.structure_base = structure_base.clone();
+            FanoutTarget {
+                processor: Processor::new(fanout_process_config(pc, name, fanout_layout)),
+                name: name.clone(),
+            }
+        })
+        .collect();
 
     let force = json_input.force.unwrap_or(false);
     let overwrite = app_config.overwrite;
     let jsonl = json_input.jsonl.unwrap_or(false);
     let passthrough = json_input.passthrough_unsupported.unwrap_or(false);
+    let sim_model = json_input.simulate_platform.as_deref().map(|name| {
+        let config = json_input.simulate_config.as_deref().map(Path::new);
+        resolve_platform_model(name, config).unwrap_or_else(|e| {
+            eprintln!("[ERROR] {}", e);
+            std::process::exit(2);
+        })
+    });
 
     // P0-FIX: 所有输入条目保留在 results 内（不存在或格式不符自动标记失败）
     // 分桶调度（与 GUI 同款 OOM 护栏）：小图并行、大图串行（多张超大 TIFF 不会并行解码撑爆内存）
     // v4.3.1：统一走 process_or_passthrough 收口（隐藏文件→skipped、不支持格式→透传/失败）
-    let results: Vec<FileResult> = map_bucketed(
+    // v4.5：batch_budget_kb 给出时按复杂度分配单文件预算并重平衡
+    let (results, budget_report) = run_batch(
+        &processor,
         &all_entries,
-        |e| is_large_image(Path::new(e)),
-        |entry| {
+        |e| Path::new(e),
+        json_input.batch_budget_kb,
+        |processor, entry, rerun| {
             let path = Path::new(entry);
 
             // 路径不存在 → 标记失败，保留结果到 results
@@ -1300,7 +2348,12 @@ pub(crate) fn run_json_mode(json_input: &JsonInput) -> Result<()> {
                 return r;
             }
 
-            let r = process_or_passthrough(&processor, path, force, overwrite, passthrough);
+            let mut r = if targets.is_empty() {
+                process_or_passthrough(processor, path, force || rerun, overwrite, passthrough)
+            } else {
+                process_fanout(&targets, path, force || rerun, overwrite, passthrough)
+            };
+            attach_platform_sim(&mut r, sim_model.as_ref(), processor, path);
             if jsonl {
                 emit_jsonl(&r);
             }
@@ -1308,7 +2361,9 @@ pub(crate) fn run_json_mode(json_input: &JsonInput) -> Result<()> {
         },
     );
 
-    let envelope = build_envelope(&results, start);
+    let mut envelope = build_envelope(&results, start);
+    attach_batch_budget(&mut envelope, budget_report);
+    attach_contact_sheet(&mut envelope, json_input.contact_sheet.as_ref(), &results);
     println!("{}", serde_json::to_string(&envelope)?);
 
     // P0-FIX: 存在任意失败即退出码 1
@@ -1379,6 +2434,23 @@ fn match_patterns(path: &Path, patterns: &[&str]) -> bool {
     patterns.iter().any(|p| wildcard_match(file_name, p))
 }
 
+/// v4.5：登记外部解码器的扩展名（目录扫描据此收录）并设置全进程外部工具并发上限；
+/// 开启 SVG 栅格化时 .svg 同样计入支持格式（feature svg）
+pub(crate) fn install_external_decoders(config: &AppConfig) {
+    xtap_compress::external::register_extensions(&config.external_decoders);
+    xtap_compress::external::set_concurrency(config.decoder_concurrency);
+    #[cfg(feature = "svg")]
+    xtap_compress::svg::set_scan_enabled(config.svg_rasterize);
+}
+
+fn svg_scan_enabled() -> bool {
+    #[cfg(feature = "svg")]
+    let enabled = xtap_compress::svg::scan_enabled();
+    #[cfg(not(feature = "svg"))]
+    let enabled = false;
+    enabled
+}
+
 pub(crate) fn is_supported_image(path: &Path) -> bool {
     if let Some(ext) = path.extension().and_then(|e| e.to_str()) {
         let ext_lower = ext.to_lowercase();
@@ -1387,10 +2459,14 @@ pub(crate) fn is_supported_image(path: &Path) -> bool {
             "jpg"
                 | "jpeg"
                 | "png"
+                | "apng"
+                | "gif"
                 | "webp"
                 | "ico"
                 | "tif"
                 | "tiff"
+                | "hdr"
+                | "exr"
                 | "dng"
                 | "cr2"
                 | "cr3"
@@ -1403,7 +2479,189 @@ pub(crate) fn is_supported_image(path: &Path) -> bool {
                 | "srw"
                 | "3fr"
         )
+            || (cfg!(feature = "heic") && matches!(ext_lower.as_str(), "heic" | "heif" | "hif"))
+            || (cfg!(feature = "jxl") && ext_lower == "jxl")
+            || (ext_lower == "svg" && svg_scan_enabled())
+            // v4.5：外部解码器登记的扩展名（.avif / .jxl 等）
+            || xtap_compress::external::is_registered_extension(&ext_lower)
     } else {
         false
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::Parser;
+
+    fn tmp_dir(tag: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("xtap_runner_{}_{}", tag, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn test_fanout_one_input_two_targets() {
+        let dir = tmp_dir("fanout");
+        let input = dir.join("photo.png");
+        image::RgbImage::from_fn(320, 240, |x, y| {
+            image::Rgb([(x % 256) as u8, (y % 256) as u8, 96])
+        })
+        .save(&input)
+        .unwrap();
+        let cli = Cli::try_parse_from(["xtap", "--platform", "wechat,xiaohongshu"]).unwrap();
+        let out_dir = Some(dir.join("out"));
+        let targets = cli_fanout_targets(&cli, &out_dir, &None);
+        assert_eq!(targets.len(), 2);
+
+        let r = process_fanout(&targets, &input, false, false, false);
+        assert!(r.success, "{:?}", r.error);
+        let outputs = r.outputs.expect("扇出结果应带 outputs");
+        let names: Vec<&str> = outputs.iter().map(|o| o.target.as_str()).collect();
+        assert_eq!(names, ["wechat", "xiaohongshu"]);
+        for o in &outputs {
+            assert!(o.result.success, "{}: {:?}", o.target, o.result.error);
+            let path = o.result.output.as_deref().unwrap();
+            assert!(Path::new(path).exists());
+            assert!(path.contains(&o.target), "{} 未按目标区分命名", path);
+        }
+        assert_ne!(outputs[0].result.output, outputs[1].result.output);
+        let total: u64 = outputs
+            .iter()
+            .filter_map(|o| o.result.compressed_size)
+            .sum();
+        assert_eq!(r.compressed_size, Some(total));
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_run_batch_rebalances_over_budget() {
+        let dir = tmp_dir("batch");
+        let flat = dir.join("flat.png");
+        let busy = dir.join("busy.png");
+        image::RgbImage::from_pixel(400, 300, image::Rgb([128, 128, 128]))
+            .save(&flat)
+            .unwrap();
+        image::RgbImage::from_fn(400, 300, |x, y| {
+            let v = (x.wrapping_mul(7919) ^ y.wrapping_mul(104729)).wrapping_mul(2654435761);
+            image::Rgb([(v >> 8) as u8, (v >> 16) as u8, (v >> 24) as u8])
+        })
+        .save(&busy)
+        .unwrap();
+        let cli = Cli::try_parse_from(["xtap"]).unwrap();
+        let processor = Processor::new(app_config_to_process_config(&cli.to_app_config(), None));
+
+        // 模拟压缩：纯色图天然只要 4KB；噪声图总是顶到份额的 95%
+        let budget_kb = 200;
+        let shares = std::sync::Mutex::new(vec![Vec::new(); 2]);
+        let items = [flat, busy];
+        let (results, report) = run_batch(
+            &processor,
+            &items,
+            |p| p.as_path(),
+            Some(budget_kb),
+            |p, path, force| {
+                let i = usize::from(path.ends_with("busy.png"));
+                let kb = p.effective_target_kb();
+                shares.lock().unwrap()[i].push(kb);
+                let cap = kb as u64 * 1024 * 95 / 100;
+                let size = if i == 0 { cap.min(4 * 1024) } else { cap };
+                assert!(!force || i == 1, "只有顶到份额的文件才应重压");
+                FileResult {
+                    input: path.display().to_string(),
+                    success: true,
+                    compressed_size: Some(size),
+                    ..Default::default()
+                }
+            },
+        );
+        let report = report.expect("给了批量预算应返回报告");
+        assert!(report.rebalance_passes >= 1, "{:?}", report);
+        assert_eq!(report.rebalanced_files, 1);
+        assert!(report.fits, "{:?}", report);
+        let shares: Vec<Vec<u32>> = shares.into_inner().unwrap();
+        // 纯色图只跑一轮；噪声图重压后份额变大（吃到纯色图用剩的余量）
+        assert_eq!(shares[0].len(), 1, "{:?}", shares);
+        assert!(shares[1].len() >= 2, "{:?}", shares);
+        assert!(shares[1].last() > shares[1].first(), "{:?}", shares);
+        let last: Vec<u32> = shares.iter().map(|s| *s.last().unwrap()).collect();
+        for (r, kb) in results.iter().zip(&last) {
+            assert!(
+                r.compressed_size.unwrap() <= *kb as u64 * 1024,
+                "{:?}",
+                shares
+            );
+        }
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    fn gradient_png(path: &Path) {
+        image::RgbImage::from_fn(640, 480, |x, y| {
+            image::Rgb([
+                (x * 255 / 640) as u8,
+                (y * 255 / 480) as u8,
+                ((x + y) % 64) as u8,
+            ])
+        })
+        .save(path)
+        .unwrap();
+    }
+
+    #[test]
+    fn test_platform_sim_covers_every_tile_and_fanout_target() {
+        let dir = tmp_dir("sim");
+        let input = dir.join("photo.png");
+        gradient_png(&input);
+        let model = builtin_platform_model("instagram").unwrap();
+
+        // 九宫格：每片对齐原图上对应的区域，platform_sim 取最差的一片
+        let cli = Cli::try_parse_from(["xtap", "--grid", "2x1"]).unwrap();
+        let config = app_config_to_process_config(&cli.to_app_config(), Some(dir.join("grid")));
+        let processor = Processor::new(config);
+        let mut r = process_one_file(&processor, &input, false, false);
+        assert!(r.success, "{:?}", r.error);
+        attach_platform_sim(&mut r, Some(&model), &processor, &input);
+        let sim = r.platform_sim.expect("切图结果应有模拟指标");
+        assert_eq!(sim.outputs_simulated, Some(2));
+        let tiles: Vec<String> = r
+            .tiles
+            .iter()
+            .flatten()
+            .map(|t| t.output.display().to_string())
+            .collect();
+        assert!(tiles.contains(sim.output.as_ref().unwrap()), "{:?}", sim);
+        assert!(sim.ssim_vs_original > 0.8, "{:?}", sim);
+
+        // 扇出：每个目标各自模拟，顶层取最差的目标
+        let cli = Cli::try_parse_from(["xtap", "--platform", "wechat,instagram"]).unwrap();
+        let targets = cli_fanout_targets(&cli, &Some(dir.join("fanout")), &None);
+        let mut r = process_fanout(&targets, &input, false, false, false);
+        assert!(r.success, "{:?}", r.error);
+        attach_platform_sim(&mut r, Some(&model), &targets[0].processor, &input);
+        let outputs = r.outputs.as_ref().unwrap();
+        assert!(outputs.iter().all(|o| o.result.platform_sim.is_some()));
+        let sim = r.platform_sim.expect("扇出结果应有模拟指标");
+        assert_eq!(sim.outputs_simulated, Some(2));
+        let worst = outputs
+            .iter()
+            .map(|o| o.result.platform_sim.as_ref().unwrap().ssim_vs_original)
+            .fold(f64::MAX, f64::min);
+        assert_eq!(sim.ssim_vs_original, worst);
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_jxl_input_follows_feature() {
+        assert_eq!(
+            is_supported_image(Path::new("photo.JXL")),
+            cfg!(feature = "jxl")
+        );
+    }
+
+    #[test]
+    fn test_single_platform_has_no_fanout_targets() {
+        let cli = Cli::try_parse_from(["xtap", "--platform", "wechat"]).unwrap();
+        assert!(cli_fanout_targets(&cli, &None, &None).is_empty());
+    }
+}
@@ -0,0 +1,159 @@
+//! v4.5：长图分页（聊天记录截图 / 1080×12000 信息长图）
+//!
+//! 长图按长边套 `max_dim` 会被压成一条窄缝，文字糊成一片。分页模式改为：
+//! - 短边（宽）缩到平台宽度（`max_dim`），高度按同比例换算，再纵向切成若干页；
+//! - 切线不落在固定位置，而是在每页底部往上的搜索窗口里找「细节最少」的行——
+//!   消息气泡之间的空白、段落间距——避免把一行字从中间劈开；
+//! - 每页独立编码、各自卡体积预算，输出编号 `_01` … `_99`（两位，文件名排序即阅读顺序）。
+//!
+//! 行细节 = 该行与上一行的亮度差 + 行内水平梯度（按宽度归一化），再做 ±2 行平滑，
+//! 保证切线两侧都留有一点空白余量。
+
+use image::RgbaImage;
+use std::path::{Path, PathBuf};
+
+/// 单图最多分页数（两位编号）
+pub const MAX_PAGES: u32 = 99;
+/// 切线搜索窗口：页高的最后 25%（页面最短为目标页高的 75%）
+const SEARCH_FRACTION: f64 = 0.25;
+/// 行细节平滑半径（行）
+const SMOOTH_RADIUS: usize = 2;
+
+/// 一页（原图像素坐标，宽度恒为整图宽度）
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PageSpan {
+    pub y: u32,
+    pub height: u32,
+}
+
+/// 逐行细节能量（按宽度归一化的亮度梯度）
+fn row_energy(img: &RgbaImage) -> Vec<f64> {
+    let (w, h) = (img.width() as usize, img.height() as usize);
+    let luma: Vec<f32> = img
+        .pixels()
+        .map(|p| 0.299 * p[0] as f32 + 0.587 * p[1] as f32 + 0.114 * p[2] as f32)
+        .collect();
+    let mut energy = vec![0f64; h];
+    for y in 0..h {
+        let row = &luma[y * w..(y + 1) * w];
+        let mut sum: f32 = row.windows(2).map(|p| (p[1] - p[0]).abs()).sum();
+        if y > 0 {
+            let above = &luma[(y - 1) * w..y * w];
+            sum += row
+                .iter()
+                .zip(above)
+                .map(|(a, b)| (a - b).abs())
+                .sum::<f32>();
+        }
+        energy[y] = sum as f64 / w.max(1) as f64;
+    }
+    // ±SMOOTH_RADIUS 行取最大值：切线附近都得足够空白才算低细节
+    (0..energy.len())
+        .map(|i| {
+            let lo = i.saturating_sub(SMOOTH_RADIUS);
+            let hi = (i + SMOOTH_RADIUS + 1).min(energy.len());
+            energy[lo..hi].iter().cloned().fold(0f64, f64::max)
+        })
+        .collect()
+}
+
+/// 页数下限：每页至多 `page_height` 行，分页前据此预检页数上限（不必先切页）
+pub fn min_pages(height: u32, page_height: u32) -> u32 {
+    match page_height {
+        0 => 1,
+        p => height.div_ceil(p).max(1),
+    }
+}
+
+/// 按目标页高（原图像素）计算分页；不足一页时返回整图一页
+pub fn slice_pages(img: &RgbaImage, page_height: u32) -> Vec<PageSpan> {
+    let h = img.height();
+    if page_height == 0 || h <= page_height {
+        return vec![PageSpan { y: 0, height: h }];
+    }
+    let energy = row_energy(img);
+    let window = ((page_height as f64 * SEARCH_FRACTION) as u32).max(1);
+    let mut pages = Vec::new();
+    let mut start = 0u32;
+    while h - start > page_height {
+        let target = start + page_height;
+        // 窗口内细节最少的行作为下一页起点；并列时取最靠下的（页尽量满）
+        let cut = (target - window..=target)
+            .rev()
+            .min_by(|&a, &b| energy[a as usize].total_cmp(&energy[b as usize]))
+            .unwrap_or(target);
+        pages.push(PageSpan {
+            y: start,
+            height: cut - start,
+        });
+        start = cut;
+    }
+    pages.push(PageSpan {
+        y: start,
+        height: h - start,
+    });
+    pages
+}
+
+/// 分页输出路径：单图输出路径文件名后追加两位页码（photo_wx.jpg → photo_wx_01.jpg）
+pub fn page_path(base: &Path, index: u32) -> PathBuf {
+    let stem = base.file_stem().unwrap_or_default().to_string_lossy();
+    let name = match base.extension() {
+        Some(ext) => format!("{}_{:02}.{}", stem, index, ext.to_string_lossy()),
+        None => format!("{}_{:02}", stem, index),
+    };
+    base.with_file_name(name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::Rgba;
+
+    /// 白底「聊天记录」：每 100 行一块 70 行高的文字（黑白条纹），块间 30 行空白
+    fn chat_log(w: u32, h: u32) -> RgbaImage {
+        let mut img = RgbaImage::from_pixel(w, h, Rgba([255, 255, 255, 255]));
+        for y in 0..h {
+            if y % 100 < 70 {
+                for x in 0..w {
+                    if (x / 3 + y / 2) % 2 == 0 {
+                        img.put_pixel(x, y, Rgba([20, 20, 20, 255]));
+                    }
+                }
+            }
+        }
+        img
+    }
+
+    #[test]
+    fn test_cuts_land_in_blank_rows() {
+        let img = chat_log(200, 2000);
+        let pages = slice_pages(&img, 450);
+        assert!(pages.len() >= 5);
+        // 页首尾相接、覆盖整图
+        assert_eq!(pages[0].y, 0);
+        for pair in pages.windows(2) {
+            assert_eq!(pair[0].y + pair[0].height, pair[1].y);
+        }
+        let last = pages.last().unwrap();
+        assert_eq!(last.y + last.height, 2000);
+        // 每条切线都落在空白带（y%100 ∈ [70,100)），且页高不超过目标
+        for p in &pages[1..] {
+            assert!((70..100).contains(&(p.y % 100)), "切线穿过文字: {:?}", p);
+        }
+        assert!(pages.iter().all(|p| p.height <= 450));
+    }
+
+    #[test]
+    fn test_short_image_single_page_and_numbering() {
+        let img = chat_log(200, 300);
+        assert_eq!(slice_pages(&img, 450), vec![PageSpan { y: 0, height: 300 }]);
+        assert_eq!(min_pages(300, 450), 1);
+        assert_eq!(min_pages(2000, 450), 5);
+        assert!(slice_pages(&chat_log(200, 2000), 450).len() as u32 >= min_pages(2000, 450));
+        assert_eq!(
+            page_path(Path::new("/out/log_wx.jpg"), 3),
+            PathBuf::from("/out/log_wx_03.jpg")
+        );
+    }
+}
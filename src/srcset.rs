@@ -0,0 +1,234 @@
+//! v4.5：响应式图片组（网页发布：一张图出 320/640/1280/2560 多档宽度 × JPEG/WebP）
+//!
+//! - 源图只解码一次（含裁剪/补边），每档宽度从同一张解码图各自缩放/编码，复用单图管线；
+//! - 宽于原图的档位不放大、直接丢弃（全部超宽时只出一档原图宽度），同宽去重；
+//! - 输出文件名在单图输出路径后追加宽度记号 `_{宽}w`（photo_da.jpg → photo_da_640w.jpg），
+//!   各格式扩展名各自独立；
+//! - 每张图旁写一份清单 `{名}.srcset.json`（全部变体 + HTML 片段）与 `{名}.srcset.html`
+//!   （`<picture>`：非 JPEG 格式作 `<source>`，JPEG 作 `<img>` 兜底）。
+
+use crate::OutputFormat;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+/// 默认档位（常见 1x/2x 断点）
+pub const DEFAULT_WIDTHS: [u32; 4] = [320, 640, 1280, 2560];
+/// 默认 sizes 属性
+pub const DEFAULT_SIZES: &str = "100vw";
+
+/// 响应式图片组规格
+#[derive(Debug, Clone, PartialEq)]
+pub struct SrcsetSpec {
+    /// 目标宽度（升序、去重）
+    pub widths: Vec<u32>,
+    /// 输出格式（仅 Jpeg / WebP；`<picture>` 中按此顺序列出）
+    pub formats: Vec<OutputFormat>,
+    /// `<img sizes>` 属性
+    pub sizes: String,
+}
+
+/// "320,640,1280"：逗号分隔的正整数宽度，返回升序去重后的列表
+pub fn parse_widths(s: &str) -> Option<Vec<u32>> {
+    let mut widths = s
+        .split(',')
+        .map(|w| {
+            w.trim()
+                .trim_end_matches('w')
+                .parse::<u32>()
+                .ok()
+                .filter(|&w| w > 0)
+        })
+        .collect::<Option<Vec<u32>>>()?;
+    widths.sort_unstable();
+    widths.dedup();
+    (!widths.is_empty()).then_some(widths)
+}
+
+/// "jpeg,webp"：逗号分隔的格式（jpeg/jpg/webp），保持顺序去重
+pub fn parse_formats(s: &str) -> Option<Vec<OutputFormat>> {
+    let mut formats = Vec::new();
+    for f in s.split(',') {
+        let fmt = match f.trim().to_ascii_lowercase().as_str() {
+            "jpeg" | "jpg" => OutputFormat::Jpeg,
+            "webp" => OutputFormat::WebP,
+            _ => return None,
+        };
+        if !formats.contains(&fmt) {
+            formats.push(fmt);
+        }
+    }
+    (!formats.is_empty()).then_some(formats)
+}
+
+/// 格式 → (扩展名, MIME)
+pub fn format_info(format: OutputFormat) -> (&'static str, &'static str) {
+    match format {
+        OutputFormat::WebP => ("webp", "image/webp"),
+        _ => ("jpg", "image/jpeg"),
+    }
+}
+
+/// 实际输出的宽度档：去掉宽于原图的档位；全部超宽时只保留原图宽度一档
+pub fn effective_widths(widths: &[u32], source_width: u32) -> Vec<u32> {
+    let kept: Vec<u32> = widths
+        .iter()
+        .copied()
+        .filter(|&w| w <= source_width)
+        .collect();
+    if kept.is_empty() {
+        vec![source_width]
+    } else {
+        kept
+    }
+}
+
+/// 变体输出路径：单图输出路径文件名后追加 `_{宽}w`，扩展名换成该格式
+pub fn variant_path(base: &Path, width: u32, ext: &str) -> PathBuf {
+    let stem = base.file_stem().unwrap_or_default().to_string_lossy();
+    base.with_file_name(format!("{}_{}w.{}", stem, width, ext))
+}
+
+/// 清单 / HTML 片段路径：`{名}.srcset.json` / `{名}.srcset.html`
+pub fn sidecar_path(base: &Path, ext: &str) -> PathBuf {
+    let stem = base.file_stem().unwrap_or_default().to_string_lossy();
+    base.with_file_name(format!("{}.srcset.{}", stem, ext))
+}
+
+/// 单个变体（JSON 输出 / 清单）
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SrcsetVariant {
+    pub width: u32,
+    pub height: u32,
+    /// MIME 类型（image/jpeg / image/webp）
+    pub format: String,
+    pub output: PathBuf,
+    pub bytes: u64,
+}
+
+/// 每张图的响应式清单（写入 `{名}.srcset.json`，同时随 JSON 结果输出）
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SrcsetManifest {
+    pub variants: Vec<SrcsetVariant>,
+    pub sizes: String,
+    /// `<picture>` 片段（路径为文件名，片段与图片同目录放置）
+    pub html: String,
+    /// 清单文件路径
+    pub manifest: PathBuf,
+    /// 低清占位图（启用 placeholder 时取第一个变体）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub placeholder: Option<crate::placeholder::Placeholder>,
+}
+
+/// 生成 `<picture>` 片段：JPEG 作 `<img>` 兜底（无 JPEG 时取最后一种格式），其余格式依次作 `<source>`
+pub fn picture_html(variants: &[SrcsetVariant], sizes: &str, alt: &str) -> String {
+    let mut formats: Vec<&str> = Vec::new();
+    for v in variants {
+        if !formats.contains(&v.format.as_str()) {
+            formats.push(&v.format);
+        }
+    }
+    let fallback = if formats.contains(&"image/jpeg") {
+        "image/jpeg"
+    } else {
+        formats.last().copied().unwrap_or("image/jpeg")
+    };
+    let srcset_of = |fmt: &str| {
+        variants
+            .iter()
+            .filter(|v| v.format == fmt)
+            .map(|v| format!("{} {}w", file_name(&v.output), v.width))
+            .collect::<Vec<_>>()
+            .join(", ")
+    };
+    let mut html = String::from("<picture>\n");
+    for fmt in formats.iter().filter(|&&f| f != fallback) {
+        html.push_str(&format!(
+            "  <source type=\"{}\" srcset=\"{}\" sizes=\"{}\">\n",
+            fmt,
+            srcset_of(fmt),
+            escape_attr(sizes)
+        ));
+    }
+    // src 取兜底格式的最宽一档；width/height 供浏览器预留版面、避免布局抖动
+    if let Some(largest) = variants
+        .iter()
+        .filter(|v| v.format == fallback)
+        .max_by_key(|v| v.width)
+    {
+        html.push_str(&format!(
+            "  <img src=\"{}\" srcset=\"{}\" sizes=\"{}\" width=\"{}\" height=\"{}\" alt=\"{}\" loading=\"lazy\" decoding=\"async\">\n",
+            file_name(&largest.output),
+            srcset_of(fallback),
+            escape_attr(sizes),
+            largest.width,
+            largest.height,
+            escape_attr(alt)
+        ));
+    }
+    html.push_str("</picture>\n");
+    html
+}
+
+fn file_name(p: &Path) -> String {
+    escape_attr(&p.file_name().unwrap_or_default().to_string_lossy())
+}
+
+fn escape_attr(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('"', "&quot;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_and_effective_widths() {
+        assert_eq!(
+            parse_widths("1280, 320,640w,320"),
+            Some(vec![320, 640, 1280])
+        );
+        assert_eq!(parse_widths("320,0"), None);
+        assert_eq!(
+            parse_formats("webp,jpg,webp"),
+            Some(vec![OutputFormat::WebP, OutputFormat::Jpeg])
+        );
+        assert_eq!(parse_formats("avif"), None);
+        assert_eq!(
+            effective_widths(&DEFAULT_WIDTHS, 1500),
+            vec![320, 640, 1280]
+        );
+        assert_eq!(effective_widths(&[640, 1280], 500), vec![500]);
+        assert_eq!(
+            variant_path(Path::new("/out/a_da.jpg"), 640, "webp"),
+            PathBuf::from("/out/a_da_640w.webp")
+        );
+    }
+
+    #[test]
+    fn test_picture_html_uses_jpeg_fallback() {
+        let v = |w: u32, h: u32, fmt: &str, ext: &str| SrcsetVariant {
+            width: w,
+            height: h,
+            format: fmt.into(),
+            output: PathBuf::from(format!("/out/a_{}w.{}", w, ext)),
+            bytes: 1,
+        };
+        let variants = vec![
+            v(320, 240, "image/webp", "webp"),
+            v(640, 480, "image/webp", "webp"),
+            v(320, 240, "image/jpeg", "jpg"),
+            v(640, 480, "image/jpeg", "jpg"),
+        ];
+        let html = picture_html(&variants, "(max-width: 600px) 100vw, 50vw", "a \"b\"");
+        assert!(html
+            .contains("<source type=\"image/webp\" srcset=\"a_320w.webp 320w, a_640w.webp 640w\""));
+        assert!(
+            html.contains("<img src=\"a_640w.jpg\" srcset=\"a_320w.jpg 320w, a_640w.jpg 640w\"")
+        );
+        assert!(html.contains("width=\"640\" height=\"480\" alt=\"a &quot;b&quot;\""));
+        assert_eq!(html.matches("<source").count(), 1);
+    }
+}
@@ -0,0 +1,182 @@
+//! v4.5：SVG 栅格化（cargo feature `svg`，resvg，纯 Rust）
+//!
+//! 运行时仍需显式开启（`svg_rasterize`）；未开启时 SVG 仍是不支持格式，由 `--passthrough-unsupported` 原样透传。
+//! - 尺寸：长边像素（`2048`）或 DPI（`300dpi`，以 SVG 的 96 DPI 用户单位为基准换算）；
+//! - 底色：缺省透明，可填 `#RRGGBB` / white / black；
+//! - 文字用系统字体排版（字体库全进程只加载一次），相对路径的外链图片按 SVG 所在目录解析；
+//! - 渲染结果交给常规管线缩放、锐化、编码（导出格式「保持原格式」时输出 PNG）。
+
+use anyhow::{anyhow, Result};
+use resvg::{tiny_skia, usvg};
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, OnceLock};
+
+pub const DEFAULT_LONG_EDGE: u32 = 2048;
+/// 渲染画布单边上限（超出按比例缩小）
+const MAX_SIDE: u32 = 16384;
+
+pub fn is_svg_extension(ext: &str) -> bool {
+    ext.eq_ignore_ascii_case("svg")
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SvgSize {
+    /// 长边像素
+    LongEdge(u32),
+    /// 按物理尺寸 × DPI
+    Dpi(f32),
+}
+
+impl SvgSize {
+    /// `2048` / `2048px` → 长边；`300dpi` → DPI（10-2400）
+    pub fn parse(s: &str) -> Option<Self> {
+        let t = s.trim().to_ascii_lowercase();
+        if let Some(dpi) = t.strip_suffix("dpi") {
+            let dpi: f32 = dpi.trim().parse().ok()?;
+            return (10.0..=2400.0).contains(&dpi).then_some(Self::Dpi(dpi));
+        }
+        let px: u32 = t.strip_suffix("px").unwrap_or(&t).trim().parse().ok()?;
+        (1..=MAX_SIDE).contains(&px).then_some(Self::LongEdge(px))
+    }
+}
+
+/// "transparent" → Some(None)；"white" / "black" / "#RRGGBB" → Some(Some(颜色))
+pub fn parse_background(s: &str) -> Option<Option<[u8; 3]>> {
+    match s.trim().to_ascii_lowercase().as_str() {
+        "transparent" | "none" => Some(None),
+        "white" => Some(Some([255, 255, 255])),
+        "black" => Some(Some([0, 0, 0])),
+        t => crate::pad::parse_hex_color(t).map(Some),
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SvgSpec {
+    pub size: SvgSize,
+    /// None = 透明
+    pub background: Option<[u8; 3]>,
+}
+
+impl Default for SvgSpec {
+    fn default() -> Self {
+        Self {
+            size: SvgSize::LongEdge(DEFAULT_LONG_EDGE),
+            background: None,
+        }
+    }
+}
+
+static SCAN_ENABLED: AtomicBool = AtomicBool::new(false);
+
+/// 开启后目录扫描收录 .svg（与外部解码器登记扩展名同理）
+pub fn set_scan_enabled(enabled: bool) {
+    SCAN_ENABLED.store(enabled, Ordering::Relaxed);
+}
+
+pub fn scan_enabled() -> bool {
+    SCAN_ENABLED.load(Ordering::Relaxed)
+}
+
+fn fontdb() -> Arc<usvg::fontdb::Database> {
+    static DB: OnceLock<Arc<usvg::fontdb::Database>> = OnceLock::new();
+    DB.get_or_init(|| {
+        let mut db = usvg::fontdb::Database::new();
+        db.load_system_fonts();
+        Arc::new(db)
+    })
+    .clone()
+}
+
+/// 读入并渲染 SVG 文件
+pub fn rasterize(path: &Path, spec: &SvgSpec) -> Result<image::DynamicImage> {
+    let data = std::fs::read(path)?;
+    render(&data, path.parent(), spec)
+}
+
+fn render(
+    data: &[u8],
+    resources_dir: Option<&Path>,
+    spec: &SvgSpec,
+) -> Result<image::DynamicImage> {
+    let opt = usvg::Options {
+        resources_dir: resources_dir.map(Path::to_path_buf),
+        fontdb: fontdb(),
+        ..Default::default()
+    };
+    let tree = usvg::Tree::from_data(data, &opt).map_err(|e| anyhow!("SVG 解析失败: {}", e))?;
+    let (w, h) = (tree.size().width(), tree.size().height());
+    let scale = match spec.size {
+        SvgSize::LongEdge(px) => px as f32 / w.max(h),
+        SvgSize::Dpi(dpi) => dpi / 96.0,
+    };
+    let scale = scale.min(MAX_SIDE as f32 / w.max(h));
+    let width = ((w * scale).round() as u32).clamp(1, MAX_SIDE);
+    let height = ((h * scale).round() as u32).clamp(1, MAX_SIDE);
+
+    let mut pixmap = tiny_skia::Pixmap::new(width, height)
+        .ok_or_else(|| anyhow!("SVG 渲染画布创建失败（{}x{}）", width, height))?;
+    if let Some([r, g, b]) = spec.background {
+        pixmap.fill(tiny_skia::Color::from_rgba8(r, g, b, 255));
+    }
+    let transform = tiny_skia::Transform::from_scale(width as f32 / w, height as f32 / h);
+    resvg::render(&tree, transform, &mut pixmap.as_mut());
+
+    // tiny-skia 为预乘 alpha，转回直通 RGBA
+    let raw = pixmap
+        .pixels()
+        .iter()
+        .flat_map(|p| {
+            let c = p.demultiply();
+            [c.red(), c.green(), c.blue(), c.alpha()]
+        })
+        .collect();
+    let img = image::RgbaImage::from_raw(width, height, raw)
+        .ok_or_else(|| anyhow!("Failed to create image buffer"))?;
+    Ok(match spec.background {
+        Some(_) => image::DynamicImage::ImageRgb8(image::DynamicImage::ImageRgba8(img).to_rgb8()),
+        None => image::DynamicImage::ImageRgba8(img),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SVG: &[u8] =
+        br##"<svg xmlns="http://www.w3.org/2000/svg" width="100" height="50" viewBox="0 0 100 50">
+        <rect x="50" y="0" width="50" height="50" fill="#ff0000"/></svg>"##;
+
+    #[test]
+    fn test_render_size_and_background() {
+        let img = render(SVG, None, &SvgSpec::default()).unwrap().to_rgba8();
+        assert_eq!(img.dimensions(), (2048, 1024));
+        assert_eq!(img.get_pixel(10, 10)[3], 0);
+        assert_eq!(img.get_pixel(1500, 500).0, [255, 0, 0, 255]);
+
+        let spec = SvgSpec {
+            size: SvgSize::Dpi(192.0),
+            background: Some([255, 255, 255]),
+        };
+        let img = render(SVG, None, &spec).unwrap();
+        assert!(!img.color().has_alpha());
+        let img = img.to_rgb8();
+        assert_eq!(img.dimensions(), (200, 100));
+        assert_eq!(img.get_pixel(10, 10).0, [255, 255, 255]);
+        assert_eq!(img.get_pixel(150, 50).0, [255, 0, 0]);
+
+        assert!(render(b"<svg", None, &spec).is_err());
+    }
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(SvgSize::parse("1080"), Some(SvgSize::LongEdge(1080)));
+        assert_eq!(SvgSize::parse("1080px"), Some(SvgSize::LongEdge(1080)));
+        assert_eq!(SvgSize::parse("300DPI"), Some(SvgSize::Dpi(300.0)));
+        assert_eq!(SvgSize::parse("0"), None);
+        assert_eq!(SvgSize::parse("5dpi"), None);
+        assert_eq!(parse_background("transparent"), Some(None));
+        assert_eq!(parse_background("#00ff00"), Some(Some([0, 255, 0])));
+        assert_eq!(parse_background("blur"), None);
+    }
+}
@@ -0,0 +1,284 @@
+//! v4.5：多页 TIFF（扫描文档 / 传真 / 带缩略子图的 TIFF）
+//!
+//! 通用解码只读第一个 IFD，后面的页面会被悄悄丢掉。这里按 IFD 链逐页定位：
+//! - `first`（缺省）：与旧版一致只取第 1 页，但结果记录总页数，批处理给出告警；
+//! - `all`：每页单独输出 `{名}_p01.jpg` …（跳过 NewSubfileType 标为缩小分辨率的子图）；
+//! - `largest`：只取像素最多的一页（金字塔 / 带预览子图的 TIFF 取原图）。
+//!
+//! 选中页的解码复用 image 的 TIFF 解码器：文件头的首 IFD 偏移改指向该页，
+//! 只替换头部 16 字节，其余内容直接读原数据，不复制整个文件。
+
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use std::io::{self, BufRead, Cursor, Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use tiff::decoder::Decoder;
+use tiff::tags::Tag;
+
+/// 单个文件最多扫描的页数
+pub const MAX_PAGES: usize = 999;
+
+pub fn is_tiff_extension(ext: &str) -> bool {
+    ext.eq_ignore_ascii_case("tif") || ext.eq_ignore_ascii_case("tiff")
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum PageMode {
+    #[default]
+    First,
+    All,
+    Largest,
+}
+
+impl PageMode {
+    pub fn parse(s: &str) -> Option<Self> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "first" => Some(Self::First),
+            "all" => Some(Self::All),
+            "largest" => Some(Self::Largest),
+            _ => None,
+        }
+    }
+}
+
+/// IFD 链上的一页
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PageInfo {
+    /// IFD 在文件中的偏移
+    pub ifd: u64,
+    pub width: u32,
+    pub height: u32,
+    /// NewSubfileType bit 0：缩小分辨率的子图（缩略图 / 金字塔层）
+    pub reduced: bool,
+}
+
+/// 已输出的一页
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PageOutput {
+    /// 源文件中的页码（1 起，按 IFD 顺序）
+    pub page: u32,
+    pub width: u32,
+    pub height: u32,
+    pub output: PathBuf,
+    pub bytes: u64,
+}
+
+/// 多页 TIFF 处理结果（仅源文件多于 1 页时记录）
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TiffPages {
+    /// 源文件总页数（含缩小分辨率子图）
+    pub count: u32,
+    /// `first` 模式下没有输出的整页数（不含缩小分辨率子图），批处理据此告警
+    pub skipped: u32,
+    pub outputs: Vec<PageOutput>,
+}
+
+/// 沿 IFD 链列出全部页（遇到无法解析的 IFD 即止）
+pub fn scan(data: &[u8]) -> Result<Vec<PageInfo>> {
+    let mut decoder =
+        Decoder::new(Cursor::new(data)).map_err(|e| anyhow!("TIFF 解析失败: {}", e))?;
+    let mut pages = Vec::new();
+    loop {
+        let ifd = decoder
+            .ifd_pointer()
+            .ok_or_else(|| anyhow!("TIFF IFD 缺失"))?;
+        let (width, height) = decoder.dimensions()?;
+        let subfile = decoder
+            .find_tag_unsigned::<u32>(Tag::NewSubfileType)
+            .ok()
+            .flatten()
+            .unwrap_or(0);
+        pages.push(PageInfo {
+            ifd: ifd.0,
+            width,
+            height,
+            reduced: subfile & 1 != 0,
+        });
+        if pages.len() >= MAX_PAGES || !decoder.more_images() || decoder.next_image().is_err() {
+            break;
+        }
+    }
+    Ok(pages)
+}
+
+/// 按模式选页（返回 `pages` 下标，至少一页）
+pub fn select(pages: &[PageInfo], mode: PageMode) -> Vec<usize> {
+    match mode {
+        PageMode::First => vec![0],
+        PageMode::Largest => {
+            let area = |i: &usize| pages[*i].width as u64 * pages[*i].height as u64;
+            // 面积相同取靠前的一页
+            let best = (0..pages.len()).rev().max_by_key(area).unwrap_or(0);
+            vec![best]
+        }
+        PageMode::All => {
+            let full: Vec<usize> = (0..pages.len()).filter(|&i| !pages[i].reduced).collect();
+            if full.is_empty() {
+                (0..pages.len()).collect()
+            } else {
+                full
+            }
+        }
+    }
+}
+
+/// 文件头可替换的字节数（BigTIFF 首 IFD 偏移在 8..16）
+const HEADER_LEN: usize = 16;
+
+/// 原数据的只读视图：前 `HEADER_LEN` 字节读改过的文件头，其余直接读原数据
+struct PatchedHeader<'a> {
+    data: &'a [u8],
+    header: [u8; HEADER_LEN],
+    pos: usize,
+}
+
+impl BufRead for PatchedHeader<'_> {
+    fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        let head = HEADER_LEN.min(self.data.len());
+        let pos = self.pos.min(self.data.len());
+        Ok(if pos < head {
+            &self.header[pos..head]
+        } else {
+            &self.data[pos..]
+        })
+    }
+
+    fn consume(&mut self, amt: usize) {
+        self.pos = self.pos.saturating_add(amt);
+    }
+}
+
+impl Read for PatchedHeader<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let src = self.fill_buf()?;
+        let n = src.len().min(buf.len());
+        buf[..n].copy_from_slice(&src[..n]);
+        self.consume(n);
+        Ok(n)
+    }
+}
+
+impl Seek for PatchedHeader<'_> {
+    fn seek(&mut self, from: SeekFrom) -> io::Result<u64> {
+        let (base, offset) = match from {
+            SeekFrom::Start(n) => {
+                self.pos = usize::try_from(n).unwrap_or(usize::MAX);
+                return Ok(n);
+            }
+            SeekFrom::End(n) => (self.data.len(), n),
+            SeekFrom::Current(n) => (self.pos, n),
+        };
+        let pos = (base as i64)
+            .checked_add(offset)
+            .filter(|p| *p >= 0)
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "TIFF 定位越界"))?;
+        self.pos = pos as usize;
+        Ok(pos as u64)
+    }
+}
+
+/// 解码指定页：文件头首 IFD 偏移改指该页后交给 image 解码
+pub fn decode_page(data: &[u8], page: &PageInfo) -> Result<image::DynamicImage> {
+    let little = match data.get(..4) {
+        Some([b'I', b'I', ..]) => true,
+        Some([b'M', b'M', ..]) => false,
+        _ => return Err(anyhow!("不是 TIFF 文件")),
+    };
+    let mut header = [0u8; HEADER_LEN];
+    let head = HEADER_LEN.min(data.len());
+    header[..head].copy_from_slice(&data[..head]);
+    let big_tiff = data.get(2..4) == Some(if little { &[43, 0] } else { &[0, 43] });
+    if big_tiff {
+        if data.len() < 16 {
+            return Err(anyhow!("BigTIFF 文件头不完整"));
+        }
+        let bytes = if little {
+            page.ifd.to_le_bytes()
+        } else {
+            page.ifd.to_be_bytes()
+        };
+        header[8..16].copy_from_slice(&bytes);
+    } else {
+        if data.len() < 8 {
+            return Err(anyhow!("TIFF 文件头不完整"));
+        }
+        let ifd = u32::try_from(page.ifd)?;
+        let bytes = if little {
+            ifd.to_le_bytes()
+        } else {
+            ifd.to_be_bytes()
+        };
+        header[4..8].copy_from_slice(&bytes);
+    }
+    let reader = PatchedHeader {
+        data,
+        header,
+        pos: 0,
+    };
+    image::ImageReader::with_format(reader, image::ImageFormat::Tiff)
+        .decode()
+        .map_err(|e| anyhow!("TIFF 页（{}x{}）解码失败: {}", page.width, page.height, e))
+}
+
+/// 分页输出路径：单图输出路径文件名后追加 `_p{两位页码}`（scan_da.jpg → scan_da_p01.jpg）
+pub fn page_path(base: &Path, index: u32) -> PathBuf {
+    let stem = base.file_stem().unwrap_or_default().to_string_lossy();
+    let name = match base.extension() {
+        Some(ext) => format!("{}_p{:02}.{}", stem, index, ext.to_string_lossy()),
+        None => format!("{}_p{:02}", stem, index),
+    };
+    base.with_file_name(name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tiff::encoder::{colortype, TiffEncoder};
+
+    /// 三页：200×100 灰 / 50×25 缩略子图 / 100×300 黑
+    fn three_pages() -> Vec<u8> {
+        let mut buf = Cursor::new(Vec::new());
+        let mut enc = TiffEncoder::new(&mut buf).unwrap();
+        enc.write_image::<colortype::Gray8>(200, 100, &vec![128; 200 * 100])
+            .unwrap();
+        let mut thumb = enc.new_image::<colortype::RGB8>(50, 25).unwrap();
+        thumb
+            .encoder()
+            .write_tag(Tag::NewSubfileType, 1u32)
+            .unwrap();
+        thumb.write_data(&vec![255; 50 * 25 * 3]).unwrap();
+        enc.write_image::<colortype::Gray8>(100, 300, &vec![0; 100 * 300])
+            .unwrap();
+        buf.into_inner()
+    }
+
+    #[test]
+    fn test_scan_select_decode() {
+        let data = three_pages();
+        let pages = scan(&data).unwrap();
+        assert_eq!(pages.len(), 3);
+        assert!(pages[1].reduced && !pages[0].reduced && !pages[2].reduced);
+
+        assert_eq!(select(&pages, PageMode::First), vec![0]);
+        assert_eq!(select(&pages, PageMode::All), vec![0, 2]);
+        assert_eq!(select(&pages, PageMode::Largest), vec![2]);
+
+        let thumb = decode_page(&data, &pages[1]).unwrap();
+        assert_eq!((thumb.width(), thumb.height()), (50, 25));
+        assert_eq!(thumb.to_rgb8().get_pixel(3, 3).0, [255, 255, 255]);
+        let last = decode_page(&data, &pages[2]).unwrap();
+        assert_eq!((last.width(), last.height()), (100, 300));
+        assert_eq!(last.to_luma8().get_pixel(5, 5).0, [0]);
+    }
+
+    #[test]
+    fn test_parse_and_page_path() {
+        assert_eq!(PageMode::parse("ALL"), Some(PageMode::All));
+        assert_eq!(PageMode::parse("largest"), Some(PageMode::Largest));
+        assert_eq!(PageMode::parse("every"), None);
+        assert_eq!(
+            page_path(Path::new("out/scan_da.jpg"), 3),
+            PathBuf::from("out/scan_da_p03.jpg")
+        );
+    }
+}
@@ -0,0 +1,401 @@
+//! v4.5：水印叠加（PNG 标志 / TTF·OTF 文字）
+//!
+//! 挂在编码管线的「缩放之后、锐化与编码之前」：水印按最终输出尺寸排版，不会被缩放糊掉，
+//! 锐化也会同时作用于水印边缘，与画面观感一致。
+//! - 尺寸全部相对输出短边：标志宽度 / 文字字号 = `scale` × 短边，边距 = `margin` × 短边；
+//! - `position` 九宫位或 `tile`（铺满平铺，奇数行错开半格，防裁切去水印）；
+//! - `opacity` 乘到水印 alpha 上，再按 alpha 混合到画面（保留原图 alpha）。
+//!
+//! 字形光栅化用 ab_glyph（纯 Rust，cargo feature `fonts`，未启用时只支持 PNG 标志）；
+//! 字体由用户提供，不内置任何字体。
+
+use anyhow::{anyhow, Result};
+use image::{imageops, RgbaImage};
+use serde::{Deserialize, Serialize};
+
+/// 标志默认宽度占短边比例
+const DEFAULT_LOGO_SCALE: f32 = 0.2;
+/// 文字默认字号占短边比例
+const DEFAULT_TEXT_SCALE: f32 = 0.05;
+
+/// 水印配置（AppConfig / JSON / GUI 持久化共用；image 与 text 二选一，image 优先）
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields, default)]
+pub struct WatermarkConfig {
+    /// PNG 标志路径（带透明通道最佳）
+    pub image: Option<String>,
+    /// 水印文字（需配合 font）
+    pub text: Option<String>,
+    /// TTF / OTF 字体路径
+    pub font: Option<String>,
+    /// 文字颜色 #RRGGBB（默认白色）
+    pub color: String,
+    /// top-left / top / top-right / left / center / right / bottom-left / bottom / bottom-right / tile
+    pub position: String,
+    /// 边距（平铺时为间距），占短边比例
+    pub margin: f32,
+    /// 不透明度 0.0-1.0
+    pub opacity: f32,
+    /// 标志宽度 / 文字字号占短边比例（缺省：标志 0.2，文字 0.05）
+    pub scale: Option<f32>,
+}
+
+impl Default for WatermarkConfig {
+    fn default() -> Self {
+        Self {
+            image: None,
+            text: None,
+            font: None,
+            color: "#FFFFFF".to_string(),
+            position: "bottom-right".to_string(),
+            margin: 0.03,
+            opacity: 0.5,
+            scale: None,
+        }
+    }
+}
+
+/// 水印位置
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WatermarkPosition {
+    /// 九宫位：(水平 0/1/2, 垂直 0/1/2) = (左/中/右, 上/中/下)
+    Anchor(u8, u8),
+    /// 铺满平铺
+    Tile,
+}
+
+impl WatermarkPosition {
+    pub fn parse(s: &str) -> Option<Self> {
+        let anchor = |h, v| Some(WatermarkPosition::Anchor(h, v));
+        match s.trim().to_lowercase().replace('_', "-").as_str() {
+            "top-left" => anchor(0, 0),
+            "top" => anchor(1, 0),
+            "top-right" => anchor(2, 0),
+            "left" => anchor(0, 1),
+            "center" => anchor(1, 1),
+            "right" => anchor(2, 1),
+            "bottom-left" => anchor(0, 2),
+            "bottom" => anchor(1, 2),
+            "bottom-right" => anchor(2, 2),
+            "tile" => Some(WatermarkPosition::Tile),
+            _ => None,
+        }
+    }
+}
+
+/// 已加载的水印素材
+enum Mark {
+    Logo(RgbaImage),
+    #[cfg(feature = "fonts")]
+    Text {
+        font: ab_glyph::FontVec,
+        text: String,
+        color: [u8; 3],
+    },
+}
+
+/// 已校验、已加载的水印（素材文件读一次，可对多张图重复使用）
+pub struct Watermark {
+    mark: Mark,
+    position: WatermarkPosition,
+    margin: f32,
+    opacity: f32,
+    scale: f32,
+}
+
+impl std::fmt::Debug for Watermark {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Watermark")
+            .field("position", &self.position)
+            .field("margin", &self.margin)
+            .field("opacity", &self.opacity)
+            .field("scale", &self.scale)
+            .finish_non_exhaustive()
+    }
+}
+
+impl WatermarkConfig {
+    /// 校验参数并加载素材（标志解码 / 字体解析）
+    pub fn load(&self) -> Result<Watermark> {
+        let position = WatermarkPosition::parse(&self.position)
+            .ok_or_else(|| anyhow!("水印位置无效: '{}'", self.position))?;
+        if !(0.0..=1.0).contains(&self.opacity) {
+            return Err(anyhow!("水印不透明度须在 0-1: {}", self.opacity));
+        }
+        if !(0.0..=0.5).contains(&self.margin) {
+            return Err(anyhow!("水印边距须在 0-0.5（占短边比例）: {}", self.margin));
+        }
+        if let Some(s) = self.scale {
+            if !(s > 0.0 && s <= 1.0) {
+                return Err(anyhow!("水印尺寸须在 0-1（占短边比例）: {}", s));
+            }
+        }
+        let (mark, default_scale) = if let Some(path) = &self.image {
+            let logo = image::open(path)
+                .map_err(|e| anyhow!("水印图片读取失败 {}: {}", path, e))?
+                .to_rgba8();
+            (Mark::Logo(logo), DEFAULT_LOGO_SCALE)
+        } else if let Some(text) = self.text.as_ref().filter(|t| !t.trim().is_empty()) {
+            (self.text_mark(text)?, DEFAULT_TEXT_SCALE)
+        } else {
+            return Err(anyhow!("水印需要 image 或 text 其一"));
+        };
+        Ok(Watermark {
+            mark,
+            position,
+            margin: self.margin,
+            opacity: self.opacity,
+            scale: self.scale.unwrap_or(default_scale),
+        })
+    }
+}
+
+impl WatermarkConfig {
+    #[cfg(feature = "fonts")]
+    fn text_mark(&self, text: &str) -> Result<Mark> {
+        let font_path = self
+            .font
+            .as_ref()
+            .ok_or_else(|| anyhow!("文字水印需要字体文件（TTF/OTF）"))?;
+        let bytes = std::fs::read(font_path)
+            .map_err(|e| anyhow!("水印字体读取失败 {}: {}", font_path, e))?;
+        let font = ab_glyph::FontVec::try_from_vec(bytes)
+            .map_err(|_| anyhow!("水印字体解析失败（需 TTF/OTF）: {}", font_path))?;
+        let color = crate::pad::parse_hex_color(&self.color)
+            .ok_or_else(|| anyhow!("水印颜色无效（#RRGGBB）: '{}'", self.color))?;
+        Ok(Mark::Text {
+            font,
+            text: text.to_string(),
+            color,
+        })
+    }
+
+    #[cfg(not(feature = "fonts"))]
+    fn text_mark(&self, _text: &str) -> Result<Mark> {
+        Err(anyhow!(
+            "文字水印需以 --features fonts 编译（本构建只支持 PNG 标志水印）"
+        ))
+    }
+}
+
+impl Watermark {
+    /// 按目标图短边排版水印素材（已乘不透明度）
+    fn render(&self, short_edge: u32) -> Option<RgbaImage> {
+        let size = (short_edge as f32 * self.scale).round().max(1.0);
+        let mut mark = match &self.mark {
+            Mark::Logo(logo) => {
+                let (lw, lh) = logo.dimensions();
+                if lw == 0 || lh == 0 {
+                    return None;
+                }
+                let w = size as u32;
+                let h = ((lh as f32 * size / lw as f32).round() as u32).max(1);
+                imageops::resize(logo, w, h, imageops::FilterType::Lanczos3)
+            }
+            #[cfg(feature = "fonts")]
+            Mark::Text { font, text, color } => render_text(font, text, size, *color)?,
+        };
+        for px in mark.pixels_mut() {
+            px[3] = (px[3] as f32 * self.opacity).round() as u8;
+        }
+        Some(mark)
+    }
+
+    /// 把水印叠加到图上（原地）
+    pub fn apply(&self, img: &mut RgbaImage) {
+        let (w, h) = img.dimensions();
+        let short = w.min(h);
+        let Some(mark) = self.render(short) else {
+            return;
+        };
+        let (mw, mh) = (mark.width() as i64, mark.height() as i64);
+        let margin = (short as f32 * self.margin).round() as i64;
+        match self.position {
+            WatermarkPosition::Anchor(hx, vy) => {
+                let x = match hx {
+                    0 => margin,
+                    1 => (w as i64 - mw) / 2,
+                    _ => w as i64 - mw - margin,
+                };
+                let y = match vy {
+                    0 => margin,
+                    1 => (h as i64 - mh) / 2,
+                    _ => h as i64 - mh - margin,
+                };
+                imageops::overlay(img, &mark, x, y);
+            }
+            WatermarkPosition::Tile => {
+                // 间距至少为水印高度，避免铺成一整片
+                let gap = margin.max(mh);
+                let (step_x, step_y) = (mw + gap, mh + gap);
+                let mut y = gap / 2;
+                let mut row = 0;
+                while y < h as i64 {
+                    let mut x = if row % 2 == 0 {
+                        gap / 2
+                    } else {
+                        gap / 2 - step_x / 2
+                    };
+                    while x < w as i64 {
+                        imageops::overlay(img, &mark, x, y);
+                        x += step_x;
+                    }
+                    y += step_y;
+                    row += 1;
+                }
+            }
+        }
+    }
+}
+
+/// 单行文字渲染成带 alpha 的图（字号 px_size，含字距调整）
+#[cfg(feature = "fonts")]
+pub(crate) fn render_text(
+    font: &ab_glyph::FontVec,
+    text: &str,
+    px_size: f32,
+    color: [u8; 3],
+) -> Option<RgbaImage> {
+    use ab_glyph::{point, Font, ScaleFont};
+    let scaled = font.as_scaled(px_size);
+    let mut glyphs = Vec::new();
+    let mut caret = 0.0f32;
+    let mut last = None;
+    for c in text.chars() {
+        let id = scaled.glyph_id(c);
+        if let Some(prev) = last {
+            caret += scaled.kern(prev, id);
+        }
+        glyphs.push(id.with_scale_and_position(px_size, point(caret, scaled.ascent())));
+        caret += scaled.h_advance(id);
+        last = Some(id);
+    }
+    let w = caret.ceil() as u32 + 2;
+    let h = (scaled.ascent() - scaled.descent()).ceil() as u32 + 2;
+    if w <= 2 {
+        return None;
+    }
+    let mut out = RgbaImage::from_pixel(w, h, image::Rgba([color[0], color[1], color[2], 0]));
+    for g in glyphs {
+        let Some(outline) = scaled.outline_glyph(g) else {
+            continue;
+        };
+        let bounds = outline.px_bounds();
+        outline.draw(|gx, gy, coverage| {
+            let x = bounds.min.x as i64 + gx as i64;
+            let y = bounds.min.y as i64 + gy as i64;
+            if x >= 0 && y >= 0 && (x as u32) < w && (y as u32) < h {
+                let px = out.get_pixel_mut(x as u32, y as u32);
+                let a = (coverage.clamp(0.0, 1.0) * 255.0).round() as u8;
+                px[3] = px[3].max(a);
+            }
+        });
+    }
+    Some(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::Rgba;
+    use std::path::PathBuf;
+
+    fn fixture_font() -> String {
+        PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+            .join("tests/fixtures/fonts/Hack-Regular.ttf")
+            .display()
+            .to_string()
+    }
+
+    fn gray(w: u32, h: u32) -> RgbaImage {
+        RgbaImage::from_pixel(w, h, Rgba([100, 100, 100, 255]))
+    }
+
+    /// 区域内偏离底色的像素数
+    fn changed(img: &RgbaImage, x0: u32, y0: u32, x1: u32, y1: u32) -> usize {
+        (y0..y1)
+            .flat_map(|y| (x0..x1).map(move |x| (x, y)))
+            .filter(|&(x, y)| img.get_pixel(x, y)[0] != 100)
+            .count()
+    }
+
+    #[cfg(feature = "fonts")]
+    #[test]
+    fn test_text_watermark_lands_in_bottom_right() {
+        let wm = WatermarkConfig {
+            text: Some("XTAP".into()),
+            font: Some(fixture_font()),
+            opacity: 1.0,
+            ..Default::default()
+        }
+        .load()
+        .unwrap();
+        let mut img = gray(800, 600);
+        wm.apply(&mut img);
+        assert!(changed(&img, 400, 300, 800, 600) > 50, "右下角应有文字");
+        assert_eq!(changed(&img, 0, 0, 400, 300), 0, "左上角不应被覆盖");
+    }
+
+    #[test]
+    fn test_logo_opacity_and_tile() {
+        let dir = std::env::temp_dir().join(format!("xtap_wm_logo_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let logo_path = dir.join("logo.png");
+        RgbaImage::from_pixel(40, 20, Rgba([255, 0, 0, 255]))
+            .save(&logo_path)
+            .unwrap();
+
+        let cfg = WatermarkConfig {
+            image: Some(logo_path.display().to_string()),
+            position: "center".into(),
+            opacity: 0.5,
+            scale: Some(0.5),
+            ..Default::default()
+        };
+        let mut img = gray(400, 200);
+        cfg.load().unwrap().apply(&mut img);
+        // 标志宽 = 0.5 × 短边 200 = 100，居中；半透明红叠灰
+        let p = img.get_pixel(200, 100);
+        assert!(p[0] > 150 && p[0] < 200 && p[1] < 100, "{:?}", p);
+        assert_eq!(img.get_pixel(5, 5)[0], 100);
+
+        let tile = WatermarkConfig {
+            position: "tile".into(),
+            scale: Some(0.1),
+            ..cfg
+        };
+        let mut img = gray(400, 400);
+        tile.load().unwrap().apply(&mut img);
+        // 平铺：四个象限都有水印
+        for (x, y) in [(0, 0), (200, 0), (0, 200), (200, 200)] {
+            assert!(
+                changed(&img, x, y, x + 200, y + 200) > 0,
+                "象限 ({},{}) 无水印",
+                x,
+                y
+            );
+        }
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn test_invalid_configs_rejected() {
+        assert!(WatermarkConfig::default().load().is_err(), "无素材");
+        let no_font = WatermarkConfig {
+            text: Some("x".into()),
+            ..Default::default()
+        };
+        assert!(no_font.load().is_err());
+        let bad_pos = WatermarkConfig {
+            text: Some("x".into()),
+            font: Some(fixture_font()),
+            position: "middle-ish".into(),
+            ..Default::default()
+        };
+        assert!(bad_pos.load().is_err());
+        assert_eq!(
+            WatermarkPosition::parse("bottom_left"),
+            Some(WatermarkPosition::Anchor(0, 2))
+        );
+    }
+}